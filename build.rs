@@ -0,0 +1,18 @@
+//! Embeds git hash, build date, target and enabled features of the build, see the `build_info` module.
+//!
+//! Values that can't be determined, e.g. the hash of a build outside of the git checkout, are set to
+//! `VERGEN_IDEMPOTENT_OUTPUT` instead of failing the build.
+use std::error::Error;
+use vergen::EmitBuilder;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    EmitBuilder::builder()
+        .build_timestamp()
+        .cargo_features()
+        .cargo_target_triple()
+        .cargo_debug()
+        .git_sha(true)
+        .git_dirty(false)
+        .emit()?;
+    Ok(())
+}
@@ -1,44 +1,382 @@
+use crate::build_info;
 use crate::config::AppCfg;
+use crate::control::{ControlCommand, ControlReply};
 
-use crate::core::bnc::error::BncResult;
+use crate::core::bnc::data::{AveragePrice, InlineOrder};
+use crate::core::bnc::decimal::{format_decimal, RoundingMode};
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::replay::{is_replay, replay_clock, ReplayClock};
+use crate::core::bnc::rest::BncRestClient;
+use crate::plan::SubscriptionPlan;
 
-use crate::core::bnc::state::book::{OrderBookManager, OrderBookReceiver};
-use crate::core::bnc::state::price::{PriceReceiver, PriceStateManager};
+use crate::core::bnc::config::BncCfg;
+use crate::core::bnc::state::anomaly::{RateAnomaly, RateMonitor};
+use crate::core::bnc::state::book::{OrderBookDisplay, TableDisplay};
+use crate::core::bnc::state::conversion::{Conversion, ConversionManager};
+use crate::core::bnc::state::market::MarketManager;
+use crate::core::bnc::state::mini_ticker::{MiniTickerManager, MiniTickerReceiver, WatchedTicker};
+use crate::core::bnc::state::portfolio::{PortfolioManager, PortfolioValue};
+use crate::core::bnc::state::stats::{StatsManager, StatsReceiver, SymbolStats};
+use crate::core::bnc::ws::combined::QueueStats;
+use crate::core::bnc::ws::worker::price::SymbolPriceUpdate;
+use crate::core::recorder::RecorderCfg;
 
-use crate::ui::{draw_background, draw_best_price, draw_order_book, get_global_layout};
+use crate::ui::format::NumberFormat;
+use crate::ui::i18n::{fill, Strings};
+use crate::ui::layout::{Pane, PaneLayout, Resize};
+use crate::ui::picker::SymbolPicker;
+use crate::ui::session::{Session, SessionFile};
+use crate::ui::view::{
+    AboutView, BookView, HoldingView, LevelView, PortfolioView, QuoteView, StatsView,
+    StripItemView, StripView,
+};
+use crate::ui::AppFrame;
 
-use std::ops::Deref;
+use crossterm::event::{KeyCode, KeyEvent};
+use futures_util::FutureExt;
+use log::{info, warn};
+use rust_decimal::Decimal;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
-use tui::backend::Backend;
-use tui::{Frame, Terminal};
+use tui::Terminal;
 
 pub type SharedTerminal<B> = Arc<Mutex<Terminal<B>>>;
 
+/// Time each symbol leads the ticker strip before the next one takes its place.
+const STRIP_STEP: Duration = Duration::from_secs(3);
+
+/// Time the stopped feeds are given to close their connections before the runtime drops them.
+pub const SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+
+/// Kind of the symbol typed by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputMode {
+    /// Symbol is added to the scraped ones.
+    Add,
+
+    /// Symbol replaces the displayed one.
+    Switch,
+
+    /// Replay is moved to the typed moment.
+    Seek,
+}
+
+/// Start feeds of the symbol, grouped by the step and recorded if the recorder is given.
+async fn start_market(
+    cfg: BncCfg,
+    recorder: Option<RecorderCfg>,
+    grouping: Option<Decimal>,
+    symbol: String,
+) -> BncResult<MarketManager> {
+    let mut market = MarketManager::start(&cfg, symbol).await?;
+    market.set_book_grouping(grouping);
+    if let Some(recorder) = recorder {
+        market.record(&recorder, &cfg.ws);
+    }
+    Ok(market)
+}
+
+/// Stop the market once its abandoned start is finished, so its feeds are not left running.
+fn discard_start(start: JoinHandle<BncResult<MarketManager>>) {
+    tokio::task::spawn(async move {
+        if let Ok(Ok(market)) = start.await {
+            market.stop();
+        }
+    });
+}
+
+/// Format the best order, with its price valued in the secondary currency if conversion is set.
+fn format_best_order(
+    order: &InlineOrder,
+    conversion: Option<&Conversion>,
+    format: &NumberFormat,
+) -> String {
+    let order_line = format!(
+        "{:<8}/{:<8}",
+        order.level(),
+        format.qty(order.qty().value())
+    );
+    let converted = conversion.and_then(|conversion| {
+        conversion.convert(order.level()).map(|value| {
+            let value = format.money(&value, &conversion.currency);
+            format!("{} (≈{})", order_line, value)
+        })
+    });
+    converted.unwrap_or(order_line)
+}
+
+fn quote_view(
+    update: &SymbolPriceUpdate,
+    average: Option<&AveragePrice>,
+    stats: Option<&SymbolStats>,
+    conversion: Option<&Conversion>,
+    format: &NumberFormat,
+) -> QuoteView {
+    // One more decimal than the prices have, as it falls between their ticks.
+    let decimals = update.ask.level().value().scale() + 1;
+    QuoteView {
+        ask: format_best_order(&update.ask, conversion, format),
+        bid: format_best_order(&update.bid, conversion, format),
+        microprice: update
+            .microprice
+            .map(|price| format_decimal(price, decimals, RoundingMode::Nearest))
+            .unwrap_or_else(|| "-".into()),
+        average: average
+            .filter(|average| !average.price.is_zero())
+            .map(|average| average.price.to_string())
+            .unwrap_or_else(|| "-".into()),
+        stats: stats.map(|stats| stats_view(stats, format)),
+    }
+}
+
+fn stats_view(stats: &SymbolStats, format: &NumberFormat) -> StatsView {
+    StatsView {
+        change_percent: stats.ticker.price_change_percent.clone(),
+        high: stats.ticker.high.to_string(),
+        low: stats.ticker.low.to_string(),
+        volume: format.qty(stats.ticker.volume.value()),
+    }
+}
+
+fn book_view(book: OrderBookDisplay, format: &NumberFormat) -> BookView {
+    let spread = book.spread().map(|spread| spread.to_string());
+    let levels = |levels: TableDisplay| -> Vec<LevelView> {
+        levels
+            .into_iter()
+            .map(|level| LevelView {
+                price: level.price.to_string(),
+                qty: format.qty(level.qty.value()),
+                cum_qty: format.qty(level.cum_qty.value()),
+            })
+            .collect()
+    };
+    BookView {
+        asks: levels(book.asks),
+        bids: levels(book.bids),
+        spread,
+        cached: book.cached,
+        grouping: book.grouping.map(|step| step.to_string()),
+    }
+}
+
+/// Next of the grouping steps, cycling through them and back to the ungrouped levels.
+fn next_grouping(current: Option<usize>, steps: usize) -> Option<usize> {
+    match current {
+        None if steps > 0 => Some(0),
+        Some(index) if index + 1 < steps => Some(index + 1),
+        _ => None,
+    }
+}
+
+/// Item of the ticker strip, price and change are unknown until the first mini ticker of the symbol.
+fn strip_item_view(symbol: &str, watched: Option<&WatchedTicker>) -> StripItemView {
+    let percents = watched.and_then(|watched| watched.change.change());
+    StripItemView {
+        symbol: symbol.to_string(),
+        price: watched
+            .map(|watched| watched.ticker.close.to_string())
+            .unwrap_or_else(|| "-".into()),
+        change: percents
+            .map(|change| format!("{:+.2}%", change.round_dp(2)))
+            .unwrap_or_else(|| "-".into()),
+        rising: percents
+            .filter(|change| !change.is_zero())
+            .map(|change| change.is_sign_positive()),
+    }
+}
+
+fn about_view(cfg: &AppCfg, queues: &[QueueStats], strings: &Strings) -> AboutView {
+    AboutView {
+        version: build_info::VERSION.to_string(),
+        commit: build_info::commit(),
+        built: build_info::BUILD_TIMESTAMP.to_string(),
+        target: format!("{} ({})", build_info::TARGET, build_info::profile()),
+        features: build_info::features().to_string(),
+        config_sources: cfg.sources.clone(),
+        queues: queues
+            .iter()
+            .map(|queue| {
+                fill(
+                    strings.about_queue,
+                    &[&queue.stream, &queue.depth, &queue.dropped],
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Warning about the displayed book's updates rate.
+fn anomaly_status(anomaly: &RateAnomaly, strings: &Strings) -> String {
+    let (template, rate, baseline) = match anomaly {
+        RateAnomaly::Stalled { rate, baseline } => (strings.book_stalled, rate, baseline),
+        RateAnomaly::Burst { rate, baseline } => (strings.book_burst, rate, baseline),
+    };
+    fill(
+        template,
+        &[&format!("{:.1}", rate), &format!("{:.1}", baseline)],
+    )
+}
+
+/// Strip of the items, starting from the one leading at the moment.
+fn strip_view(mut items: Vec<StripItemView>, elapsed: Duration) -> StripView {
+    if !items.is_empty() {
+        let step = (elapsed.as_millis() / STRIP_STEP.as_millis()) as usize;
+        let len = items.len();
+        items.rotate_left(step % len);
+    }
+    StripView { items }
+}
+
+fn portfolio_view(portfolio: PortfolioValue, format: &NumberFormat) -> PortfolioView {
+    let decimals = |value: Option<_>, decimals| {
+        value
+            .map(|value| format_decimal(value, decimals, RoundingMode::Nearest))
+            .unwrap_or_else(|| "-".into())
+    };
+    let holdings = portfolio
+        .holdings
+        .into_iter()
+        .map(|holding| HoldingView {
+            amount: format.qty(holding.amount.normalize()),
+            price: decimals(holding.price, 4),
+            value: holding
+                .value
+                .map(|value| format.notional(value, 2))
+                .unwrap_or_else(|| "-".into()),
+            change: holding
+                .change
+                .map(|change| format!("{:+.2}%", change.round_dp(2)))
+                .unwrap_or_else(|| "-".into()),
+            asset: holding.asset,
+        })
+        .collect();
+
+    let total = format.notional(portfolio.total, 2);
+    PortfolioView {
+        total: format.quote_money(&total, &portfolio.quote),
+        holdings,
+    }
+}
+
 /// General application that controls both ui and data scraping.
+///
+/// Several symbols are scraped concurrently, one of them is displayed at a time.
 pub struct App<'a> {
-    symbol: String,
+    cfg: &'a AppCfg,
+
+    /// Symbols the application is started with.
+    symbols: Vec<String>,
 
     should_quit: bool,
 
-    price_manager: PriceStateManager<'a>,
-    order_book_manager: OrderBookManager<'a>,
+    /// Markets of all the scraped symbols.
+    markets: Vec<MarketManager>,
+
+    /// Index of the displayed market.
+    selected: usize,
+
+    /// Start of the market we are migrating the displayed one to, together with its slot and symbol.
+    /// It becomes the pending market once it is started.
+    pending_start: Option<(usize, String, JoinHandle<BncResult<MarketManager>>)>,
+
+    /// Market we are migrating the displayed one to, together with its slot.
+    /// It replaces the displayed one as soon as it is synced.
+    pending_market: Option<(usize, MarketManager)>,
+
+    /// Symbol typed by the user, if symbol input is active.
+    symbol_input: Option<(InputMode, String)>,
+
+    /// Last status message to be shown to the user.
+    status: Option<String>,
+
+    /// Focus and sizes of the panes chosen by the user.
+    layout: PaneLayout,
+
+    /// File the visibility of the panes is kept in between the runs, if it is kept.
+    session: Option<SessionFile>,
+
+    /// Background feed of the secondary currency rate, if it is configured.
+    conversion: Option<ConversionManager>,
+
+    /// Feeds of the held assets, if the portfolio is configured.
+    portfolio: Option<PortfolioManager>,
 
-    price_state_watcher: Option<PriceReceiver>,
-    book_state_watcher: Option<OrderBookReceiver>,
+    /// Watches the rate of the displayed book's updates.
+    book_rate: RateMonitor,
+
+    /// Feed of the ticker strip, if it is shown.
+    mini_tickers: Option<(MiniTickerManager, MiniTickerReceiver)>,
+
+    /// Feed of the displayed symbol's 24hr statistics, once there is a symbol to display.
+    stats: Option<(StatsManager, StatsReceiver)>,
+
+    /// Moment the ticker strip started cycling from.
+    strip_started: Instant,
+
+    /// Index of the configured step the books are grouped by, if they are.
+    grouping: Option<usize>,
+
+    /// Clock of the playback, if the recordings are replayed instead of binance.
+    replay: Option<&'static ReplayClock>,
+
+    /// Whether the scraped symbols are recorded, since the start or the control command.
+    recording: bool,
+
+    /// Symbol picker, if it is open.
+    picker: Option<SymbolPicker>,
+
+    /// Whether the build information is shown.
+    about: bool,
+
+    /// Stream queues of the statistics connection, refreshed while the build information is shown.
+    queues: Vec<QueueStats>,
+
+    /// Refresh of the stream queues, if it is in progress.
+    queues_loading: Option<JoinHandle<BncResult<Vec<QueueStats>>>>,
+
+    /// Symbols listed by the exchange, once they are loaded for the picker.
+    listed_symbols: Option<Vec<String>>,
+
+    /// Loading of the listed symbols, if it is in progress.
+    symbols_loading: Option<JoinHandle<BncResult<Vec<String>>>>,
 }
 
 impl<'a> App<'a> {
-    pub fn new(cfg: &'a AppCfg, symbol: String) -> Self {
+    pub fn new(cfg: &'a AppCfg, symbols: Vec<String>) -> Self {
         Self {
-            price_manager: PriceStateManager::from_cfg(&cfg.core.bnc.ws),
-            order_book_manager: OrderBookManager::from_cfg(&cfg.core.bnc),
-            symbol,
+            cfg,
+            symbols,
             should_quit: false,
-            price_state_watcher: None,
-            book_state_watcher: None,
+            markets: vec![],
+            selected: 0,
+            pending_start: None,
+            pending_market: None,
+            symbol_input: None,
+            status: None,
+            layout: PaneLayout::default()
+                .with_preset(cfg.ui.layout)
+                .with_hidden(&cfg.ui.hidden_panes),
+            session: cfg.ui.session_file.as_ref().map(SessionFile::new),
+            conversion: None,
+            portfolio: None,
+            book_rate: RateMonitor::new(cfg.core.bnc.book_anomaly.clone()),
+            mini_tickers: None,
+            stats: None,
+            strip_started: Instant::now(),
+            grouping: None,
+            replay: is_replay(&cfg.core.bnc.baseurl).then(replay_clock),
+            recording: cfg.recorder.enabled,
+            picker: None,
+            about: false,
+            queues: vec![],
+            queues_loading: None,
+            listed_symbols: None,
+            symbols_loading: None,
         }
     }
 
@@ -46,43 +384,886 @@ impl<'a> App<'a> {
         self.should_quit
     }
 
-    /// Initialise BNC app - it will fetch the snapshot, then schedules workers to infinitely update the current state.
+    /// Initialise BNC app - it will fetch the snapshots, then schedules workers to infinitely update the current state.
+    ///
+    /// Refuses to start if the symbols would exceed the configured limits.
     pub async fn init(&mut self) -> BncResult<()> {
-        let price_state_receiver = self.price_manager.init(&self.symbol);
-        let order_book_receiver = self.order_book_manager.init(&self.symbol).await?;
-        self.price_state_watcher = Some(price_state_receiver);
-        self.book_state_watcher = Some(order_book_receiver);
+        if let Some(session) = self.session.as_ref().and_then(SessionFile::load) {
+            self.layout = self.layout.with_hidden(&session.hidden_panes);
+        }
+        self.check_limits(&self.symbols, self.recording)?;
+        self.validate_symbols().await?;
+        for symbol in self.symbols.clone() {
+            if self.market_index(&symbol).is_some() {
+                continue;
+            }
+            let market = self.start_market(symbol).await?;
+            self.markets.push(market);
+        }
+
+        if let Some(conversion) = self.cfg.ui.conversion.clone() {
+            self.conversion = Some(ConversionManager::start(conversion, &self.cfg.core.bnc.ws));
+        }
+        if let Some(portfolio) = self.cfg.ui.portfolio.as_ref() {
+            self.portfolio = Some(PortfolioManager::start(portfolio, &self.cfg.core.bnc.ws));
+        }
+        if self.cfg.ui.ticker_strip {
+            let mut manager = MiniTickerManager::from_cfg(&self.cfg.core.bnc.ws);
+            let receiver = manager.init();
+            manager.watch(self.symbols());
+            self.mini_tickers = Some((manager, receiver));
+        }
+        if let Some(symbol) = self.symbol().filter(|_| self.cfg.ui.stats) {
+            self.stats = Some(StatsManager::start(&self.cfg.core.bnc, symbol)?);
+        }
 
         Ok(())
     }
 
-    /// Draw current state of the application on the provided frame.
-    pub fn draw<B: Backend>(&mut self, frame: &mut Frame<B>) {
-        draw_background(frame);
-        let layout = get_global_layout(frame);
-        if let Some(order_book_rx) = self.book_state_watcher.as_mut() {
-            draw_order_book(
-                frame,
-                layout.order_book,
-                order_book_rx.borrow_and_update().deref(),
-            );
+    /// Make sure the exchange trades all the symbols, as the streams of the unknown ones are just silent.
+    ///
+    /// Listed symbols are kept for the picker.
+    async fn validate_symbols(&mut self) -> BncResult<()> {
+        let info = BncRestClient::from_cfg(&self.cfg.core.bnc)?
+            .fetch_exchange_info()
+            .await?;
+        for symbol in self.symbols.iter() {
+            info.validate(symbol)?;
+        }
+        self.listed_symbols = Some(info.trading_symbols());
+        Ok(())
+    }
+
+    /// Check the subscription plan of the symbols, recorded ones included, against the configured limits.
+    fn check_limits(&self, symbols: &[String], recording: bool) -> BncResult<()> {
+        let ws = &self.cfg.core.bnc.ws;
+        let mut plan = SubscriptionPlan::new(ws, &self.cfg.ui, symbols);
+        if recording {
+            plan = plan.with_recording(ws, &self.cfg.recorder);
+        }
+        self.cfg.limits.check(&plan)
+    }
+
+    /// Start feeds of the symbol, grouped and recorded the way the rest of the markets are.
+    ///
+    /// Started market does not borrow the application, so it could be started in the background.
+    fn start_market(
+        &self,
+        symbol: String,
+    ) -> impl Future<Output = BncResult<MarketManager>> + Send + 'static {
+        let recorder = self.recording.then(|| self.cfg.recorder.clone());
+        start_market(
+            self.cfg.core.bnc.clone(),
+            recorder,
+            self.grouping_step(),
+            symbol,
+        )
+    }
+
+    /// Abandon the migration of the displayed market, if it is in progress. Returns the symbol it migrated to.
+    fn cancel_migration(&mut self) -> Option<String> {
+        if let Some((_, symbol, start)) = self.pending_start.take() {
+            discard_start(start);
+            return Some(symbol);
+        }
+        self.pending_market.take().map(|(_, market)| {
+            market.stop();
+            market.symbol().to_string()
+        })
+    }
+
+    fn market_index(&self, symbol: &str) -> Option<usize> {
+        self.markets
+            .iter()
+            .position(|market| market.symbol() == symbol)
+    }
+
+    /// Symbol that is currently displayed.
+    pub fn symbol(&self) -> Option<&str> {
+        self.markets
+            .get(self.selected)
+            .map(|market| market.symbol())
+    }
+
+    /// Symbols of all the scraped markets.
+    pub fn symbols(&self) -> Vec<&str> {
+        self.markets.iter().map(|market| market.symbol()).collect()
+    }
+
+    /// Display market at the index, wrapping around the scraped ones.
+    pub fn select(&mut self, index: usize) {
+        if self.markets.is_empty() {
+            return;
         }
+        let index = index % self.markets.len();
+        if index != self.selected {
+            self.selected = index;
+            self.book_rate = RateMonitor::new(self.cfg.core.bnc.book_anomaly.clone());
+        }
+    }
+
+    fn select_previous(&mut self) {
+        let len = self.markets.len().max(1);
+        self.select(self.selected + len - 1);
+    }
 
-        if let Some(price_rx) = self.price_state_watcher.as_mut() {
-            draw_best_price(
-                frame,
-                layout.best_prices,
-                price_rx.borrow_and_update().deref(),
-            );
+    /// Start scraping one more symbol and display it. Already scraped symbol is just displayed.
+    pub async fn add_symbol(&mut self, symbol: String) -> BncResult<()> {
+        if let Some(index) = self.market_index(&symbol) {
+            self.select(index);
+            return Ok(());
+        }
+
+        info!("Adding symbol {}.", symbol);
+        let mut symbols: Vec<String> = self.symbols().into_iter().map(String::from).collect();
+        symbols.push(symbol.clone());
+        let started = match self.check_limits(&symbols, self.recording) {
+            Ok(()) => self.start_market(symbol.clone()).await,
+            Err(err) => Err(err),
+        };
+        match started {
+            Ok(market) => {
+                self.markets.push(market);
+                self.select(self.markets.len() - 1);
+                self.status = None;
+                Ok(())
+            }
+            Err(err) => {
+                warn!("Could not add symbol {}. Error: {}", symbol, err);
+                self.status = Some(fill(self.strings().add_failed, &[&symbol, &err]));
+                Err(err)
+            }
         }
     }
 
-    /// Finalize application - abort tasks, clear the state. In other words, graceful shutdown.
-    pub fn finalize(&mut self) -> BncResult<()> {
-        self.order_book_manager.stop();
-        self.price_manager.stop();
+    /// Move the replay to the typed moment. Moving back starts the feeds over, as their books are already past it.
+    async fn seek(&mut self, input: &str) -> BncResult<()> {
+        let clock = match self.replay {
+            Some(clock) => clock,
+            None => return Ok(()),
+        };
+        let moment = match clock.parse_moment(input) {
+            Some(moment) => moment,
+            None => {
+                self.status = Some(fill(self.strings().seek_failed, &[&input]));
+                return Ok(());
+            }
+        };
+        info!("Seeking replay to {}.", moment);
+        if !clock.seek(moment) {
+            return Ok(());
+        }
+
+        self.cancel_migration();
+        for index in 0..self.markets.len() {
+            let symbol = self.markets[index].symbol().to_string();
+            let market = self.start_market(symbol).await?;
+            std::mem::replace(&mut self.markets[index], market).stop();
+        }
+        Ok(())
+    }
+
+    /// Stop scraping the symbol. The last symbol can't be removed.
+    ///
+    /// Removal cancels the pending migration, as slots of the markets are shifted.
+    pub fn remove_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        let pending_symbol = self.cancel_migration();
+        if pending_symbol.is_some() {
+            self.status = None;
+        }
+        if pending_symbol.as_deref() == Some(symbol) {
+            return Ok(());
+        }
+
+        let index = self
+            .market_index(symbol)
+            .ok_or_else(|| fill(self.strings().not_scraped, &[&symbol]))?;
+        if self.markets.len() == 1 {
+            return Err(self.strings().last_symbol.into());
+        }
+
+        info!("Removing symbol {}.", symbol);
+        self.markets.remove(index).stop();
+        if self.selected >= index && self.selected > 0 {
+            self.selected -= 1;
+            self.book_rate = RateMonitor::new(self.cfg.core.bnc.book_anomaly.clone());
+        }
+        Ok(())
+    }
+
+    /// Start feeds of the new symbol in the background, to replace the displayed one.
+    ///
+    /// Currently displayed symbol is kept on the screen until the new one is started and synced,
+    /// see [`App::on_tick`]. Failed start is shown in the status line.
+    ///
+    /// New symbol opens connections of its own, the ones of the replaced symbol are closed once it takes over.
+    /// Statistics are switched over their connection instead, see [`StatsManager`].
+    pub fn migrate(&mut self, symbol: String) {
+        self.cancel_migration();
+
+        info!("Migrating to symbol {}.", symbol);
+        self.status = Some(fill(self.strings().switching, &[&symbol]));
+        let start = tokio::task::spawn(self.start_market(symbol.clone()));
+        self.pending_start = Some((self.selected, symbol, start));
+    }
+
+    /// Make the started market the pending one, once its start is finished.
+    fn poll_pending_start(&mut self) {
+        if !self
+            .pending_start
+            .as_ref()
+            .is_some_and(|(_, _, start)| start.is_finished())
+        {
+            return;
+        }
+        let (index, symbol, start) = match self.pending_start.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+        let started = match start.now_or_never() {
+            Some(Ok(started)) => started,
+            Some(Err(err)) => Err(BncError::TaskFailed(err.to_string())),
+            None => return,
+        };
+        match started {
+            Ok(mut market) => {
+                // Grouping and recording might be changed while the market was started.
+                market.set_book_grouping(self.grouping_step());
+                if self.recording && !market.is_recorded() {
+                    market.record(&self.cfg.recorder, &self.cfg.core.bnc.ws);
+                }
+                self.pending_market = Some((index, market));
+            }
+            Err(err) => {
+                warn!("Could not migrate to symbol {}. Error: {}", symbol, err);
+                self.status = Some(fill(self.strings().switch_failed, &[&symbol, &err]));
+            }
+        }
+    }
+
+    /// Open the symbol picker. Symbols of the exchange are loaded in the background, once.
+    fn open_picker(&mut self) {
+        self.picker = Some(SymbolPicker::new(self.listed_symbols.clone()));
+        if self.listed_symbols.is_some() || self.symbols_loading.is_some() {
+            return;
+        }
+        match BncRestClient::from_cfg(&self.cfg.core.bnc) {
+            Ok(client) => {
+                self.symbols_loading =
+                    Some(tokio::spawn(async move { client.fetch_symbols().await }))
+            }
+            Err(err) => self.picker_failed(err.to_string()),
+        }
+    }
+
+    fn picker_failed(&mut self, err: String) {
+        warn!("Could not load symbols of the exchange. Error: {}", err);
+        self.picker = None;
+        self.status = Some(fill(self.strings().picker_failed, &[&err]));
+    }
+
+    /// Pass the loaded symbols to the picker, once the loading is finished.
+    fn poll_symbols_loading(&mut self) {
+        if !self
+            .symbols_loading
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            return;
+        }
+        let loaded = self
+            .symbols_loading
+            .take()
+            .and_then(|loading| loading.now_or_never());
+        match loaded {
+            Some(Ok(Ok(symbols))) => {
+                if let Some(picker) = self.picker.as_mut() {
+                    picker.set_symbols(symbols.clone());
+                }
+                self.listed_symbols = Some(symbols);
+            }
+            Some(Ok(Err(err))) => self.picker_failed(err.to_string()),
+            Some(Err(err)) => self.picker_failed(err.to_string()),
+            None => {}
+        }
+    }
+
+    /// Take the refreshed stream queues and refresh them again, as long as the build information is shown.
+    fn poll_queues(&mut self) {
+        if self
+            .queues_loading
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            match self
+                .queues_loading
+                .take()
+                .and_then(|loading| loading.now_or_never())
+            {
+                Some(Ok(Ok(queues))) => self.queues = queues,
+                Some(Ok(Err(err))) => warn!("Could not get the stream queues. Error: {}", err),
+                Some(Err(err)) => warn!("Could not get the stream queues. Error: {}", err),
+                None => {}
+            }
+        }
+        if !self.about || self.queues_loading.is_some() {
+            return;
+        }
+        if let Some((manager, _)) = self.stats.as_ref() {
+            self.queues_loading = Some(tokio::spawn(manager.queues()));
+        }
+    }
+
+    /// Display the picked symbol - the markets of the displayed one are replaced, unless it is scraped already.
+    fn pick(&mut self, symbol: String) {
+        match self.market_index(&symbol) {
+            Some(index) => self.select(index),
+            None => self.migrate(symbol),
+        }
+    }
+
+    fn on_picker_key(&mut self, key: KeyEvent) {
+        let picker = match self.picker.as_mut() {
+            Some(picker) => picker,
+            None => return,
+        };
+        match key.code {
+            KeyCode::Esc => self.picker = None,
+            KeyCode::Enter => {
+                if let Some(symbol) = picker.picked() {
+                    self.picker = None;
+                    self.pick(symbol);
+                }
+            }
+            KeyCode::Up => picker.previous(),
+            KeyCode::Down => picker.next(),
+            KeyCode::Backspace => picker.pop(),
+            KeyCode::Char(char) => picker.push(char),
+            _ => {}
+        }
+    }
+
+    /// Periodical application's routine - watches the book's updates rate, swaps the markets once the pending
+    /// one is synced.
+    pub fn on_tick(&mut self) {
+        self.poll_symbols_loading();
+        self.poll_queues();
+        self.poll_pending_start();
+        if let Some((manager, _)) = self.mini_tickers.as_ref() {
+            manager.watch(self.symbols());
+        }
+        if let (Some((manager, _)), Some(symbol)) = (self.stats.as_ref(), self.symbol()) {
+            manager.switch(symbol);
+        }
+
+        if let Some(market) = self.markets.get(self.selected) {
+            let was_anomalous = self.book_rate.anomaly().is_some();
+            let anomaly = self.book_rate.record(market.book_updates(), Instant::now());
+            match anomaly {
+                Some(anomaly) if !was_anomalous => {
+                    warn!("Order book of {} {}.", market.symbol(), anomaly)
+                }
+                None if was_anomalous => {
+                    info!(
+                        "Order book updates of {} are back to normal.",
+                        market.symbol()
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        let is_synced = self
+            .pending_market
+            .as_ref()
+            .map(|(_, market)| market.is_synced())
+            .unwrap_or(false);
+        if !is_synced {
+            return;
+        }
+
+        if let Some((index, market)) = self.pending_market.take() {
+            info!("Migrated to symbol {}.", market.symbol());
+            match self.markets.get_mut(index) {
+                Some(slot) => std::mem::replace(slot, market).stop(),
+                None => self.markets.push(market),
+            }
+        }
+        self.status = None;
+        self.book_rate = RateMonitor::new(self.cfg.core.bnc.book_anomaly.clone());
+    }
+
+    /// Show the hidden pane or hide the visible one, the visibility is kept in the session file.
+    fn toggle_pane(&mut self, pane: Pane) {
+        self.layout.toggle(pane);
+        let session = Session {
+            hidden_panes: self.layout.hidden(),
+        };
+        if let Some(file) = self.session.as_ref() {
+            if let Err(err) = file.save(&session) {
+                warn!(
+                    "Could not save session {}. Error: {}",
+                    file.path().display(),
+                    err
+                );
+            }
+        }
+    }
+
+    /// Step the books are grouped by, if they are.
+    fn grouping_step(&self) -> Option<Decimal> {
+        self.grouping
+            .and_then(|index| self.cfg.ui.book_grouping.get(index))
+            .map(|step| step.value())
+    }
+
+    /// Group the books by the next configured step, the one after the last displays the levels as they are.
+    fn cycle_grouping(&mut self) {
+        self.grouping = next_grouping(self.grouping, self.cfg.ui.book_grouping.len());
+        let step = self.grouping_step();
+        self.markets
+            .iter_mut()
+            .chain(self.pending_market.iter_mut().map(|(_, market)| market))
+            .for_each(|market| market.set_book_grouping(step));
+    }
+
+    /// Process user's input. Ctrl + C is handled by the runner itself.
+    pub async fn on_key(&mut self, key: KeyEvent) {
+        if self.picker.is_some() {
+            return self.on_picker_key(key);
+        }
+        // Any key closes the build information.
+        if self.about {
+            self.about = false;
+            return;
+        }
+        let (mode, input) = match self.symbol_input.as_mut() {
+            Some((mode, input)) => (*mode, input),
+            None => {
+                match key.code {
+                    KeyCode::Char('a') | KeyCode::Char('A') => {
+                        self.symbol_input = Some((InputMode::Add, String::new()))
+                    }
+                    KeyCode::Char('s') | KeyCode::Char('S') => {
+                        self.symbol_input = Some((InputMode::Switch, String::new()))
+                    }
+                    KeyCode::Char('p') | KeyCode::Char('P') => self.open_picker(),
+                    KeyCode::Char('i') | KeyCode::Char('I') => self.about = true,
+                    KeyCode::Char('x') | KeyCode::Char('X') => {
+                        if let Some(symbol) = self.symbol().map(str::to_string) {
+                            if let Err(err) = self.remove_symbol(&symbol) {
+                                self.status = Some(err);
+                            }
+                        }
+                    }
+                    KeyCode::Char(']') => self.select(self.selected + 1),
+                    KeyCode::Char('[') => self.select_previous(),
+                    KeyCode::Tab => self.layout.focus_next(),
+                    KeyCode::Char('1') => self.toggle_pane(Pane::BestPrices),
+                    KeyCode::Char('2') => self.toggle_pane(Pane::OrderBook),
+                    KeyCode::Char('v') | KeyCode::Char('V') => self.layout.switch_preset(),
+                    KeyCode::Char('g') | KeyCode::Char('G') => self.cycle_grouping(),
+                    KeyCode::Char(' ') => self.replay.iter().for_each(|clock| clock.toggle_pause()),
+                    KeyCode::Char('r') | KeyCode::Char('R') => self
+                        .replay
+                        .iter()
+                        .for_each(|clock| clock.set_speed(clock.speed().next())),
+                    KeyCode::Char('t') | KeyCode::Char('T') if self.replay.is_some() => {
+                        self.symbol_input = Some((InputMode::Seek, String::new()))
+                    }
+                    KeyCode::Char('h') | KeyCode::Char('H') => self.layout.resize(Resize::Narrower),
+                    KeyCode::Char('j') | KeyCode::Char('J') => self.layout.resize(Resize::Taller),
+                    KeyCode::Char('k') | KeyCode::Char('K') => self.layout.resize(Resize::Shorter),
+                    KeyCode::Char('l') | KeyCode::Char('L') => self.layout.resize(Resize::Wider),
+                    _ => {}
+                }
+                return;
+            }
+        };
+
+        match key.code {
+            KeyCode::Char(char) => input.push(char.to_ascii_uppercase()),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.symbol_input = None,
+            KeyCode::Enter => {
+                let symbol = self.symbol_input.take().map(|(_, symbol)| symbol);
+                let symbol = match symbol {
+                    Some(symbol) if !symbol.is_empty() => symbol,
+                    _ => return,
+                };
+                // Failures are shown in the status line.
+                match mode {
+                    InputMode::Add => {
+                        let _ = self.add_symbol(symbol).await;
+                    }
+                    // Already scraped symbol is just displayed.
+                    InputMode::Switch => self.pick(symbol),
+                    InputMode::Seek => {
+                        if let Err(err) = self.seek(&symbol).await {
+                            warn!("Could not restart the replay. Error: {}", err);
+                            self.status = Some(fill(self.strings().replay_restart_failed, &[&err]));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Execute command received by the control endpoint.
+    pub async fn on_command(&mut self, command: ControlCommand) -> ControlReply {
+        info!("Received control command {:?}.", command);
+        match command {
+            ControlCommand::AddSymbol { symbol } => self
+                .add_symbol(symbol.to_ascii_uppercase())
+                .await
+                .map_err(|err| err.to_string())
+                .into(),
+            ControlCommand::RemoveSymbol { symbol } => {
+                self.remove_symbol(&symbol.to_ascii_uppercase()).into()
+            }
+            ControlCommand::SetAlert {} => ControlReply::error("Alerts are not supported"),
+            ControlCommand::StartRecording {} if self.replay.is_some() => {
+                ControlReply::error("Replayed symbols are not recorded")
+            }
+            ControlCommand::StartRecording {} => {
+                self.start_recording().map_err(|err| err.to_string()).into()
+            }
+            ControlCommand::Stop => match self.finalize().await {
+                Ok(_) => ControlReply::ok(),
+                Err(err) => ControlReply::error(err.to_string()),
+            },
+        }
+    }
+
+    /// Record the scraped symbols and the ones added later, as the configured recorder does.
+    fn start_recording(&mut self) -> BncResult<()> {
+        if self.recording {
+            return Ok(());
+        }
+        let symbols: Vec<String> = self.symbols().into_iter().map(String::from).collect();
+        self.check_limits(&symbols, true)?;
+
+        info!(
+            "Recording {} into {}.",
+            symbols.join(", "),
+            self.cfg.recorder.dir
+        );
+        self.recording = true;
+        let ws = &self.cfg.core.bnc.ws;
+        self.markets
+            .iter_mut()
+            .chain(self.pending_market.iter_mut().map(|(_, market)| market))
+            .for_each(|market| market.record(&self.cfg.recorder, ws));
+        Ok(())
+    }
+
+    /// Whether the scraped symbols are recorded, so the application is worth keeping without the UI.
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Scraped symbols with the displayed one in brackets.
+    fn symbols_line(&self) -> String {
+        self.markets
+            .iter()
+            .enumerate()
+            .map(|(index, market)| {
+                if index == self.selected {
+                    format!("[{}]", market.symbol())
+                } else {
+                    market.symbol().to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Texts of the configured locale.
+    fn strings(&self) -> &'static Strings {
+        self.cfg.ui.locale.strings()
+    }
+
+    /// Prepare current state of the application to be drawn by the ui.
+    pub fn frame(&mut self) -> AppFrame {
+        let conversion = self
+            .conversion
+            .as_ref()
+            .map(|conversion| conversion.conversion());
+        let strings = self.strings();
+        let (book, quote) = match self.markets.get_mut(self.selected) {
+            Some(market) => {
+                if let Some(resync) = market.take_resync() {
+                    self.status = Some(fill(strings.book_resynced, &[&resync.symbol]));
+                }
+                let format = &self.cfg.ui.number_format;
+                let book = book_view(market.book_watcher().borrow_and_update().clone(), format);
+                let average = market.average_watcher().borrow_and_update().clone();
+                // Statistics of the previously displayed symbol are left until the switched ones arrive.
+                let stats = self
+                    .stats
+                    .as_ref()
+                    .and_then(|(_, receiver)| receiver.borrow().clone())
+                    .filter(|stats| stats.symbol == market.symbol());
+                let quote = quote_view(
+                    &market.price_watcher().borrow_and_update(),
+                    average.as_ref(),
+                    stats.as_ref(),
+                    conversion.as_ref(),
+                    format,
+                );
+                (Some(book), Some(quote))
+            }
+            None => (None, None),
+        };
+
+        let status = match self.symbol_input.as_ref() {
+            Some((InputMode::Add, input)) => fill(strings.prompt_add, &[input]),
+            Some((InputMode::Switch, input)) => fill(strings.prompt_switch, &[input]),
+            Some((InputMode::Seek, input)) => fill(strings.prompt_seek, &[input]),
+            None => self
+                .status
+                .clone()
+                .unwrap_or_else(|| format!("{} | {}", self.symbols_line(), strings.hints)),
+        };
+        let status = match self.replay {
+            Some(clock) => {
+                let pace = match clock.is_paused() {
+                    true => strings.replay_paused.to_string(),
+                    false => clock.speed().to_string(),
+                };
+                let replay = fill(strings.replay_status, &[&pace, &clock.offset()]);
+                format!("{} | {} | {}", replay, strings.replay_hints, status)
+            }
+            None => status,
+        };
+        let status = match self.book_rate.anomaly() {
+            Some(anomaly) => format!("{} | {}", status, anomaly_status(anomaly, strings)),
+            None => status,
+        };
+
+        let strip = self.mini_tickers.as_ref().map(|(_, receiver)| {
+            let tickers = receiver.borrow();
+            let items = self
+                .markets
+                .iter()
+                .map(|market| strip_item_view(market.symbol(), tickers.get(market.symbol())))
+                .collect();
+            strip_view(items, self.strip_started.elapsed())
+        });
+
+        AppFrame {
+            book,
+            quote,
+            strip,
+            status,
+            layout: self.layout,
+            locale: self.cfg.ui.locale,
+            picker: self.picker.as_ref().map(SymbolPicker::view),
+            about: self
+                .about
+                .then(|| about_view(self.cfg, &self.queues, strings)),
+            portfolio: self
+                .portfolio
+                .as_mut()
+                .map(|portfolio| portfolio_view(portfolio.value(), &self.cfg.ui.number_format)),
+        }
+    }
+
+    /// Finalize application - stop tasks, wait for them to finish, clear the state. In other words, graceful shutdown.
+    ///
+    /// Tasks that failed are logged, the ones that are not finished within `SHUTDOWN_GRACE` are left to the runtime.
+    pub async fn finalize(&mut self) -> BncResult<()> {
+        if let Some((_, _, start)) = self.pending_start.take() {
+            discard_start(start);
+        }
+        if let Some(loading) = self.queues_loading.take() {
+            loading.abort();
+        }
+        let mut markets: Vec<MarketManager> = self.markets.drain(..).collect();
+        markets.extend(self.pending_market.take().map(|(_, market)| market));
+        let mut conversion = self.conversion.take();
+        let mut portfolio = self.portfolio.take();
+        let mut mini_tickers = self.mini_tickers.take().map(|(manager, _)| manager);
+        let mut stats = self.stats.take().map(|(manager, _)| manager);
+
+        markets.iter().for_each(MarketManager::stop);
+        conversion.iter().for_each(ConversionManager::stop);
+        portfolio.iter().for_each(PortfolioManager::stop);
+        mini_tickers.iter().for_each(MiniTickerManager::stop);
+        stats.iter().for_each(StatsManager::stop);
+
+        let finished = tokio::time::timeout(SHUTDOWN_GRACE, async {
+            for market in markets.iter_mut() {
+                if let Err(err) = market.join().await {
+                    warn!("Feeds of {} failed: {}", market.symbol(), err);
+                }
+            }
+            if let Some(manager) = conversion.as_mut() {
+                if let Err(err) = manager.join().await {
+                    warn!("Conversion feed failed: {}", err);
+                }
+            }
+            if let Some(manager) = portfolio.as_mut() {
+                if let Err(err) = manager.join().await {
+                    warn!("Portfolio feeds failed: {}", err);
+                }
+            }
+            if let Some(manager) = mini_tickers.as_mut() {
+                if let Err(err) = manager.join().await {
+                    warn!("Mini tickers feed failed: {}", err);
+                }
+            }
+            if let Some(manager) = stats.as_mut() {
+                if let Err(err) = manager.join().await {
+                    warn!("Statistics feed failed: {}", err);
+                }
+            }
+        })
+        .await;
+        if finished.is_err() {
+            warn!("Feeds did not stop within {:?}.", SHUTDOWN_GRACE);
+        }
 
         self.should_quit = true;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::decimal::parse;
+    use crate::core::bnc::state::book::DisplayLevel;
+    use crate::core::bnc::state::portfolio::HoldingValue;
+    use crate::core::bnc::ws::worker::mini_ticker::SymbolMiniTicker;
+
+    #[test]
+    fn it_formats_portfolio_view() {
+        let portfolio = PortfolioValue {
+            quote: "USDT".into(),
+            holdings: vec![HoldingValue {
+                asset: "BTC".into(),
+                amount: parse("0.50").unwrap(),
+                price: Some(parse("21000.123456").unwrap()),
+                value: Some(parse("10500.061728").unwrap()),
+                change: None,
+            }],
+            total: parse("10500.061728").unwrap(),
+        };
+
+        let view = portfolio_view(portfolio.clone(), &NumberFormat::default());
+        assert_eq!(view.total, "10500.06 USDT");
+        let holding = &view.holdings[0];
+        assert_eq!(holding.amount, "0.5");
+        assert_eq!(holding.price, "21000.1235");
+        assert_eq!(holding.change, "-");
+
+        let compact = NumberFormat {
+            compact: true,
+            currency_symbol: Some("$".into()),
+            ..Default::default()
+        };
+        let view = portfolio_view(portfolio, &compact);
+        assert_eq!(view.total, "10.5k$");
+        assert_eq!(view.holdings[0].value, "10.5k");
+    }
+
+    #[test]
+    fn it_cycles_ticker_strip() {
+        let mut watched = WatchedTicker {
+            ticker: SymbolMiniTicker {
+                close: "99.4950".parse().unwrap(),
+                ..Default::default()
+            },
+            change: Default::default(),
+        };
+        let start = Instant::now();
+        watched.change.record(parse("100.50").unwrap(), start);
+        watched
+            .change
+            .record(parse("99.495").unwrap(), start + Duration::from_secs(1));
+
+        let item = strip_item_view("BTCUSDT", Some(&watched));
+        assert_eq!(item.price, "99.4950");
+        assert_eq!(item.change, "-1.00%");
+        assert_eq!(item.rising, Some(false));
+
+        let unknown = strip_item_view("ETHUSDT", None);
+        assert_eq!((unknown.price.as_str(), unknown.rising), ("-", None));
+
+        let symbols = |strip: StripView| {
+            strip
+                .items
+                .into_iter()
+                .map(|item| item.symbol)
+                .collect::<Vec<_>>()
+        };
+        let items = vec![item, unknown];
+        assert_eq!(
+            symbols(strip_view(items.clone(), Duration::ZERO)),
+            ["BTCUSDT", "ETHUSDT"]
+        );
+        assert_eq!(
+            symbols(strip_view(items, STRIP_STEP * 3)),
+            ["ETHUSDT", "BTCUSDT"]
+        );
+    }
+
+    #[test]
+    fn it_computes_book_spread() {
+        let level = |price: &str| DisplayLevel {
+            price: price.parse().unwrap(),
+            qty: "1".parse().unwrap(),
+            cum_qty: "1".parse().unwrap(),
+            cum_notional: Default::default(),
+        };
+        let view = book_view(
+            OrderBookDisplay {
+                asks: vec![level("16500.15"), level("16500.20")],
+                bids: vec![level("16500.10")],
+                cached: false,
+                grouping: None,
+            },
+            &NumberFormat::default(),
+        );
+        assert_eq!(view.spread.as_deref(), Some("0.05"));
+
+        let view = book_view(
+            OrderBookDisplay {
+                asks: vec![],
+                bids: vec![level("16500.10")],
+                cached: false,
+                grouping: Some("0.5".parse().unwrap()),
+            },
+            &NumberFormat::default(),
+        );
+        assert_eq!(view.spread, None);
+        assert_eq!(view.grouping.as_deref(), Some("0.5"));
+    }
+
+    #[test]
+    fn it_cycles_book_grouping() {
+        let mut grouping = None;
+        let mut seen = vec![];
+        for _ in 0..4 {
+            grouping = next_grouping(grouping, 3);
+            seen.push(grouping);
+        }
+        assert_eq!(seen, [Some(0), Some(1), Some(2), None]);
+        assert_eq!(next_grouping(None, 0), None);
+    }
+
+    #[tokio::test]
+    async fn it_starts_recording_on_command() {
+        let cfg: AppCfg = serde_json::from_value(serde_json::json!({})).unwrap();
+        let mut app = App::new(&cfg, vec![]);
+        assert!(!app.is_recording());
+
+        let reply = app.on_command(ControlCommand::StartRecording {}).await;
+        assert_eq!(reply, ControlReply::ok());
+        assert!(app.is_recording());
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::OnceLock;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Commit the binary is built from, with `-dirty` if the checkout had uncommitted changes.
+pub const GIT_SHA: &str = env!("VERGEN_GIT_SHA");
+pub const GIT_DIRTY: &str = env!("VERGEN_GIT_DIRTY");
+
+/// Moment of the build, in RFC 3339.
+pub const BUILD_TIMESTAMP: &str = env!("VERGEN_BUILD_TIMESTAMP");
+pub const TARGET: &str = env!("VERGEN_CARGO_TARGET_TRIPLE");
+
+/// Whether the binary is built with the debug info, `true` for the dev profile.
+pub const DEBUG: &str = env!("VERGEN_CARGO_DEBUG");
+
+/// Cargo features the binary is built with, separated by commas.
+pub const FEATURES: &str = env!("VERGEN_CARGO_FEATURES");
+
+pub fn commit() -> String {
+    match GIT_DIRTY {
+        "true" => format!("{}-dirty", GIT_SHA),
+        _ => GIT_SHA.to_string(),
+    }
+}
+
+pub fn features() -> &'static str {
+    match FEATURES {
+        "" => "none",
+        features => features,
+    }
+}
+
+pub fn profile() -> &'static str {
+    match DEBUG {
+        "true" => "debug",
+        _ => "release",
+    }
+}
+
+/// Full description of the build, printed by `--version` to be pasted into bug reports.
+pub fn long_version() -> &'static str {
+    static LONG_VERSION: OnceLock<String> = OnceLock::new();
+    LONG_VERSION.get_or_init(|| {
+        format!(
+            "{}\ncommit:   {}\nbuilt:    {}\ntarget:   {} ({})\nfeatures: {}",
+            VERSION,
+            commit(),
+            BUILD_TIMESTAMP,
+            TARGET,
+            profile(),
+            features()
+        )
+    })
+}
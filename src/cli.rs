@@ -0,0 +1,198 @@
+use crate::build_info;
+use crate::config::BASE_CONFIG_DIR;
+use crate::core::recorder::RecordFormat;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+/// Binance market data scraper - order books and best prices in the terminal, recorded or streamed.
+#[derive(Debug, Parser)]
+#[command(name = "bnc-scraper", version, long_version = build_info::long_version())]
+pub struct Cli {
+    /// Directory the configuration files are looked up in.
+    #[arg(long, global = true, default_value = BASE_CONFIG_DIR)]
+    pub config: String,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Symbols the command works with, separated by spaces or commas, e.g. `BTCUSDT ETHUSDT` or `btcusdt,ethusdt`.
+#[derive(Debug, Clone, Args)]
+pub struct SymbolsArgs {
+    /// Symbols to scrape. The UI asks for them if none are given, the rest of the commands take BTCUSDT.
+    #[arg(value_name = "SYMBOLS")]
+    pub symbols: Vec<String>,
+
+    /// Symbol to scrape, may be repeated. Same as the positional ones, handy in scripts.
+    #[arg(long = "symbol", short = 's', value_name = "SYMBOL")]
+    pub flagged: Vec<String>,
+}
+
+impl SymbolsArgs {
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty() && self.flagged.is_empty()
+    }
+
+    /// Symbols as a single line, to be split the same way the typed ones are.
+    pub fn line(&self) -> String {
+        self.flagged
+            .iter()
+            .chain(self.symbols.iter())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CliFormat {
+    Jsonl,
+    Csv,
+}
+
+impl From<CliFormat> for RecordFormat {
+    fn from(format: CliFormat) -> Self {
+        match format {
+            CliFormat::Jsonl => RecordFormat::Jsonl,
+            CliFormat::Csv => RecordFormat::Csv,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Display the order books and the best prices of the symbols.
+    Tui {
+        #[command(flatten)]
+        symbols: SymbolsArgs,
+
+        /// Record the raw updates of the symbols into the directory, alongside the UI.
+        #[arg(long, value_name = "DIR")]
+        record: Option<String>,
+
+        /// Milliseconds between screen updates, the configured ones by default.
+        #[arg(long, value_name = "MS")]
+        tick_rate: Option<u64>,
+    },
+
+    /// Record the raw updates of the symbols without any UI, until Ctrl+C is pressed.
+    Record {
+        #[command(flatten)]
+        symbols: SymbolsArgs,
+
+        /// Directory the records are stored in, the configured one by default.
+        #[arg(long, short, value_name = "DIR")]
+        output: Option<String>,
+
+        /// Format of the records, the configured one by default.
+        #[arg(long, value_enum)]
+        format: Option<CliFormat>,
+    },
+
+    /// Display the recorded updates of the symbols instead of the live ones.
+    Replay {
+        #[command(flatten)]
+        symbols: SymbolsArgs,
+
+        /// Directory the records are read from.
+        #[arg(long, short, value_name = "DIR", default_value = "records")]
+        input: String,
+    },
+
+    /// Fetch the order book snapshots of the symbols and print them as JSON lines.
+    Snapshot {
+        #[command(flatten)]
+        symbols: SymbolsArgs,
+
+        /// File the snapshots are written to instead of stdout.
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Stream every change of the best prices and the books of the symbols as JSON lines.
+    Headless {
+        #[command(flatten)]
+        symbols: SymbolsArgs,
+
+        /// File the updates are written to instead of stdout.
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Print the websocket connections the symbols would take, without connecting.
+    Plan {
+        #[command(flatten)]
+        symbols: SymbolsArgs,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("bnc-scraper").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn it_parses_commands() {
+        let cli = parse(&["tui", "btcusdt,ethusdt", "BNBUSDT"]);
+        assert_eq!(cli.config, BASE_CONFIG_DIR);
+        match cli.command {
+            Command::Tui {
+                symbols,
+                record,
+                tick_rate,
+            } => {
+                assert_eq!(symbols.line(), "btcusdt,ethusdt BNBUSDT");
+                assert_eq!(record, None);
+                assert_eq!(tick_rate, None);
+            }
+            command => panic!("Unexpected command {:?}", command),
+        }
+
+        let cli = parse(&[
+            "record",
+            "BTCUSDT",
+            "-o",
+            "/tmp/records",
+            "--format",
+            "csv",
+            "--config",
+            "prod",
+        ]);
+        assert_eq!(cli.config, "prod");
+        assert!(matches!(
+            cli.command,
+            Command::Record { output: Some(output), format: Some(CliFormat::Csv), .. } if output == "/tmp/records"
+        ));
+
+        let cli = parse(&[
+            "tui",
+            "--symbol",
+            "ETHUSDT",
+            "-s",
+            "BNBUSDT",
+            "--tick-rate",
+            "250",
+        ]);
+        match cli.command {
+            Command::Tui {
+                symbols, tick_rate, ..
+            } => {
+                assert_eq!(symbols.line(), "ETHUSDT BNBUSDT");
+                assert_eq!(tick_rate, Some(250));
+            }
+            command => panic!("Unexpected command {:?}", command),
+        }
+        assert!(
+            matches!(parse(&["tui"]).command, Command::Tui { symbols, .. } if symbols.is_empty())
+        );
+
+        let cli = parse(&["replay", "BTCUSDT"]);
+        assert!(matches!(cli.command, Command::Replay { input, .. } if input == "records"));
+
+        assert!(Cli::try_parse_from(["bnc-scraper"]).is_err());
+        assert!(Cli::try_parse_from(["bnc-scraper", "record", "--format", "xml"]).is_err());
+    }
+}
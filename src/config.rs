@@ -1,12 +1,19 @@
+use crate::control::ControlCfg;
 use crate::core::config::CoreCfg;
 use crate::core::logging::LogCfg;
+use crate::core::recorder::RecorderCfg;
+use crate::core::runtime::RuntimeCfg;
+use crate::instance::InstanceCfg;
+use crate::limits::LimitsCfg;
 use crate::ui::config::UICfg;
 use config::{Config, ConfigError, Environment, File};
 use derive_getters::Getters;
 use serde::Deserialize;
 use std::env;
+use std::path::Path;
 
-const BASE_CONFIG_DIR: &str = "config";
+/// Directory the configuration files are looked up in, unless another one is given.
+pub const BASE_CONFIG_DIR: &str = "config";
 
 #[derive(Getters, Debug, Clone, Deserialize)]
 pub struct AppCfg {
@@ -20,6 +27,30 @@ pub struct AppCfg {
 
     #[serde(default)]
     pub ui: UICfg,
+
+    /// Tokio runtime settings.
+    #[serde(default)]
+    pub runtime: RuntimeCfg,
+
+    /// Remote control endpoint settings.
+    #[serde(default)]
+    pub control: ControlCfg,
+
+    /// Instance lock settings.
+    #[serde(default)]
+    pub instance: InstanceCfg,
+
+    /// Recording of the raw updates, alongside the UI or instead of it.
+    #[serde(default)]
+    pub recorder: RecorderCfg,
+
+    /// Caps the application refuses to start or to add symbols beyond.
+    #[serde(default)]
+    pub limits: LimitsCfg,
+
+    /// Files the configuration is loaded from and the environment, if any of its variables are set.
+    #[serde(skip)]
+    pub sources: Vec<String>,
 }
 
 impl AppCfg {
@@ -27,15 +58,20 @@ impl AppCfg {
     ///
     /// 1) From default.* files;
     /// 2) From the environment file(environment is set via RUN_MODE env variable).
-    ///     e.g. If you have RUN_MODE=dev, it will load dev.*;
+    ///    e.g. If you have RUN_MODE=dev, it will load dev.*;
     /// 3) From local.* files;
     /// 4) Finally, from environment variables prefixed with standard prefix.
     ///
     pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(BASE_CONFIG_DIR)
+    }
+
+    /// Load the configuration the same way as [`AppCfg::load`] does, with the files looked up in the directory.
+    pub fn load_from(dir: &str) -> Result<Self, ConfigError> {
         let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "dev".into());
-        let default_config_file = format!("{}/default", BASE_CONFIG_DIR);
-        let environment_file = format!("{}/{}", BASE_CONFIG_DIR, run_mode);
-        let local_file = format!("{}/local", BASE_CONFIG_DIR);
+        let default_config_file = format!("{}/default", dir);
+        let environment_file = format!("{}/{}", dir, run_mode);
+        let local_file = format!("{}/local", dir);
 
         let s = Config::builder()
             .add_source(File::with_name(&default_config_file).required(false))
@@ -44,10 +80,33 @@ impl AppCfg {
             .add_source(Environment::with_prefix("app").separator("_"))
             .build()?;
 
-        s.try_deserialize()
+        let mut cfg: Self = s.try_deserialize()?;
+        cfg.sources = [default_config_file, environment_file, local_file]
+            .iter()
+            .filter_map(|name| found_file(name))
+            .collect();
+        if env::vars().any(|(key, _)| key.to_ascii_uppercase().starts_with(ENV_PREFIX)) {
+            cfg.sources
+                .push(format!("{}* environment variables", ENV_PREFIX));
+        }
+        Ok(cfg)
     }
 }
 
+/// Prefix of the environment variables overriding the configuration.
+const ENV_PREFIX: &str = "APP_";
+
+/// Extensions of the formats the configuration files may be written in.
+const CONFIG_EXTENSIONS: [&str; 7] = ["json", "toml", "yaml", "yml", "ini", "ron", "json5"];
+
+/// File of the configuration source, if there is one - sources are named without extensions.
+fn found_file(name: &str) -> Option<String> {
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|extension| format!("{}.{}", name, extension))
+        .find(|path| Path::new(path).is_file())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,4 +118,17 @@ mod tests {
     fn it_deserializes_default_app_config() {
         AppCfg::load().unwrap();
     }
+
+    #[test]
+    fn it_reports_config_sources() {
+        let dir = std::env::temp_dir().join(format!("bnc-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("local.json"), r#"{"ui":{"tick_rate":250}}"#).unwrap();
+
+        let cfg = AppCfg::load_from(dir.to_str().unwrap()).unwrap();
+        assert_eq!(cfg.ui.tick_rate, 250);
+        assert!(cfg.sources[0].ends_with("local.json"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
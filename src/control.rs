@@ -0,0 +1,385 @@
+use futures_util::StreamExt;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use subtle::ConstantTimeEq;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::codec::{FramedRead, LinesCodec};
+
+/// Longest command line accepted, the client is disconnected once it sends a longer one.
+const MAX_LINE_LENGTH: usize = 4096;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ControlCfg {
+    /// Whether the control endpoint is opened at all.
+    pub enabled: bool,
+
+    /// Address the control endpoint listens on, e.g. `127.0.0.1:7878`.
+    pub address: String,
+
+    /// Secret every command is to carry in its `token` field. Commands are not authenticated without it.
+    pub token: Option<String>,
+
+    /// Addresses the clients are accepted from, e.g. `["127.0.0.1", "192.168.1.20"]`. Empty accepts anyone.
+    pub allowed_peers: Vec<IpAddr>,
+}
+
+impl Default for ControlCfg {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:7878".into(),
+            token: None,
+            allowed_peers: vec![],
+        }
+    }
+}
+
+/// Command accepted by the control endpoint - a single json object per line,
+/// e.g. `{"command":"add_symbol","symbol":"ETHUSDT"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    AddSymbol { symbol: String },
+    RemoveSymbol { symbol: String },
+    SetAlert {},
+    StartRecording {},
+    Stop,
+}
+
+/// Command along with the secret it is authenticated by, e.g. `{"command":"stop","token":"secret"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ControlRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+
+    #[serde(flatten)]
+    command: ControlCommand,
+}
+
+/// Reply written back for each command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlReply {
+    pub ok: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ControlReply {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            error: None,
+        }
+    }
+
+    pub fn error(error: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(error.into()),
+        }
+    }
+}
+
+impl From<Result<(), String>> for ControlReply {
+    fn from(result: Result<(), String>) -> Self {
+        match result {
+            Ok(_) => Self::ok(),
+            Err(err) => Self::error(err),
+        }
+    }
+}
+
+/// Command received by the endpoint together with the way to reply to it.
+pub type ControlMessage = (ControlCommand, oneshot::Sender<ControlReply>);
+
+/// TCP endpoint that lets external scripts drive a running instance.
+///
+/// Commands are handed over to the application, see [`ControlServer::start`].
+pub struct ControlServer {
+    address: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl ControlServer {
+    /// Start listening for the commands. Received commands are sent to the returned receiver.
+    pub async fn start(cfg: &ControlCfg) -> io::Result<(Self, mpsc::Receiver<ControlMessage>)> {
+        let listener = TcpListener::bind(&cfg.address).await?;
+        let address = listener.local_addr()?;
+        info!("Control endpoint listens on {}.", address);
+
+        if cfg.token.is_none() && !address.ip().is_loopback() {
+            warn!(
+                "Control endpoint {} is reachable from the network without a token.",
+                address
+            );
+        }
+
+        let (commands, receiver) = mpsc::channel(16);
+        let (token, allowed_peers) = (cfg.token.clone(), cfg.allowed_peers.clone());
+        let task = tokio::task::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((_, peer))
+                        if !allowed_peers.is_empty() && !allowed_peers.contains(&peer.ip()) =>
+                    {
+                        warn!("Control client {} is not allowed, disconnected.", peer);
+                    }
+                    Ok((stream, peer)) => {
+                        debug!("Control client {} connected.", peer);
+                        let serving = serve_client(stream, token.clone(), commands.clone());
+                        tokio::task::spawn(serving);
+                    }
+                    Err(err) => warn!("Could not accept control client. Error: {}", err),
+                }
+            }
+        });
+
+        Ok((Self { address, task }, receiver))
+    }
+
+    /// Address the endpoint is actually bound to.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Send a single command to the control endpoint of a running instance and wait for the reply.
+///
+/// The token is to be the one the endpoint is configured with, if any.
+pub async fn send_command(
+    address: impl ToSocketAddrs,
+    token: Option<&str>,
+    command: &ControlCommand,
+) -> io::Result<ControlReply> {
+    let stream = TcpStream::connect(address).await?;
+    let (reader, mut writer) = stream.into_split();
+
+    let request = ControlRequest {
+        token: token.map(String::from),
+        command: command.clone(),
+    };
+    let mut request =
+        serde_json::to_string(&request).expect("Control command is always serializable.");
+    request.push('\n');
+    writer.write_all(request.as_bytes()).await?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Control endpoint closed connection",
+            )
+        })?;
+    serde_json::from_str(&line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Whether the request carries the configured token, compared in constant time so it can't be guessed by timing.
+fn is_authorized(request: &ControlRequest, token: Option<&str>) -> bool {
+    match (token, request.token.as_deref()) {
+        (None, _) => true,
+        (Some(token), Some(given)) => bool::from(token.as_bytes().ct_eq(given.as_bytes())),
+        (Some(_), None) => false,
+    }
+}
+
+/// Serve commands of a single client, one per line.
+///
+/// Client is disconnected once it is refused for the wrong token or sends a line longer than `MAX_LINE_LENGTH`.
+async fn serve_client(
+    stream: TcpStream,
+    token: Option<String>,
+    commands: mpsc::Sender<ControlMessage>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = FramedRead::new(reader, LinesCodec::new_with_max_length(MAX_LINE_LENGTH));
+
+    while let Some(line) = lines.next().await {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                warn!("Control client is disconnected. Error: {}", err);
+                return;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (reply, authorized) = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) if !is_authorized(&request, token.as_deref()) => {
+                warn!(
+                    "Control command {:?} is rejected - wrong token.",
+                    request.command
+                );
+                (ControlReply::error("Unauthorized"), false)
+            }
+            Ok(request) => (dispatch(request.command, &commands).await, true),
+            Err(err) => (
+                ControlReply::error(format!("Malformed command: {}", err)),
+                true,
+            ),
+        };
+
+        let mut reply =
+            serde_json::to_string(&reply).expect("Control reply is always serializable.");
+        reply.push('\n');
+        if writer.write_all(reply.as_bytes()).await.is_err() || !authorized {
+            return;
+        }
+    }
+}
+
+async fn dispatch(
+    command: ControlCommand,
+    commands: &mpsc::Sender<ControlMessage>,
+) -> ControlReply {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    if commands.send((command, reply_tx)).await.is_err() {
+        return ControlReply::error("Application is shutting down");
+    }
+    reply_rx
+        .await
+        .unwrap_or_else(|_| ControlReply::error("Application dropped the command"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_commands() {
+        let parse = |line: &str| serde_json::from_str::<ControlCommand>(line).unwrap();
+
+        assert_eq!(
+            parse(r#"{"command":"add_symbol","symbol":"ETHUSDT"}"#),
+            ControlCommand::AddSymbol {
+                symbol: "ETHUSDT".into()
+            }
+        );
+        assert_eq!(parse(r#"{"command":"stop"}"#), ControlCommand::Stop);
+        assert_eq!(
+            parse(r#"{"command":"set_alert","symbol":"BTCUSDT","above":"30000"}"#),
+            ControlCommand::SetAlert {}
+        );
+        assert!(serde_json::from_str::<ControlCommand>(r#"{"command":"reboot"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn it_hands_commands_to_application() {
+        let cfg = ControlCfg {
+            enabled: true,
+            address: "127.0.0.1:0".into(),
+            ..Default::default()
+        };
+        let (server, mut commands) = ControlServer::start(&cfg).await.unwrap();
+        tokio::task::spawn(async move {
+            while let Some((command, reply)) = commands.recv().await {
+                let _ = reply.send(match command {
+                    ControlCommand::Stop => ControlReply::ok(),
+                    _ => ControlReply::error("unsupported"),
+                });
+            }
+        });
+
+        let stream = TcpStream::connect(server.address()).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut replies = BufReader::new(reader).lines();
+        writer
+            .write_all(b"{\"command\":\"stop\"}\ngarbage\n")
+            .await
+            .unwrap();
+
+        let reply =
+            |line: Option<String>| serde_json::from_str::<ControlReply>(&line.unwrap()).unwrap();
+        assert_eq!(
+            reply(replies.next_line().await.unwrap()),
+            ControlReply::ok()
+        );
+        assert!(!reply(replies.next_line().await.unwrap()).ok);
+
+        let command = ControlCommand::AddSymbol {
+            symbol: "ETHUSDT".into(),
+        };
+        let reply = send_command(server.address(), None, &command)
+            .await
+            .unwrap();
+        assert_eq!(reply, ControlReply::error("unsupported"));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_unauthorized_clients() {
+        let cfg = ControlCfg {
+            enabled: true,
+            address: "127.0.0.1:0".into(),
+            token: Some("secret".into()),
+            allowed_peers: vec!["127.0.0.1".parse().unwrap()],
+        };
+        let (server, mut commands) = ControlServer::start(&cfg).await.unwrap();
+        tokio::task::spawn(async move {
+            while let Some((_, reply)) = commands.recv().await {
+                let _ = reply.send(ControlReply::ok());
+            }
+        });
+
+        let stop = ControlCommand::Stop;
+        let reply = send_command(server.address(), None, &stop).await.unwrap();
+        assert_eq!(reply, ControlReply::error("Unauthorized"));
+        let reply = send_command(server.address(), Some("guess"), &stop)
+            .await
+            .unwrap();
+        assert_eq!(reply, ControlReply::error("Unauthorized"));
+        let reply = send_command(server.address(), Some("secret"), &stop)
+            .await
+            .unwrap();
+        assert_eq!(reply, ControlReply::ok());
+
+        // Refused client can't keep guessing on the same connection.
+        let stream = TcpStream::connect(server.address()).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut replies = BufReader::new(reader).lines();
+        writer
+            .write_all(b"{\"command\":\"stop\",\"token\":\"guess\"}\n")
+            .await
+            .unwrap();
+        assert!(replies.next_line().await.unwrap().is_some());
+        let _ = writer
+            .write_all(b"{\"command\":\"stop\",\"token\":\"secret\"}\n")
+            .await;
+        assert_eq!(replies.next_line().await.ok().flatten(), None);
+
+        // Nor send the endless line.
+        let stream = TcpStream::connect(server.address()).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut replies = BufReader::new(reader).lines();
+        let _ = writer.write_all(&[b'x'; MAX_LINE_LENGTH + 1]).await;
+        assert_eq!(replies.next_line().await.ok().flatten(), None);
+
+        let cfg = ControlCfg {
+            allowed_peers: vec!["10.0.0.1".parse().unwrap()],
+            ..cfg
+        };
+        let (server, _commands) = ControlServer::start(&cfg).await.unwrap();
+        assert!(send_command(server.address(), Some("secret"), &stop)
+            .await
+            .is_err());
+    }
+}
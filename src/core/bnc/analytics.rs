@@ -0,0 +1,250 @@
+use crate::core::bnc::data::{Price, Quantity};
+use crate::core::bnc::state::book::OrderBook;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tokio::sync::watch::{channel, Receiver, Sender};
+
+pub type MetricsReceiver = Receiver<BookMetrics>;
+
+/// Basis points in one.
+const BPS: u32 = 10_000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnalyticsCfg {
+    /// Levels of each side the imbalance and the weighted mid are measured over.
+    pub levels: usize,
+
+    /// Distance from the mid price, in basis points, the liquidity is measured within.
+    pub band_bps: u32,
+}
+
+impl Default for AnalyticsCfg {
+    fn default() -> Self {
+        Self {
+            levels: 10,
+            band_bps: 10,
+        }
+    }
+}
+
+/// Resting liquidity of a single side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Liquidity {
+    pub qty: Decimal,
+
+    /// Sum of the levels' prices multiplied by their quantities, in the quote asset.
+    pub notional: Decimal,
+}
+
+impl Liquidity {
+    fn add(&mut self, price: &Price, qty: &Quantity) {
+        self.qty += qty.value();
+        self.notional += price.value() * qty.value();
+    }
+
+    /// Volume weighted average price of the levels, if there are any.
+    fn average_price(&self) -> Option<Decimal> {
+        self.notional.checked_div(self.qty)
+    }
+}
+
+/// Microstructure metrics of the order book. Metrics of a missing side are none.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookMetrics {
+    /// `(bids - asks) / (bids + asks)` of the quantities over the top levels, from -1 to 1.
+    ///
+    /// Positive one means there are more buyers than sellers.
+    pub imbalance: Option<Decimal>,
+
+    /// Mid of the volume weighted average prices of both sides' top levels.
+    pub weighted_mid: Option<Decimal>,
+
+    /// Mid of the best levels weighted by the quantity of the opposite side.
+    ///
+    /// It leans towards the side that is thinner and so is more likely to be taken first.
+    pub microprice: Option<Decimal>,
+
+    /// Bids within the band below the mid price.
+    pub bid_liquidity: Liquidity,
+
+    /// Asks within the band above the mid price.
+    pub ask_liquidity: Liquidity,
+}
+
+/// Sum of the top levels of the side.
+fn top_liquidity<'a>(
+    levels: impl Iterator<Item = (&'a Price, &'a Quantity)>,
+    depth: usize,
+) -> Liquidity {
+    let mut liquidity = Liquidity::default();
+    levels
+        .take(depth)
+        .for_each(|(price, qty)| liquidity.add(price, qty));
+    liquidity
+}
+
+/// Sum of the levels of the side as long as they are within the band, the best one first.
+fn band_liquidity<'a>(
+    levels: impl Iterator<Item = (&'a Price, &'a Quantity)>,
+    within: impl Fn(Decimal) -> bool,
+) -> Liquidity {
+    let mut liquidity = Liquidity::default();
+    levels
+        .take_while(|(price, _)| within(price.value()))
+        .for_each(|(price, qty)| liquidity.add(price, qty));
+    liquidity
+}
+
+impl BookMetrics {
+    /// Measure the metrics of the current state of the book.
+    pub fn measure(book: &OrderBook, cfg: &AnalyticsCfg) -> Self {
+        let (best_bid, best_ask) = match (book.bids().next(), book.asks().next()) {
+            (Some(bid), Some(ask)) => (bid, ask),
+            _ => return Self::default(),
+        };
+
+        let bids = top_liquidity(book.bids(), cfg.levels);
+        let asks = top_liquidity(book.asks(), cfg.levels);
+        let imbalance = (bids.qty - asks.qty).checked_div(bids.qty + asks.qty);
+        let weighted_mid = match (bids.average_price(), asks.average_price()) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / Decimal::TWO),
+            _ => None,
+        };
+
+        let ((bid, bid_qty), (ask, ask_qty)) = (best_bid, best_ask);
+        let microprice = (bid.value() * ask_qty.value() + ask.value() * bid_qty.value())
+            .checked_div(bid_qty.value() + ask_qty.value());
+
+        let mid = (bid.value() + ask.value()) / Decimal::TWO;
+        let band = mid * Decimal::from(cfg.band_bps) / Decimal::from(BPS);
+
+        Self {
+            imbalance,
+            weighted_mid,
+            microprice,
+            bid_liquidity: band_liquidity(book.bids(), |price| price >= mid - band),
+            ask_liquidity: band_liquidity(book.asks(), |price| price <= mid + band),
+        }
+    }
+}
+
+/// Measures the book on its every change and sends the metrics to the receivers.
+#[derive(Debug, Clone)]
+pub struct BookAnalytics {
+    cfg: AnalyticsCfg,
+    sender: Sender<BookMetrics>,
+}
+
+impl BookAnalytics {
+    pub fn new(cfg: AnalyticsCfg) -> Self {
+        Self {
+            cfg,
+            sender: channel(BookMetrics::default()).0,
+        }
+    }
+
+    pub fn subscribe(&self) -> MetricsReceiver {
+        self.sender.subscribe()
+    }
+
+    /// Measure the book and send its metrics, unless they are the same.
+    pub fn update(&self, book: &OrderBook) {
+        let metrics = BookMetrics::measure(book, &self.cfg);
+        self.sender.send_if_modified(|current| {
+            if *current == metrics {
+                return false;
+            }
+            *current = metrics;
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::data::InlineOrder;
+    use crate::core::bnc::snapshot::SymbolSnapshot;
+
+    fn order(level: &str, qty: &str) -> InlineOrder {
+        InlineOrder::new(level.parse().unwrap(), qty.parse().unwrap())
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn book() -> OrderBook {
+        OrderBook::from(SymbolSnapshot {
+            last_update_id: 1,
+            bids: vec![order("99", "4"), order("99.9", "1"), order("100", "3")],
+            asks: vec![order("100.1", "1"), order("100.2", "2"), order("101", "5")],
+        })
+    }
+
+    #[test]
+    fn it_measures_book_metrics() {
+        let cfg = AnalyticsCfg {
+            levels: 2,
+            band_bps: 10,
+        };
+        let metrics = BookMetrics::measure(&book(), &cfg);
+
+        // Top 2 levels: bids 3 + 1, asks 1 + 2.
+        assert_eq!(metrics.imbalance, Some(decimal("1") / decimal("7")));
+        // Bids average (300 + 99.9) / 4, asks average (100.1 + 200.4) / 3.
+        assert_eq!(
+            metrics.weighted_mid.map(|mid| mid.round_dp(4)),
+            Some(decimal("100.0708"))
+        );
+        // Thin best ask pulls the microprice up to it.
+        assert_eq!(metrics.microprice, Some(decimal("100.075")));
+
+        // Mid is 100.05, the band is 0.10005 around it.
+        assert_eq!(
+            metrics.bid_liquidity,
+            Liquidity {
+                qty: decimal("3"),
+                notional: decimal("300"),
+            }
+        );
+        assert_eq!(
+            metrics.ask_liquidity,
+            Liquidity {
+                qty: decimal("1"),
+                notional: decimal("100.1"),
+            }
+        );
+    }
+
+    #[test]
+    fn it_leaves_one_sided_book_unmeasured() {
+        let book = OrderBook::from(SymbolSnapshot {
+            last_update_id: 1,
+            bids: vec![order("100", "3")],
+            asks: vec![],
+        });
+
+        assert_eq!(
+            BookMetrics::measure(&book, &Default::default()),
+            BookMetrics::default()
+        );
+    }
+
+    #[test]
+    fn it_sends_changed_metrics_only() {
+        let analytics = BookAnalytics::new(Default::default());
+        let mut receiver = analytics.subscribe();
+
+        analytics.update(&book());
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(
+            receiver.borrow_and_update().microprice,
+            Some(decimal("100.075"))
+        );
+
+        analytics.update(&book());
+        assert!(!receiver.has_changed().unwrap());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::core::bnc::snapshot::SymbolSnapshot;
+use derive_getters::Getters;
+use log::debug;
+use serde::Deserialize;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Configuration of the on-disk snapshots cache.
+#[derive(Debug, Clone, Deserialize, Getters)]
+#[serde(default)]
+pub struct SnapshotCacheCfg {
+    pub enabled: bool,
+
+    /// Directory to store snapshots in. One file per symbol.
+    pub dir: String,
+
+    /// Milliseconds between persisting the current book.
+    pub interval: u64,
+
+    /// Milliseconds after which the stored snapshot is too old to be displayed. Never, if not set.
+    pub max_age: Option<u64>,
+}
+
+impl Default for SnapshotCacheCfg {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: String::from("cache"),
+            interval: 5000,
+            max_age: Some(600000),
+        }
+    }
+}
+
+/// Stores latest known order book of the symbol, so restarted application can display something immediately.
+#[derive(Debug, Clone)]
+pub struct SnapshotCache {
+    dir: PathBuf,
+    max_age: Option<Duration>,
+}
+
+impl SnapshotCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_age: None,
+        }
+    }
+
+    /// Snapshots stored longer than that ago are treated as absent ones.
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Returns None if cache is disabled by the configuration.
+    pub fn from_cfg(cfg: &SnapshotCacheCfg) -> Option<Self> {
+        cfg.enabled
+            .then(|| Self::new(&cfg.dir).with_max_age(cfg.max_age.map(Duration::from_millis)))
+    }
+
+    fn path(&self, symbol: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", symbol.to_ascii_uppercase()))
+    }
+
+    /// Load cached snapshot of the symbol. Missing, outdated or malformed cache is treated as absent one.
+    pub async fn load(&self, symbol: &str) -> Option<SymbolSnapshot> {
+        let path = self.path(symbol);
+        if let Some(max_age) = self.max_age {
+            let modified = tokio::fs::metadata(&path).await.ok()?.modified().ok()?;
+            // Modification time in the future is not outdated.
+            let age = modified.elapsed().unwrap_or_default();
+            if age > max_age {
+                debug!("Cached snapshot of {} is outdated by {:?}.", symbol, age);
+                return None;
+            }
+        }
+        let data = tokio::fs::read(path).await.ok()?;
+        match serde_json::from_slice(&data) {
+            Ok(snapshot) => Some(snapshot),
+            Err(err) => {
+                debug!("Cached snapshot of {} is malformed. Error: {}", symbol, err);
+                None
+            }
+        }
+    }
+
+    /// Persist snapshot of the symbol. File is replaced atomically so crash never leaves torn cache.
+    pub async fn store(&self, symbol: &str, snapshot: &SymbolSnapshot) -> io::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.path(symbol);
+        let tmp_path = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, serde_json::to_vec(snapshot)?).await?;
+        tokio::fs::rename(tmp_path, path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::data::InlineOrder;
+
+    #[tokio::test]
+    async fn it_restores_stored_snapshot() {
+        let cache = SnapshotCache::new(std::env::temp_dir().join("bnc-scraper-cache-test"));
+        let snapshot = SymbolSnapshot {
+            last_update_id: 42,
+            bids: vec![InlineOrder::new(
+                "1.5".parse().unwrap(),
+                "2".parse().unwrap(),
+            )],
+            asks: vec![InlineOrder::new(
+                "1.6".parse().unwrap(),
+                "3".parse().unwrap(),
+            )],
+        };
+
+        cache.store("TESTUSDT", &snapshot).await.unwrap();
+        let restored = cache.load("TESTUSDT").await.unwrap();
+
+        assert_eq!(restored.last_update_id, 42);
+        assert_eq!(restored.bids, snapshot.bids);
+        assert_eq!(restored.asks, snapshot.asks);
+        assert!(cache.load("MISSINGUSDT").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn it_ignores_outdated_snapshot() {
+        let cache = SnapshotCache::new(std::env::temp_dir().join("bnc-scraper-cache-age-test"));
+        let snapshot = SymbolSnapshot {
+            last_update_id: 42,
+            bids: vec![],
+            asks: vec![],
+        };
+        cache.store("TESTUSDT", &snapshot).await.unwrap();
+
+        let cache = cache.with_max_age(Some(Duration::from_secs(60)));
+        assert!(cache.load("TESTUSDT").await.is_some());
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let cache = cache.with_max_age(Some(Duration::from_millis(10)));
+        assert!(cache.load("TESTUSDT").await.is_none());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::core::bnc::config::BncCfg;
+use crate::core::bnc::error::BncResult;
+use crate::core::bnc::rest::BncRestClient;
+use crate::core::bnc::state::tasks::TaskGroup;
+use log::{debug, warn};
+use serde::Deserialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch::{channel, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClockSyncCfg {
+    /// Off by default, as nothing in the application consumes the drift yet.
+    pub enabled: bool,
+
+    /// Milliseconds between the measurements.
+    pub interval: u64,
+}
+
+impl Default for ClockSyncCfg {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 60_000,
+        }
+    }
+}
+
+/// Local time, in milliseconds since epoch.
+pub fn local_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Offset of the local clock from the binance one, measured by a single server time request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockDrift {
+    /// Milliseconds to add to the local time to get the server one, negative if the local clock is ahead.
+    pub offset: i64,
+
+    /// Time the request took.
+    pub round_trip: Duration,
+}
+
+impl ClockDrift {
+    /// Server time is taken to be read in the middle of the request, which was sent at the local time.
+    pub fn measure(sent: u64, round_trip: Duration, server_time: u64) -> Self {
+        let midpoint = sent + round_trip.as_millis() as u64 / 2;
+        Self {
+            offset: server_time as i64 - midpoint as i64,
+            round_trip,
+        }
+    }
+
+    /// Server time at the local time.
+    pub fn correct(&self, local: u64) -> u64 {
+        local.saturating_add_signed(self.offset)
+    }
+}
+
+pub type ClockDriftReceiver = Receiver<Option<ClockDrift>>;
+
+/// Periodically measures the drift of the local clock, so the timestamps compared to the binance ones are corrected.
+///
+/// Library-only for now: the application does not start it, it is meant for the consumers that compare
+/// local time to the event times of binance, e.g. latency metrics or signed requests.
+pub struct ClockSync {
+    client: BncRestClient,
+
+    /// Time between the measurements, none if the clock is not measured.
+    interval: Option<Duration>,
+    receiver: ClockDriftReceiver,
+    tasks: TaskGroup,
+
+    /// Stops the measurements.
+    cancel: CancellationToken,
+}
+
+impl ClockSync {
+    /// Fails only if configured proxy is malformed.
+    pub fn from_cfg(cfg: &BncCfg) -> BncResult<Self> {
+        Ok(Self {
+            client: BncRestClient::from_cfg(cfg)?,
+            interval: cfg
+                .clock_sync
+                .enabled
+                .then(|| Duration::from_millis(cfg.clock_sync.interval.max(1))),
+            receiver: channel(None).1,
+            tasks: TaskGroup::new("clock"),
+            cancel: Default::default(),
+        })
+    }
+
+    /// Start measuring the drift, the first time right away. Nothing is measured if the sync is disabled.
+    pub fn init(&mut self) -> ClockDriftReceiver {
+        self.cancel = CancellationToken::new();
+        self.tasks = TaskGroup::new("clock");
+        let (sender, receiver) = channel(None);
+        if let Some(interval) = self.interval {
+            let measurer =
+                drift_measurer(self.client.clone(), interval, sender, self.cancel.clone());
+            self.tasks.adopt("measurer", measurer);
+        }
+        self.receiver = receiver.clone();
+        receiver
+    }
+
+    /// The latest measured drift, none until the first measurement.
+    pub fn drift(&self) -> Option<ClockDrift> {
+        *self.receiver.borrow()
+    }
+
+    /// Binance time at the moment, the local one until the drift is measured.
+    pub fn now_millis(&self) -> u64 {
+        let local = local_millis();
+        match self.drift() {
+            Some(drift) => drift.correct(local),
+            None => local,
+        }
+    }
+
+    /// Time passed since the binance event happened, none if the event seems to come from the future.
+    pub fn latency(&self, event_time: u64) -> Option<Duration> {
+        self.now_millis()
+            .checked_sub(event_time)
+            .map(Duration::from_millis)
+    }
+
+    /// Stop measuring the drift, the latest one is kept.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Wait for the stopped measurer to finish.
+    pub async fn join(&mut self) -> BncResult<()> {
+        self.tasks.join().await
+    }
+}
+
+/// Measure the drift once per interval. Failed measurements keep the last known drift.
+fn drift_measurer(
+    client: BncRestClient,
+    interval: Duration,
+    sender: Sender<Option<ClockDrift>>,
+    cancel: CancellationToken,
+) -> JoinHandle<BncResult<()>> {
+    tokio::task::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = cancel.cancelled() => break,
+            }
+            let drift = tokio::select! {
+                drift = client.measure_clock_drift() => drift,
+                _ = cancel.cancelled() => break,
+            };
+            match drift {
+                Ok(drift) => {
+                    debug!(
+                        "Local clock is off by {}ms, measured in {:?}.",
+                        drift.offset, drift.round_trip
+                    );
+                    // Nobody watches the drift anymore.
+                    if sender.send(Some(drift)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => warn!("Could not measure the clock drift. Error: {}", err),
+            }
+        }
+        debug!("Clock drift measurer is stopped.");
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn it_measures_drift_at_the_middle_of_request() {
+        let drift = ClockDrift::measure(1_000, Duration::from_millis(100), 1_250);
+        assert_eq!(drift.offset, 200);
+        assert_eq!(drift.correct(2_000), 2_200);
+
+        let ahead = ClockDrift::measure(1_000, Duration::from_millis(40), 900);
+        assert_eq!(ahead.offset, -120);
+        assert_eq!(ahead.correct(5_000), 4_880);
+        assert_eq!(ahead.correct(100), 0);
+    }
+
+    #[tokio::test]
+    async fn it_syncs_with_local_clock_of_synthetic_market() -> Result<()> {
+        let cfg = BncCfg {
+            baseurl: "synthetic://1".into(),
+            clock_sync: ClockSyncCfg {
+                enabled: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut sync = ClockSync::from_cfg(&cfg)?;
+        assert_eq!(sync.drift(), None);
+
+        let mut receiver = sync.init();
+        receiver.changed().await?;
+        let drift = sync.drift().unwrap();
+        assert!(drift.offset.abs() < 1000);
+        assert!(sync.latency(local_millis() - 5_000).unwrap() >= Duration::from_secs(4));
+        assert_eq!(sync.latency(local_millis() + 60_000), None);
+
+        sync.stop();
+        sync.join().await?;
+        Ok(())
+    }
+}
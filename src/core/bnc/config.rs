@@ -1,19 +1,168 @@
+use super::analytics::AnalyticsCfg;
+use super::cache::SnapshotCacheCfg;
+use super::clock::ClockSyncCfg;
+use super::retry::RetryCfg;
+use super::state::anomaly::AnomalyCfg;
+use super::state::average::DEFAULT_AVG_PRICE_INTERVAL;
+use super::state::book::DEFAULT_BOOK_DEPTH;
+use super::state::volume::DEFAULT_RECENT_TRADES;
 use super::ws::config::WsCfg;
 use derive_getters::Getters;
 use serde::Deserialize;
 
+/// Market the symbols are traded on. Base urls are to point to the market's hosts, e.g. `https://fapi.binance.com`
+/// and `wss://fstream.binance.com` for the futures.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarketType {
+    #[default]
+    Spot,
+
+    /// USD-M futures - their depth updates are chained differently, see [`DepthSync`](super::state::book::DepthSync).
+    Futures,
+}
+
+impl MarketType {
+    /// Path of the order book snapshot endpoint.
+    pub fn depth_path(&self) -> &'static str {
+        match self {
+            Self::Spot => "/api/v3/depth",
+            Self::Futures => "/fapi/v1/depth",
+        }
+    }
+
+    /// Path of the trading rules endpoint.
+    pub fn exchange_info_path(&self) -> &'static str {
+        match self {
+            Self::Spot => "/api/v3/exchangeInfo",
+            Self::Futures => "/fapi/v1/exchangeInfo",
+        }
+    }
+
+    /// Path of the server time endpoint.
+    pub fn time_path(&self) -> &'static str {
+        match self {
+            Self::Spot => "/api/v3/time",
+            Self::Futures => "/fapi/v1/time",
+        }
+    }
+
+    /// Path of the aggregated trades endpoint.
+    pub fn agg_trades_path(&self) -> &'static str {
+        match self {
+            Self::Spot => "/api/v3/aggTrades",
+            Self::Futures => "/fapi/v1/aggTrades",
+        }
+    }
+
+    /// Path of the klines endpoint.
+    pub fn klines_path(&self) -> &'static str {
+        match self {
+            Self::Spot => "/api/v3/klines",
+            Self::Futures => "/fapi/v1/klines",
+        }
+    }
+
+    /// Path of the rolling 24hr statistics endpoint.
+    pub fn ticker_24h_path(&self) -> &'static str {
+        match self {
+            Self::Spot => "/api/v3/ticker/24hr",
+            Self::Futures => "/fapi/v1/ticker/24hr",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Getters, Deserialize)]
 pub struct BncCfg {
     pub baseurl: String,
 
+    #[serde(default)]
+    pub market: MarketType,
+
+    /// Alternative REST clusters to be tried if the main one is geo-blocked.
+    #[serde(default)]
+    pub fallback_baseurls: Vec<String>,
+
+    /// Proxy all the REST requests are sent through, e.g. `http://127.0.0.1:3128`.
+    #[serde(default)]
+    pub proxy: Option<String>,
+
+    /// Log every REST call(with secrets redacted) on the debug level.
+    #[serde(default)]
+    pub log_http: bool,
+
+    /// Milliseconds a REST call may take before it fails as a timed out one.
+    #[serde(default = "default_request_timeout")]
+    pub request_timeout: u64,
+
     pub ws: WsCfg,
+
+    #[serde(default)]
+    pub cache: SnapshotCacheCfg,
+
+    /// Retry policy of all the REST calls - server errors, rate limiting and timeouts are retried.
+    #[serde(default, alias = "snapshot_retry")]
+    pub rest_retry: RetryCfg,
+
+    /// Watching the rate of the order book updates for feed issues and volatility events.
+    #[serde(default)]
+    pub book_anomaly: AnomalyCfg,
+
+    /// Levels of each side of the order book passed to the display, e.g. 5, 20 or 50.
+    #[serde(default = "default_book_depth")]
+    pub book_depth: usize,
+
+    /// Microstructure metrics measured on every change of the order book.
+    #[serde(default)]
+    pub book_analytics: AnalyticsCfg,
+
+    /// Milliseconds between polls of the symbol's average price, zero to not poll it. Spot market only.
+    #[serde(default = "default_avg_price_interval")]
+    pub avg_price_interval: u64,
+
+    /// Recent trades fetched into the symbol's volume windows before the live ones, zero to start them empty.
+    #[serde(default = "default_recent_trades")]
+    pub recent_trades: u16,
+
+    /// Measuring the offset of the local clock from the binance one.
+    #[serde(default)]
+    pub clock_sync: ClockSyncCfg,
+}
+
+fn default_request_timeout() -> u64 {
+    10_000
+}
+
+fn default_book_depth() -> usize {
+    DEFAULT_BOOK_DEPTH
+}
+
+fn default_avg_price_interval() -> u64 {
+    DEFAULT_AVG_PRICE_INTERVAL
+}
+
+fn default_recent_trades() -> u16 {
+    DEFAULT_RECENT_TRADES
 }
 
 impl Default for BncCfg {
     fn default() -> Self {
         Self {
             baseurl: "https://api.binance.com".into(),
+            market: MarketType::default(),
+            fallback_baseurls: vec![],
+            proxy: None,
+            log_http: false,
+            request_timeout: default_request_timeout(),
             ws: Default::default(),
+            cache: Default::default(),
+            rest_retry: Default::default(),
+            book_anomaly: Default::default(),
+            book_depth: DEFAULT_BOOK_DEPTH,
+            book_analytics: Default::default(),
+            avg_price_interval: DEFAULT_AVG_PRICE_INTERVAL,
+            recent_trades: DEFAULT_RECENT_TRADES,
+            clock_sync: Default::default(),
         }
     }
 }
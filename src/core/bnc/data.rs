@@ -1,30 +1,103 @@
-use serde::{Deserialize, Serialize};
+use crate::core::bnc::decimal::parse;
+use crate::core::bnc::error::{BncError, BncResult};
+use rust_decimal::Decimal;
+use serde::de::{Error as DeError, IgnoredAny};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 /// UpdateID is supplied in most of the required binance API parts, so it's better to include it here.
 pub type UpdateId = u64;
 
-pub type PriceLevel = String;
-pub type Qty = String;
+/// Binance sends decimals as strings to keep their precision, so are they (de)serialized.
+///
+/// Precision of the received value is kept as well, e.g. `0.01000000` is displayed as is.
+macro_rules! decimal_wrapper {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub Decimal);
+
+        impl $name {
+            pub fn value(&self) -> Decimal {
+                self.0
+            }
+
+            pub fn is_zero(&self) -> bool {
+                self.0.is_zero()
+            }
+        }
+
+        impl From<Decimal> for $name {
+            fn from(value: Decimal) -> Self {
+                Self(value)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = BncError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                parse(value).map(Self)
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                // Padded, so it can be aligned as the plain strings.
+                f.pad(&self.0.to_string())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = Cow::<str>::deserialize(deserializer)?;
+                value.parse().map_err(D::Error::custom)
+            }
+        }
+    };
+}
+
+decimal_wrapper!(
+    /// Price level of the order, trade, etc.
+    Price
+);
+
+decimal_wrapper!(
+    /// Amount of the base asset.
+    Quantity
+);
 
 /// Binance order representation - holds price and amount.
 ///
 /// Again, due to strange binance implementation we are to use tuple syntax here
 /// as they've provided arrays instead of json in some places.
-#[derive(Deserialize, Default, Clone, Debug, PartialOrd, PartialEq, Eq, Ord)]
-pub struct InlineOrder(pub PriceLevel, pub Qty);
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialOrd, PartialEq, Eq, Ord)]
+pub struct InlineOrder(pub Price, pub Quantity);
 
 impl InlineOrder {
-    pub fn new(price_lvl: PriceLevel, qty: Qty) -> Self {
+    pub fn new(price_lvl: Price, qty: Quantity) -> Self {
         Self(price_lvl, qty)
     }
 
-    pub fn level(&self) -> &str {
-        &self.0
+    pub fn level(&self) -> Price {
+        self.0
     }
 
-    pub fn qty(&self) -> &str {
-        &self.1
+    pub fn qty(&self) -> Quantity {
+        self.1
+    }
+
+    /// Value of the order in the quote asset.
+    pub fn notional(&self) -> Decimal {
+        self.0.value() * self.1.value()
     }
 }
 
@@ -38,3 +111,395 @@ impl Display for InlineOrder {
 pub struct SymbolContainer<'a> {
     pub symbol: &'a str,
 }
+
+/// Trading rule of the symbol. Only the ones the application is interested in are parsed.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "filterType")]
+pub enum SymbolFilter {
+    /// Prices are to be multiples of the tick size.
+    #[serde(rename = "PRICE_FILTER", rename_all = "camelCase")]
+    Price { tick_size: Price },
+
+    /// Quantities are to be multiples of the step size.
+    #[serde(rename = "LOT_SIZE", rename_all = "camelCase")]
+    LotSize { step_size: Quantity },
+
+    #[serde(other)]
+    Other,
+}
+
+/// Symbol listed by the exchange, only the parts the application is interested in.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeSymbol {
+    pub symbol: String,
+
+    /// `TRADING` for the active symbols, e.g. `BREAK` for the suspended ones.
+    pub status: String,
+
+    #[serde(default)]
+    pub base_asset: String,
+
+    #[serde(default)]
+    pub quote_asset: String,
+
+    #[serde(default)]
+    pub filters: Vec<SymbolFilter>,
+}
+
+impl ExchangeSymbol {
+    /// Symbol without any known rules, e.g. the synthetic one.
+    pub fn trading(symbol: String) -> Self {
+        Self {
+            symbol,
+            status: "TRADING".into(),
+            base_asset: String::new(),
+            quote_asset: String::new(),
+            filters: vec![],
+        }
+    }
+
+    pub fn is_trading(&self) -> bool {
+        self.status == "TRADING"
+    }
+
+    /// Step of the symbol's prices, if the exchange has told it.
+    pub fn tick_size(&self) -> Option<Price> {
+        self.filters.iter().find_map(|filter| match filter {
+            SymbolFilter::Price { tick_size } => Some(*tick_size),
+            _ => None,
+        })
+    }
+
+    /// Step of the symbol's quantities, if the exchange has told it.
+    pub fn step_size(&self) -> Option<Quantity> {
+        self.filters.iter().find_map(|filter| match filter {
+            SymbolFilter::LotSize { step_size } => Some(*step_size),
+            _ => None,
+        })
+    }
+}
+
+/// Trading rules of the exchange, see `/api/v3/exchangeInfo`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExchangeInfo {
+    pub symbols: Vec<ExchangeSymbol>,
+}
+
+impl ExchangeInfo {
+    pub fn symbol(&self, symbol: &str) -> Option<&ExchangeSymbol> {
+        self.symbols.iter().find(|listed| listed.symbol == symbol)
+    }
+
+    /// Symbols trading at the moment, in order.
+    pub fn trading_symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.is_trading())
+            .map(|symbol| symbol.symbol.clone())
+            .collect();
+        symbols.sort_unstable();
+        symbols
+    }
+
+    /// Make sure the symbol is listed and trading, so its streams are not silent.
+    pub fn validate(&self, symbol: &str) -> BncResult<()> {
+        match self.symbol(symbol) {
+            Some(listed) if listed.is_trading() => Ok(()),
+            Some(listed) => Err(BncError::SymbolNotTrading {
+                symbol: symbol.to_string(),
+                status: listed.status.clone(),
+            }),
+            None => Err(BncError::UnknownSymbol {
+                symbol: symbol.to_string(),
+            }),
+        }
+    }
+}
+
+/// Rolling 24 hours statistics of the symbol, see `/api/v3/ticker/24hr`.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolTicker24h {
+    pub symbol: String,
+    pub price_change: Price,
+    pub price_change_percent: String,
+    pub weighted_avg_price: Price,
+    pub last_price: Price,
+
+    #[serde(rename = "highPrice")]
+    pub high: Price,
+
+    #[serde(rename = "lowPrice")]
+    pub low: Price,
+
+    /// Traded volume of the base asset.
+    pub volume: Quantity,
+
+    /// Traded volume of the quote asset.
+    pub quote_volume: Quantity,
+
+    /// End of the statistics' window, in milliseconds since epoch.
+    pub close_time: u64,
+}
+
+/// Average price of the symbol over the last minutes, see `/api/v3/avgPrice`.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AveragePrice {
+    /// Minutes the price is averaged over, 5 at the moment.
+    pub mins: u64,
+    pub price: Price,
+
+    /// Time of the last trade the average includes, in milliseconds since epoch.
+    #[serde(default)]
+    pub close_time: u64,
+}
+
+/// Length of the kline, e.g. `1m` or `1d`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KlineInterval {
+    #[serde(rename = "1s")]
+    Second,
+    #[serde(rename = "1m")]
+    Minute,
+    #[serde(rename = "3m")]
+    Minutes3,
+    #[serde(rename = "5m")]
+    Minutes5,
+    #[serde(rename = "15m")]
+    Minutes15,
+    #[serde(rename = "30m")]
+    Minutes30,
+    #[serde(rename = "1h")]
+    Hour,
+    #[serde(rename = "2h")]
+    Hours2,
+    #[serde(rename = "4h")]
+    Hours4,
+    #[serde(rename = "6h")]
+    Hours6,
+    #[serde(rename = "8h")]
+    Hours8,
+    #[serde(rename = "12h")]
+    Hours12,
+    #[serde(rename = "1d")]
+    Day,
+    #[serde(rename = "3d")]
+    Days3,
+    #[serde(rename = "1w")]
+    Week,
+    #[serde(rename = "1M")]
+    Month,
+}
+
+impl KlineInterval {
+    /// Length of the interval in milliseconds. Month is taken as 30 days, while binance follows the calendar.
+    pub fn millis(&self) -> u64 {
+        const MINUTE: u64 = 60_000;
+        const HOUR: u64 = 60 * MINUTE;
+        const DAY: u64 = 24 * HOUR;
+        match self {
+            Self::Second => 1000,
+            Self::Minute => MINUTE,
+            Self::Minutes3 => 3 * MINUTE,
+            Self::Minutes5 => 5 * MINUTE,
+            Self::Minutes15 => 15 * MINUTE,
+            Self::Minutes30 => 30 * MINUTE,
+            Self::Hour => HOUR,
+            Self::Hours2 => 2 * HOUR,
+            Self::Hours4 => 4 * HOUR,
+            Self::Hours6 => 6 * HOUR,
+            Self::Hours8 => 8 * HOUR,
+            Self::Hours12 => 12 * HOUR,
+            Self::Day => DAY,
+            Self::Days3 => 3 * DAY,
+            Self::Week => 7 * DAY,
+            Self::Month => 30 * DAY,
+        }
+    }
+}
+
+/// Candlestick of the symbol's trades, see `/api/v3/klines`. Times are in milliseconds since epoch.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "KlineRow")]
+pub struct Kline {
+    pub open_time: u64,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+
+    /// Traded volume of the base asset.
+    pub volume: Quantity,
+    pub close_time: u64,
+
+    /// Traded volume of the quote asset.
+    pub quote_volume: Quantity,
+    pub trades: u64,
+    pub taker_buy_volume: Quantity,
+    pub taker_buy_quote_volume: Quantity,
+}
+
+/// Binance sends klines as arrays, the last value is unused.
+#[derive(Deserialize)]
+struct KlineRow(
+    u64,
+    Price,
+    Price,
+    Price,
+    Price,
+    Quantity,
+    u64,
+    Quantity,
+    u64,
+    Quantity,
+    Quantity,
+    IgnoredAny,
+);
+
+impl From<KlineRow> for Kline {
+    fn from(row: KlineRow) -> Self {
+        Self {
+            open_time: row.0,
+            open: row.1,
+            high: row.2,
+            low: row.3,
+            close: row.4,
+            volume: row.5,
+            close_time: row.6,
+            quote_volume: row.7,
+            trades: row.8,
+            taker_buy_volume: row.9,
+            taker_buy_quote_volume: row.10,
+        }
+    }
+}
+
+/// Current time of the exchange, see `/api/v3/time`.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerTime {
+    /// Milliseconds since epoch.
+    pub server_time: u64,
+}
+
+/// Query of the latest trades.
+#[derive(Serialize, Debug, Clone)]
+pub struct RecentTradesQuery<'a> {
+    pub symbol: &'a str,
+
+    /// Amount of trades, 500 by default, 1000 at most.
+    pub limit: u16,
+}
+
+/// Query of the klines - the latest ones are sent unless the window is given.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct KlinesQuery<'a> {
+    pub symbol: &'a str,
+    pub interval: KlineInterval,
+
+    /// Amount of klines, 500 by default, 1000 at most.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_exchange_info() {
+        let info: ExchangeInfo = serde_json::from_str(
+            r#"{"symbols":[{"symbol":"BTCUSDT","status":"TRADING","baseAsset":"BTC","quoteAsset":"USDT",
+                "filters":[{"filterType":"PRICE_FILTER","minPrice":"0.01000000","tickSize":"0.01000000"},
+                {"filterType":"LOT_SIZE","minQty":"0.00001000","stepSize":"0.00001000"},
+                {"filterType":"ICEBERG_PARTS","limit":10}]},
+                {"symbol":"LUNAUSDT","status":"BREAK"}]}"#,
+        )
+        .unwrap();
+
+        let btc = info.symbol("BTCUSDT").unwrap();
+        assert_eq!(btc.quote_asset, "USDT");
+        assert_eq!(btc.tick_size().unwrap().to_string(), "0.01000000");
+        assert_eq!(btc.step_size().unwrap().to_string(), "0.00001000");
+        assert_eq!(info.symbol("LUNAUSDT").unwrap().tick_size(), None);
+
+        assert_eq!(info.trading_symbols(), vec!["BTCUSDT"]);
+        assert!(info.validate("BTCUSDT").is_ok());
+        assert!(matches!(
+            info.validate("LUNAUSDT"),
+            Err(BncError::SymbolNotTrading { status, .. }) if status == "BREAK"
+        ));
+        assert!(matches!(
+            info.validate("BTCUSD"),
+            Err(BncError::UnknownSymbol { .. })
+        ));
+    }
+
+    #[test]
+    fn it_parses_ticker_24h() {
+        let ticker: SymbolTicker24h = serde_json::from_str(
+            r#"{"symbol":"BTCUSDT","priceChange":"-120.50","priceChangePercent":"-0.725",
+                "weightedAvgPrice":"16530.12","prevClosePrice":"16620.00","lastPrice":"16499.50",
+                "lastQty":"0.01","openPrice":"16620.00","highPrice":"16700.00","lowPrice":"16450.00",
+                "volume":"12000.5","quoteVolume":"198361000.1","openTime":1672429382136,
+                "closeTime":1672515782136,"firstId":100,"lastId":200,"count":101}"#,
+        )
+        .unwrap();
+
+        assert_eq!(ticker.price_change_percent, "-0.725");
+        assert_eq!(ticker.high.to_string(), "16700.00");
+        assert_eq!(ticker.low.to_string(), "16450.00");
+        assert_eq!(ticker.quote_volume.to_string(), "198361000.1");
+        assert_eq!(ticker.close_time, 1672515782136);
+    }
+
+    #[test]
+    fn it_parses_klines() {
+        let klines: Vec<Kline> = serde_json::from_str(
+            r#"[[1499040000000,"0.01634790","0.80000000","0.01575800","0.01577100","148976.11427815",
+                1499644799999,"2434.19055334",308,"1756.87402397","28.46694368","0"]]"#,
+        )
+        .unwrap();
+
+        let kline = &klines[0];
+        assert_eq!(kline.open_time, 1499040000000);
+        assert_eq!(kline.high.to_string(), "0.80000000");
+        assert_eq!(kline.close.to_string(), "0.01577100");
+        assert_eq!(kline.quote_volume.to_string(), "2434.19055334");
+        assert_eq!(kline.trades, 308);
+        assert_eq!(kline.taker_buy_quote_volume.to_string(), "28.46694368");
+    }
+
+    #[test]
+    fn it_keeps_precision_of_decimals() {
+        let order: InlineOrder =
+            serde_json::from_str(r#"["16500.10000000","0.00100000"]"#).unwrap();
+
+        assert_eq!(order.to_string(), "16500.10000000/0.00100000");
+        assert_eq!(
+            serde_json::to_string(&order).unwrap(),
+            r#"["16500.10000000","0.00100000"]"#
+        );
+        assert_eq!(order.notional(), "16.5001".parse().unwrap());
+    }
+
+    #[test]
+    fn it_orders_prices_numerically() {
+        let low: Price = "9.5".parse().unwrap();
+        let high: Price = "10.00".parse().unwrap();
+
+        assert!(low < high);
+        assert_eq!(high, "10".parse().unwrap());
+        assert!(serde_json::from_str::<Quantity>(r#""1,5""#).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::core::bnc::error::{BncError, BncResult};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Suffixes of the thousand powers compact values are shortened with, the smallest one first.
+const COMPACT_SUFFIXES: [&str; 4] = ["k", "M", "B", "T"];
+
+/// Significant digits of the compact values.
+const COMPACT_DIGITS: u32 = 3;
+
+/// Direction price level is rounded in.
+///
+/// Grouped books are usually built with bids rounded down and asks rounded up, so the grouped level never
+/// looks better than the real orders behind it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    Floor,
+    Ceil,
+    Nearest,
+}
+
+impl RoundingMode {
+    /// Mode traders expect the bids to be grouped with.
+    pub fn for_bids() -> Self {
+        Self::Floor
+    }
+
+    /// Mode traders expect the asks to be grouped with.
+    pub fn for_asks() -> Self {
+        Self::Ceil
+    }
+
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            Self::Floor => RoundingStrategy::ToNegativeInfinity,
+            Self::Ceil => RoundingStrategy::ToPositiveInfinity,
+            Self::Nearest => RoundingStrategy::MidpointAwayFromZero,
+        }
+    }
+}
+
+/// Parse binance decimal, e.g. price level or quantity.
+pub fn parse(value: &str) -> BncResult<Decimal> {
+    Decimal::from_str(value).map_err(|_| BncError::InvalidDecimal {
+        value: value.to_string(),
+    })
+}
+
+/// Round value to the multiple of the step. Zero step leaves the value untouched.
+pub fn round_to_step(value: Decimal, step: Decimal, mode: RoundingMode) -> Decimal {
+    if step.is_zero() {
+        return value;
+    }
+    (value / step).round_dp_with_strategy(0, mode.strategy()) * step
+}
+
+/// Round price level to the multiple of the step, formatted with the step's precision.
+pub fn round_level(level: &str, step: &str, mode: RoundingMode) -> BncResult<String> {
+    let step = parse(step)?;
+    let rounded = round_to_step(parse(level)?, step, mode);
+    Ok(format!("{:.*}", step.scale() as usize, rounded))
+}
+
+/// Format value with the given number of decimal places.
+pub fn format_decimal(value: Decimal, decimals: u32, mode: RoundingMode) -> String {
+    let rounded = value.round_dp_with_strategy(decimals, mode.strategy());
+    format!("{:.*}", decimals as usize, rounded)
+}
+
+/// Format value with three significant digits and the suffix of its magnitude, e.g. `12.4k` or `1.02M`.
+///
+/// Values below a thousand are only stripped of their trailing zeros.
+pub fn format_compact(value: Decimal) -> String {
+    let thousand = Decimal::ONE_THOUSAND;
+    if value.abs() < thousand {
+        return value.normalize().to_string();
+    }
+
+    // Rounded first, so 999_999 becomes 1.00M rather than 1000k.
+    let mut scaled = value
+        .round_sf_with_strategy(COMPACT_DIGITS, RoundingMode::Nearest.strategy())
+        .unwrap_or(value);
+    let mut suffix = COMPACT_SUFFIXES[0];
+    for next in COMPACT_SUFFIXES {
+        if scaled.abs() < thousand {
+            break;
+        }
+        scaled /= thousand;
+        suffix = next;
+    }
+
+    let integer_digits = scaled.abs().trunc().to_string().len() as u32;
+    let decimals = COMPACT_DIGITS.saturating_sub(integer_digits);
+    format!("{:.*}{}", decimals as usize, scaled, suffix)
+}
+
+/// Format price level with the given number of decimal places.
+pub fn format_level(level: &str, decimals: u32, mode: RoundingMode) -> BncResult<String> {
+    Ok(format_decimal(parse(level)?, decimals, mode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_rounds_to_step() {
+        assert_eq!(
+            round_level("27123.45000000", "0.5", RoundingMode::for_bids()).unwrap(),
+            "27123.0"
+        );
+        assert_eq!(
+            round_level("27123.45000000", "0.5", RoundingMode::for_asks()).unwrap(),
+            "27123.5"
+        );
+        assert_eq!(
+            round_level("27123.24", "0.5", RoundingMode::Nearest).unwrap(),
+            "27123.0"
+        );
+        assert_eq!(
+            round_level("27123.45", "10", RoundingMode::Nearest).unwrap(),
+            "27120"
+        );
+    }
+
+    #[test]
+    fn it_keeps_levels_already_on_step() {
+        for mode in [
+            RoundingMode::Floor,
+            RoundingMode::Ceil,
+            RoundingMode::Nearest,
+        ] {
+            assert_eq!(round_level("100.50", "0.25", mode).unwrap(), "100.50");
+        }
+    }
+
+    #[test]
+    fn it_formats_levels() {
+        assert_eq!(
+            format_level("0.00012345", 6, RoundingMode::Floor).unwrap(),
+            "0.000123"
+        );
+        assert_eq!(
+            format_level("0.00012345", 6, RoundingMode::Ceil).unwrap(),
+            "0.000124"
+        );
+        assert_eq!(
+            format_level("1.5", 3, RoundingMode::Nearest).unwrap(),
+            "1.500"
+        );
+    }
+
+    #[test]
+    fn it_formats_compact_values() {
+        let compact = |value: &str| format_compact(parse(value).unwrap());
+        assert_eq!(compact("0.50000000"), "0.5");
+        assert_eq!(compact("999.12"), "999.12");
+        assert_eq!(compact("12420.5"), "12.4k");
+        assert_eq!(compact("1020000"), "1.02M");
+        assert_eq!(compact("999999"), "1.00M");
+        assert_eq!(compact("-153000"), "-153k");
+        assert_eq!(compact("4200000000000000"), "4200T");
+    }
+
+    #[test]
+    fn it_rejects_malformed_levels() {
+        assert!(matches!(
+            round_level("abc", "0.5", RoundingMode::Floor),
+            Err(BncError::InvalidDecimal { .. })
+        ));
+    }
+}
@@ -1,6 +1,65 @@
 use reqwest::Error;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
 use thiserror::Error;
 
+/// Standard error envelope returned by the binance REST API, e.g. `{"code":-1121,"msg":"Invalid symbol."}`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ApiErrorPayload {
+    pub code: i64,
+    pub msg: String,
+}
+
+/// Binance error codes we know how to explain.
+///
+/// Full list is here: https://binance-docs.github.io/apidocs/spot/en/#error-codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorCode {
+    Disconnected,
+    TooManyRequests,
+    InvalidTimestamp,
+    IllegalChars,
+    MandatoryParamMissing,
+    BadPrecision,
+    InvalidSymbol,
+    Unknown(i64),
+}
+
+impl From<i64> for ApiErrorCode {
+    fn from(code: i64) -> Self {
+        match code {
+            -1001 => Self::Disconnected,
+            -1003 => Self::TooManyRequests,
+            -1021 => Self::InvalidTimestamp,
+            -1100 => Self::IllegalChars,
+            -1102 => Self::MandatoryParamMissing,
+            -1111 => Self::BadPrecision,
+            -1121 => Self::InvalidSymbol,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl Display for ApiErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Disconnected => f.write_str("Binance internal error, try again later"),
+            Self::TooManyRequests => f.write_str("Request limit is exceeded, slow down"),
+            Self::InvalidTimestamp => {
+                f.write_str("Request timestamp is outside of the receive window, check local clock")
+            }
+            Self::IllegalChars => f.write_str("Request contains illegal characters"),
+            Self::MandatoryParamMissing => f.write_str("Mandatory parameter is missing"),
+            Self::BadPrecision => f.write_str("Value has too many decimal places"),
+            Self::InvalidSymbol => {
+                f.write_str("Symbol is not traded on binance, check its spelling")
+            }
+            Self::Unknown(code) => write!(f, "Binance error {}", code),
+        }
+    }
+}
+
 /// Errors that BNC fetch part can return.
 #[derive(Error, Debug)]
 pub enum BncError {
@@ -11,13 +70,97 @@ pub enum BncError {
     SerdeError(serde_json::Error),
 
     #[error("Interaction with WS module failed. Origin error: {}", .0)]
-    WsError(tokio_tungstenite::tungstenite::Error),
+    WsError(Box<tokio_tungstenite::tungstenite::Error>),
 
     #[error("Could not send thread's data to the thread's master.")]
     DataTransmitError,
 
     #[error("Data was rejected by predicate. Possibly some conditions were unmet.")]
     DataRejected,
+
+    #[error("Binance rejected the request with HTTP {}, its API is probably unavailable in your region. \
+    Set core.bnc.fallback_baseurls(e.g. https://data-api.binance.vision) or core.bnc.proxy to get around.", .status)]
+    RegionBlocked { status: u16 },
+
+    #[error("Binance responded with unexpected HTTP {}.", .status)]
+    UnexpectedStatus { status: u16 },
+
+    /// HTTP 429 asks to back off, HTTP 418 means the IP is already banned for exceeding the limits.
+    #[error("Binance limited the request rate with HTTP {}, retry allowed after {:?}.", .status, .retry_after)]
+    RateLimited {
+        status: u16,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("{}. Binance message: {}", .code, .msg)]
+    Api { code: ApiErrorCode, msg: String },
+
+    #[error("Symbol {} is not listed on binance, check its spelling.", .symbol)]
+    UnknownSymbol { symbol: String },
+
+    #[error("Symbol {} is not trading at the moment, its status is {}.", .symbol, .status)]
+    SymbolNotTrading { symbol: String, status: String },
+
+    #[error("Binance entity is malformed: {}", .0)]
+    MalformedData(String),
+
+    #[error("Could not parse decimal value {:?}.", .value)]
+    InvalidDecimal { value: String },
+
+    #[error("All {} attempts failed. Errors: {}", .errors.len(), join_errors(.errors))]
+    RetriesExhausted { errors: Vec<BncError> },
+
+    #[error("Task panicked or was aborted: {}", .0)]
+    TaskFailed(String),
+
+    #[error("Refused to exceed the configured resource limit: {}", .0)]
+    LimitExceeded(String),
+
+    #[error("Combined connection has no streams to connect to.")]
+    NoStreams,
+}
+
+fn join_errors(errors: &[BncError]) -> String {
+    errors
+        .iter()
+        .enumerate()
+        .map(|(i, err)| format!("#{}: {}", i + 1, err))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl BncError {
+    /// Whether the operation that failed with this error is worth retrying.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::RequestError(err) => {
+                err.is_timeout()
+                    || err.is_connect()
+                    || err
+                        .status()
+                        .is_some_and(|status| status.is_server_error() || status.as_u16() == 429)
+            }
+            Self::WsError(_) => true,
+            Self::UnexpectedStatus { status } => *status >= 500,
+            // Retrying while banned only prolongs the ban.
+            Self::RateLimited { status, .. } => *status == 429,
+            Self::Api { code, .. } => matches!(
+                code,
+                ApiErrorCode::Disconnected | ApiErrorCode::TooManyRequests
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl BncError {
+    /// Time binance asked to wait before the next request, if any.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
 }
 
 pub type BncResult<T> = Result<T, BncError>;
@@ -28,6 +171,15 @@ impl From<reqwest::Error> for BncError {
     }
 }
 
+impl From<ApiErrorPayload> for BncError {
+    fn from(payload: ApiErrorPayload) -> Self {
+        Self::Api {
+            code: payload.code.into(),
+            msg: payload.msg,
+        }
+    }
+}
+
 impl From<serde_json::Error> for BncError {
     fn from(err: serde_json::Error) -> Self {
         Self::SerdeError(err)
@@ -36,6 +188,6 @@ impl From<serde_json::Error> for BncError {
 
 impl From<tokio_tungstenite::tungstenite::Error> for BncError {
     fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
-        Self::WsError(err)
+        Self::WsError(Box::new(err))
     }
 }
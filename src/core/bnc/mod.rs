@@ -12,11 +12,35 @@ pub mod snapshot;
 /// Not all the deserializable traits are included here, some are moved to specific submodules, like snapshot module.
 pub mod data;
 
+/// Holds decimal arithmetic over price levels - rounding used by the grouping and formatting.
+pub mod decimal;
+
 /// Holds error and result definitions for this part of the core.
 pub mod error;
 
+/// Holds retry policy for the fallible binance interactions.
+pub mod retry;
+
+/// Holds measurement of the local clock's offset from the binance one.
+pub mod clock;
+
 /// Holds realtime interactions with BNC API.
 pub mod ws;
 
+/// Holds on-disk cache of the latest order books, used to display something right after restart.
+pub mod cache;
+
+/// Holds composable consumers of the updates streams.
+pub mod sink;
+
+/// Holds deterministic generator of the market data, served instead of binance for `synthetic://` base urls.
+pub mod synthetic;
+
+/// Holds playback of the recorded market data, served instead of binance for `replay://` base urls.
+pub mod replay;
+
+/// Holds microstructure metrics of the order book - imbalance, weighted prices and liquidity around the mid.
+pub mod analytics;
+
 /// Holds general controller that absorbs workers, schedules tasks and provides current state of bnc data.
 pub mod state;
@@ -0,0 +1,944 @@
+use crate::core::bnc::snapshot::SymbolSnapshot;
+use futures::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Scheme of the base urls served from the recorded files instead of binance, e.g. `replay://records`.
+pub const REPLAY_SCHEME: &str = "replay://";
+
+/// Lines read ahead of the playback per recorded feed, as well as messages per replayed stream.
+const REPLAY_READ_AHEAD: usize = 1024;
+
+pub fn is_replay(url: &str) -> bool {
+    url.starts_with(REPLAY_SCHEME)
+}
+
+/// Pace the recordings are played back at.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaySpeed {
+    /// Updates are pushed with the same delays they are received with.
+    #[default]
+    Normal,
+    Fast,
+
+    /// Updates are pushed as fast as they are consumed.
+    Max,
+}
+
+impl ReplaySpeed {
+    /// How many times faster than received the updates are pushed, none if they are not delayed at all.
+    fn factor(&self) -> Option<u64> {
+        match self {
+            ReplaySpeed::Normal => Some(1),
+            ReplaySpeed::Fast => Some(10),
+            ReplaySpeed::Max => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ReplaySpeed::Normal => ReplaySpeed::Fast,
+            ReplaySpeed::Fast => ReplaySpeed::Max,
+            ReplaySpeed::Max => ReplaySpeed::Normal,
+        }
+    }
+}
+
+impl Display for ReplaySpeed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.factor() {
+            Some(factor) => write!(f, "{}x", factor),
+            None => f.write_str("max"),
+        }
+    }
+}
+
+/// State of the playback, all the moments are the recorded ones - milliseconds since epoch.
+#[derive(Debug, Clone, Copy)]
+struct Playback {
+    /// The earliest replayed moment, none until anything is replayed.
+    origin: Option<u64>,
+
+    /// Moment the playback is at as of the anchor.
+    position: u64,
+    anchor: Instant,
+
+    speed: ReplaySpeed,
+    paused: bool,
+
+    /// Incremented whenever the playback is moved back, so the replayed sources are started over.
+    generation: u64,
+}
+
+impl Playback {
+    fn position_at(&self, now: Instant) -> u64 {
+        match (self.paused, self.speed.factor()) {
+            (false, Some(factor)) => {
+                let elapsed = now.saturating_duration_since(self.anchor).as_millis() as u64;
+                self.position + elapsed * factor
+            }
+            _ => self.position,
+        }
+    }
+
+    /// Fix the current position, so the pace could be changed from now on.
+    fn reanchor(&mut self, now: Instant) {
+        self.position = self.position_at(now);
+        self.anchor = now;
+    }
+
+    /// When the update received at the moment is due, none if it is not while paused.
+    fn due(&self, received: u64, now: Instant) -> Option<Instant> {
+        if self.origin.is_none() || received <= self.position_at(now) {
+            return Some(now);
+        }
+        match (self.paused, self.speed.factor()) {
+            (true, _) => None,
+            (false, None) => Some(now),
+            (false, Some(factor)) => {
+                let ahead = (received - self.position).div_ceil(factor);
+                Some(self.anchor + Duration::from_millis(ahead))
+            }
+        }
+    }
+}
+
+/// Clock all the replayed streams are paced by, so they stay in sync while paused, sped up or moved.
+#[derive(Debug)]
+pub struct ReplayClock {
+    playback: watch::Sender<Playback>,
+}
+
+impl Default for ReplayClock {
+    fn default() -> Self {
+        Self {
+            playback: watch::channel(Playback {
+                origin: None,
+                position: 0,
+                anchor: Instant::now(),
+                speed: Default::default(),
+                paused: false,
+                generation: 0,
+            })
+            .0,
+        }
+    }
+}
+
+impl ReplayClock {
+    /// Recorded moment the playback is at, none until anything is replayed.
+    pub fn position(&self) -> Option<u64> {
+        let playback = self.playback.borrow();
+        playback
+            .origin
+            .map(|_| playback.position_at(Instant::now()))
+    }
+
+    /// Times the playback is moved back so far.
+    pub fn generation(&self) -> u64 {
+        self.playback.borrow().generation
+    }
+
+    pub fn speed(&self) -> ReplaySpeed {
+        self.playback.borrow().speed
+    }
+
+    pub fn set_speed(&self, speed: ReplaySpeed) {
+        self.playback.send_modify(|playback| {
+            playback.reanchor(Instant::now());
+            playback.speed = speed;
+        });
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.playback.borrow().paused
+    }
+
+    pub fn toggle_pause(&self) {
+        self.playback.send_modify(|playback| {
+            playback.reanchor(Instant::now());
+            playback.paused = !playback.paused;
+        });
+    }
+
+    /// Move the playback to the recorded moment. Updates up to it are pushed right away.
+    ///
+    /// Returns true if it is moved back - the updates are already applied, so the replayed state is to be rebuilt
+    /// by the new streams.
+    pub fn seek(&self, moment: u64) -> bool {
+        let mut is_back = false;
+        self.playback.send_modify(|playback| {
+            let now = Instant::now();
+            is_back = moment < playback.position_at(now);
+            if is_back {
+                playback.generation += 1;
+            }
+            playback.origin.get_or_insert(moment);
+            playback.position = moment;
+            playback.anchor = now;
+        });
+        is_back
+    }
+
+    /// Moment the input points to - either the milliseconds since epoch, e.g. `1672515782136`,
+    /// or the time since the start of the replay, e.g. `90` or `1:30` or `0:01:30`.
+    pub fn parse_moment(&self, input: &str) -> Option<u64> {
+        let input = input.trim();
+        if input.len() >= 12 {
+            return input.parse().ok();
+        }
+        let mut seconds = 0u64;
+        for part in input.split(':') {
+            seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+        }
+        let origin = self.playback.borrow().origin?;
+        Some(origin + seconds * 1000)
+    }
+
+    /// Time played since the first recorded update, e.g. `+00:01:30`.
+    pub fn offset(&self) -> String {
+        let playback = *self.playback.borrow();
+        let elapsed = playback
+            .origin
+            .map_or(0, |origin| playback.position_at(Instant::now()) - origin)
+            / 1000;
+        format!(
+            "+{:02}:{:02}:{:02}",
+            elapsed / 3600,
+            elapsed / 60 % 60,
+            elapsed % 60
+        )
+    }
+
+    /// Position, pace and state of the playback, e.g. `Replay 10x at +00:01:30`.
+    pub fn status(&self) -> String {
+        let pace = match self.is_paused() {
+            true => "paused".to_string(),
+            false => self.speed().to_string(),
+        };
+        format!("Replay {} at {}", pace, self.offset())
+    }
+
+    /// Wait until the update received at the moment is due.
+    async fn wait(&self, received: u64) {
+        let mut changes = self.playback.subscribe();
+        loop {
+            let due = changes.borrow_and_update().due(received, Instant::now());
+            match due {
+                Some(due) if due <= Instant::now() => break,
+                Some(due) => {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(due) => {}
+                        _ = changes.changed() => {}
+                    }
+                }
+                None => {
+                    let _ = changes.changed().await;
+                }
+            }
+        }
+        self.playback.send_if_modified(|playback| {
+            let now = Instant::now();
+            if playback.origin.is_some() && received <= playback.position_at(now) {
+                return false;
+            }
+            playback.origin.get_or_insert(received);
+            playback.position = received;
+            playback.anchor = now;
+            true
+        });
+    }
+}
+
+/// Clock of the replay the application is driven by.
+pub fn replay_clock() -> &'static ReplayClock {
+    static CLOCK: OnceLock<ReplayClock> = OnceLock::new();
+    CLOCK.get_or_init(Default::default)
+}
+
+/// Directory of the recordings the url is served from.
+fn replay_dir(url: &str) -> PathBuf {
+    let url = url.trim_start_matches(REPLAY_SCHEME);
+    PathBuf::from(url.split_once("/stream?").map_or(url, |(dir, _)| dir))
+}
+
+/// Symbol and the recorded feed the stream is restored from, e.g. `btcusdt@depth@100ms` is the `depth` of BTCUSDT.
+fn recorded_feed(name: &str) -> Option<(String, &'static str)> {
+    let (symbol, kind) = name.split_once('@')?;
+    let feed = match kind {
+        "bookTicker" => "price",
+        "depth" | "depth@100ms" => "depth",
+        "aggTrade" => "trades",
+        _ => return None,
+    };
+    Some((symbol.to_ascii_uppercase(), feed))
+}
+
+/// Whether the file is a recording - JSONL or CSV one, either plain or zstd compressed.
+fn is_recording(name: &str) -> bool {
+    let name = name.strip_suffix(".zst").unwrap_or(name);
+    name.ends_with(".jsonl") || name.ends_with(".csv")
+}
+
+/// Recorded files of the feed, the earliest first.
+///
+/// Recordings are named by the moment they are started at, so several sessions are replayed one after another.
+fn recorded_files(dir: &Path, symbol: &str, feed: &str) -> Vec<PathBuf> {
+    let prefix = format!("{}-{}-", symbol, feed);
+    let mut files = vec![];
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!("Recordings in {:?} can't be read. Error: {}", dir, err);
+            return files;
+        }
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(&prefix) && is_recording(&name) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Symbols of all the recordings in the directory, in order.
+pub async fn replay_symbols(base_url: &str) -> Vec<String> {
+    let mut symbols = BTreeSet::new();
+    if let Ok(mut entries) = tokio::fs::read_dir(replay_dir(base_url)).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !is_recording(&name) {
+                continue;
+            }
+            if let Some((symbol, _)) = name.split_once('-') {
+                symbols.insert(symbol.to_string());
+            }
+        }
+    }
+    symbols.into_iter().collect()
+}
+
+/// Decoded content of the recording. Compressed one is decoded on the fly.
+fn open_recording(path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+    let file = std::fs::File::open(path)?;
+    if path.extension().is_some_and(|extension| extension == "zst") {
+        return Ok(Box::new(BufReader::new(zstd::Decoder::new(file)?)));
+    }
+    Ok(Box::new(BufReader::new(file)))
+}
+
+/// Value of the CSV column, in the type it has in the JSONL recordings.
+fn csv_value(column: &str, value: &str) -> Value {
+    match column {
+        _ if value.is_empty() => Value::Null,
+        "received" | "trade_time" => value.parse::<u64>().map_or(Value::Null, Value::from),
+        _ if column.ends_with("_id") => value.parse::<u64>().map_or(Value::Null, Value::from),
+        "is_buyer_maker" => Value::from(value == "true"),
+        _ => Value::from(value),
+    }
+}
+
+/// Line of the CSV recording, in the shape of the JSONL one. Depth row carries the single level of its side.
+fn csv_line(feed: &str, columns: &[String], row: &str) -> Option<Value> {
+    let values: Vec<&str> = row.split(',').collect();
+    // Torn last row of the interrupted recording is skipped.
+    if values.len() != columns.len() {
+        return None;
+    }
+    let mut line: serde_json::Map<String, Value> = columns
+        .iter()
+        .zip(values)
+        .map(|(column, value)| (column.clone(), csv_value(column, value)))
+        .collect();
+    let order = |line: &mut serde_json::Map<String, Value>, price: &str, qty: &str| {
+        json!([line.remove(price), line.remove(qty)])
+    };
+    match feed {
+        "price" => {
+            let bid = order(&mut line, "bid_price", "bid_qty");
+            let ask = order(&mut line, "ask_price", "ask_qty");
+            line.insert("bid".into(), bid);
+            line.insert("ask".into(), ask);
+        }
+        "depth" => {
+            let level = order(&mut line, "price", "qty");
+            let side = line.remove("side")?;
+            let (bids, asks) = match side.as_str()? {
+                "bid" => (vec![level], vec![]),
+                _ => (vec![], vec![level]),
+            };
+            line.insert("bids".into(), bids.into());
+            line.insert("asks".into(), asks.into());
+        }
+        _ => {}
+    }
+    Some(Value::Object(line))
+}
+
+/// Whether the CSV depth rows are the levels of the same update.
+fn is_same_update(line: &Value, other: &Value) -> bool {
+    ["received", "first_update_id", "final_update_id"]
+        .iter()
+        .all(|key| line[key] == other[key])
+}
+
+/// Lines of the recordings of the feed, read one at a time. Files are read one after another.
+///
+/// Malformed lines, e.g. the last one of the interrupted recording, are skipped. Rows of the depth update in CSV
+/// are joined back into the single line, so the updates without levels are not replayed from CSV.
+struct RecordedLines {
+    feed: &'static str,
+    files: std::vec::IntoIter<PathBuf>,
+    current: Option<(PathBuf, Box<dyn BufRead + Send>)>,
+
+    /// Columns of the current CSV recording, none for the JSONL one.
+    columns: Option<Vec<String>>,
+
+    /// CSV depth row read ahead, the first one of the next update.
+    pending: Option<Value>,
+}
+
+impl RecordedLines {
+    fn open(dir: &Path, symbol: &str, feed: &'static str) -> Self {
+        Self {
+            feed,
+            files: recorded_files(dir, symbol, feed).into_iter(),
+            current: None,
+            columns: None,
+            pending: None,
+        }
+    }
+
+    /// Next line of the current file, starting the next file once the current one is over.
+    fn next_line(&mut self) -> Option<Value> {
+        let mut raw = vec![];
+        loop {
+            let (path, reader) = match self.current.as_mut() {
+                Some(current) => current,
+                None => {
+                    let path = self.files.next()?;
+                    match open_recording(&path) {
+                        Ok(reader) => self.current = Some((path, reader)),
+                        Err(err) => warn!("Recording {:?} can't be read. Error: {}", path, err),
+                    }
+                    self.columns = None;
+                    continue;
+                }
+            };
+            raw.clear();
+            match reader.read_until(b'\n', &mut raw) {
+                Ok(0) => {
+                    self.current = None;
+                    continue;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    // Recording that is interrupted ends with the unfinished frame, it is replayed up to it.
+                    warn!(
+                        "Recording {:?} is cut short, it is replayed up to the cut. Error: {}",
+                        path, err
+                    );
+                    self.current = None;
+                    continue;
+                }
+            }
+            let raw = String::from_utf8_lossy(&raw);
+            let raw = raw.trim_end();
+            let is_csv = path
+                .to_string_lossy()
+                .trim_end_matches(".zst")
+                .ends_with(".csv");
+            let line = match self.columns.as_ref() {
+                Some(columns) => csv_line(self.feed, columns, raw),
+                None if is_csv => {
+                    self.columns = Some(raw.split(',').map(String::from).collect());
+                    continue;
+                }
+                None => serde_json::from_str::<Value>(raw).ok(),
+            };
+            if line.is_some() {
+                return line;
+            }
+        }
+    }
+}
+
+impl Iterator for RecordedLines {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let line = self.pending.take().or_else(|| self.next_line())?;
+        if self.feed != "depth" || self.columns.is_none() {
+            return Some(line);
+        }
+        let mut update = line;
+        while let Some(row) = self.next_line() {
+            if self.columns.is_none() || !is_same_update(&update, &row) {
+                self.pending = Some(row);
+                break;
+            }
+            for side in ["bids", "asks"] {
+                if let (Some(levels), Some(row_levels)) =
+                    (update[side].as_array_mut(), row[side].as_array())
+                {
+                    levels.extend(row_levels.iter().cloned());
+                }
+            }
+        }
+        Some(update)
+    }
+}
+
+/// Lines of the feed read ahead of the playback, by the blocking task.
+fn read_feed(dir: PathBuf, symbol: String, feed: &'static str) -> mpsc::Receiver<Value> {
+    let (sender, receiver) = mpsc::channel(REPLAY_READ_AHEAD);
+    tokio::task::spawn_blocking(move || {
+        for line in RecordedLines::open(&dir, &symbol, feed) {
+            if sender.blocking_send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Data of the recorded line, in the format of the corresponding binance stream.
+fn stream_data(symbol: &str, feed: &str, line: &Value) -> Value {
+    let received = &line["received"];
+    match feed {
+        "price" => json!({
+            "u": line["update_id"], "s": symbol,
+            "b": line["bid"][0], "B": line["bid"][1],
+            "a": line["ask"][0], "A": line["ask"][1],
+        }),
+        "depth" => json!({
+            "e": "depthUpdate", "E": received, "s": symbol,
+            "U": line["first_update_id"], "u": line["final_update_id"],
+            "pu": line["previous_final_update_id"],
+            "b": line["bids"], "a": line["asks"],
+        }),
+        _ => json!({
+            "e": "aggTrade", "E": received, "s": symbol,
+            "a": line["trade_id"], "p": line["price"], "q": line["qty"],
+            "f": line["trade_id"], "l": line["trade_id"], "T": line["trade_time"],
+            "m": line["is_buyer_maker"],
+        }),
+    }
+}
+
+/// Streams of the replayed endpoint, the ones its messages are pushed to.
+type Subscribers = Arc<Mutex<Vec<mpsc::Sender<Message>>>>;
+
+/// Sources played at the moment, by the endpoint and the [`ReplayClock::generation`] they are started in.
+fn replay_sources() -> &'static Mutex<HashMap<(String, u64), Subscribers>> {
+    static SOURCES: OnceLock<Mutex<HashMap<(String, u64), Subscribers>>> = OnceLock::new();
+    SOURCES.get_or_init(Default::default)
+}
+
+/// Subscribers that are still listening.
+fn listening(subscribers: &Subscribers) -> Vec<mpsc::Sender<Message>> {
+    let mut subscribers = subscribers.lock().unwrap();
+    subscribers.retain(|subscriber| !subscriber.is_closed());
+    subscribers.clone()
+}
+
+/// Resolves once nobody listens to the source anymore.
+async fn abandoned(subscribers: &Subscribers) {
+    loop {
+        let listening = listening(subscribers);
+        if listening.is_empty() {
+            return;
+        }
+        futures::future::join_all(listening.iter().map(|subscriber| subscriber.closed())).await;
+    }
+}
+
+/// Recorded feed of the replayed endpoint, along with its line that is due next.
+struct ReplayedFeed {
+    name: String,
+    symbol: String,
+    feed: &'static str,
+    lines: mpsc::Receiver<Value>,
+    next: Option<Value>,
+}
+
+/// Push the recorded messages of the endpoint to its subscribers, paced by the [`replay_clock`].
+///
+/// Feeds are read lazily and merged by the moments their lines are received at. Every message waits for
+/// the slowest subscriber, so none of them is lost.
+async fn play(endpoint: String, key: (String, u64), subscribers: Subscribers) {
+    let dir = replay_dir(&endpoint);
+    let names = endpoint
+        .split_once("streams=")
+        .map(|(_, names)| names)
+        .unwrap_or_default();
+
+    let mut feeds = vec![];
+    for name in names.split('/') {
+        let (symbol, feed) = match recorded_feed(name) {
+            Some(recorded) => recorded,
+            None => {
+                warn!("Stream {} is not recorded, so it is not replayed.", name);
+                continue;
+            }
+        };
+        info!("Replaying {} from {:?}.", name, dir);
+        let mut lines = read_feed(dir.clone(), symbol.clone(), feed);
+        let next = lines.recv().await;
+        feeds.push(ReplayedFeed {
+            name: name.to_string(),
+            symbol,
+            feed,
+            lines,
+            next,
+        });
+    }
+
+    loop {
+        // The earlier feed of the endpoint goes first among the lines received at the same millisecond.
+        let next = feeds
+            .iter()
+            .enumerate()
+            .filter_map(|(index, feed)| {
+                let received = feed.next.as_ref()?["received"].as_u64().unwrap_or(0);
+                Some((received, index))
+            })
+            .min();
+        let (received, index) = match next {
+            Some(next) => next,
+            None => break,
+        };
+        let feed = &mut feeds[index];
+        let line = std::mem::replace(&mut feed.next, feed.lines.recv().await).unwrap_or_default();
+        let data = stream_data(&feed.symbol, feed.feed, &line);
+        let message = Message::Text(json!({ "stream": feed.name, "data": data }).to_string());
+
+        tokio::select! {
+            _ = replay_clock().wait(received) => {}
+            _ = abandoned(&subscribers) => break,
+        }
+        let listening = listening(&subscribers);
+        if listening.is_empty() {
+            break;
+        }
+        for subscriber in listening {
+            // Closed one is dropped on the next message.
+            let _ = subscriber.send(message.clone()).await;
+        }
+    }
+
+    let mut sources = replay_sources().lock().unwrap();
+    if sources
+        .get(&key)
+        .is_some_and(|source| Arc::ptr_eq(source, &subscribers))
+    {
+        sources.remove(&key);
+    }
+    // Streams end along with the recordings, so the last state stays on the screen.
+    subscribers.lock().unwrap().clear();
+}
+
+/// Messages of the replayed endpoint, in the format of the binance combined streams.
+///
+/// Messages are paced by the [`replay_clock`], the ones it is already past are pushed right away.
+/// Redundant workers of the endpoint share the single source, the later ones get the messages from the moment
+/// they join it. The source is started over once the replay is moved back.
+/// The stream ends once the recordings are over, so the last state stays on the screen.
+pub fn replay_stream(endpoint: &str) -> BoxStream<'static, Message> {
+    let (sender, receiver) = mpsc::channel(REPLAY_READ_AHEAD);
+    let key = (endpoint.to_string(), replay_clock().generation());
+
+    let mut sources = replay_sources().lock().unwrap();
+    match sources.get(&key) {
+        Some(subscribers) => subscribers.lock().unwrap().push(sender),
+        None => {
+            let subscribers = Arc::new(Mutex::new(vec![sender]));
+            sources.insert(key.clone(), subscribers.clone());
+            tokio::task::spawn(play(endpoint.to_string(), key, subscribers));
+        }
+    }
+
+    stream::unfold(receiver, |mut receiver| async move {
+        let message = receiver.recv().await?;
+        Some((message, receiver))
+    })
+    .boxed()
+}
+
+/// Book the replayed depth updates are applied to.
+///
+/// Depth is recorded as the updates only, so the book is started empty right before the first of them
+/// and is filled up by the levels they change. The first spot update has to cover the id after the snapshot,
+/// while the first futures one - the recorded with `pu` - has to cover the snapshot id itself.
+pub async fn replay_snapshot(base_url: &str, symbol: &str) -> SymbolSnapshot {
+    let dir = replay_dir(base_url);
+    let symbol = symbol.to_ascii_uppercase();
+    let first =
+        tokio::task::spawn_blocking(move || RecordedLines::open(&dir, &symbol, "depth").next())
+            .await
+            .ok()
+            .flatten();
+    let first = first.as_ref();
+    let first_update_id = first
+        .and_then(|line| line["first_update_id"].as_u64())
+        .unwrap_or(1);
+    let is_futures = first.is_some_and(|line| line["previous_final_update_id"].is_u64());
+    SymbolSnapshot {
+        last_update_id: match is_futures {
+            true => first_update_id,
+            false => first_update_id.saturating_sub(1),
+        },
+        bids: vec![],
+        asks: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::data::InlineOrder;
+    use crate::core::bnc::state::book::{DepthSync, OrderBook};
+    use crate::core::bnc::ws::data::WsDataContainer;
+    use crate::core::bnc::ws::worker::depth::SymbolDepthUpdate;
+    use crate::core::bnc::ws::worker::price::SymbolBookTick;
+    use std::io::Write;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn order(level: &str, qty: &str) -> InlineOrder {
+        InlineOrder::new(level.parse().unwrap(), qty.parse().unwrap())
+    }
+
+    fn records_dir() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!("bnc-replay-{}", nanos))
+    }
+
+    fn write(dir: &Path, name: &str, lines: &[Value]) {
+        let content: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        std::fs::write(dir.join(name), content).unwrap();
+    }
+
+    #[test]
+    fn it_paces_playback() {
+        let clock = ReplayClock::default();
+        let now = Instant::now();
+        let due = |received| clock.playback.borrow().due(received, now);
+        assert_eq!(due(5000), Some(now));
+
+        assert!(!clock.seek(5000));
+        clock.playback.send_modify(|playback| playback.anchor = now);
+        assert_eq!(due(4000), Some(now));
+        assert_eq!(due(5500), Some(now + Duration::from_millis(500)));
+
+        clock.set_speed(ReplaySpeed::Fast);
+        clock.playback.send_modify(|playback| playback.anchor = now);
+        assert_eq!(due(5500), Some(now + Duration::from_millis(50)));
+
+        clock.toggle_pause();
+        assert!(clock.is_paused());
+        assert_eq!(due(5500), None);
+        assert_eq!(clock.status(), "Replay paused at +00:00:00");
+
+        assert_eq!(clock.parse_moment("1:30"), Some(95_000));
+        assert_eq!(clock.parse_moment("1672515782136"), Some(1672515782136));
+        assert_eq!(clock.parse_moment("soon"), None);
+        assert!(!clock.seek(95_000));
+        assert!(clock.seek(6000));
+        assert_eq!(clock.status(), "Replay paused at +00:00:01");
+    }
+
+    #[tokio::test]
+    async fn it_replays_recorded_updates() {
+        let dir = records_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        write(
+            &dir,
+            "BTCUSDT-depth-1000-0000.jsonl",
+            &[
+                json!({"received": 1000, "first_update_id": 10, "final_update_id": 12,
+                    "bids": [order("100.0", "1.5")], "asks": [order("101.0", "2")]}),
+                json!({"received": 1010, "first_update_id": 13, "final_update_id": 13,
+                    "bids": [order("100.0", "0")], "asks": []}),
+            ],
+        );
+        write(
+            &dir,
+            "BTCUSDT-price-1000-0000.jsonl",
+            &[json!({"received": 1005, "update_id": 7,
+                "bid": order("100.0", "1.5"), "ask": order("101.0", "2")})],
+        );
+        let base_url = format!("{}{}", REPLAY_SCHEME, dir.display());
+        assert_eq!(replay_symbols(&base_url).await, vec!["BTCUSDT"]);
+
+        let mut book = OrderBook::from(replay_snapshot(&base_url, "btcusdt").await);
+        let endpoint = format!("{}/stream?streams=btcusdt@depth@100ms", base_url);
+        let messages: Vec<Message> = replay_stream(&endpoint).collect().await;
+        assert_eq!(messages.len(), 2);
+        for message in messages {
+            let container: WsDataContainer<SymbolDepthUpdate> =
+                serde_json::from_slice(&message.into_data()).unwrap();
+            assert!(book.add_depth_update(container.data));
+        }
+        let top = book.top(1, None);
+        assert_eq!(top.best_bid(), None);
+        assert_eq!(top.best_ask(), Some("101.0".parse().unwrap()));
+
+        let endpoint = format!(
+            "{}/stream?streams=btcusdt@bookTicker/btcusdt@ticker",
+            base_url
+        );
+        let messages: Vec<Message> = replay_stream(&endpoint).collect().await;
+        assert_eq!(messages.len(), 1);
+        let container: WsDataContainer<SymbolBookTick> =
+            serde_json::from_slice(&messages[0].clone().into_data()).unwrap();
+        assert_eq!(container.stream, "btcusdt@bookTicker");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_replays_recorded_futures_updates() {
+        let dir = records_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        write(
+            &dir,
+            "BTCUSDT-depth-1000-0000.jsonl",
+            &[
+                json!({"received": 1000, "first_update_id": 10, "final_update_id": 12,
+                    "previous_final_update_id": 8, "bids": [order("100.0", "1.5")], "asks": []}),
+                json!({"received": 1010, "first_update_id": 15, "final_update_id": 17,
+                    "previous_final_update_id": 12, "bids": [], "asks": [order("101.0", "2")]}),
+            ],
+        );
+        let base_url = format!("{}{}", REPLAY_SCHEME, dir.display());
+
+        let mut book = OrderBook::from(replay_snapshot(&base_url, "BTCUSDT").await)
+            .with_sync(DepthSync::Futures);
+        let endpoint = format!("{}/stream?streams=btcusdt@depth@100ms", base_url);
+        let messages: Vec<Message> = replay_stream(&endpoint).collect().await;
+        for message in messages {
+            let container: WsDataContainer<SymbolDepthUpdate> =
+                serde_json::from_slice(&message.into_data()).unwrap();
+            assert!(container.data.previous_final_update_id.is_some());
+            assert!(book.add_depth_update(container.data));
+        }
+        assert_eq!(book.last_update_id(), 17);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_replays_csv_recording() {
+        let dir = records_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("BTCUSDT-depth-1000-0000.csv"),
+            "received,first_update_id,final_update_id,previous_final_update_id,side,price,qty\n\
+            1000,10,12,,bid,100.0,1.5\n\
+            1000,10,12,,ask,101.0,2\n\
+            1010,13,13,,bid,100.0,0\n\
+            1020,14,14,,ask,10",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("BTCUSDT-price-1000-0000.csv"),
+            "received,update_id,bid_price,bid_qty,ask_price,ask_qty\n1005,7,100.0,1.5,101.0,2\n",
+        )
+        .unwrap();
+        let base_url = format!("{}{}", REPLAY_SCHEME, dir.display());
+        assert_eq!(replay_symbols(&base_url).await, vec!["BTCUSDT"]);
+
+        let mut book = OrderBook::from(replay_snapshot(&base_url, "BTCUSDT").await);
+        let endpoint = format!(
+            "{}/stream?streams=btcusdt@depth@100ms/btcusdt@bookTicker",
+            base_url
+        );
+        let messages: Vec<Message> = replay_stream(&endpoint).collect().await;
+        assert_eq!(messages.len(), 3);
+        let tick: WsDataContainer<SymbolBookTick> =
+            serde_json::from_slice(&messages[1].clone().into_data()).unwrap();
+        assert_eq!(tick.stream, "btcusdt@bookTicker");
+        for message in [&messages[0], &messages[2]] {
+            let container: WsDataContainer<SymbolDepthUpdate> =
+                serde_json::from_slice(&message.clone().into_data()).unwrap();
+            assert!(book.add_depth_update(container.data));
+        }
+        let top = book.top(1, None);
+        assert_eq!(top.best_bid(), None);
+        assert_eq!(top.best_ask(), Some("101.0".parse().unwrap()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_shares_replay_between_redundant_workers() {
+        let dir = records_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let lines: Vec<Value> = (1..=3)
+            .map(|id| {
+                json!({"received": 1000 + id, "update_id": id,
+                    "bid": order("100.0", "1.5"), "ask": order("101.0", "2")})
+            })
+            .collect();
+        write(&dir, "BTCUSDT-price-1000-0000.jsonl", &lines);
+        let endpoint = format!(
+            "{}{}/stream?streams=btcusdt@bookTicker",
+            REPLAY_SCHEME,
+            dir.display()
+        );
+
+        let first = replay_stream(&endpoint);
+        let second = replay_stream(&endpoint);
+        let key = (endpoint.clone(), replay_clock().generation());
+        assert_eq!(
+            replay_sources().lock().unwrap()[&key].lock().unwrap().len(),
+            2
+        );
+
+        let (first, second): (Vec<Message>, Vec<Message>) =
+            futures::join!(first.collect(), second.collect());
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 3);
+        assert!(!replay_sources().lock().unwrap().contains_key(&key));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_replays_interrupted_compressed_recording() {
+        let dir = records_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut encoder = zstd::Encoder::new(Vec::new(), 3).unwrap();
+        for id in 1..=3 {
+            let line = json!({"received": 1000 + id, "update_id": id,
+                "bid": order("100.0", "1.5"), "ask": order("101.0", "2")});
+            writeln!(encoder, "{}", line).unwrap();
+            encoder.flush().unwrap();
+        }
+        // Recording is cut in the middle of the frame, before it is finished.
+        let mut content = encoder.get_ref().clone();
+        content.extend_from_slice(&[0x01, 0x02]);
+        std::fs::write(dir.join("BTCUSDT-price-1000-0000.jsonl.zst"), content).unwrap();
+
+        let lines: Vec<Value> = RecordedLines::open(&dir, "BTCUSDT", "price").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2]["update_id"], 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,24 +1,140 @@
-use super::config::BncCfg;
-use super::error::BncResult;
+use super::clock::{local_millis, ClockDrift};
+use super::config::{BncCfg, MarketType};
+use super::error::{ApiErrorPayload, BncError, BncResult};
+use super::retry::{retry, RetryCfg};
 use super::snapshot::SnapshotFetcher;
 use super::snapshot::SymbolSnapshot;
-use crate::core::bnc::data::SymbolContainer;
+use crate::core::bnc::data::{
+    AveragePrice, ExchangeInfo, ExchangeSymbol, Kline, KlineInterval, KlinesQuery,
+    RecentTradesQuery, ServerTime, SymbolContainer, SymbolTicker24h,
+};
+use crate::core::bnc::replay::{is_replay, replay_snapshot, replay_symbols};
+use crate::core::bnc::synthetic::{
+    is_synthetic, synthetic_avg_price, synthetic_klines, synthetic_snapshot, synthetic_ticker,
+    SYNTHETIC_MARKET,
+};
+use crate::core::bnc::ws::worker::trade::SymbolTradeUpdate;
 use async_trait::async_trait;
-use reqwest::Client;
+use log::{debug, warn};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Method, Proxy, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub struct BncRestClient {
     base_url: String,
+    fallback_base_urls: Vec<String>,
     client: Client,
+    log_http: bool,
+    market: MarketType,
+    retry: RetryCfg,
+}
+
+/// Query parameters whose values must never appear in logs.
+const SECRET_PARAMS: [&str; 4] = ["signature", "apikey", "api_key", "secret"];
+
+/// Replace values of the secret parameters in the query string.
+fn redact_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if SECRET_PARAMS.contains(&key.to_ascii_lowercase().as_str()) => {
+                format!("{}=<redacted>", key)
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Log a single REST call along with request weight binance has accounted to us.
+fn log_call(method: &Method, url: &Url, response: &Response, elapsed: Duration) {
+    let query = url.query().map(redact_query).unwrap_or_default();
+    let weight = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with("x-mbx-used-weight"))
+        .map(|(name, value)| format!("{}={}", name, value.to_str().unwrap_or("?")))
+        .collect::<Vec<_>>()
+        .join(", ");
+    debug!(
+        "{} {}{}?{} -> {}; weight: [{}]; took {:?}",
+        method,
+        url.host_str().unwrap_or_default(),
+        url.path(),
+        query,
+        response.status(),
+        weight,
+        elapsed
+    );
+}
+
+/// Binance answers with these statuses to the requests from restricted regions.
+fn check_region(status: StatusCode) -> BncResult<()> {
+    match status {
+        StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS | StatusCode::FORBIDDEN => {
+            Err(BncError::RegionBlocked {
+                status: status.as_u16(),
+            })
+        }
+        _ => Ok(()),
+    }
 }
 
 impl BncRestClient {
     pub fn new(client: Client, base_url: String) -> Self {
-        Self { client, base_url }
+        Self {
+            client,
+            base_url,
+            fallback_base_urls: vec![],
+            log_http: false,
+            market: MarketType::default(),
+            retry: RetryCfg::default(),
+        }
     }
 
-    pub fn from_cfg(cfg: &BncCfg) -> Self {
-        Self::new(Client::new(), cfg.baseurl.clone())
+    /// Policy every call is retried by, if it fails with a transient error.
+    pub fn with_retry(mut self, retry: RetryCfg) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Policy every call is retried by.
+    pub fn retry_cfg(&self) -> &RetryCfg {
+        &self.retry
+    }
+
+    /// Market the snapshots are fetched from, the base urls are to point to its hosts.
+    pub fn with_market(mut self, market: MarketType) -> Self {
+        self.market = market;
+        self
+    }
+
+    /// Log every call made by the client.
+    pub fn with_http_logging(mut self, log_http: bool) -> Self {
+        self.log_http = log_http;
+        self
+    }
+
+    /// Clusters to be tried, in order, if the main one is geo-blocked.
+    pub fn with_fallbacks(mut self, base_urls: Vec<String>) -> Self {
+        self.fallback_base_urls = base_urls;
+        self
+    }
+
+    /// Build client from the configuration. Fails only if configured proxy is malformed.
+    pub fn from_cfg(cfg: &BncCfg) -> BncResult<Self> {
+        let mut builder = Client::builder().timeout(Duration::from_millis(cfg.request_timeout));
+        if let Some(proxy) = cfg.proxy.as_ref() {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        Ok(Self::new(builder.build()?, cfg.baseurl.clone())
+            .with_fallbacks(cfg.fallback_baseurls.clone())
+            .with_http_logging(cfg.log_http)
+            .with_market(cfg.market)
+            .with_retry(cfg.rest_retry.clone()))
     }
 
     /// Get full path for the given relative path.
@@ -26,24 +142,244 @@ impl BncRestClient {
     /// Basically concatenation of base url and given str
     ///
     /// You definitely should provide starting slash and it's better to avoid trailing slash.
-    fn rel_path(&self, rel: &str) -> String {
-        format!("{}{}", self.base_url, rel)
+    fn rel_path(base_url: &str, rel: &str) -> String {
+        format!("{}{}", base_url, rel)
+    }
+
+    /// Send GET request and deserialize its response, retrying transient failures according to the policy.
+    async fn get<Q: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        rel: &str,
+        query: &Q,
+    ) -> BncResult<T> {
+        let what = format!("GET {}", rel);
+        retry(&self.retry, &what, || self.get_once(rel, query)).await
+    }
+
+    /// Send GET request once and deserialize its response.
+    async fn get_once<Q: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        rel: &str,
+        query: &Q,
+    ) -> BncResult<T> {
+        Ok(self.get_once_timed(rel, query).await?.0)
+    }
+
+    /// Send GET request once and deserialize its response, along with the local time the request was sent at
+    /// and the time it took.
+    ///
+    /// If a cluster rejects the request due to region restrictions, the next configured one is tried.
+    /// Only the attempt that got through is timed.
+    async fn get_once_timed<Q: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        rel: &str,
+        query: &Q,
+    ) -> BncResult<(T, u64, Duration)> {
+        let mut blocked = None;
+        for base_url in std::iter::once(&self.base_url).chain(&self.fallback_base_urls) {
+            let request = self
+                .client
+                .get(Self::rel_path(base_url, rel))
+                .query(query)
+                .build()?;
+
+            let (method, url) = (request.method().clone(), request.url().clone());
+            let sent = local_millis();
+            let started = Instant::now();
+            let response = self.client.execute(request).await?;
+            if self.log_http {
+                log_call(&method, &url, &response, started.elapsed());
+            }
+            match check_region(response.status()) {
+                Ok(_) => {
+                    let parsed = Self::parse(response).await?;
+                    return Ok((parsed, sent, started.elapsed()));
+                }
+                Err(err) => {
+                    warn!("Cluster {} is unavailable. Error: {}", base_url, err);
+                    blocked = Some(err);
+                }
+            }
+        }
+        // There is always at least the main cluster to try, so the error is known here.
+        Err(blocked.unwrap_or_else(|| BncError::MalformedData("no REST cluster to try".into())))
+    }
+}
+
+impl BncRestClient {
+    /// Symbols the exchange is trading at the moment, in order. Suspended and delisted ones are skipped.
+    ///
+    /// Synthetic market lists its generated symbols, the replayed one - the recorded symbols.
+    pub async fn fetch_symbols(&self) -> BncResult<Vec<String>> {
+        Ok(self.fetch_exchange_info().await?.trading_symbols())
+    }
+
+    /// Symbols of the exchange along with their trading rules.
+    ///
+    /// Synthetic market lists its generated symbols, the replayed one - the recorded symbols, both without any rules.
+    pub async fn fetch_exchange_info(&self) -> BncResult<ExchangeInfo> {
+        let symbols: Vec<String> = if is_synthetic(&self.base_url) {
+            SYNTHETIC_MARKET
+                .iter()
+                .map(|symbol| symbol.to_string())
+                .collect()
+        } else if is_replay(&self.base_url) {
+            replay_symbols(&self.base_url).await
+        } else {
+            return self.get(self.market.exchange_info_path(), &()).await;
+        };
+        Ok(ExchangeInfo {
+            symbols: symbols.into_iter().map(ExchangeSymbol::trading).collect(),
+        })
+    }
+
+    /// Rolling 24hr statistics of the symbol, so they are known before its ticker stream sends anything.
+    ///
+    /// Recordings have no statistics, so the replayed symbol's ones are empty.
+    pub async fn fetch_ticker_24h(&self, symbol: &str) -> BncResult<SymbolTicker24h> {
+        if is_synthetic(&self.base_url) {
+            return Ok(synthetic_ticker(&self.base_url, symbol));
+        }
+        if is_replay(&self.base_url) {
+            return Ok(SymbolTicker24h {
+                symbol: symbol.to_ascii_uppercase(),
+                ..Default::default()
+            });
+        }
+        self.get(self.market.ticker_24h_path(), &SymbolContainer { symbol })
+            .await
+    }
+
+    /// Current 5 minutes average price of the symbol. Spot market only, futures have no such endpoint.
+    ///
+    /// Recordings have no trades, so the replayed symbol's average is zero.
+    pub async fn fetch_avg_price(&self, symbol: &str) -> BncResult<AveragePrice> {
+        if is_synthetic(&self.base_url) {
+            return Ok(synthetic_avg_price(&self.base_url, symbol));
+        }
+        if is_replay(&self.base_url) {
+            return Ok(AveragePrice::default());
+        }
+        self.get("/api/v3/avgPrice", &SymbolContainer { symbol })
+            .await
+    }
+
+    /// Klines of the symbol, the oldest first. Limit is 500 by default, the window - the latest klines.
+    ///
+    /// Recordings have no trades, so the replayed symbol has no klines.
+    pub async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: KlineInterval,
+        limit: Option<u16>,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> BncResult<Vec<Kline>> {
+        let query = KlinesQuery {
+            symbol,
+            interval,
+            limit,
+            start_time: start,
+            end_time: end,
+        };
+        if is_synthetic(&self.base_url) {
+            return Ok(synthetic_klines(&self.base_url, &query));
+        }
+        if is_replay(&self.base_url) {
+            return Ok(vec![]);
+        }
+        self.get(self.market.klines_path(), &query).await
+    }
+
+    /// Latest trades of the symbol, the oldest first, at most 1000 of them.
+    ///
+    /// Aggregated trades are fetched, so they are followed by the ones of the `aggTrade` stream.
+    /// Synthetic and replayed trades exist only in their streams, so none are fetched.
+    pub async fn fetch_recent_trades(
+        &self,
+        symbol: &str,
+        limit: u16,
+    ) -> BncResult<Vec<SymbolTradeUpdate>> {
+        if is_synthetic(&self.base_url) || is_replay(&self.base_url) {
+            return Ok(vec![]);
+        }
+        self.get(
+            self.market.agg_trades_path(),
+            &RecentTradesQuery { symbol, limit },
+        )
+        .await
+    }
+
+    /// Current time of the exchange, in milliseconds since epoch. Synthetic and replayed markets run on the local clock.
+    pub async fn fetch_server_time(&self) -> BncResult<u64> {
+        if is_synthetic(&self.base_url) || is_replay(&self.base_url) {
+            return Ok(local_millis());
+        }
+        let time: ServerTime = self.get(self.market.time_path(), &()).await?;
+        Ok(time.server_time)
+    }
+
+    /// Drift of the local clock, measured by a single server time request.
+    ///
+    /// The request is not retried, as the backoff would be taken for the round trip and skew the offset.
+    /// Synthetic and replayed markets run on the local clock, so they have no drift.
+    pub async fn measure_clock_drift(&self) -> BncResult<ClockDrift> {
+        if is_synthetic(&self.base_url) || is_replay(&self.base_url) {
+            let now = local_millis();
+            return Ok(ClockDrift::measure(now, Duration::ZERO, now));
+        }
+        let (time, sent, round_trip): (ServerTime, _, _) =
+            self.get_once_timed(self.market.time_path(), &()).await?;
+        Ok(ClockDrift::measure(sent, round_trip, time.server_time))
+    }
+
+    /// Deserialize successful response. Failed one is parsed as binance error envelope if possible.
+    async fn parse<T: DeserializeOwned>(response: Response) -> BncResult<T> {
+        if response.status().is_success() {
+            return Ok(response.json().await?);
+        }
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::IM_A_TEAPOT {
+            // Binance sends the seconds to wait, the date form is not expected.
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs);
+            return Err(BncError::RateLimited {
+                status: status.as_u16(),
+                retry_after,
+            });
+        }
+        let body = response.bytes().await?;
+        match serde_json::from_slice::<ApiErrorPayload>(&body) {
+            Ok(payload) => Err(payload.into()),
+            Err(_) => {
+                warn!(
+                    "Binance responded with HTTP {} and unexpected body.",
+                    status
+                );
+                Err(BncError::UnexpectedStatus {
+                    status: status.as_u16(),
+                })
+            }
+        }
     }
 }
 
 #[async_trait]
 impl SnapshotFetcher for BncRestClient {
     async fn fetch_snapshot(&self, symbol: &str) -> BncResult<SymbolSnapshot> {
-        let path = self.rel_path("/api/v3/depth");
-
-        let request = self
-            .client
-            .get(&path)
-            .query(&SymbolContainer { symbol })
-            .build()?;
-
-        let response = self.client.execute(request).await?.json().await?;
-        Ok(response)
+        if is_synthetic(&self.base_url) {
+            return Ok(synthetic_snapshot(&self.base_url, symbol));
+        }
+        if is_replay(&self.base_url) {
+            return Ok(replay_snapshot(&self.base_url, symbol).await);
+        }
+        self.get(self.market.depth_path(), &SymbolContainer { symbol })
+            .await
     }
 }
 
@@ -51,7 +387,10 @@ impl SnapshotFetcher for BncRestClient {
 mod tests {
     use super::*;
     use crate::config::AppCfg;
+    use crate::core::bnc::error::ApiErrorCode;
     use anyhow::Result;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
 
     struct TestCtx {
         client: BncRestClient,
@@ -62,12 +401,245 @@ mod tests {
         fn new() -> Self {
             let cfg = AppCfg::load().unwrap();
             Self {
-                client: BncRestClient::from_cfg(cfg.core.bnc()),
+                client: BncRestClient::from_cfg(cfg.core.bnc()).unwrap(),
                 symbol: "BTCUSDT".into(),
             }
         }
     }
 
+    /// Serve the given raw HTTP response to the first connection. Returns base url of the server.
+    async fn serve_once(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    fn json_response(status: &str, body: &str) -> &'static str {
+        let response = format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        Box::leak(response.into_boxed_str())
+    }
+
+    #[test]
+    fn it_redacts_secrets_from_query() {
+        assert_eq!(
+            redact_query("symbol=BTCUSDT&timestamp=1&signature=abcdef&apiKey=key"),
+            "symbol=BTCUSDT&timestamp=1&signature=<redacted>&apiKey=<redacted>"
+        );
+        assert_eq!(redact_query("symbol=BTCUSDT"), "symbol=BTCUSDT");
+    }
+
+    #[test]
+    fn it_detects_region_blocking() {
+        assert!(check_region(StatusCode::OK).is_ok());
+        assert!(check_region(StatusCode::BAD_REQUEST).is_ok());
+        assert!(matches!(
+            check_region(StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS),
+            Err(BncError::RegionBlocked { status: 451 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_fails_over_blocked_cluster() -> Result<()> {
+        let blocked = serve_once("HTTP/1.1 451 Unavailable\r\ncontent-length: 0\r\n\r\n").await;
+        let response = json_response(
+            "200 OK",
+            r#"{"lastUpdateId":1,"bids":[["1.0","2.0"]],"asks":[]}"#,
+        );
+        let fallback = serve_once(response).await;
+
+        let client = BncRestClient::new(Client::new(), blocked).with_fallbacks(vec![fallback]);
+        let snapshot = client.fetch_snapshot("BTCUSDT").await?;
+        assert_eq!(snapshot.last_update_id, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_parses_api_error() {
+        let response = json_response(
+            "400 Bad Request",
+            r#"{"code":-1121,"msg":"Invalid symbol."}"#,
+        );
+        let client = BncRestClient::new(Client::new(), serve_once(response).await);
+
+        let snapshot = client.fetch_snapshot("NOTFOUND").await;
+        match snapshot {
+            Err(BncError::Api { code, msg }) => {
+                assert_eq!(code, ApiErrorCode::InvalidSymbol);
+                assert_eq!(msg, "Invalid symbol.");
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_lists_trading_symbols() -> Result<()> {
+        let response = json_response(
+            "200 OK",
+            r#"{"timezone":"UTC","symbols":[{"symbol":"ETHUSDT","status":"TRADING","baseAsset":"ETH"},
+                {"symbol":"LUNAUSDT","status":"BREAK"},{"symbol":"BTCUSDT","status":"TRADING"}]}"#,
+        );
+        let client = BncRestClient::new(Client::new(), serve_once(response).await);
+
+        assert_eq!(client.fetch_symbols().await?, vec!["BTCUSDT", "ETHUSDT"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_fetches_ticker_24h() -> Result<()> {
+        let client = BncRestClient::new(Client::new(), "synthetic://5".into());
+        let ticker = client.fetch_ticker_24h("btcusdt").await?;
+        assert_eq!(ticker.symbol, "BTCUSDT");
+        assert_eq!(ticker.high, ticker.low);
+        assert_eq!(ticker.last_price, ticker.high);
+
+        let response = json_response(
+            "400 Bad Request",
+            r#"{"code":-1121,"msg":"Invalid symbol."}"#,
+        );
+        let client = BncRestClient::new(Client::new(), serve_once(response).await);
+        assert!(matches!(
+            client.fetch_ticker_24h("NOTFOUND").await,
+            Err(BncError::Api {
+                code: ApiErrorCode::InvalidSymbol,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_fetches_avg_price() -> Result<()> {
+        let response = json_response(
+            "200 OK",
+            r#"{"mins":5,"price":"16512.34567891","closeTime":1672515782136}"#,
+        );
+        let client = BncRestClient::new(Client::new(), serve_once(response).await);
+
+        let average = client.fetch_avg_price("BTCUSDT").await?;
+        assert_eq!(average.mins, 5);
+        assert_eq!(average.price.to_string(), "16512.34567891");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_fetches_klines_of_window() -> Result<()> {
+        let client = BncRestClient::new(Client::new(), "synthetic://4".into());
+        let klines = client
+            .fetch_klines(
+                "BTCUSDT",
+                KlineInterval::Minute,
+                Some(10),
+                Some(90_000),
+                Some(300_000),
+            )
+            .await?;
+        let open_times: Vec<u64> = klines.iter().map(|kline| kline.open_time).collect();
+        assert_eq!(open_times, vec![60_000, 120_000, 180_000, 240_000, 300_000]);
+        assert_eq!(klines[0].close_time, 119_999);
+
+        let latest = client
+            .fetch_klines("BTCUSDT", KlineInterval::Hour, Some(3), None, None)
+            .await?;
+        assert_eq!(latest.len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_fetches_recent_trades() -> Result<()> {
+        let response = json_response(
+            "200 OK",
+            r#"[{"a":26129,"p":"0.01633102","q":"4.70443515","f":27781,"l":27781,"T":1498793709153,"m":true,"M":true},
+                {"a":26130,"p":"0.01633200","q":"1.00000000","f":27782,"l":27783,"T":1498793709160,"m":false,"M":true}]"#,
+        );
+        let client = BncRestClient::new(Client::new(), serve_once(response).await);
+
+        let trades = client.fetch_recent_trades("BTCUSDT", 2).await?;
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].id, 26129);
+        assert!(trades[0].is_buyer_maker);
+        assert_eq!(trades[1].last_trade_id, 27783);
+        assert_eq!(trades[1].price.to_string(), "0.01633200");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_retries_transient_failures() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let responses = [
+            "HTTP/1.1 503 Service Unavailable\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+            json_response("200 OK", r#"{"serverTime":1499827319559}"#),
+        ];
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        let retry = RetryCfg {
+            attempts: 2,
+            backoff: 1,
+            max_backoff: 1,
+        };
+
+        let client = BncRestClient::new(Client::new(), base_url.clone()).with_retry(retry.clone());
+        assert_eq!(client.fetch_server_time().await?, 1499827319559);
+
+        // Nobody answers anymore.
+        let client = client.with_retry(RetryCfg {
+            attempts: 1,
+            ..retry
+        });
+        assert!(client.fetch_server_time().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_parses_rate_limit() {
+        let limited = serve_once(
+            "HTTP/1.1 429 Too Many Requests\r\nretry-after: 7\r\ncontent-length: 0\r\n\r\n",
+        )
+        .await;
+        let client = BncRestClient::new(Client::new(), limited).with_retry(RetryCfg {
+            attempts: 1,
+            ..Default::default()
+        });
+
+        let snapshot = client.fetch_snapshot("BTCUSDT").await;
+        match snapshot {
+            Err(err @ BncError::RateLimited { status: 429, .. }) => {
+                assert_eq!(err.retry_after(), Some(Duration::from_secs(7)));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_reports_region_blocking() {
+        let blocked = serve_once("HTTP/1.1 403 Forbidden\r\ncontent-length: 0\r\n\r\n").await;
+
+        let client = BncRestClient::new(Client::new(), blocked);
+        let snapshot = client.fetch_snapshot("BTCUSDT").await;
+        assert!(matches!(
+            snapshot,
+            Err(BncError::RegionBlocked { status: 403 })
+        ));
+    }
+
     // We are satisfied even if this is not panicking behaviour - deserialize and we have a deal here.
     #[tokio::test]
     async fn it_gets_normal_snapshot() -> Result<()> {
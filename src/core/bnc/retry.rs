@@ -0,0 +1,194 @@
+use crate::core::bnc::error::{BncError, BncResult};
+use derive_getters::Getters;
+use log::warn;
+use serde::Deserialize;
+use std::future::Future;
+use std::time::Duration;
+
+/// Bounded retry policy with exponential backoff.
+#[derive(Debug, Clone, Deserialize, Getters)]
+#[serde(default)]
+pub struct RetryCfg {
+    /// Total amount of attempts, including the first one.
+    pub attempts: u32,
+
+    /// Milliseconds to wait before the second attempt. Doubled for each next one.
+    pub backoff: u64,
+
+    /// Upper bound of milliseconds between attempts.
+    pub max_backoff: u64,
+}
+
+impl Default for RetryCfg {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: 500,
+            max_backoff: 5000,
+        }
+    }
+}
+
+impl RetryCfg {
+    /// Delay before the given(starting from 1) retry.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u64.saturating_pow(retry.saturating_sub(1));
+        Duration::from_millis(self.backoff.saturating_mul(factor).min(self.max_backoff))
+    }
+}
+
+/// Run the operation until it succeeds, fails with non-transient error or attempts are exhausted.
+///
+/// Retry is delayed at least as long as binance asked to; if it asked to wait longer than the policy ever waits,
+/// it is not retried at all. Every failed attempt is logged; if all of them fail, their errors are returned together.
+pub async fn retry<T, F, Fut>(cfg: &RetryCfg, what: &str, mut operation: F) -> BncResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = BncResult<T>>,
+{
+    let attempts = cfg.attempts.max(1);
+    let mut errors = vec![];
+    let max_backoff = Duration::from_millis(cfg.max_backoff);
+    for attempt in 1..=attempts {
+        let retry_after = match operation().await {
+            Ok(result) => return Ok(result),
+            Err(err) if !err.is_transient() => return Err(err),
+            Err(err) => {
+                warn!(
+                    "Attempt {}/{} to {} failed. Error: {}",
+                    attempt, attempts, what, err
+                );
+                let retry_after = err.retry_after();
+                errors.push(err);
+                retry_after
+            }
+        };
+        if attempt == attempts {
+            break;
+        }
+        if retry_after.is_some_and(|retry_after| retry_after > max_backoff) {
+            warn!(
+                "Binance asked to wait {:?} before the next attempt to {}, giving up.",
+                retry_after.unwrap_or_default(),
+                what
+            );
+            break;
+        }
+        tokio::time::sleep(cfg.delay(attempt).max(retry_after.unwrap_or_default())).await;
+    }
+
+    if errors.len() == 1 {
+        return Err(errors.remove(0));
+    }
+    Err(BncError::RetriesExhausted { errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::error::ApiErrorCode;
+    use std::cell::Cell;
+
+    fn fast_cfg(attempts: u32) -> RetryCfg {
+        RetryCfg {
+            attempts,
+            backoff: 1,
+            max_backoff: 1,
+        }
+    }
+
+    #[test]
+    fn it_grows_delay_exponentially() {
+        let cfg = RetryCfg {
+            attempts: 10,
+            backoff: 100,
+            max_backoff: 1000,
+        };
+        assert_eq!(cfg.delay(1), Duration::from_millis(100));
+        assert_eq!(cfg.delay(3), Duration::from_millis(400));
+        assert_eq!(cfg.delay(9), Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn it_retries_transient_errors() {
+        let calls = Cell::new(0);
+        let result = retry(&fast_cfg(3), "test", || {
+            calls.set(calls.get() + 1);
+            async {
+                if calls.get() < 3 {
+                    Err(BncError::UnexpectedStatus { status: 503 })
+                } else {
+                    Ok(calls.get())
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn it_collects_errors_of_all_attempts() {
+        let result: BncResult<()> = retry(&fast_cfg(2), "test", || async {
+            Err(BncError::UnexpectedStatus { status: 502 })
+        })
+        .await;
+
+        match result {
+            Err(BncError::RetriesExhausted { errors }) => assert_eq!(errors.len(), 2),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_respects_rate_limits() {
+        let calls = Cell::new(0);
+        let rate_limited = |status, retry_after| {
+            calls.set(calls.get() + 1);
+            async move {
+                Err::<(), _>(BncError::RateLimited {
+                    status,
+                    retry_after: Some(Duration::from_millis(retry_after)),
+                })
+            }
+        };
+        let cfg = RetryCfg {
+            attempts: 3,
+            backoff: 1,
+            max_backoff: 100,
+        };
+
+        let started = tokio::time::Instant::now();
+        assert!(retry(&cfg, "test", || rate_limited(429, 30)).await.is_err());
+        assert_eq!(calls.get(), 3);
+        assert!(started.elapsed() >= Duration::from_millis(60));
+
+        calls.set(0);
+        assert!(retry(&cfg, "test", || rate_limited(429, 1000))
+            .await
+            .is_err());
+        assert_eq!(calls.get(), 1);
+
+        calls.set(0);
+        assert!(retry(&cfg, "test", || rate_limited(418, 1)).await.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_gives_up_on_permanent_errors() {
+        let calls = Cell::new(0);
+        let result: BncResult<()> = retry(&fast_cfg(5), "test", || {
+            calls.set(calls.get() + 1);
+            async {
+                Err(BncError::Api {
+                    code: ApiErrorCode::InvalidSymbol,
+                    msg: "Invalid symbol.".into(),
+                })
+            }
+        })
+        .await;
+
+        assert!(matches!(result, Err(BncError::Api { .. })));
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,337 @@
+use crate::core::bnc::clock::local_millis;
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::ws::worker::MessageSender;
+use log::debug;
+use serde::Serialize;
+use serde_json::json;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Consumer of the updates stream. Every [`MessageSender`] that could be shared across tasks is a sink.
+///
+/// Sinks are composed declaratively in one place using [`SinkExt`] combinators and [`FanoutSink`],
+/// so the same stream could be consumed by the ui, storage and metrics simultaneously.
+pub trait Sink<T: Send + Sync>: MessageSender<T> + Sync {}
+
+impl<T: Send + Sync, S: MessageSender<T> + Sync> Sink<T> for S {}
+
+/// Combinators available for all the sinks.
+pub trait SinkExt<T: Send + Sync>: Sink<T> + Sized {
+    /// Forward only messages satisfying the predicate.
+    fn filter<F: Fn(&T) -> bool + Send + Sync>(self, predicate: F) -> FilterSink<Self, F> {
+        FilterSink {
+            inner: self,
+            predicate,
+        }
+    }
+
+    /// Forward only each n-th message.
+    fn sample(self, every: u64) -> SamplingSink<Self> {
+        SamplingSink {
+            inner: self,
+            every: every.max(1),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Forward at most one message per interval - the latest one, intermediate messages are dropped.
+    ///
+    /// Spawns the delivering task, so it must be called within the tokio runtime.
+    fn throttle(self, interval: Duration) -> ThrottleSink<T>
+    where
+        Self: 'static,
+        T: Clone + 'static,
+    {
+        ThrottleSink::new(self, interval)
+    }
+}
+
+impl<T: Send + Sync, S: Sink<T>> SinkExt<T> for S {}
+
+/// Watch sender is the usual end of the stream - it holds the latest state for the ui.
+#[async_trait::async_trait]
+impl<T: Send + Sync> MessageSender<T> for watch::Sender<T> {
+    async fn send(&self, data: T) -> BncResult<()> {
+        watch::Sender::send(self, data).map_err(|_| BncError::DataTransmitError)
+    }
+}
+
+/// Sends copy of each message to all the inner sinks.
+///
+/// Message is accepted if at least one of the sinks accepted it.
+pub struct FanoutSink<T> {
+    sinks: Vec<Box<dyn Sink<T>>>,
+}
+
+impl<T> Default for FanoutSink<T> {
+    fn default() -> Self {
+        Self { sinks: vec![] }
+    }
+}
+
+impl<T: Send + Sync> FanoutSink<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, sink: impl Sink<T> + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Clone + Send + Sync> MessageSender<T> for FanoutSink<T> {
+    async fn send(&self, data: T) -> BncResult<()> {
+        let mut accepted = false;
+        let mut failure = None;
+        for sink in self.sinks.iter() {
+            match sink.send(data.clone()).await {
+                Ok(_) => accepted = true,
+                Err(BncError::DataRejected) => {}
+                Err(err) => {
+                    debug!("One of the fanout sinks failed. Error: {}", err);
+                    failure = Some(err);
+                }
+            }
+        }
+
+        match (accepted, failure) {
+            (true, _) => Ok(()),
+            (false, Some(err)) => Err(err),
+            (false, None) => Err(BncError::DataRejected),
+        }
+    }
+}
+
+/// Rejects messages that don't satisfy the predicate.
+pub struct FilterSink<S, F> {
+    inner: S,
+    predicate: F,
+}
+
+#[async_trait::async_trait]
+impl<T, S, F> MessageSender<T> for FilterSink<S, F>
+where
+    T: Send + Sync + 'static,
+    S: Sink<T>,
+    F: Fn(&T) -> bool + Send + Sync,
+{
+    async fn send(&self, data: T) -> BncResult<()> {
+        if !(self.predicate)(&data) {
+            return Err(BncError::DataRejected);
+        }
+        self.inner.send(data).await
+    }
+}
+
+/// Forwards only each n-th message, rejecting the rest.
+pub struct SamplingSink<S> {
+    inner: S,
+    every: u64,
+    counter: AtomicU64,
+}
+
+#[async_trait::async_trait]
+impl<T, S> MessageSender<T> for SamplingSink<S>
+where
+    T: Send + Sync + 'static,
+    S: Sink<T>,
+{
+    async fn send(&self, data: T) -> BncResult<()> {
+        let index = self.counter.fetch_add(1, Ordering::Relaxed);
+        if !index.is_multiple_of(self.every) {
+            return Err(BncError::DataRejected);
+        }
+        self.inner.send(data).await
+    }
+}
+
+/// Keeps only the latest message and delivers it to the inner sink at most once per interval.
+///
+/// Suits consumers that don't need each tick, e.g. metrics or remote dashboards. Producers are never slowed down -
+/// sending just replaces the pending message.
+pub struct ThrottleSink<T> {
+    latest: watch::Sender<Option<T>>,
+    task: JoinHandle<()>,
+}
+
+impl<T: Clone + Send + Sync + 'static> ThrottleSink<T> {
+    pub fn new(inner: impl Sink<T> + 'static, interval: Duration) -> Self {
+        let (latest, mut receiver) = watch::channel(None);
+        let task = tokio::task::spawn(async move {
+            while receiver.changed().await.is_ok() {
+                let data = receiver.borrow_and_update().clone();
+                if let Some(data) = data {
+                    if let Err(err) = inner.send(data).await {
+                        debug!("Throttled sink did not accept data. Error: {}", err);
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+        Self { latest, task }
+    }
+}
+
+impl<T> Drop for ThrottleSink<T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Send + Sync> MessageSender<T> for ThrottleSink<T> {
+    async fn send(&self, data: T) -> BncResult<()> {
+        self.latest
+            .send(Some(data))
+            .map_err(|_| BncError::DataTransmitError)
+    }
+}
+
+/// Writes each message as a JSON line, along with the symbol and the feed it belongs to and the moment it is written at,
+/// e.g. `{"symbol":"BTCUSDT","feed":"price","time":1672515782136,"data":{...}}`.
+///
+/// Sinks sharing the writer never interleave their lines.
+pub struct NdjsonSink<W> {
+    symbol: String,
+    feed: &'static str,
+    writer: Arc<Mutex<W>>,
+}
+
+impl<W> NdjsonSink<W> {
+    pub fn new(writer: Arc<Mutex<W>>, symbol: &str, feed: &'static str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            feed,
+            writer,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Serialize + Send + Sync + 'static, W: Write + Send> MessageSender<T> for NdjsonSink<W> {
+    async fn send(&self, data: T) -> BncResult<()> {
+        let time = local_millis();
+        let line = json!({ "symbol": self.symbol, "feed": self.feed, "time": time, "data": data });
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| BncError::DataTransmitError)?;
+        writeln!(writer, "{}", line)
+            .and_then(|_| writer.flush())
+            .map_err(|_| BncError::DataTransmitError)
+    }
+}
+
+/// Send every change of the watched state into the sink. Task ends when the state's sender is dropped.
+pub fn forward<T, S>(mut receiver: watch::Receiver<T>, sink: S) -> JoinHandle<()>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Sink<T> + 'static,
+{
+    tokio::task::spawn(async move {
+        while receiver.changed().await.is_ok() {
+            let data = receiver.borrow_and_update().clone();
+            if let Err(err) = sink.send(data).await {
+                debug!("Sink did not accept forwarded data. Error: {}", err);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn it_composes_sinks() {
+        let (all_tx, mut all_rx) = mpsc::channel(10);
+        let (even_tx, mut even_rx) = mpsc::channel(10);
+        let (sampled_tx, mut sampled_rx) = mpsc::channel(10);
+
+        let sink = FanoutSink::new()
+            .with(all_tx)
+            .with(even_tx.filter(|value: &u64| value.is_multiple_of(2)))
+            .with(sampled_tx.sample(3));
+
+        for value in 0..6u64 {
+            sink.send(value).await.unwrap();
+        }
+        drop(sink);
+
+        let collect = |rx: &mut mpsc::Receiver<u64>| {
+            let mut values = vec![];
+            while let Ok(value) = rx.try_recv() {
+                values.push(value);
+            }
+            values
+        };
+        assert_eq!(collect(&mut all_rx), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(collect(&mut even_rx), vec![0, 2, 4]);
+        assert_eq!(collect(&mut sampled_rx), vec![0, 3]);
+    }
+
+    #[tokio::test]
+    async fn it_writes_json_lines() {
+        let writer = Arc::new(Mutex::new(Vec::new()));
+        let prices = NdjsonSink::new(writer.clone(), "BTCUSDT", "price");
+        let volumes = NdjsonSink::new(writer.clone(), "ETHUSDT", "volume");
+        prices.send(vec!["101.5"]).await.unwrap();
+        volumes.send(42u64).await.unwrap();
+
+        let output = String::from_utf8(writer.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["symbol"], "BTCUSDT");
+        assert_eq!(lines[0]["data"], json!(["101.5"]));
+        assert_eq!(lines[1]["feed"], "volume");
+        assert!(lines[1]["time"].as_u64().is_some());
+    }
+
+    #[tokio::test]
+    async fn it_rejects_when_no_sink_accepts() {
+        let (tx, _rx) = mpsc::channel(10);
+        let sink = FanoutSink::new().with(tx.filter(|_: &u64| false));
+
+        assert!(matches!(sink.send(1).await, Err(BncError::DataRejected)));
+    }
+
+    #[tokio::test]
+    async fn it_throttles_to_latest_message() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let sink = tx.throttle(Duration::from_millis(200));
+
+        sink.send(1u64).await.unwrap();
+        assert_eq!(rx.recv().await, Some(1));
+
+        // Burst within the interval - only the latest message survives.
+        for value in 2..=5u64 {
+            sink.send(value).await.unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+        assert_eq!(rx.recv().await, Some(5));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn it_forwards_watched_state() {
+        let (state_tx, state_rx) = watch::channel(0u64);
+        let (tx, mut rx) = mpsc::channel(10);
+        let task = forward(state_rx, tx);
+
+        state_tx.send(7).unwrap();
+        assert_eq!(rx.recv().await, Some(7));
+
+        drop(state_tx);
+        task.await.unwrap();
+    }
+}
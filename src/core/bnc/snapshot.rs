@@ -2,10 +2,10 @@ use super::data::InlineOrder;
 use super::data::UpdateId;
 use super::error::BncResult;
 use async_trait::async_trait;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug, Clone)]
-#[serde(rename_all(deserialize = "camelCase"))]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SymbolSnapshot {
     pub last_update_id: UpdateId,
     pub bids: Vec<InlineOrder>,
@@ -0,0 +1,186 @@
+use derive_getters::Getters;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// Weight of the latest window in the baseline rate.
+const BASELINE_WEIGHT: f64 = 0.2;
+
+#[derive(Debug, Clone, Deserialize, Getters)]
+#[serde(default)]
+pub struct AnomalyCfg {
+    /// Whether the events rate is watched at all.
+    pub enabled: bool,
+
+    /// Milliseconds the events are counted in before the rate is compared with the baseline.
+    pub window: u64,
+
+    /// How many times the rate should differ from the baseline to be reported.
+    pub factor: f64,
+
+    /// Amount of windows the baseline is learned in before anything is reported.
+    pub warmup: u32,
+}
+
+impl Default for AnomalyCfg {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window: 5000,
+            factor: 5.0,
+            warmup: 3,
+        }
+    }
+}
+
+/// Events rate that is far away from the usual one. Rates are per second.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateAnomaly {
+    /// Rate collapsed - probably the feed is broken.
+    Stalled { rate: f64, baseline: f64 },
+
+    /// Rate exploded - probably the market is volatile.
+    Burst { rate: f64, baseline: f64 },
+}
+
+impl Display for RateAnomaly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stalled { rate, baseline } => write!(
+                f,
+                "updates stalled: {:.1}/s, usually {:.1}/s",
+                rate, baseline
+            ),
+            Self::Burst { rate, baseline } => {
+                write!(f, "updates burst: {:.1}/s, usually {:.1}/s", rate, baseline)
+            }
+        }
+    }
+}
+
+/// Watches the rate of the events against the baseline learned from the previous windows.
+///
+/// Anomalous windows don't affect the baseline, so the long outage is reported until it is over.
+#[derive(Debug, Clone)]
+pub struct RateMonitor {
+    cfg: AnomalyCfg,
+    baseline: Option<f64>,
+    windows: u32,
+    window_start: Instant,
+    window_events: Option<u64>,
+    anomaly: Option<RateAnomaly>,
+}
+
+impl RateMonitor {
+    pub fn new(cfg: AnomalyCfg) -> Self {
+        Self {
+            cfg,
+            baseline: None,
+            windows: 0,
+            window_start: Instant::now(),
+            window_events: None,
+            anomaly: None,
+        }
+    }
+
+    /// Account the total amount of events happened so far. Returns the anomaly of the last finished window.
+    pub fn record(&mut self, events: u64, now: Instant) -> Option<&RateAnomaly> {
+        if !self.cfg.enabled {
+            return None;
+        }
+
+        let window_events = match self.window_events {
+            Some(window_events) => window_events,
+            None => {
+                self.window_start = now;
+                self.window_events = Some(events);
+                return None;
+            }
+        };
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < Duration::from_millis(self.cfg.window) {
+            return self.anomaly.as_ref();
+        }
+
+        let rate = events.saturating_sub(window_events) as f64 / elapsed.as_secs_f64();
+        self.window_start = now;
+        self.window_events = Some(events);
+        self.windows += 1;
+        self.anomaly = self.check(rate);
+        self.anomaly.as_ref()
+    }
+
+    /// Latest reported anomaly.
+    pub fn anomaly(&self) -> Option<&RateAnomaly> {
+        self.anomaly.as_ref()
+    }
+
+    fn check(&mut self, rate: f64) -> Option<RateAnomaly> {
+        let baseline = match self.baseline {
+            Some(baseline) => baseline,
+            None => {
+                self.baseline = Some(rate);
+                return None;
+            }
+        };
+
+        if self.windows > self.cfg.warmup && baseline > 0.0 {
+            if rate * self.cfg.factor < baseline {
+                return Some(RateAnomaly::Stalled { rate, baseline });
+            }
+            if rate > baseline * self.cfg.factor {
+                return Some(RateAnomaly::Burst { rate, baseline });
+            }
+        }
+
+        self.baseline = Some(baseline + (rate - baseline) * BASELINE_WEIGHT);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed the monitor with windows of the given amounts of events, each a second long.
+    fn feed(monitor: &mut RateMonitor, start: Instant, windows: &[u64]) -> Option<RateAnomaly> {
+        let mut events = 0;
+        monitor.record(events, start);
+        let mut anomaly = None;
+        for (i, window) in windows.iter().enumerate() {
+            events += window;
+            let now = start + Duration::from_secs(i as u64 + 1);
+            anomaly = monitor.record(events, now).cloned();
+        }
+        anomaly
+    }
+
+    fn monitor() -> RateMonitor {
+        RateMonitor::new(AnomalyCfg {
+            window: 1000,
+            warmup: 2,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn it_reports_stalled_feed() {
+        let anomaly = feed(&mut monitor(), Instant::now(), &[10, 10, 10, 10, 1]);
+        assert!(matches!(anomaly, Some(RateAnomaly::Stalled { .. })));
+    }
+
+    #[test]
+    fn it_reports_burst() {
+        let anomaly = feed(&mut monitor(), Instant::now(), &[10, 10, 10, 10, 100]);
+        assert!(matches!(anomaly, Some(RateAnomaly::Burst { .. })));
+    }
+
+    #[test]
+    fn it_learns_baseline_during_warmup() {
+        let anomaly = feed(&mut monitor(), Instant::now(), &[10, 0]);
+        assert_eq!(anomaly, None);
+
+        let anomaly = feed(&mut monitor(), Instant::now(), &[10, 12, 8, 11, 9]);
+        assert_eq!(anomaly, None);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::core::bnc::config::{BncCfg, MarketType};
+use crate::core::bnc::data::AveragePrice;
+use crate::core::bnc::error::BncResult;
+use crate::core::bnc::rest::BncRestClient;
+use crate::core::bnc::state::tasks::TaskGroup;
+use log::{debug, warn};
+use std::time::Duration;
+use tokio::sync::watch::{channel, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Milliseconds between polls of the average price, unless configured otherwise.
+pub const DEFAULT_AVG_PRICE_INTERVAL: u64 = 30_000;
+
+/// Latest known average price of the symbol, none until it is fetched.
+pub type AveragePriceReceiver = Receiver<Option<AveragePrice>>;
+
+/// Polls the average price of the symbol, as binance has no stream of it.
+pub struct AveragePriceManager {
+    client: BncRestClient,
+
+    /// Time between the polls, none if the price is not polled.
+    interval: Option<Duration>,
+    tasks: TaskGroup,
+
+    /// Stops the poller.
+    cancel: CancellationToken,
+}
+
+impl AveragePriceManager {
+    /// Fails only if configured proxy is malformed.
+    ///
+    /// The price is polled on spot only, futures market has no such endpoint.
+    pub fn from_cfg(cfg: &BncCfg) -> BncResult<Self> {
+        Ok(Self {
+            client: BncRestClient::from_cfg(cfg)?,
+            interval: (cfg.market == MarketType::Spot && cfg.avg_price_interval > 0)
+                .then(|| Duration::from_millis(cfg.avg_price_interval)),
+            tasks: TaskGroup::new("average price"),
+            cancel: Default::default(),
+        })
+    }
+
+    /// Start polling the symbol's average price. Nothing is ever received if polling is disabled.
+    pub fn init(&mut self, symbol: &str) -> AveragePriceReceiver {
+        self.cancel = CancellationToken::new();
+        self.tasks = TaskGroup::new(format!("{} average price", symbol));
+        let (sender, receiver) = channel(None);
+        if let Some(interval) = self.interval {
+            let poller = average_poller(
+                self.client.clone(),
+                symbol.to_string(),
+                interval,
+                sender,
+                self.cancel.clone(),
+            );
+            self.tasks.adopt("poller", poller);
+        }
+        receiver
+    }
+
+    /// Stop polling the price.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Wait for the stopped poller to finish.
+    pub async fn join(&mut self) -> BncResult<()> {
+        self.tasks.join().await
+    }
+}
+
+/// Fetch the average price right away, then once per interval. Failed polls keep the last known price.
+fn average_poller(
+    client: BncRestClient,
+    symbol: String,
+    interval: Duration,
+    sender: Sender<Option<AveragePrice>>,
+    cancel: CancellationToken,
+) -> JoinHandle<BncResult<()>> {
+    tokio::task::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = cancel.cancelled() => break,
+            }
+            let average = tokio::select! {
+                average = client.fetch_avg_price(&symbol) => average,
+                _ = cancel.cancelled() => break,
+            };
+            match average {
+                Ok(average) => {
+                    // Nobody watches the price anymore.
+                    if sender.send(Some(average)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => warn!(
+                    "Could not fetch average price of {}. Error: {}",
+                    symbol, err
+                ),
+            }
+        }
+        debug!("Average price poller of {} is stopped.", symbol);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn it_polls_average_price() -> Result<()> {
+        let cfg = BncCfg {
+            baseurl: "synthetic://9".into(),
+            avg_price_interval: 10,
+            ..Default::default()
+        };
+        let mut manager = AveragePriceManager::from_cfg(&cfg)?;
+        let mut receiver = manager.init("BTCUSDT");
+        receiver.changed().await?;
+        let average = receiver.borrow_and_update().clone().unwrap();
+        assert_eq!(average.mins, 5);
+        assert!(!average.price.is_zero());
+
+        manager.stop();
+        manager.join().await?;
+
+        let mut futures = AveragePriceManager::from_cfg(&BncCfg {
+            market: MarketType::Futures,
+            ..cfg.clone()
+        })?;
+        futures.init("BTCUSDT");
+        assert_eq!(futures.tasks.alive(), 0);
+
+        let mut disabled = AveragePriceManager::from_cfg(&BncCfg {
+            avg_price_interval: 0,
+            ..cfg
+        })?;
+        let receiver = disabled.init("BTCUSDT");
+        assert!(receiver.borrow().is_none());
+        assert_eq!(disabled.tasks.alive(), 0);
+        Ok(())
+    }
+}
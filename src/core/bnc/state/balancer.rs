@@ -1,7 +1,13 @@
 use super::super::error::{BncError, BncResult};
 use super::super::ws::worker::price::SymbolPriceUpdate;
+use super::super::ws::worker::ticker::SymbolTickerUpdate;
 use super::super::ws::worker::MessageSender;
+use super::history::SharedHistory;
+use super::scaling::DeliveryCounters;
+use log::warn;
+use serde::Deserialize;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::watch::Sender;
 use tokio::sync::Mutex;
 
@@ -17,6 +23,30 @@ impl BalancedEntity for SymbolPriceUpdate {
     }
 }
 
+impl BalancedEntity for SymbolTickerUpdate {
+    fn update_id(&self) -> u64 {
+        self.time
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BalancerCfg {
+    /// Messages in a row behind the latest accepted one, after which the feed is taken as restarted
+    /// and the latest update id is re-seeded. None to never reset.
+    ///
+    /// Update ids may start over after a reconnect, so all the new data would be rejected otherwise.
+    pub reset_after: Option<u32>,
+}
+
+impl Default for BalancerCfg {
+    fn default() -> Self {
+        Self {
+            reset_after: Some(50),
+        }
+    }
+}
+
 /// State to hold balance data. It will be moved into needed clojure to compare its data with new entries.
 ///
 /// MessageSender is implemented for the shared state of message balancer.
@@ -24,6 +54,15 @@ impl BalancedEntity for SymbolPriceUpdate {
 pub struct MessageBalancer<T> {
     last_update_id: Option<u64>,
     sender: Sender<T>,
+    counters: Arc<DeliveryCounters>,
+
+    /// Messages in a row that were not ahead of the latest accepted one.
+    behind: u32,
+
+    reset_after: Option<u32>,
+
+    /// Accepted messages are recorded into it, if any.
+    history: Option<SharedHistory<T>>,
 }
 
 impl<T> MessageBalancer<T> {
@@ -31,23 +70,63 @@ impl<T> MessageBalancer<T> {
         Self {
             last_update_id: None,
             sender,
+            counters: Default::default(),
+            behind: 0,
+            reset_after: None,
+            history: None,
         }
     }
+
+    /// Record the accepted messages into the history.
+    pub fn with_history(mut self, history: SharedHistory<T>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Re-seed the latest update id once the feed sustainably regresses, as configured.
+    pub fn with_cfg(mut self, cfg: &BalancerCfg) -> Self {
+        self.reset_after = cfg.reset_after;
+        self
+    }
+
+    /// Account accepted and rejected messages in the given counters.
+    pub fn with_counters(mut self, counters: Arc<DeliveryCounters>) -> Self {
+        self.counters = counters;
+        self
+    }
 }
 
 /// We implement sending messages that could be balanced(e.g. implements Balanced trait) for shared MessageBalancer state.
 #[async_trait::async_trait]
-impl<B: BalancedEntity + Send + Sync> MessageSender<B> for Arc<Mutex<MessageBalancer<B>>> {
+impl<B: BalancedEntity + Clone + Send + Sync> MessageSender<B> for Arc<Mutex<MessageBalancer<B>>> {
     async fn send(&self, data: B) -> BncResult<()> {
         let mut balancer = self.lock().await;
-        if let Some(ref mut last_update_id) = balancer.last_update_id {
-            if data.update_id() > *last_update_id {
-                *last_update_id = data.update_id();
-            } else {
-                return Err(BncError::DataRejected);
+        if let Some(last_update_id) = balancer.last_update_id {
+            if data.update_id() <= last_update_id {
+                balancer.behind += 1;
+                // Redundant workers deliver the same messages, so only a long row of them means a restart.
+                let restarted =
+                    matches!(balancer.reset_after, Some(after) if balancer.behind >= after);
+                if !restarted {
+                    balancer.counters.duplicate();
+                    return Err(BncError::DataRejected);
+                }
+                warn!(
+                    "{} messages in a row are behind the update {}, re-seeding the feed from {}.",
+                    balancer.behind,
+                    last_update_id,
+                    data.update_id()
+                );
             }
-        } else {
-            balancer.last_update_id = Some(data.update_id());
+        }
+        balancer.last_update_id = Some(data.update_id());
+        balancer.behind = 0;
+        balancer.counters.accepted();
+        if let Some(history) = balancer.history.as_ref() {
+            history
+                .write()
+                .expect("History is poisoned.")
+                .push(data.clone(), Instant::now());
         }
 
         balancer
@@ -58,3 +137,91 @@ impl<B: BalancedEntity + Send + Sync> MessageSender<B> for Arc<Mutex<MessageBala
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::state::history::History;
+    use tokio::sync::watch::{channel, Receiver};
+
+    fn balancer(
+        reset_after: Option<u32>,
+    ) -> (
+        Arc<Mutex<MessageBalancer<SymbolPriceUpdate>>>,
+        Receiver<SymbolPriceUpdate>,
+    ) {
+        let (sender, receiver) = channel(SymbolPriceUpdate::default());
+        let balancer = MessageBalancer::new(sender).with_cfg(&BalancerCfg { reset_after });
+        (Arc::new(Mutex::new(balancer)), receiver)
+    }
+
+    fn update(id: u64) -> SymbolPriceUpdate {
+        SymbolPriceUpdate {
+            id,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn it_reseeds_update_id_after_reconnect() {
+        let (balancer, receiver) = balancer(Some(3));
+        for id in 100..105 {
+            balancer.send(update(id)).await.unwrap();
+        }
+
+        // Feed is restarted from the lower ids.
+        assert!(balancer.send(update(10)).await.is_err());
+        assert!(balancer.send(update(11)).await.is_err());
+        balancer.send(update(12)).await.unwrap();
+        assert_eq!(receiver.borrow().id, 12);
+
+        balancer.send(update(13)).await.unwrap();
+        assert!(balancer.send(update(13)).await.is_err());
+        assert_eq!(receiver.borrow().id, 13);
+    }
+
+    #[tokio::test]
+    async fn it_keeps_update_id_while_redundant_workers_catch_up() {
+        let (balancer, receiver) = balancer(Some(3));
+
+        // Every update is delivered by three workers, the lagging ones are behind by a few updates.
+        for id in 100..110 {
+            balancer.send(update(id)).await.unwrap();
+            assert!(balancer.send(update(id)).await.is_err());
+            assert!(balancer.send(update(id - 2)).await.is_err());
+        }
+        assert_eq!(receiver.borrow().id, 109);
+    }
+
+    #[tokio::test]
+    async fn it_records_accepted_messages_into_history() {
+        let (sender, _receiver) = channel(SymbolPriceUpdate::default());
+        let history = History::shared(2);
+        let balancer = Arc::new(Mutex::new(
+            MessageBalancer::new(sender).with_history(history.clone()),
+        ));
+
+        for id in [1, 2, 2, 3] {
+            let _ = balancer.send(update(id)).await;
+        }
+
+        let history = history.read().unwrap();
+        let ids: Vec<u64> = history
+            .entries()
+            .iter()
+            .map(|entry| entry.value.id)
+            .collect();
+        assert_eq!(ids, [2, 3]);
+    }
+
+    #[tokio::test]
+    async fn it_never_resets_without_threshold() {
+        let (balancer, receiver) = balancer(None);
+        balancer.send(update(100)).await.unwrap();
+
+        for id in 0..100 {
+            assert!(balancer.send(update(id)).await.is_err());
+        }
+        assert_eq!(receiver.borrow().id, 100);
+    }
+}
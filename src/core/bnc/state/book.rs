@@ -1,27 +1,50 @@
-use crate::core::bnc::config::BncCfg;
-use crate::core::bnc::data::{InlineOrder, PriceLevel, Qty};
+use crate::core::bnc::analytics::{BookAnalytics, MetricsReceiver};
+use crate::core::bnc::cache::{SnapshotCache, SnapshotCacheCfg};
+use crate::core::bnc::config::{BncCfg, MarketType};
+use crate::core::bnc::data::{InlineOrder, Price, Quantity};
+use crate::core::bnc::decimal::{round_to_step, RoundingMode};
 use crate::core::bnc::error::BncError::DataTransmitError;
 use crate::core::bnc::error::{BncError, BncResult};
 use crate::core::bnc::rest::BncRestClient;
 use crate::core::bnc::snapshot::{SnapshotFetcher, SymbolSnapshot};
-use crate::core::bnc::ws::worker::depth::{SymbolDepthUpdate, SymbolDepthWatcher};
+use crate::core::bnc::state::scaling::{spawn_workers, DeliveryCounters, ScalingCfg};
+use crate::core::bnc::state::tasks::TaskGroup;
+use crate::core::bnc::ws::config::{ReconnectCfg, UpdateSpeed};
+use crate::core::bnc::ws::endpoints::EndpointPool;
+use crate::core::bnc::ws::reconnect::{
+    ReconnectPolicy, ReconnectReceiver, ReconnectSender, RECONNECT_EVENTS_CAPACITY,
+};
+use crate::core::bnc::ws::worker::depth::{
+    SymbolDepthUpdate, SymbolDepthWatcher, SymbolPartialDepthWatcher,
+};
 use crate::core::bnc::ws::worker::{MessageSender, WsWorker};
-use log::debug;
-use reqwest::Client;
+use crate::core::runtime::spawn_storage;
+use log::{debug, info, warn};
+use rust_decimal::Decimal;
+use serde::Serialize;
 use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
 use tokio::sync::watch::{channel, Receiver, Sender};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 /// Mode of current Order Book.
 ///
+/// Cached is for order book restored from the local cache - it can't be updated until the fresh snapshot arrives.
+///
 /// Snapshot is for just initialised order book.
 ///
 /// Update is for order book that was updated with incremental changes.
 #[derive(Debug)]
 pub enum OrderBookMode {
+    Cached {
+        last_update_id: u64,
+    },
     Snapshot {
         last_update_id: u64,
     },
@@ -31,26 +54,209 @@ pub enum OrderBookMode {
     },
 }
 
-pub type TableDisplay = Vec<(PriceLevel, Qty)>;
+/// Rules the depth updates are chained to the book by, depending on the market they come from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DepthSync {
+    /// Each update starts right after the final id of the previous one.
+    #[default]
+    Spot,
+
+    /// Ids are not contiguous, each update refers to the final id of the previous one by its `pu`.
+    /// More info is here:
+    /// https://binance-docs.github.io/apidocs/futures/en/#how-to-manage-a-local-order-book-correctly
+    Futures,
+}
+
+impl From<MarketType> for DepthSync {
+    fn from(market: MarketType) -> Self {
+        match market {
+            MarketType::Spot => Self::Spot,
+            MarketType::Futures => Self::Futures,
+        }
+    }
+}
+
+impl DepthSync {
+    /// Whether the update follows the book in the mode, so it can be applied.
+    fn is_satisfying(&self, mode: &OrderBookMode, update: &SymbolDepthUpdate) -> bool {
+        match (self, mode) {
+            (_, OrderBookMode::Cached { .. }) => {
+                debug!("Depth update would not be merged into cached order book.");
+                return false;
+            }
+            (Self::Spot, OrderBookMode::Snapshot { last_update_id }) => {
+                // The first update is the one covering the id right after the snapshot.
+                // More info is here:
+                // https://binance-docs.github.io/apidocs/spot/en/#how-to-manage-a-local-order-book-correctly
+                if let Some(next_id) = last_update_id.checked_add(1) {
+                    if update.first_update_id <= next_id && update.final_update_id >= next_id {
+                        return true;
+                    }
+                }
+            }
+            (
+                Self::Spot,
+                OrderBookMode::Update {
+                    final_update_id, ..
+                },
+            ) => {
+                // Checked, so malformed update with zero id can't underflow.
+                if final_update_id.checked_add(1) == Some(update.first_update_id) {
+                    return true;
+                }
+            }
+            (Self::Futures, OrderBookMode::Snapshot { last_update_id }) => {
+                // The first update is the one covering the snapshot.
+                if update.first_update_id <= *last_update_id
+                    && update.final_update_id >= *last_update_id
+                {
+                    return true;
+                }
+            }
+            (
+                Self::Futures,
+                OrderBookMode::Update {
+                    final_update_id, ..
+                },
+            ) => {
+                if update.previous_final_update_id == Some(*final_update_id) {
+                    return true;
+                }
+            }
+        }
+        debug!(
+            "Depth update would not be merged into current order book.\
+            Sync: {:?}; Current book mode: {:?}; \
+            Update: first_update_id = {}, final_update_id = {}, previous_final_update_id = {:?}\
+            ",
+            self,
+            mode,
+            update.first_update_id,
+            update.final_update_id,
+            update.previous_final_update_id
+        );
+        false
+    }
+
+    /// Whether the update can't be applied to the book in the mode because some of the previous ones are missing.
+    fn is_ahead(&self, mode: &OrderBookMode, update: &SymbolDepthUpdate) -> bool {
+        match (self, mode) {
+            (
+                Self::Spot,
+                OrderBookMode::Update {
+                    final_update_id, ..
+                },
+            ) => final_update_id
+                .checked_add(1)
+                .is_some_and(|next_id| update.first_update_id > next_id),
+            (Self::Spot, OrderBookMode::Snapshot { last_update_id }) => last_update_id
+                .checked_add(1)
+                .is_some_and(|next_id| update.first_update_id > next_id),
+            (Self::Futures, OrderBookMode::Snapshot { last_update_id }) => {
+                update.first_update_id > *last_update_id
+            }
+            (
+                Self::Futures,
+                OrderBookMode::Update {
+                    final_update_id, ..
+                },
+            ) => update
+                .previous_final_update_id
+                .is_some_and(|previous_id| previous_id > *final_update_id),
+            (_, OrderBookMode::Cached { .. }) => false,
+        }
+    }
+}
+
+/// Time the redundant workers are given to deliver the missing updates before the book is resynced.
+const RESYNC_GRACE: Duration = Duration::from_millis(1000);
+
+/// Capacity of the resync events channel - lagging subscribers lose the oldest events.
+const RESYNC_EVENTS_CAPACITY: usize = 16;
+
+/// Book is rebuilt from the fresh snapshot because of a gap in the depth updates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookResync {
+    pub symbol: String,
+
+    /// Id of the latest update applied before the gap.
+    pub gap_after: u64,
+
+    /// Id of the snapshot the book is rebuilt from.
+    pub last_update_id: u64,
+}
+
+pub type ResyncSender = broadcast::Sender<BookResync>;
+pub type ResyncReceiver = broadcast::Receiver<BookResync>;
+
+/// Amount of levels of each side exported by the book, unless configured otherwise.
+pub const DEFAULT_BOOK_DEPTH: usize = 10;
+
+/// Displayed level of the book, along with the running totals from the best level down to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DisplayLevel {
+    pub price: Price,
+    pub qty: Quantity,
+
+    /// Quantity of this level and all the better ones.
+    pub cum_qty: Quantity,
+
+    /// Value in the quote asset of this level and all the better ones.
+    pub cum_notional: Decimal,
+}
+
+pub type TableDisplay = Vec<DisplayLevel>;
 pub type OrderBookReceiver = Receiver<OrderBookDisplay>;
 pub type OrderBookSender = Sender<OrderBookDisplay>;
 
+/// Side of the book - the best ask is the lowest one, while the best bid is the highest one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BookSide {
+    Asks,
+    Bids,
+}
+
+impl BookSide {
+    /// Levels are grouped away from the spread, so the bucket never looks better than the orders behind it.
+    fn rounding(self) -> RoundingMode {
+        match self {
+            Self::Asks => RoundingMode::for_asks(),
+            Self::Bids => RoundingMode::for_bids(),
+        }
+    }
+}
+
 /// Structure that provides easy access to price levels.
-struct OrderTable(BTreeMap<String, Qty>);
+///
+/// Levels are keyed by their numeric price, so `99.5` goes before `101` whatever their text is.
+struct OrderTable {
+    side: BookSide,
+    levels: BTreeMap<Price, Quantity>,
+}
 
 impl OrderTable {
-    pub fn from_orders(data: Vec<InlineOrder>) -> Self {
-        Self(data.into_iter().map(|order| (order.0, order.1)).collect())
+    pub fn from_orders(side: BookSide, data: Vec<InlineOrder>) -> Self {
+        Self {
+            side,
+            levels: data.into_iter().map(|order| (order.0, order.1)).collect(),
+        }
+    }
+
+    /// Levels of the table, the best one first - ascending asks and descending bids.
+    pub fn best_first(&self) -> Box<dyn Iterator<Item = (&Price, &Quantity)> + '_> {
+        match self.side {
+            BookSide::Asks => Box::new(self.levels.iter()),
+            BookSide::Bids => Box::new(self.levels.iter().rev()),
+        }
     }
 
     /// Update this table so level will satisfy provided order.
     pub fn update_level(&mut self, order: InlineOrder) {
-        let qty_not_empty = order.1.chars().any(|char| char != '0' && char != '.');
-        if !qty_not_empty {
-            self.0.remove(&order.0);
+        if order.1.is_zero() {
+            self.levels.remove(&order.0);
             return;
         }
-        let entry = self.0.entry(order.0);
+        let entry = self.levels.entry(order.0);
         match entry {
             Entry::Vacant(vc) => {
                 vc.insert(order.1);
@@ -61,15 +267,45 @@ impl OrderTable {
         }
     }
 
-    /// Get owned version of table's top, copying levels' identities.
+    /// Get owned version of table's top, the best level first, with the totals accumulated along the way.
     ///
-    /// It is limited to top N as it is bad for the performance.
-    pub fn owned_top(&self) -> TableDisplay {
-        self.0
-            .keys()
-            .take(10)
-            .rev()
-            .map(|key| (key.clone(), self.0.get(key).unwrap().clone()))
+    /// Levels are grouped into the buckets of the step, if any. It is limited to top N as it is bad for the performance.
+    pub fn owned_top(&self, depth: usize, grouping: Option<Decimal>) -> TableDisplay {
+        let mut top: TableDisplay = Vec::with_capacity(depth);
+        let mut cum_qty = Decimal::ZERO;
+        let mut cum_notional = Decimal::ZERO;
+        for (price, qty) in self.best_first() {
+            let bucket: Price = match grouping {
+                Some(step) => round_to_step(price.value(), step, self.side.rounding()).into(),
+                None => *price,
+            };
+            cum_qty += qty.value();
+            cum_notional += price.value() * qty.value();
+            let full = top.len() == depth;
+            match top.last_mut() {
+                // Levels are ordered, so the ones of the same bucket follow each other.
+                Some(level) if level.price == bucket => {
+                    level.qty = (level.qty.value() + qty.value()).into();
+                    level.cum_qty = cum_qty.into();
+                    level.cum_notional = cum_notional;
+                }
+                _ if full => break,
+                _ => top.push(DisplayLevel {
+                    price: bucket,
+                    qty: *qty,
+                    cum_qty: cum_qty.into(),
+                    cum_notional,
+                }),
+            }
+        }
+        top
+    }
+
+    /// Get all the levels of the table as orders, in ascending order of their prices.
+    pub fn orders(&self) -> Vec<InlineOrder> {
+        self.levels
+            .iter()
+            .map(|(level, qty)| InlineOrder::new(*level, *qty))
             .collect()
     }
 }
@@ -77,14 +313,39 @@ impl OrderTable {
 /// Holds current mode of order book and its tables.
 pub struct OrderBook {
     mode: OrderBookMode,
+    sync: DepthSync,
     bids: OrderTable,
     asks: OrderTable,
 }
 
-#[derive(Clone)]
+/// Top of the book, the best levels of each side first.
+#[derive(Clone, Serialize)]
 pub struct OrderBookDisplay {
     pub bids: TableDisplay,
     pub asks: TableDisplay,
+
+    /// Whether the book is restored from cache and is not live yet.
+    pub cached: bool,
+
+    /// Step the levels are grouped by, if they are.
+    pub grouping: Option<Decimal>,
+}
+
+impl OrderBookDisplay {
+    /// The lowest ask.
+    pub fn best_ask(&self) -> Option<Price> {
+        self.asks.first().map(|level| level.price)
+    }
+
+    /// The highest bid.
+    pub fn best_bid(&self) -> Option<Price> {
+        self.bids.first().map(|level| level.price)
+    }
+
+    /// Difference between the best ask and the best bid, if both sides are there.
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask()?.value() - self.best_bid()?.value())
+    }
 }
 
 impl From<SymbolSnapshot> for OrderBook {
@@ -93,8 +354,9 @@ impl From<SymbolSnapshot> for OrderBook {
             mode: OrderBookMode::Snapshot {
                 last_update_id: snapshot.last_update_id,
             },
-            bids: OrderTable::from_orders(snapshot.bids),
-            asks: OrderTable::from_orders(snapshot.asks),
+            sync: DepthSync::default(),
+            bids: OrderTable::from_orders(BookSide::Bids, snapshot.bids),
+            asks: OrderTable::from_orders(BookSide::Asks, snapshot.asks),
         }
     }
 }
@@ -106,13 +368,46 @@ impl From<SymbolDepthUpdate> for OrderBook {
                 first_update_id: update.first_update_id,
                 final_update_id: update.final_update_id,
             },
-            bids: OrderTable::from_orders(update.bids),
-            asks: OrderTable::from_orders(update.asks),
+            sync: DepthSync::default(),
+            bids: OrderTable::from_orders(BookSide::Bids, update.bids),
+            asks: OrderTable::from_orders(BookSide::Asks, update.asks),
         }
     }
 }
 
 impl OrderBook {
+    /// Build order book from the cached snapshot. It will reject all the updates until replaced by a fresh one.
+    pub fn from_cached(snapshot: SymbolSnapshot) -> Self {
+        Self {
+            mode: OrderBookMode::Cached {
+                last_update_id: snapshot.last_update_id,
+            },
+            sync: DepthSync::default(),
+            bids: OrderTable::from_orders(BookSide::Bids, snapshot.bids),
+            asks: OrderTable::from_orders(BookSide::Asks, snapshot.asks),
+        }
+    }
+
+    /// Id of the latest event applied to the book.
+    pub fn last_update_id(&self) -> u64 {
+        match self.mode {
+            OrderBookMode::Cached { last_update_id }
+            | OrderBookMode::Snapshot { last_update_id } => last_update_id,
+            OrderBookMode::Update {
+                final_update_id, ..
+            } => final_update_id,
+        }
+    }
+
+    /// Export current state of the book in the snapshot form.
+    pub fn to_snapshot(&self) -> SymbolSnapshot {
+        SymbolSnapshot {
+            last_update_id: self.last_update_id(),
+            bids: self.bids.orders(),
+            asks: self.asks.orders(),
+        }
+    }
+
     fn process_depth_update(&mut self, update: SymbolDepthUpdate) {
         self.mode = OrderBookMode::Update {
             first_update_id: update.first_update_id,
@@ -126,41 +421,24 @@ impl OrderBook {
         }
     }
 
+    /// Chain the updates by the rules of the market, spot ones are expected by default.
+    pub fn with_sync(mut self, sync: DepthSync) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Replace the whole book with the snapshot, keeping the rules the updates are chained by.
+    pub fn reset(&mut self, snapshot: SymbolSnapshot) {
+        *self = Self::from(snapshot).with_sync(self.sync);
+    }
+
     fn is_update_satisfying(&self, update: &SymbolDepthUpdate) -> bool {
-        match self.mode {
-            OrderBookMode::Snapshot { last_update_id } => {
-                // There should be also compare with the initial value, but it's omitted due to task preferences.
-                // More info about REAL order book management is here:
-                // https://binance-docs.github.io/apidocs/spot/en/#how-to-manage-a-local-order-book-correctly
-                // Basically it means that snapshot should go AFTER you started ur ws workers.
-                // So here is mostly incorrect logic.
-                if update.final_update_id > last_update_id {
-                    return true;
-                }
-                debug!(
-                    "Depth update would not be merged into current order book's snapshot.\
-                    Snapshot last_update_id: {last_update_id};\
-                    Update: first_update_id = {}, final_update_id = {}\
-                ",
-                    update.first_update_id, update.final_update_id
-                )
-            }
-            OrderBookMode::Update {
-                final_update_id, ..
-            } => {
-                if update.first_update_id - 1 == final_update_id {
-                    return true;
-                }
-                debug!(
-                    "Depth update would not be merged into current book incrementing state.\
-                    Current book mode: {:?}; \
-                    Current update first_id: {}; Current update final_id: {}\
-                ",
-                    self.mode, update.first_update_id, update.final_update_id
-                )
-            }
-        }
-        false
+        self.sync.is_satisfying(&self.mode, update)
+    }
+
+    /// Whether the update can't be applied because some of the previous ones are missing.
+    fn is_update_ahead(&self, update: &SymbolDepthUpdate) -> bool {
+        self.sync.is_ahead(&self.mode, update)
     }
 
     /// To be called when you want to sum received depth update with current book state.
@@ -174,10 +452,39 @@ impl OrderBook {
         is_satisfying
     }
 
-    pub fn top(&self) -> OrderBookDisplay {
+    /// To be called when the partial depth of the book is received - it replaces the whole book.
+    ///
+    /// Returns true if the partial depth is newer than the current book, false otherwise.
+    pub fn add_partial_depth(&mut self, snapshot: SymbolSnapshot) -> bool {
+        if snapshot.last_update_id <= self.last_update_id() {
+            debug!(
+                "Partial depth would not replace the newer book. Book last_update_id: {}; Partial depth last_update_id: {}",
+                self.last_update_id(),
+                snapshot.last_update_id
+            );
+            return false;
+        }
+        self.reset(snapshot);
+        true
+    }
+
+    /// Bid levels, the highest one first.
+    pub fn bids(&self) -> impl Iterator<Item = (&Price, &Quantity)> + '_ {
+        self.bids.best_first()
+    }
+
+    /// Ask levels, the lowest one first.
+    pub fn asks(&self) -> impl Iterator<Item = (&Price, &Quantity)> + '_ {
+        self.asks.best_first()
+    }
+
+    /// Top of the book, limited to the depth levels of each side, optionally grouped by the step.
+    pub fn top(&self, depth: usize, grouping: Option<Decimal>) -> OrderBookDisplay {
         OrderBookDisplay {
-            asks: self.asks.owned_top(),
-            bids: self.bids.owned_top(),
+            asks: self.asks.owned_top(depth, grouping),
+            bids: self.bids.owned_top(depth, grouping),
+            grouping,
+            cached: matches!(self.mode, OrderBookMode::Cached { .. }),
         }
     }
 }
@@ -186,6 +493,97 @@ impl OrderBook {
 struct OrderBookBalancer {
     sender: OrderBookSender,
     book: OrderBook,
+
+    /// Amount of depth updates applied to the book so far.
+    updates: Arc<AtomicU64>,
+
+    counters: Arc<DeliveryCounters>,
+
+    /// Wakes the resyncer up when a gap is detected, if the book is resynced at all.
+    resync: Option<Arc<Notify>>,
+
+    /// Id of the latest update applied before the detected gap, until the gap is filled or the book is resynced.
+    gap_after: Option<u64>,
+
+    /// Updates received while the resync snapshot is fetched, replayed once it is there.
+    buffered: Option<Vec<SymbolDepthUpdate>>,
+
+    /// Levels of each side sent to the receivers.
+    depth: usize,
+
+    /// Step the sent levels are grouped by, if any.
+    grouping: Option<Decimal>,
+
+    /// Metrics measured on every change of the book, if anybody needs them.
+    analytics: Option<BookAnalytics>,
+}
+
+impl OrderBookBalancer {
+    fn new(sender: OrderBookSender, book: OrderBook, updates: Arc<AtomicU64>) -> Self {
+        Self {
+            sender,
+            book,
+            updates,
+            counters: Default::default(),
+            resync: None,
+            gap_after: None,
+            buffered: None,
+            depth: DEFAULT_BOOK_DEPTH,
+            grouping: None,
+            analytics: None,
+        }
+    }
+
+    fn top(&self) -> OrderBookDisplay {
+        self.book.top(self.depth, self.grouping)
+    }
+
+    /// Send the top of the book to the receivers, along with its metrics.
+    fn publish(&self) -> BncResult<()> {
+        if let Some(analytics) = self.analytics.as_ref() {
+            analytics.update(&self.book);
+        }
+        self.sender.send(self.top()).map_err(|_| DataTransmitError)
+    }
+
+    /// Remember the gap and wake the resyncer up, unless it is already aware of it.
+    fn report_gap(&mut self) {
+        self.counters.gap();
+        if self.gap_after.is_some() {
+            return;
+        }
+        if let Some(resync) = self.resync.as_ref() {
+            debug!("Gap in depth updates after {}.", self.book.last_update_id());
+            self.gap_after = Some(self.book.last_update_id());
+            resync.notify_one();
+        }
+    }
+
+    /// Apply the update to the book, counting it as accepted, duplicate or the one after a gap.
+    fn apply(&mut self, update: SymbolDepthUpdate) -> bool {
+        let is_ahead = self.book.is_update_ahead(&update);
+        if !self.book.add_depth_update(update) {
+            if is_ahead {
+                self.report_gap();
+            } else {
+                self.counters.duplicate();
+            }
+            return false;
+        }
+        self.updates.fetch_add(1, Ordering::Relaxed);
+        self.counters.accepted();
+        true
+    }
+
+    /// Apply the updates buffered during the resync, returning whether any of them changed the book.
+    fn replay_buffered(&mut self) -> bool {
+        let buffered = self.buffered.take().unwrap_or_default();
+        let mut is_updated = false;
+        for update in buffered {
+            is_updated |= self.apply(update);
+        }
+        is_updated
+    }
 }
 
 #[async_trait::async_trait]
@@ -193,77 +591,470 @@ impl MessageSender<SymbolDepthUpdate> for Arc<Mutex<OrderBookBalancer>> {
     async fn send(&self, data: SymbolDepthUpdate) -> BncResult<()> {
         let mut lock = self.lock().await;
 
-        let is_updated = lock.book.add_depth_update(data);
+        if let Some(buffered) = lock.buffered.as_mut() {
+            buffered.push(data);
+            return Ok(());
+        }
+        if !lock.apply(data) {
+            return Err(BncError::DataRejected);
+        }
+
+        lock.publish()
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageSender<SymbolSnapshot> for Arc<Mutex<OrderBookBalancer>> {
+    async fn send(&self, data: SymbolSnapshot) -> BncResult<()> {
+        let mut lock = self.lock().await;
+
+        let is_updated = lock.book.add_partial_depth(data);
         if !is_updated {
+            lock.counters.duplicate();
             return Err(BncError::DataRejected);
         }
+        lock.updates.fetch_add(1, Ordering::Relaxed);
+        lock.counters.accepted();
 
-        lock.sender
-            .send(lock.book.top())
-            .map_err(|_| DataTransmitError)?;
+        lock.publish()
+    }
+}
+
+/// Fetch fresh snapshot and replace the cached book of the balancer with it.
+///
+/// Failed fetches are repeated with the backoff of the client's retry policy until one succeeds or the task is cancelled.
+fn snapshot_refresher(
+    client: BncRestClient,
+    symbol: String,
+    balancer: Arc<Mutex<OrderBookBalancer>>,
+    cancel: CancellationToken,
+) -> JoinHandle<BncResult<()>> {
+    tokio::task::spawn(async move {
+        let mut round = 0;
+        let snapshot = loop {
+            let snapshot = tokio::select! {
+                snapshot = client.fetch_snapshot(&symbol) => snapshot,
+                _ = cancel.cancelled() => return Ok(()),
+            };
+            match snapshot {
+                Ok(snapshot) => break snapshot,
+                Err(err) => {
+                    round += 1;
+                    let delay = client.retry_cfg().delay(round);
+                    warn!(
+                        "Could not fetch fresh snapshot of {}, book stays cached for {:?} more. Error: {}",
+                        symbol, delay, err
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = cancel.cancelled() => return Ok(()),
+                    }
+                }
+            }
+        };
+
+        let mut lock = balancer.lock().await;
+        lock.book.reset(snapshot);
+        lock.publish()?;
+        info!(
+            "Cached book of {} is replaced with the fresh snapshot.",
+            symbol
+        );
 
         Ok(())
-    }
+    })
+}
+
+/// Rebuild the book from the fresh snapshot whenever the gap in its updates is not filled in the grace time.
+fn book_resyncer(
+    client: BncRestClient,
+    symbol: String,
+    balancer: Arc<Mutex<OrderBookBalancer>>,
+    resync: Arc<Notify>,
+    events: ResyncSender,
+    cancel: CancellationToken,
+) -> JoinHandle<BncResult<()>> {
+    tokio::task::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = resync.notified() => {}
+                _ = cancel.cancelled() => break,
+            }
+            tokio::time::sleep(RESYNC_GRACE).await;
+
+            let gap_after = {
+                let mut lock = balancer.lock().await;
+                match lock.gap_after {
+                    Some(gap_after) if gap_after == lock.book.last_update_id() => {
+                        // Updates are kept from now on, the snapshot is likely behind some of them.
+                        lock.buffered = Some(Vec::new());
+                        gap_after
+                    }
+                    _ => {
+                        debug!("Gap in depth updates of {} is filled by workers.", symbol);
+                        lock.gap_after = None;
+                        continue;
+                    }
+                }
+            };
+
+            warn!(
+                "Depth updates of {} are missing after {}, resyncing the book.",
+                symbol, gap_after
+            );
+            let snapshot = client.fetch_snapshot(&symbol).await;
+
+            let mut lock = balancer.lock().await;
+            // Next gap is reported again, whatever the result is.
+            lock.gap_after = None;
+            let snapshot = match snapshot {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    warn!("Could not resync book of {}. Error: {}", symbol, err);
+                    // Late updates of the redundant workers may still fill the gap.
+                    if lock.replay_buffered() {
+                        lock.publish()?;
+                    }
+                    continue;
+                }
+            };
+            let last_update_id = snapshot.last_update_id;
+            lock.book.reset(snapshot);
+            lock.replay_buffered();
+            lock.publish()?;
+            info!(
+                "Book of {} is resynced with the snapshot {}.",
+                symbol, last_update_id
+            );
+
+            // Nobody listens - nothing to report.
+            let _ = events.send(BookResync {
+                symbol: symbol.clone(),
+                gap_after,
+                last_update_id,
+            });
+        }
+        debug!("Resyncer of {} is stopped.", symbol);
+        Ok(())
+    })
+}
+
+/// Periodically store current state of the book into the cache. It is stored the last time once cancelled.
+fn snapshot_persister(
+    cache: SnapshotCache,
+    symbol: String,
+    interval: Duration,
+    balancer: Arc<Mutex<OrderBookBalancer>>,
+    cancel: CancellationToken,
+) -> JoinHandle<BncResult<()>> {
+    spawn_storage(async move {
+        let mut interval = tokio::time::interval(interval);
+        let mut stopping = false;
+        while !stopping {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = cancel.cancelled() => stopping = true,
+            }
+
+            let snapshot = {
+                let lock = balancer.lock().await;
+                if let OrderBookMode::Cached { .. } = lock.book.mode {
+                    continue;
+                }
+                lock.book.to_snapshot()
+            };
+
+            match cache.store(&symbol, &snapshot).await {
+                Ok(_) => debug!("Stored snapshot of {} into the cache.", symbol),
+                Err(err) => warn!("Could not cache snapshot of {}. Error: {}", symbol, err),
+            }
+        }
+        debug!("Persister of {} is stopped.", symbol);
+        Ok(())
+    })
 }
 
 /// Settings for order book manager.
 ///
 /// Just an encapsulation over ordinary app's configuration.
-struct ManagerCfg<'a> {
+struct ManagerCfg {
     workers: u64,
-    ws_conn_url: &'a str,
-    rest_conn_url: &'a str,
+    ws_conn_url: String,
+    endpoints: EndpointPool,
+    rest: BncCfg,
+    cache: SnapshotCacheCfg,
+
+    /// Levels of the partial book stream, if the book is fed by it instead of the diff one.
+    partial_depth: Option<u8>,
+
+    update_speed: UpdateSpeed,
+
+    /// Rules the depth updates are chained by.
+    sync: DepthSync,
+
+    reconnect: ReconnectCfg,
+
+    scaling: ScalingCfg,
+
+    /// Levels of each side sent to the receivers.
+    depth: usize,
 }
 
-impl<'a> ManagerCfg<'a> {
-    fn from_cfg(cfg: &'a BncCfg) -> Self {
+impl ManagerCfg {
+    fn from_cfg(cfg: &BncCfg) -> Self {
         Self {
-            workers: cfg.ws.workers,
-            ws_conn_url: &cfg.ws.baseurl,
-            rest_conn_url: &cfg.baseurl,
+            workers: cfg.ws.depth_workers_count(),
+            ws_conn_url: cfg.ws.baseurl.primary().to_string(),
+            endpoints: EndpointPool::new(cfg.ws.baseurl.all()),
+            rest: cfg.clone(),
+            cache: cfg.cache.clone(),
+            partial_depth: cfg.ws.partial_depth,
+            update_speed: cfg.ws.update_speed,
+            sync: DepthSync::from(cfg.market),
+            reconnect: cfg.ws.reconnect.clone(),
+            scaling: cfg.ws.scaling.clone(),
+            depth: cfg.book_depth,
         }
     }
 }
 
 /// Schedules workers to update order book in realtime, provide notifications of its updates.
-pub struct OrderBookManager<'a> {
-    cfg: ManagerCfg<'a>,
-    tasks: Vec<JoinHandle<BncResult<()>>>,
+pub struct OrderBookManager {
+    cfg: ManagerCfg,
+    tasks: TaskGroup,
+    updates: Arc<AtomicU64>,
+    reconnects: ReconnectSender,
+    resyncs: ResyncSender,
+
+    /// Step the displayed levels are grouped by, if any.
+    grouping: Option<Decimal>,
+
+    /// Balancer of the initialised book, to regroup its top right away.
+    balancer: Option<Arc<Mutex<OrderBookBalancer>>>,
+
+    /// Stops the scheduled tasks.
+    cancel: CancellationToken,
+
+    analytics: BookAnalytics,
 }
 
-impl<'a> OrderBookManager<'a> {
+impl OrderBookManager {
     /// Schedule workers, get receiver of current book's top.
+    ///
+    /// If the symbol's book is cached, it is returned immediately and the fresh snapshot is fetched in the background.
     pub async fn init(&mut self, symbol: &str) -> BncResult<OrderBookReceiver> {
-        let client = BncRestClient::new(Client::new(), self.cfg.rest_conn_url.to_string());
-        let snapshot = client.fetch_snapshot(symbol).await?;
-        let book = OrderBook::from(snapshot);
+        self.cancel = CancellationToken::new();
+        self.tasks = TaskGroup::new(format!("{} depth", symbol));
+        if let Some(levels) = self.cfg.partial_depth {
+            return Ok(self.init_partial(symbol, levels));
+        }
+
+        let client = BncRestClient::from_cfg(&self.cfg.rest)?;
+        let cache = SnapshotCache::from_cfg(&self.cfg.cache);
+
+        let cached = match cache.as_ref() {
+            Some(cache) => cache.load(symbol).await,
+            None => None,
+        };
+        let is_cached = cached.is_some();
+        let book = match cached {
+            Some(snapshot) => {
+                info!(
+                    "Displaying cached book of {} until fresh one arrives.",
+                    symbol
+                );
+                OrderBook::from_cached(snapshot)
+            }
+            None => OrderBook::from(client.fetch_snapshot(symbol).await?),
+        }
+        .with_sync(self.cfg.sync);
 
-        let (sender, receiver) = channel(book.top());
+        let (sender, receiver) = channel(book.top(self.cfg.depth, self.grouping));
+        let counters = Arc::new(DeliveryCounters::default());
+        let resync = Arc::new(Notify::new());
 
-        let balancer = Arc::new(Mutex::new(OrderBookBalancer { sender, book }));
+        let balancer = Arc::new(Mutex::new(OrderBookBalancer {
+            counters: counters.clone(),
+            resync: Some(resync.clone()),
+            depth: self.cfg.depth,
+            grouping: self.grouping,
+            analytics: Some(self.analytics.clone()),
+            ..OrderBookBalancer::new(sender, book, self.updates.clone())
+        }));
+        self.balancer = Some(balancer.clone());
 
-        let worker = WsWorker::new(self.cfg.ws_conn_url);
-        let mut tasks = vec![];
+        self.tasks.adopt(
+            "resyncer",
+            book_resyncer(
+                client.clone(),
+                symbol.to_string(),
+                balancer.clone(),
+                resync,
+                self.resyncs.clone(),
+                self.cancel.clone(),
+            ),
+        );
 
-        for i in 0..self.cfg.workers {
-            debug!("Initialised #{} worker of symbol depth receiver.", i);
-            tasks.push(worker.depth_updates_watcher(symbol, balancer.clone()));
+        if is_cached {
+            self.tasks.adopt(
+                "refresher",
+                snapshot_refresher(
+                    client,
+                    symbol.to_string(),
+                    balancer.clone(),
+                    self.cancel.clone(),
+                ),
+            );
         }
 
-        self.tasks = tasks;
+        if let Some(cache) = cache {
+            self.tasks.adopt(
+                "persister",
+                snapshot_persister(
+                    cache,
+                    symbol.to_string(),
+                    Duration::from_millis(self.cfg.cache.interval),
+                    balancer.clone(),
+                    self.cancel.clone(),
+                ),
+            );
+        }
+
+        let base_url = self.cfg.ws_conn_url.clone();
+        let reconnect = self.reconnect_policy();
+        let symbol = symbol.to_string();
+        let speed = self.cfg.update_speed;
+        debug!(
+            "Initialising {} workers of symbol depth receiver.",
+            self.cfg.workers
+        );
+        let workers = spawn_workers(
+            &self.cfg.scaling,
+            self.cfg.workers,
+            counters,
+            self.tasks.scaled_workers(),
+            self.cancel.clone(),
+            move || {
+                WsWorker::new(&base_url)
+                    .with_reconnect(reconnect.clone())
+                    .depth_updates_watcher(&symbol, speed, balancer.clone())
+            },
+        );
+        self.tasks.adopt_all("worker", workers);
 
         Ok(receiver)
     }
 
-    /// Terminate scheduled tasks.
+    /// Schedule workers of the partial book stream. No snapshot is needed - every message is the whole top of the book.
+    fn init_partial(&mut self, symbol: &str, levels: u8) -> OrderBookReceiver {
+        let book = OrderBook::from(SymbolSnapshot {
+            last_update_id: 0,
+            bids: vec![],
+            asks: vec![],
+        });
+        let (sender, receiver) = channel(book.top(self.cfg.depth, self.grouping));
+        let counters = Arc::new(DeliveryCounters::default());
+
+        let balancer = Arc::new(Mutex::new(OrderBookBalancer {
+            counters: counters.clone(),
+            depth: self.cfg.depth,
+            grouping: self.grouping,
+            analytics: Some(self.analytics.clone()),
+            ..OrderBookBalancer::new(sender, book, self.updates.clone())
+        }));
+        self.balancer = Some(balancer.clone());
+
+        let base_url = self.cfg.ws_conn_url.clone();
+        let reconnect = self.reconnect_policy();
+        let symbol = symbol.to_string();
+        debug!(
+            "Initialising {} workers of symbol partial depth receiver.",
+            self.cfg.workers
+        );
+        let workers = spawn_workers(
+            &self.cfg.scaling,
+            self.cfg.workers,
+            counters,
+            self.tasks.scaled_workers(),
+            self.cancel.clone(),
+            move || {
+                WsWorker::new(&base_url)
+                    .with_reconnect(reconnect.clone())
+                    .partial_depth_watcher(&symbol, levels, balancer.clone())
+            },
+        );
+        self.tasks.adopt_all("worker", workers);
+
+        receiver
+    }
+
+    /// Subscribe to the reconnect events of the scheduled workers.
+    pub fn reconnects(&self) -> ReconnectReceiver {
+        self.reconnects.subscribe()
+    }
+
+    /// Subscribe to the resyncs of the book caused by gaps in its updates.
+    pub fn resyncs(&self) -> ResyncReceiver {
+        self.resyncs.subscribe()
+    }
+
+    /// Subscribe to the microstructure metrics of the book, measured on its every change.
+    pub fn metrics(&self) -> MetricsReceiver {
+        self.analytics.subscribe()
+    }
+
+    fn reconnect_policy(&self) -> ReconnectPolicy {
+        ReconnectPolicy::new(self.cfg.reconnect.clone(), Some(self.reconnects.clone()))
+            .with_endpoints(self.cfg.endpoints.clone())
+            .with_cancel(self.cancel.clone())
+    }
+
+    /// Let the scheduled tasks finish - workers close their connections, the book is cached the last time.
     pub fn stop(&self) {
-        self.tasks.iter().for_each(|task| task.abort());
+        self.cancel.cancel();
+    }
+
+    /// Wait for the stopped tasks to finish. Returns the first error any of them failed with.
+    pub async fn join(&mut self) -> BncResult<()> {
+        self.tasks.join().await
+    }
+
+    /// Amount of scheduled tasks that are still running.
+    pub fn alive_tasks(&self) -> usize {
+        self.tasks.alive()
+    }
+
+    /// Amount of depth updates applied to the book since it was initialised.
+    pub fn applied_updates(&self) -> u64 {
+        self.updates.load(Ordering::Relaxed)
+    }
+
+    /// Group the levels of the book's top into buckets of the step, none to display the levels as they are.
+    ///
+    /// The top is regrouped and sent to the receivers without waiting for the next update.
+    pub fn set_grouping(&mut self, grouping: Option<Decimal>) {
+        self.grouping = grouping;
+        if let Some(balancer) = self.balancer.clone() {
+            tokio::task::spawn(async move {
+                let mut lock = balancer.lock().await;
+                lock.grouping = grouping;
+                // Nobody listens - nothing to regroup for.
+                let _ = lock.sender.send(lock.top());
+            });
+        }
     }
 
-    pub fn from_cfg(cfg: &'a BncCfg) -> Self {
+    pub fn from_cfg(cfg: &BncCfg) -> Self {
         Self {
             cfg: ManagerCfg::from_cfg(cfg),
-            tasks: vec![],
+            tasks: TaskGroup::new("depth"),
+            updates: Arc::new(AtomicU64::new(0)),
+            reconnects: broadcast::channel(RECONNECT_EVENTS_CAPACITY).0,
+            resyncs: broadcast::channel(RESYNC_EVENTS_CAPACITY).0,
+            grouping: None,
+            balancer: None,
+            cancel: Default::default(),
+            analytics: BookAnalytics::new(cfg.book_analytics.clone()),
         }
     }
 }
@@ -276,6 +1067,270 @@ mod tests {
     use anyhow::Result;
     use std::ops::Deref;
 
+    fn order(level: &str, qty: &str) -> InlineOrder {
+        InlineOrder::new(level.parse().unwrap(), qty.parse().unwrap())
+    }
+
+    fn test_snapshot() -> SymbolSnapshot {
+        SymbolSnapshot {
+            last_update_id: 10,
+            bids: vec![order("1.0", "5")],
+            asks: vec![order("1.1", "4")],
+        }
+    }
+
+    fn test_update(first_update_id: u64, final_update_id: u64) -> SymbolDepthUpdate {
+        SymbolDepthUpdate {
+            first_update_id,
+            final_update_id,
+            previous_final_update_id: None,
+            bids: vec![order("1.0", "0.000")],
+            asks: vec![],
+        }
+    }
+
+    fn futures_update(
+        first_update_id: u64,
+        final_update_id: u64,
+        previous_final_update_id: u64,
+    ) -> SymbolDepthUpdate {
+        SymbolDepthUpdate {
+            previous_final_update_id: Some(previous_final_update_id),
+            ..test_update(first_update_id, final_update_id)
+        }
+    }
+
+    #[test]
+    fn it_rejects_updates_of_cached_book() {
+        let mut book = OrderBook::from_cached(test_snapshot());
+
+        assert!(book.top(DEFAULT_BOOK_DEPTH, None).cached);
+        assert!(!book.add_depth_update(test_update(11, 12)));
+        assert_eq!(book.to_snapshot().bids, test_snapshot().bids);
+    }
+
+    #[test]
+    fn it_rejects_updates_with_malformed_ids() {
+        let mut book = OrderBook::from(test_snapshot());
+        assert!(book.add_depth_update(test_update(11, u64::MAX)));
+
+        assert!(!book.add_depth_update(test_update(0, 0)));
+        assert!(!book.add_depth_update(test_update(u64::MAX, u64::MAX)));
+    }
+
+    #[test]
+    fn it_exports_updated_book_as_snapshot() {
+        let mut book = OrderBook::from(test_snapshot());
+
+        assert!(book.add_depth_update(test_update(11, 12)));
+        let snapshot = book.to_snapshot();
+        assert_eq!(snapshot.last_update_id, 12);
+        assert!(snapshot.bids.is_empty());
+        assert_eq!(snapshot.asks, test_snapshot().asks);
+    }
+
+    #[test]
+    fn it_shows_best_levels_first() {
+        let book = OrderBook::from(SymbolSnapshot {
+            last_update_id: 1,
+            bids: vec![order("9.5", "1"), order("10.5", "1"), order("100", "1")],
+            asks: vec![order("1000", "1"), order("101", "1"), order("99.5", "1")],
+        });
+
+        let top = book.top(DEFAULT_BOOK_DEPTH, None);
+        let levels = |table: &TableDisplay| -> Vec<String> {
+            table.iter().map(|level| level.price.to_string()).collect()
+        };
+        assert_eq!(levels(&top.bids), ["100", "10.5", "9.5"]);
+        assert_eq!(levels(&top.asks), ["99.5", "101", "1000"]);
+        assert_eq!(top.spread(), Some("-0.5".parse().unwrap()));
+
+        let top = book.top(2, None);
+        assert_eq!(levels(&top.bids), ["100", "10.5"]);
+        assert_eq!(levels(&top.asks), ["99.5", "101"]);
+    }
+
+    #[test]
+    fn it_groups_levels_away_from_spread() {
+        let book = OrderBook::from(SymbolSnapshot {
+            last_update_id: 1,
+            bids: vec![order("99.9", "1"), order("99.4", "2"), order("98.7", "3")],
+            asks: vec![
+                order("100.1", "1"),
+                order("100.4", "2"),
+                order("101.2", "3"),
+            ],
+        });
+
+        let top = book.top(2, Some("0.5".parse().unwrap()));
+        let levels = |table: &TableDisplay| -> Vec<(String, String, String)> {
+            table
+                .iter()
+                .map(|level| {
+                    (
+                        level.price.to_string(),
+                        level.qty.to_string(),
+                        level.cum_qty.to_string(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            levels(&top.bids),
+            [
+                ("99.5".into(), "1".into(), "1".into()),
+                ("99.0".into(), "2".into(), "3".into())
+            ]
+        );
+        assert_eq!(
+            levels(&top.asks),
+            [
+                ("100.5".into(), "3".into(), "3".into()),
+                ("101.5".into(), "3".into(), "6".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn it_accumulates_depth_totals() {
+        let book = OrderBook::from(SymbolSnapshot {
+            last_update_id: 1,
+            bids: vec![order("99", "2"), order("100", "1.5")],
+            asks: vec![order("102", "0.5"), order("101", "1")],
+        });
+
+        let top = book.top(DEFAULT_BOOK_DEPTH, None);
+        let totals = |table: &TableDisplay| -> Vec<(String, String)> {
+            table
+                .iter()
+                .map(|level| (level.cum_qty.to_string(), level.cum_notional.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            totals(&top.bids),
+            [
+                ("1.5".into(), "150.0".into()),
+                ("3.5".into(), "348.0".into())
+            ]
+        );
+        assert_eq!(
+            totals(&top.asks),
+            [("1".into(), "101".into()), ("1.5".into(), "152.0".into())]
+        );
+    }
+
+    #[test]
+    fn it_detects_gaps_in_updates() {
+        let mut book = OrderBook::from(test_snapshot());
+        assert!(book.is_update_ahead(&test_update(20, 21)));
+        assert!(!book.is_update_ahead(&test_update(5, 9)));
+
+        assert!(book.add_depth_update(test_update(11, 12)));
+        assert!(!book.is_update_ahead(&test_update(11, 12)));
+        assert!(!book.is_update_ahead(&test_update(13, 14)));
+        assert!(book.is_update_ahead(&test_update(15, 16)));
+    }
+
+    #[test]
+    fn it_applies_only_update_covering_spot_snapshot() {
+        let mut book = OrderBook::from(test_snapshot());
+        // Stale one, the one ending at the snapshot and the one starting after the next id.
+        assert!(!book.add_depth_update(test_update(5, 9)));
+        assert!(!book.add_depth_update(test_update(8, 10)));
+        assert!(!book.add_depth_update(test_update(12, 13)));
+
+        assert!(book.add_depth_update(test_update(9, 13)));
+        assert_eq!(book.last_update_id(), 13);
+    }
+
+    #[test]
+    fn it_chains_futures_updates_by_previous_id() {
+        let mut book = OrderBook::from(test_snapshot()).with_sync(DepthSync::Futures);
+        // Stale one and the one not covering the snapshot.
+        assert!(!book.add_depth_update(futures_update(5, 9, 4)));
+        assert!(!book.is_update_ahead(&futures_update(5, 9, 4)));
+        assert!(book.is_update_ahead(&futures_update(11, 12, 10)));
+
+        assert!(book.add_depth_update(futures_update(8, 15, 7)));
+        // Ids are not contiguous, only the previous one matters.
+        assert!(book.add_depth_update(futures_update(20, 25, 15)));
+        assert!(!book.add_depth_update(futures_update(26, 27, 24)));
+        assert!(!book.is_update_ahead(&futures_update(26, 27, 24)));
+        assert!(book.is_update_ahead(&futures_update(30, 32, 28)));
+        assert_eq!(book.last_update_id(), 25);
+
+        // Spot update following the book is not enough.
+        assert!(!book.add_depth_update(test_update(26, 27)));
+
+        book.reset(test_snapshot());
+        assert_eq!(book.sync, DepthSync::Futures);
+    }
+
+    #[test]
+    fn it_replaces_book_with_newer_partial_depth() {
+        let mut book = OrderBook::from(test_snapshot());
+
+        assert!(!book.add_partial_depth(test_snapshot()));
+        let partial = SymbolSnapshot {
+            last_update_id: 11,
+            bids: vec![],
+            asks: vec![order("1.2", "1")],
+        };
+        assert!(book.add_partial_depth(partial.clone()));
+        assert_eq!(book.to_snapshot().asks, partial.asks);
+        assert!(book.to_snapshot().bids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_reports_gap_to_resyncer_once() {
+        let book = OrderBook::from(test_snapshot());
+        let (sender, _receiver) = channel(book.top(DEFAULT_BOOK_DEPTH, None));
+        let resync = Arc::new(Notify::new());
+        let balancer = Arc::new(Mutex::new(OrderBookBalancer {
+            resync: Some(resync.clone()),
+            ..OrderBookBalancer::new(sender, book, Default::default())
+        }));
+
+        assert!(balancer.send(test_update(11, 12)).await.is_ok());
+        assert!(balancer.send(test_update(15, 16)).await.is_err());
+        assert!(balancer.send(test_update(17, 18)).await.is_err());
+
+        let lock = balancer.lock().await;
+        assert_eq!(lock.gap_after, Some(12));
+        assert_eq!(lock.counters.stats().gaps, 2);
+        resync.notified().await;
+    }
+
+    #[tokio::test]
+    async fn it_replays_updates_buffered_during_resync() {
+        let book = OrderBook::from(test_snapshot());
+        let (sender, _receiver) = channel(book.top(DEFAULT_BOOK_DEPTH, None));
+        let balancer = Arc::new(Mutex::new(OrderBookBalancer {
+            buffered: Some(Vec::new()),
+            ..OrderBookBalancer::new(sender, book, Default::default())
+        }));
+
+        for update in [
+            test_update(15, 16),
+            test_update(17, 18),
+            test_update(19, 20),
+        ] {
+            assert!(balancer.send(update).await.is_ok());
+        }
+        let mut lock = balancer.lock().await;
+        assert_eq!(lock.book.last_update_id(), 10);
+
+        lock.book.reset(SymbolSnapshot {
+            last_update_id: 17,
+            ..test_snapshot()
+        });
+        assert!(lock.replay_buffered());
+        assert!(lock.buffered.is_none());
+        assert_eq!(lock.book.last_update_id(), 20);
+        let stats = lock.counters.stats();
+        assert_eq!((stats.accepted, stats.duplicates), (2, 1));
+    }
+
     #[tokio::test]
     async fn it_watches_for_book_updates() -> Result<()> {
         let cfg = AppCfg::load()?;
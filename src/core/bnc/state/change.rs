@@ -0,0 +1,91 @@
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Window the price change of the ticker strip is measured over.
+pub const CHANGE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Recent prices of the symbol, just enough to tell their change over the window.
+#[derive(Debug, Clone)]
+pub struct PriceChange {
+    window: Duration,
+
+    /// Prices with the moments they were observed at. Only changes of the price are kept.
+    samples: VecDeque<(Instant, Decimal)>,
+}
+
+impl PriceChange {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Account the price observed at the moment.
+    pub fn record(&mut self, price: Decimal, now: Instant) {
+        if self.last() != Some(price) {
+            self.samples.push_back((now, price));
+        }
+
+        // The latest price older than the window is kept - it is the one the change is measured against.
+        while self.samples.len() > 1 && now.duration_since(self.samples[1].0) >= self.window {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Latest observed price.
+    pub fn last(&self) -> Option<Decimal> {
+        self.samples.back().map(|(_, price)| *price)
+    }
+
+    /// Change of the price over the window in percents, measured against the oldest price until the window is full.
+    pub fn change(&self) -> Option<Decimal> {
+        let (_, first) = self.samples.front()?;
+        let last = self.last()?;
+        if first.is_zero() {
+            return None;
+        }
+        Some((last - first) / first * Decimal::ONE_HUNDRED)
+    }
+}
+
+impl Default for PriceChange {
+    fn default() -> Self {
+        Self::new(CHANGE_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::decimal::parse;
+
+    #[test]
+    fn it_measures_change_over_window() {
+        let start = Instant::now();
+        let mut change = PriceChange::default();
+        assert_eq!(change.change(), None);
+
+        change.record(parse("100").unwrap(), start);
+        change.record(parse("110").unwrap(), start + Duration::from_secs(30));
+        assert_eq!(change.change(), Some(parse("10").unwrap()));
+
+        // Price of 100 is out of the window, but 110 was the price a minute ago.
+        change.record(parse("121").unwrap(), start + Duration::from_secs(95));
+        assert_eq!(change.change(), Some(parse("10").unwrap()));
+        assert_eq!(change.last(), Some(parse("121").unwrap()));
+    }
+
+    #[test]
+    fn it_keeps_only_price_changes() {
+        let start = Instant::now();
+        let mut change = PriceChange::default();
+        for second in 0..120 {
+            change.record(parse("100").unwrap(), start + Duration::from_secs(second));
+        }
+
+        assert_eq!(change.samples.len(), 1);
+        assert_eq!(change.change(), Some(Decimal::ZERO));
+    }
+}
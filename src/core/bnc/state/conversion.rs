@@ -0,0 +1,148 @@
+use crate::core::bnc::data::Price;
+use crate::core::bnc::decimal::{format_decimal, RoundingMode};
+use crate::core::bnc::error::BncResult;
+use crate::core::bnc::state::price::{PriceReceiver, PriceStateManager};
+use crate::core::bnc::ws::config::WsCfg;
+use crate::core::bnc::ws::worker::price::SymbolPriceUpdate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// Secondary valuation of the displayed prices, e.g. in EUR next to the USDT ones.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConversionCfg {
+    /// Pair which price is used as the conversion rate, e.g. `EURUSDT`.
+    pub pair: String,
+
+    /// Whether prices are divided by the rate rather than multiplied.
+    ///
+    /// Set it if the displayed quote currency is the quote of the pair as well, e.g. USDT prices via `EURUSDT`.
+    #[serde(default)]
+    pub invert: bool,
+
+    /// Label of the target currency, e.g. `EUR`.
+    pub currency: String,
+
+    /// Decimal places of the converted values.
+    #[serde(default = "default_decimals")]
+    pub decimals: u32,
+}
+
+fn default_decimals() -> u32 {
+    2
+}
+
+/// Conversion rate known at the moment.
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    pub currency: String,
+    rate: Option<Decimal>,
+    invert: bool,
+    decimals: u32,
+}
+
+impl Conversion {
+    /// Convert the price level, if the rate is already known.
+    pub fn convert(&self, level: Price) -> Option<String> {
+        let rate = self.rate.filter(|rate| !rate.is_zero())?;
+        let level = level.value();
+        let value = if self.invert {
+            level / rate
+        } else {
+            level * rate
+        };
+        Some(format_decimal(value, self.decimals, RoundingMode::Nearest))
+    }
+}
+
+/// Middle of the best bid and ask, if both are known. Zero price is the one of the update that is not received yet.
+pub fn mid_price(update: &SymbolPriceUpdate) -> Option<Decimal> {
+    let bid = update.bid.level();
+    let ask = update.ask.level();
+    if bid.is_zero() || ask.is_zero() {
+        return None;
+    }
+    Some((bid.value() + ask.value()) / Decimal::TWO)
+}
+
+/// Keeps the best prices feed of the conversion pair in the background.
+pub struct ConversionManager {
+    cfg: ConversionCfg,
+    price_manager: PriceStateManager,
+    price_watcher: PriceReceiver,
+}
+
+impl ConversionManager {
+    pub fn start(cfg: ConversionCfg, ws: &WsCfg) -> Self {
+        let mut price_manager = PriceStateManager::from_cfg(ws);
+        let price_watcher = price_manager.init(&cfg.pair.to_ascii_uppercase());
+        Self {
+            cfg,
+            price_manager,
+            price_watcher,
+        }
+    }
+
+    /// Conversion by the latest rate.
+    pub fn conversion(&self) -> Conversion {
+        Conversion {
+            currency: self.cfg.currency.clone(),
+            rate: mid_price(&self.price_watcher.borrow()),
+            invert: self.cfg.invert,
+            decimals: self.cfg.decimals,
+        }
+    }
+
+    pub fn stop(&self) {
+        self.price_manager.stop();
+    }
+
+    /// Wait for the stopped feed to finish. Returns the first error its tasks failed with.
+    pub async fn join(&mut self) -> BncResult<()> {
+        self.price_manager.join().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::data::InlineOrder;
+
+    fn level(level: &str) -> Price {
+        level.parse().unwrap()
+    }
+
+    fn conversion(bid: &str, ask: &str, invert: bool) -> Conversion {
+        let update = SymbolPriceUpdate::new(
+            1,
+            InlineOrder::new(bid.parse().unwrap(), "1".parse().unwrap()),
+            InlineOrder::new(ask.parse().unwrap(), "1".parse().unwrap()),
+        );
+        Conversion {
+            currency: "EUR".into(),
+            rate: mid_price(&update),
+            invert,
+            decimals: 2,
+        }
+    }
+
+    #[test]
+    fn it_converts_by_mid_price() {
+        assert_eq!(
+            conversion("1.0800", "1.0900", true).convert(level("27000.00")),
+            Some("24884.79".into())
+        );
+        assert_eq!(
+            conversion("0.92", "0.94", false).convert(level("27000.00")),
+            Some("25110.00".into())
+        );
+    }
+
+    #[test]
+    fn it_skips_unknown_rate() {
+        assert_eq!(conversion("0", "0", true).convert(level("27000.00")), None);
+        assert_eq!(
+            conversion("0", "1.09", true).convert(level("27000.00")),
+            None
+        );
+    }
+}
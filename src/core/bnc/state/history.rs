@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+/// Value of the history along with the moment it was recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timed<T> {
+    pub time: Instant,
+    pub value: T,
+}
+
+/// The latest values of a feed, the oldest first. The oldest ones are dropped once it is full.
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    entries: VecDeque<Timed<T>>,
+    capacity: usize,
+}
+
+/// History shared by the feed that records it and its readers.
+pub type SharedHistory<T> = Arc<RwLock<History<T>>>;
+
+impl<T> History<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Empty history to be shared.
+    pub fn shared(capacity: usize) -> SharedHistory<T> {
+        Arc::new(RwLock::new(Self::new(capacity)))
+    }
+
+    pub fn push(&mut self, value: T, time: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Timed { time, value });
+    }
+
+    pub fn entries(&self) -> &VecDeque<Timed<T>> {
+        &self.entries
+    }
+
+    pub fn latest(&self) -> Option<&Timed<T>> {
+        self.entries.back()
+    }
+
+    /// Entries recorded after the moment, the oldest first.
+    pub fn since(&self, time: Instant) -> impl Iterator<Item = &Timed<T>> + '_ {
+        let start = self.entries.partition_point(|entry| entry.time <= time);
+        self.entries.range(start..)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn it_keeps_latest_entries() {
+        let start = Instant::now();
+        let mut history = History::new(3);
+        for value in 0..5u64 {
+            history.push(value, start + Duration::from_secs(value));
+        }
+
+        let values: Vec<u64> = history.entries().iter().map(|entry| entry.value).collect();
+        assert_eq!(values, [2, 3, 4]);
+        assert_eq!(history.latest().map(|entry| entry.value), Some(4));
+
+        let recent: Vec<u64> = history
+            .since(start + Duration::from_secs(2))
+            .map(|entry| entry.value)
+            .collect();
+        assert_eq!(recent, [3, 4]);
+    }
+}
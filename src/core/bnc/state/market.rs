@@ -0,0 +1,182 @@
+use crate::core::bnc::analytics::MetricsReceiver;
+use crate::core::bnc::config::BncCfg;
+use crate::core::bnc::error::BncResult;
+use crate::core::bnc::rest::BncRestClient;
+use crate::core::bnc::sink::forward;
+use crate::core::bnc::state::average::{AveragePriceManager, AveragePriceReceiver};
+use crate::core::bnc::state::book::{
+    BookResync, OrderBookManager, OrderBookReceiver, ResyncReceiver,
+};
+use crate::core::bnc::state::price::{PriceReceiver, PriceStateManager};
+use crate::core::bnc::state::spread::{SpreadReceiver, SpreadTracker, SPREAD_HISTORY_CAPACITY};
+use crate::core::bnc::state::volume::{VolumeReceiver, VolumeTracker};
+use crate::core::bnc::ws::config::WsCfg;
+use crate::core::recorder::{RecorderCfg, RecorderManager};
+use rust_decimal::Decimal;
+use tokio::sync::broadcast::error::TryRecvError;
+
+/// Holds all the feeds of a single symbol together with receivers of their current state.
+pub struct MarketManager {
+    symbol: String,
+
+    price_manager: PriceStateManager,
+    order_book_manager: OrderBookManager,
+    average_manager: AveragePriceManager,
+    volume_tracker: VolumeTracker,
+
+    price_watcher: PriceReceiver,
+    book_watcher: OrderBookReceiver,
+    book_resyncs: ResyncReceiver,
+    book_metrics: MetricsReceiver,
+
+    spread_watcher: SpreadReceiver,
+    average_watcher: AveragePriceReceiver,
+    volume_watcher: VolumeReceiver,
+
+    /// Records raw updates of the symbol, if it is recorded.
+    recorder: Option<RecorderManager>,
+}
+
+impl MarketManager {
+    /// Fetch the snapshot of the symbol and schedule its workers. Trade tape is backfilled with the recent trades.
+    pub async fn start(cfg: &BncCfg, symbol: String) -> BncResult<Self> {
+        let mut price_manager = PriceStateManager::from_cfg(&cfg.ws);
+        let mut order_book_manager = OrderBookManager::from_cfg(cfg);
+        let mut average_manager = AveragePriceManager::from_cfg(cfg)?;
+        let mut volume_tracker = VolumeTracker::from_cfg(&cfg.ws);
+        if cfg.recent_trades > 0 {
+            volume_tracker =
+                volume_tracker.with_backfill(BncRestClient::from_cfg(cfg)?, cfg.recent_trades);
+        }
+
+        let book_watcher = order_book_manager.init(&symbol).await?;
+        let price_watcher = price_manager.init(&symbol);
+        let book_resyncs = order_book_manager.resyncs();
+        let book_metrics = order_book_manager.metrics();
+        let average_watcher = average_manager.init(&symbol);
+        let volume_watcher = volume_tracker.init(&symbol);
+
+        let spread_tracker = SpreadTracker::new(SPREAD_HISTORY_CAPACITY);
+        let spread_watcher = spread_tracker.subscribe();
+        // Tracker is fed until the price feed is stopped.
+        forward(price_watcher.clone(), spread_tracker);
+
+        Ok(Self {
+            symbol,
+            price_manager,
+            order_book_manager,
+            average_manager,
+            volume_tracker,
+            price_watcher,
+            book_watcher,
+            book_resyncs,
+            book_metrics,
+            spread_watcher,
+            average_watcher,
+            volume_watcher,
+            recorder: None,
+        })
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Amount of depth updates applied to the book so far.
+    pub fn book_updates(&self) -> u64 {
+        self.order_book_manager.applied_updates()
+    }
+
+    pub fn price_watcher(&mut self) -> &mut PriceReceiver {
+        &mut self.price_watcher
+    }
+
+    pub fn book_watcher(&mut self) -> &mut OrderBookReceiver {
+        &mut self.book_watcher
+    }
+
+    /// Microstructure metrics of the book, measured on its every change.
+    pub fn metrics_watcher(&mut self) -> &mut MetricsReceiver {
+        &mut self.book_metrics
+    }
+
+    /// Recent mid prices and spreads of the best prices.
+    pub fn spread_watcher(&mut self) -> &mut SpreadReceiver {
+        &mut self.spread_watcher
+    }
+
+    /// Average price of the last minutes, polled in the background.
+    pub fn average_watcher(&mut self) -> &mut AveragePriceReceiver {
+        &mut self.average_watcher
+    }
+
+    /// Rolling VWAP and volumes of the symbol's trades.
+    pub fn volume_watcher(&mut self) -> &mut VolumeReceiver {
+        &mut self.volume_watcher
+    }
+
+    /// Record raw updates of the symbol into the files, along with its feeds.
+    pub fn record(&mut self, cfg: &RecorderCfg, ws: &WsCfg) {
+        let mut recorder = RecorderManager::from_cfg(cfg, ws);
+        recorder.init(&self.symbol);
+        if let Some(previous) = self.recorder.replace(recorder) {
+            previous.stop();
+        }
+    }
+
+    pub fn is_recorded(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Group the displayed levels of the book by the step, none to display them as they are.
+    pub fn set_book_grouping(&mut self, grouping: Option<Decimal>) {
+        self.order_book_manager.set_grouping(grouping);
+    }
+
+    /// Latest resync of the book since the last call, if any.
+    pub fn take_resync(&mut self) -> Option<BookResync> {
+        let mut latest = None;
+        loop {
+            match self.book_resyncs.try_recv() {
+                Ok(resync) => latest = Some(resync),
+                // Lagged receiver just skips the oldest ones, the latest is still to be received.
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return latest,
+            }
+        }
+    }
+
+    /// Whether the market is ready to be displayed - book received its first incremental update
+    /// on top of the snapshot and first price tick has arrived.
+    ///
+    /// Only meaningful until someone marks receivers' values as seen.
+    pub fn is_synced(&self) -> bool {
+        let book_synced = self.book_watcher.has_changed().unwrap_or(false);
+        let price_received = self.price_watcher.has_changed().unwrap_or(false);
+        book_synced && price_received
+    }
+
+    /// Terminate all the feeds of the market.
+    pub fn stop(&self) {
+        self.order_book_manager.stop();
+        self.price_manager.stop();
+        self.average_manager.stop();
+        self.volume_tracker.stop();
+        if let Some(recorder) = self.recorder.as_ref() {
+            recorder.stop();
+        }
+    }
+
+    /// Wait for the stopped feeds to finish. Returns the first error any of their tasks failed with.
+    pub async fn join(&mut self) -> BncResult<()> {
+        let book = self.order_book_manager.join().await;
+        let price = self.price_manager.join().await;
+        let average = self.average_manager.join().await;
+        let volume = self.volume_tracker.join().await;
+        let recording = match self.recorder.as_mut() {
+            Some(recorder) => recorder.join().await,
+            None => Ok(()),
+        };
+        book.and(price).and(average).and(volume).and(recording)
+    }
+}
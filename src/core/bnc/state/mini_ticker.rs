@@ -0,0 +1,218 @@
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::state::change::PriceChange;
+use crate::core::bnc::state::tasks::TaskGroup;
+use crate::core::bnc::ws::config::{ReconnectCfg, WsCfg};
+use crate::core::bnc::ws::endpoints::EndpointPool;
+use crate::core::bnc::ws::reconnect::{
+    ReconnectPolicy, ReconnectReceiver, ReconnectSender, RECONNECT_EVENTS_CAPACITY,
+};
+use crate::core::bnc::ws::worker::mini_ticker::{AllMiniTickersWatcher, SymbolMiniTicker};
+use crate::core::bnc::ws::worker::{MessageSender, WsWorker};
+use log::debug;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tokio::sync::broadcast;
+use tokio::sync::watch::{channel, Receiver, Sender};
+use tokio_util::sync::CancellationToken;
+
+/// Latest mini ticker of the watched symbol, along with the recent change of its last price.
+#[derive(Debug, Clone)]
+pub struct WatchedTicker {
+    pub ticker: SymbolMiniTicker,
+    pub change: PriceChange,
+}
+
+/// Mini tickers of the watched symbols by their names.
+pub type MiniTickers = HashMap<String, WatchedTicker>;
+pub type MiniTickerReceiver = Receiver<MiniTickers>;
+
+/// Symbols the mini tickers are kept for.
+type Watchlist = Arc<RwLock<HashSet<String>>>;
+
+/// Merges the mini tickers of the whole market into the ones of the watched symbols.
+///
+/// The stream carries only the symbols changed since the previous push, so the rest are kept as they are.
+struct MiniTickerCache {
+    sender: Sender<MiniTickers>,
+    watchlist: Watchlist,
+}
+
+impl MiniTickerCache {
+    /// Merge the tickers into the cache. Returns whether any of the watched ones were there.
+    fn merge(&self, tickers: Vec<SymbolMiniTicker>, now: Instant) -> bool {
+        let watchlist = self.watchlist.read().expect("Watchlist is poisoned.");
+        self.sender.send_if_modified(|cache| {
+            let unwatched = cache.len();
+            cache.retain(|symbol, _| watchlist.contains(symbol));
+            let mut modified = cache.len() != unwatched;
+
+            for ticker in tickers {
+                if !watchlist.contains(&ticker.symbol) {
+                    continue;
+                }
+                modified = true;
+                let watched = cache
+                    .entry(ticker.symbol.clone())
+                    .or_insert_with(|| WatchedTicker {
+                        ticker: ticker.clone(),
+                        change: Default::default(),
+                    });
+                watched.change.record(ticker.close.value(), now);
+                watched.ticker = ticker;
+            }
+            modified
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageSender<Vec<SymbolMiniTicker>> for MiniTickerCache {
+    async fn send(&self, data: Vec<SymbolMiniTicker>) -> BncResult<()> {
+        if self.sender.is_closed() {
+            return Err(BncError::DataTransmitError);
+        }
+        if !self.merge(data, Instant::now()) {
+            return Err(BncError::DataRejected);
+        }
+        Ok(())
+    }
+}
+
+struct MiniTickerManagerCfg {
+    ws_base_url: String,
+    endpoints: EndpointPool,
+    reconnect: ReconnectCfg,
+}
+
+impl MiniTickerManagerCfg {
+    fn from_cfg(cfg: &WsCfg) -> Self {
+        Self {
+            ws_base_url: cfg.baseurl.primary().to_string(),
+            endpoints: EndpointPool::new(cfg.baseurl.all()),
+            reconnect: cfg.reconnect.clone(),
+        }
+    }
+}
+
+/// Keeps mini tickers of the watched symbols, fed by a single connection whatever the amount of the symbols is.
+///
+/// Meant for the ambient displays, so there are no redundant workers.
+pub struct MiniTickerManager {
+    cfg: MiniTickerManagerCfg,
+    watchlist: Watchlist,
+    tasks: TaskGroup,
+    reconnects: ReconnectSender,
+
+    /// Stops the scheduled tasks.
+    cancel: CancellationToken,
+}
+
+impl MiniTickerManager {
+    pub fn from_cfg(cfg: &WsCfg) -> Self {
+        Self {
+            cfg: MiniTickerManagerCfg::from_cfg(cfg),
+            watchlist: Default::default(),
+            tasks: TaskGroup::new("mini tickers"),
+            reconnects: broadcast::channel(RECONNECT_EVENTS_CAPACITY).0,
+            cancel: Default::default(),
+        }
+    }
+
+    pub fn init(&mut self) -> MiniTickerReceiver {
+        self.cancel = CancellationToken::new();
+        let (sender, receiver) = channel(MiniTickers::new());
+        let cache = MiniTickerCache {
+            sender,
+            watchlist: self.watchlist.clone(),
+        };
+        let reconnect =
+            ReconnectPolicy::new(self.cfg.reconnect.clone(), Some(self.reconnects.clone()))
+                .with_endpoints(self.cfg.endpoints.clone())
+                .with_cancel(self.cancel.clone());
+
+        debug!("Initialising worker of all market mini tickers.");
+        self.tasks = TaskGroup::new("mini tickers");
+        self.tasks.adopt(
+            "worker",
+            WsWorker::new(&self.cfg.ws_base_url)
+                .with_reconnect(reconnect)
+                .mini_tickers_watcher(cache),
+        );
+
+        receiver
+    }
+
+    /// Keep the tickers of these symbols only, the rest are dropped with the next push.
+    pub fn watch<'s>(&self, symbols: impl IntoIterator<Item = &'s str>) {
+        let symbols: HashSet<String> = symbols.into_iter().map(str::to_string).collect();
+        if *self.watchlist.read().expect("Watchlist is poisoned.") == symbols {
+            return;
+        }
+        debug!("Watching mini tickers of {:?}.", symbols);
+        *self.watchlist.write().expect("Watchlist is poisoned.") = symbols;
+    }
+
+    /// Subscribe to the reconnect events of the worker.
+    pub fn reconnects(&self) -> ReconnectReceiver {
+        self.reconnects.subscribe()
+    }
+
+    /// Let the worker close its connection and stop.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Wait for the stopped worker to finish. Returns the error it failed with, if any.
+    pub async fn join(&mut self) -> BncResult<()> {
+        self.tasks.join().await
+    }
+
+    /// Amount of scheduled tasks that are still running.
+    pub fn alive_tasks(&self) -> usize {
+        self.tasks.alive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticker(symbol: &str, close: &str) -> SymbolMiniTicker {
+        SymbolMiniTicker {
+            symbol: symbol.into(),
+            close: close.parse().unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_keeps_watched_tickers_only() {
+        let (sender, receiver) = channel(MiniTickers::new());
+        let watchlist: Watchlist = Default::default();
+        watchlist.write().unwrap().insert("BTCUSDT".into());
+        let cache = MiniTickerCache {
+            sender,
+            watchlist: watchlist.clone(),
+        };
+
+        let now = Instant::now();
+        assert!(!cache.merge(vec![ticker("ETHUSDT", "1200")], now));
+        assert!(cache.merge(
+            vec![ticker("ETHUSDT", "1200"), ticker("BTCUSDT", "100")],
+            now
+        ));
+        assert!(cache.merge(vec![ticker("BTCUSDT", "110")], now));
+
+        let tickers = receiver.borrow().clone();
+        assert_eq!(tickers.len(), 1);
+        let btc = &tickers["BTCUSDT"];
+        assert_eq!(btc.ticker.close.to_string(), "110");
+        assert_eq!(btc.change.change(), Some("10".parse().unwrap()));
+
+        // Symbol that is not watched anymore is dropped with the next push.
+        watchlist.write().unwrap().clear();
+        assert!(cache.merge(vec![], now));
+        assert!(receiver.borrow().is_empty());
+    }
+}
@@ -1,3 +1,17 @@
+pub mod anomaly;
+pub mod average;
 pub mod balancer;
 pub mod book;
+pub mod change;
+pub mod conversion;
+pub mod history;
+pub mod market;
+pub mod mini_ticker;
+pub mod portfolio;
 pub mod price;
+pub mod scaling;
+pub mod spread;
+pub mod stats;
+pub mod tasks;
+pub mod ticker;
+pub mod volume;
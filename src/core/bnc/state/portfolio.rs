@@ -0,0 +1,246 @@
+use crate::core::bnc::decimal::parse;
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::state::conversion::mid_price;
+use crate::core::bnc::state::price::PriceReceiver;
+use crate::core::bnc::ws::combined::{CombinedStreamConnection, CombinedStreamHandle};
+use crate::core::bnc::ws::config::WsCfg;
+use crate::core::bnc::ws::worker::price::{book_ticker_stream, SymbolBookTick, SymbolPriceUpdate};
+use crate::core::bnc::ws::worker::MessageSender;
+use log::warn;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::sync::watch;
+
+/// Holdings the portfolio panel values by the live prices.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PortfolioCfg {
+    /// Currency the holdings are valued in, e.g. `USDT`.
+    #[serde(default = "default_quote")]
+    pub quote: String,
+
+    pub holdings: Vec<HoldingCfg>,
+}
+
+fn default_quote() -> String {
+    "USDT".into()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HoldingCfg {
+    /// Held asset, e.g. `BTC`.
+    pub asset: String,
+
+    /// Held amount, e.g. `0.5`.
+    pub amount: String,
+}
+
+/// Valuation of a single holding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoldingValue {
+    pub asset: String,
+    pub amount: Decimal,
+
+    /// Latest mid-price of the asset, unknown until the first tick arrives.
+    pub price: Option<Decimal>,
+    pub value: Option<Decimal>,
+
+    /// Price change since the portfolio was started, in percents.
+    pub change: Option<Decimal>,
+}
+
+/// Valuation of all the holdings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortfolioValue {
+    pub quote: String,
+    pub holdings: Vec<HoldingValue>,
+
+    /// Sum of the known holdings' values.
+    pub total: Decimal,
+}
+
+/// Passes the best prices of the held asset to its receiver.
+struct HoldingPrices(watch::Sender<SymbolPriceUpdate>);
+
+#[async_trait::async_trait]
+impl MessageSender<SymbolBookTick> for HoldingPrices {
+    async fn send(&self, data: SymbolBookTick) -> BncResult<()> {
+        self.0
+            .send(data.into())
+            .map_err(|_| BncError::DataTransmitError)
+    }
+}
+
+/// Price feed of a single holding. Quote currency itself has no feed, its price is always one.
+struct HoldingFeed {
+    asset: String,
+    amount: Decimal,
+    feed: Option<PriceReceiver>,
+
+    /// Price the change is measured against - the first one received.
+    initial_price: Option<Decimal>,
+}
+
+impl HoldingFeed {
+    fn price(&self) -> Option<Decimal> {
+        match self.feed.as_ref() {
+            Some(receiver) => mid_price(&receiver.borrow()),
+            None => Some(Decimal::ONE),
+        }
+    }
+}
+
+/// Keeps best prices feeds of all the held assets and values the holdings by them.
+pub struct PortfolioManager {
+    quote: String,
+    holdings: Vec<HoldingFeed>,
+
+    /// Single connection carrying the best prices of all the held assets, none if only the quote currency is held.
+    connection: Option<CombinedStreamHandle>,
+}
+
+impl PortfolioManager {
+    /// Connect to the best prices of the holdings. Holdings with malformed amounts are skipped.
+    pub fn start(cfg: &PortfolioCfg, ws: &WsCfg) -> Self {
+        let quote = cfg.quote.to_ascii_uppercase();
+        let mut connection = CombinedStreamConnection::from_cfg(ws);
+        let mut feeds: HashMap<String, PriceReceiver> = HashMap::new();
+        let holdings = cfg
+            .holdings
+            .iter()
+            .filter_map(|holding| {
+                let amount = match parse(&holding.amount) {
+                    Ok(amount) => amount,
+                    Err(err) => {
+                        warn!("Skipping holding of {}. Error: {}", holding.asset, err);
+                        return None;
+                    }
+                };
+                let asset = holding.asset.to_ascii_uppercase();
+                let feed = (asset != quote).then(|| {
+                    let pair = format!("{}{}", asset, quote);
+                    // Asset held a few times shares the feed.
+                    feeds
+                        .entry(pair)
+                        .or_insert_with_key(|pair| {
+                            let (sender, receiver) = watch::channel(SymbolPriceUpdate::default());
+                            connection.subscribe(&book_ticker_stream(pair), HoldingPrices(sender));
+                            receiver
+                        })
+                        .clone()
+                });
+                Some(HoldingFeed {
+                    asset,
+                    amount,
+                    feed,
+                    initial_price: None,
+                })
+            })
+            .collect();
+
+        // Fails only if there are no streams, i.e. only the quote currency is held.
+        let connection = connection.spawn().ok();
+        Self {
+            quote,
+            holdings,
+            connection,
+        }
+    }
+
+    /// Value the holdings by the latest prices.
+    pub fn value(&mut self) -> PortfolioValue {
+        let holdings: Vec<HoldingValue> = self
+            .holdings
+            .iter_mut()
+            .map(|holding| {
+                let price = holding.price();
+                if holding.initial_price.is_none() {
+                    holding.initial_price = price;
+                }
+                value_holding(holding, price)
+            })
+            .collect();
+        let total = holdings.iter().filter_map(|holding| holding.value).sum();
+
+        PortfolioValue {
+            quote: self.quote.clone(),
+            holdings,
+            total,
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some(connection) = self.connection.as_ref() {
+            connection.stop();
+        }
+    }
+
+    /// Wait for the stopped connection to finish.
+    pub async fn join(&mut self) -> BncResult<()> {
+        match self.connection.take() {
+            Some(connection) => connection.join().await,
+            None => Ok(()),
+        }
+    }
+}
+
+fn value_holding(holding: &HoldingFeed, price: Option<Decimal>) -> HoldingValue {
+    let change = price
+        .zip(holding.initial_price)
+        .filter(|(_, initial)| !initial.is_zero())
+        .map(|(price, initial)| (price - initial) / initial * Decimal::ONE_HUNDRED);
+
+    HoldingValue {
+        asset: holding.asset.clone(),
+        amount: holding.amount,
+        price,
+        value: price.map(|price| price * holding.amount),
+        change,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holding(asset: &str, amount: &str, initial_price: Option<&str>) -> HoldingFeed {
+        HoldingFeed {
+            asset: asset.into(),
+            amount: parse(amount).unwrap(),
+            feed: None,
+            initial_price: initial_price.map(|price| parse(price).unwrap()),
+        }
+    }
+
+    #[test]
+    fn it_values_holding_with_change() {
+        let value = value_holding(
+            &holding("BTC", "0.5", Some("20000")),
+            Some(parse("21000").unwrap()),
+        );
+
+        assert_eq!(value.value, Some(parse("10500").unwrap()));
+        assert_eq!(value.change, Some(parse("5").unwrap()));
+    }
+
+    #[test]
+    fn it_values_quote_currency_as_is() {
+        let mut manager = PortfolioManager {
+            quote: "USDT".into(),
+            holdings: vec![holding("USDT", "150.5", None)],
+            connection: None,
+        };
+
+        let value = manager.value();
+        assert_eq!(value.total, parse("150.5").unwrap());
+        assert_eq!(value.holdings[0].change, Some(Decimal::ZERO));
+    }
+
+    #[test]
+    fn it_skips_unknown_prices() {
+        let value = value_holding(&holding("ETH", "2", None), None);
+
+        assert_eq!(value.value, None);
+        assert_eq!(value.change, None);
+    }
+}
@@ -1,64 +1,144 @@
 use crate::core::bnc::error::BncResult;
-use crate::core::bnc::state::balancer::MessageBalancer;
-use crate::core::bnc::ws::config::WsCfg;
+use crate::core::bnc::state::balancer::{BalancerCfg, MessageBalancer};
+use crate::core::bnc::state::history::{History, SharedHistory};
+use crate::core::bnc::state::scaling::{spawn_workers, DeliveryCounters, ScalingCfg};
+use crate::core::bnc::state::tasks::TaskGroup;
+use crate::core::bnc::ws::config::{ReconnectCfg, WsCfg};
+use crate::core::bnc::ws::endpoints::EndpointPool;
+use crate::core::bnc::ws::reconnect::{
+    ReconnectPolicy, ReconnectReceiver, ReconnectSender, RECONNECT_EVENTS_CAPACITY,
+};
 
 use crate::core::bnc::ws::worker::price::{SymbolPriceUpdate, SymbolPriceWatcher};
 use crate::core::bnc::ws::worker::WsWorker;
 use log::debug;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 use tokio::sync::watch::{channel, Receiver};
 use tokio::sync::Mutex;
-use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 pub type PriceReceiver = Receiver<SymbolPriceUpdate>;
 
-struct PriceManagerCfg<'a> {
-    ws_base_url: &'a str,
+/// The latest best prices of the symbol, along with the moments they were received.
+pub type PriceHistory = SharedHistory<SymbolPriceUpdate>;
+
+struct PriceManagerCfg {
+    ws_base_url: String,
+    endpoints: EndpointPool,
     workers: u64,
+    reconnect: ReconnectCfg,
+    scaling: ScalingCfg,
+    balancer: BalancerCfg,
 }
 
-impl<'a> PriceManagerCfg<'a> {
-    fn from_cfg(cfg: &'a WsCfg) -> Self {
+impl PriceManagerCfg {
+    fn from_cfg(cfg: &WsCfg) -> Self {
         Self {
-            ws_base_url: &cfg.baseurl,
-            workers: cfg.workers,
+            ws_base_url: cfg.baseurl.primary().to_string(),
+            endpoints: EndpointPool::new(cfg.baseurl.all()),
+            workers: cfg.price_workers_count(),
+            reconnect: cfg.reconnect.clone(),
+            scaling: cfg.scaling.clone(),
+            balancer: cfg.balancer.clone(),
         }
     }
 }
 
-pub struct PriceStateManager<'a> {
-    cfg: PriceManagerCfg<'a>,
-    tasks: Vec<JoinHandle<BncResult<()>>>,
+pub struct PriceStateManager {
+    cfg: PriceManagerCfg,
+    tasks: TaskGroup,
+    reconnects: ReconnectSender,
+
+    /// Stops the scheduled tasks.
+    cancel: CancellationToken,
+
+    /// The latest updates, if they are kept.
+    history: Option<PriceHistory>,
 }
 
-impl<'a> PriceStateManager<'a> {
-    pub fn from_cfg(cfg: &'a WsCfg) -> Self {
+impl PriceStateManager {
+    pub fn from_cfg(cfg: &WsCfg) -> Self {
         Self {
             cfg: PriceManagerCfg::from_cfg(cfg),
-            tasks: vec![],
+            tasks: TaskGroup::new("price"),
+            reconnects: broadcast::channel(RECONNECT_EVENTS_CAPACITY).0,
+            cancel: Default::default(),
+            history: None,
         }
     }
 
-    pub fn init(&mut self, symbol: &str) -> PriceReceiver {
-        let (sender, receiver) = channel(SymbolPriceUpdate::default());
+    /// Keep the last `capacity` updates, e.g. to compute momentum or draw sparklines.
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history = Some(History::shared(capacity));
+        self
+    }
 
-        let balancer = Arc::new(Mutex::new(MessageBalancer::new(sender)));
+    /// The latest updates, oldest first. None if they are not kept.
+    pub fn history(&self) -> Option<PriceHistory> {
+        self.history.clone()
+    }
 
-        let worker = WsWorker::new(self.cfg.ws_base_url);
-        let mut tasks = vec![];
+    pub fn init(&mut self, symbol: &str) -> PriceReceiver {
+        self.cancel = CancellationToken::new();
+        self.tasks = TaskGroup::new(format!("{} price", symbol));
+        let (sender, receiver) = channel(SymbolPriceUpdate::default());
 
-        for i in 0..self.cfg.workers {
-            debug!("Initialised #{} worker of symbol price receiver.", i);
-            tasks.push(worker.price_updates_watcher(symbol, balancer.clone()));
+        let counters = Arc::new(DeliveryCounters::default());
+        let mut balancer = MessageBalancer::new(sender)
+            .with_counters(counters.clone())
+            .with_cfg(&self.cfg.balancer);
+        if let Some(history) = self.history.clone() {
+            balancer = balancer.with_history(history);
         }
-
-        self.tasks = tasks;
+        let balancer = Arc::new(Mutex::new(balancer));
+
+        let base_url = self.cfg.ws_base_url.clone();
+        let reconnect =
+            ReconnectPolicy::new(self.cfg.reconnect.clone(), Some(self.reconnects.clone()))
+                .with_endpoints(self.cfg.endpoints.clone())
+                .with_cancel(self.cancel.clone());
+        let symbol = symbol.to_string();
+        debug!(
+            "Initialising {} workers of symbol price receiver.",
+            self.cfg.workers
+        );
+        let workers = spawn_workers(
+            &self.cfg.scaling,
+            self.cfg.workers,
+            counters,
+            self.tasks.scaled_workers(),
+            self.cancel.clone(),
+            move || {
+                WsWorker::new(&base_url)
+                    .with_reconnect(reconnect.clone())
+                    .price_updates_watcher(&symbol, balancer.clone())
+            },
+        );
+
+        self.tasks.adopt_all("worker", workers);
 
         receiver
     }
 
+    /// Subscribe to the reconnect events of the scheduled workers.
+    pub fn reconnects(&self) -> ReconnectReceiver {
+        self.reconnects.subscribe()
+    }
+
+    /// Let the workers close their connections and stop.
     pub fn stop(&self) {
-        self.tasks.iter().for_each(|task| task.abort());
+        self.cancel.cancel();
+    }
+
+    /// Wait for the stopped tasks to finish. Returns the first error any of them failed with.
+    pub async fn join(&mut self) -> BncResult<()> {
+        self.tasks.join().await
+    }
+
+    /// Amount of scheduled tasks that are still running.
+    pub fn alive_tasks(&self) -> usize {
+        self.tasks.alive()
     }
 }
 #[cfg(test)]
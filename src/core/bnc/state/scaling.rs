@@ -0,0 +1,327 @@
+use crate::core::bnc::error::BncResult;
+use derive_getters::Getters;
+use log::{debug, info};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Adaptive amount of redundant workers of a feed.
+#[derive(Debug, Clone, Deserialize, Getters)]
+#[serde(default)]
+pub struct ScalingCfg {
+    /// Whether workers are scaled at all. Static workers count is used otherwise.
+    pub enabled: bool,
+
+    pub min_workers: u64,
+    pub max_workers: u64,
+
+    /// Milliseconds between scaling decisions.
+    pub interval: u64,
+
+    /// Share of the messages the redundant workers should deliver as duplicates for one of them to be dropped.
+    pub redundancy: f64,
+
+    /// Amount of consecutive intervals without gaps before a worker is dropped.
+    pub calm_intervals: u32,
+}
+
+impl Default for ScalingCfg {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_workers: 1,
+            max_workers: 8,
+            interval: 10000,
+            redundancy: 0.9,
+            calm_intervals: 6,
+        }
+    }
+}
+
+impl ScalingCfg {
+    /// Amount of workers to start with, the configured one within the bounds.
+    pub fn initial_workers(&self, configured: u64) -> u64 {
+        configured.clamp(self.min_workers.max(1), self.max_workers.max(1))
+    }
+}
+
+/// Delivery counters shared by the balancer of the feed and its scaler.
+#[derive(Debug, Default)]
+pub struct DeliveryCounters {
+    accepted: AtomicU64,
+    duplicates: AtomicU64,
+    gaps: AtomicU64,
+}
+
+impl DeliveryCounters {
+    pub fn accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Message was already delivered by another worker.
+    pub fn duplicate(&self) {
+        self.duplicates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Message is ahead of the state - some of the previous ones were missed.
+    pub fn gap(&self) {
+        self.gaps.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> DeliveryStats {
+        DeliveryStats {
+            accepted: self.accepted.load(Ordering::Relaxed),
+            duplicates: self.duplicates.load(Ordering::Relaxed),
+            gaps: self.gaps.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Totals of the delivered messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeliveryStats {
+    pub accepted: u64,
+    pub duplicates: u64,
+    pub gaps: u64,
+}
+
+impl DeliveryStats {
+    fn since(&self, previous: &Self) -> Self {
+        Self {
+            accepted: self.accepted.saturating_sub(previous.accepted),
+            duplicates: self.duplicates.saturating_sub(previous.duplicates),
+            gaps: self.gaps.saturating_sub(previous.gaps),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalingDecision {
+    Up,
+    Down,
+    Keep,
+}
+
+/// Decides on the amount of workers by the delivery stats of the last interval.
+///
+/// Gaps and dead workers add a worker right away. A worker is dropped once the redundant ones deliver
+/// almost everything as duplicates for several intervals in a row.
+#[derive(Debug, Clone)]
+pub struct WorkerScaler {
+    cfg: ScalingCfg,
+    previous: DeliveryStats,
+    calm_intervals: u32,
+}
+
+impl WorkerScaler {
+    pub fn new(cfg: ScalingCfg) -> Self {
+        Self {
+            cfg,
+            previous: DeliveryStats::default(),
+            calm_intervals: 0,
+        }
+    }
+
+    /// Account the totals of the feed, given amount of workers were alive during the interval.
+    pub fn decide(
+        &mut self,
+        stats: DeliveryStats,
+        workers: u64,
+        dead_workers: u64,
+    ) -> ScalingDecision {
+        let interval = stats.since(&self.previous);
+        self.previous = stats;
+
+        if interval.gaps > 0 || dead_workers > 0 {
+            self.calm_intervals = 0;
+            if workers < self.cfg.max_workers {
+                return ScalingDecision::Up;
+            }
+            return ScalingDecision::Keep;
+        }
+
+        let redundant = workers.saturating_sub(1);
+        let expected_duplicates = (interval.accepted * redundant) as f64 * self.cfg.redundancy;
+        let is_redundant =
+            interval.accepted > 0 && interval.duplicates as f64 >= expected_duplicates;
+        if !is_redundant {
+            self.calm_intervals = 0;
+            return ScalingDecision::Keep;
+        }
+
+        self.calm_intervals += 1;
+        if self.calm_intervals >= self.cfg.calm_intervals && workers > self.cfg.min_workers.max(1) {
+            self.calm_intervals = 0;
+            return ScalingDecision::Down;
+        }
+        ScalingDecision::Keep
+    }
+}
+
+/// Handles of the scaled workers, aborted together with the scaler.
+struct WorkerHandles(Vec<JoinHandle<BncResult<()>>>);
+
+impl Drop for WorkerHandles {
+    fn drop(&mut self) {
+        self.0.iter().for_each(|task| task.abort());
+    }
+}
+
+/// Spawn workers with the given spawner, keep their amount adjusted by the delivery stats of the feed.
+///
+/// Amount of alive workers is reported via `alive`. Aborting the returned task aborts all the workers.
+///
+/// Once the token is cancelled, the scaler waits for the workers to stop - they are to be cancelled by the same token.
+pub fn spawn_scaled_workers<F>(
+    cfg: ScalingCfg,
+    initial: u64,
+    counters: Arc<DeliveryCounters>,
+    alive: Arc<AtomicU64>,
+    cancel: CancellationToken,
+    spawn_worker: F,
+) -> JoinHandle<BncResult<()>>
+where
+    F: Fn() -> JoinHandle<BncResult<()>> + Send + 'static,
+{
+    tokio::task::spawn(async move {
+        let mut workers = WorkerHandles((0..initial).map(|_| spawn_worker()).collect());
+        alive.store(initial, Ordering::Relaxed);
+        let mut scaler = WorkerScaler::new(cfg.clone());
+        let mut interval = tokio::time::interval(Duration::from_millis(cfg.interval.max(1)));
+        // The first tick completes immediately.
+        interval.tick().await;
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = cancel.cancelled() => break,
+            }
+
+            let before = workers.0.len();
+            workers.0.retain(|task| !task.is_finished());
+            let dead_workers = (before - workers.0.len()) as u64;
+            let count = workers.0.len() as u64;
+
+            match scaler.decide(counters.stats(), count, dead_workers) {
+                ScalingDecision::Up => {
+                    info!(
+                        "Scaling workers up to {}, {} of them died.",
+                        count + 1,
+                        dead_workers
+                    );
+                    workers.0.push(spawn_worker());
+                }
+                ScalingDecision::Down => {
+                    info!("Scaling workers down to {}.", count - 1);
+                    if let Some(task) = workers.0.pop() {
+                        task.abort();
+                    }
+                }
+                ScalingDecision::Keep => debug!("Keeping {} workers.", count),
+            }
+            alive.store(workers.0.len() as u64, Ordering::Relaxed);
+        }
+
+        for task in workers.0.drain(..) {
+            // Result of the worker is already logged by itself.
+            let _ = task.await;
+        }
+        alive.store(0, Ordering::Relaxed);
+        debug!("Scaler of the workers is stopped.");
+        Ok(())
+    })
+}
+
+/// Spawn workers of the feed - the static amount of them, or the adaptively scaled one if scaling is enabled.
+///
+/// Scaled workers are owned by the single scaler task, their alive amount is reported via `alive`.
+pub fn spawn_workers<F>(
+    cfg: &ScalingCfg,
+    workers: u64,
+    counters: Arc<DeliveryCounters>,
+    alive: Arc<AtomicU64>,
+    cancel: CancellationToken,
+    spawn_worker: F,
+) -> Vec<JoinHandle<BncResult<()>>>
+where
+    F: Fn() -> JoinHandle<BncResult<()>> + Send + 'static,
+{
+    if cfg.enabled {
+        let initial = cfg.initial_workers(workers);
+        vec![spawn_scaled_workers(
+            cfg.clone(),
+            initial,
+            counters,
+            alive,
+            cancel,
+            spawn_worker,
+        )]
+    } else {
+        (0..workers).map(|_| spawn_worker()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scaler() -> WorkerScaler {
+        WorkerScaler::new(ScalingCfg {
+            enabled: true,
+            min_workers: 1,
+            max_workers: 3,
+            calm_intervals: 2,
+            ..Default::default()
+        })
+    }
+
+    fn stats(accepted: u64, duplicates: u64, gaps: u64) -> DeliveryStats {
+        DeliveryStats {
+            accepted,
+            duplicates,
+            gaps,
+        }
+    }
+
+    #[test]
+    fn it_scales_up_on_gaps_within_bounds() {
+        let mut scaler = scaler();
+        assert_eq!(scaler.decide(stats(10, 20, 1), 2, 0), ScalingDecision::Up);
+        assert_eq!(scaler.decide(stats(20, 40, 2), 3, 0), ScalingDecision::Keep);
+        assert_eq!(scaler.decide(stats(30, 60, 2), 2, 1), ScalingDecision::Up);
+    }
+
+    #[test]
+    fn it_scales_down_after_calm_intervals() {
+        let mut scaler = scaler();
+        assert_eq!(scaler.decide(stats(10, 20, 0), 3, 0), ScalingDecision::Keep);
+        assert_eq!(scaler.decide(stats(20, 40, 0), 3, 0), ScalingDecision::Down);
+        assert_eq!(scaler.decide(stats(30, 50, 0), 2, 0), ScalingDecision::Keep);
+        assert_eq!(scaler.decide(stats(40, 60, 0), 2, 0), ScalingDecision::Down);
+        assert_eq!(scaler.decide(stats(50, 60, 0), 1, 0), ScalingDecision::Keep);
+        assert_eq!(scaler.decide(stats(60, 60, 0), 1, 0), ScalingDecision::Keep);
+    }
+
+    #[test]
+    fn it_keeps_workers_that_miss_messages() {
+        let mut scaler = scaler();
+        assert_eq!(scaler.decide(stats(10, 5, 0), 3, 0), ScalingDecision::Keep);
+        assert_eq!(scaler.decide(stats(20, 10, 0), 3, 0), ScalingDecision::Keep);
+        assert_eq!(scaler.decide(stats(30, 15, 0), 3, 0), ScalingDecision::Keep);
+    }
+
+    #[test]
+    fn it_clamps_initial_workers() {
+        let cfg = ScalingCfg {
+            min_workers: 2,
+            max_workers: 4,
+            ..Default::default()
+        };
+        assert_eq!(cfg.initial_workers(1), 2);
+        assert_eq!(cfg.initial_workers(3), 3);
+        assert_eq!(cfg.initial_workers(9), 4);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::state::conversion::mid_price;
+use crate::core::bnc::ws::worker::price::SymbolPriceUpdate;
+use crate::core::bnc::ws::worker::MessageSender;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::time::Instant;
+use tokio::sync::watch::{channel, Receiver, Sender};
+
+/// Samples kept by default - a few minutes of a busy symbol.
+pub const SPREAD_HISTORY_CAPACITY: usize = 600;
+
+/// Basis points in one.
+const BPS: u32 = 10_000;
+
+/// Mid price and spread of the best prices at some moment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpreadSample {
+    pub time: Instant,
+
+    /// Id of the price update the sample is taken from.
+    pub update_id: u64,
+
+    pub mid: Decimal,
+
+    /// Difference between the best ask and bid.
+    pub spread: Decimal,
+
+    /// Spread relative to the mid price, in basis points.
+    pub spread_bps: Decimal,
+}
+
+impl SpreadSample {
+    /// Sample of the update, none if either of its sides is not known yet.
+    fn of(update: &SymbolPriceUpdate, time: Instant) -> Option<Self> {
+        let mid = mid_price(update)?;
+        let spread = update.ask.level().value() - update.bid.level().value();
+        Some(Self {
+            time,
+            update_id: update.id,
+            mid,
+            spread,
+            spread_bps: spread * Decimal::from(BPS) / mid,
+        })
+    }
+}
+
+/// The latest samples of the symbol, the oldest first. The oldest ones are dropped once it is full.
+#[derive(Debug, Clone)]
+pub struct SpreadHistory {
+    samples: VecDeque<SpreadSample>,
+    capacity: usize,
+}
+
+impl SpreadHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn latest(&self) -> Option<&SpreadSample> {
+        self.samples.back()
+    }
+
+    /// Up to `count` latest samples, the oldest first.
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &SpreadSample> + '_ {
+        self.samples
+            .iter()
+            .skip(self.samples.len().saturating_sub(count))
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Record the sample unless neither the mid nor the spread changed since the latest one.
+    fn push(&mut self, sample: SpreadSample) -> bool {
+        if let Some(latest) = self.latest() {
+            if latest.mid == sample.mid && latest.spread == sample.spread {
+                return false;
+            }
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        true
+    }
+}
+
+pub type SpreadReceiver = Receiver<SpreadHistory>;
+
+/// Records mid price and spread of every best prices change of the symbol.
+///
+/// Changes of the quantities only are not recorded, so the history covers more time.
+pub struct SpreadTracker {
+    sender: Sender<SpreadHistory>,
+}
+
+impl SpreadTracker {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sender: channel(SpreadHistory::new(capacity)).0,
+        }
+    }
+
+    pub fn subscribe(&self) -> SpreadReceiver {
+        self.sender.subscribe()
+    }
+
+    /// Record the update. Returns whether it changed the history.
+    fn record(&self, update: &SymbolPriceUpdate, time: Instant) -> bool {
+        match SpreadSample::of(update, time) {
+            Some(sample) => self.sender.send_if_modified(|history| history.push(sample)),
+            None => false,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageSender<SymbolPriceUpdate> for SpreadTracker {
+    async fn send(&self, data: SymbolPriceUpdate) -> BncResult<()> {
+        if !self.record(&data, Instant::now()) {
+            return Err(BncError::DataRejected);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::data::InlineOrder;
+
+    fn update(id: u64, bid: &str, ask: &str) -> SymbolPriceUpdate {
+        SymbolPriceUpdate::new(
+            id,
+            InlineOrder::new(bid.parse().unwrap(), "1".parse().unwrap()),
+            InlineOrder::new(ask.parse().unwrap(), "1".parse().unwrap()),
+        )
+    }
+
+    #[test]
+    fn it_records_mid_and_spread() {
+        let tracker = SpreadTracker::new(SPREAD_HISTORY_CAPACITY);
+        let receiver = tracker.subscribe();
+        let now = Instant::now();
+
+        assert!(!tracker.record(&SymbolPriceUpdate::default(), now));
+        assert!(tracker.record(&update(1, "99.95", "100.05"), now));
+        // Prices did not move.
+        assert!(!tracker.record(&update(2, "99.95", "100.05"), now));
+
+        let history = receiver.borrow();
+        let latest = history.latest().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(latest.update_id, 1);
+        assert_eq!(latest.mid, "100".parse().unwrap());
+        assert_eq!(latest.spread, "0.10".parse().unwrap());
+        assert_eq!(latest.spread_bps, "10".parse().unwrap());
+    }
+
+    #[test]
+    fn it_keeps_bounded_history() {
+        let tracker = SpreadTracker::new(3);
+        let now = Instant::now();
+        for id in 1..=5 {
+            let ask = format!("{}", 100 + id);
+            tracker.record(&update(id, "100", &ask), now);
+        }
+
+        let history = tracker.subscribe().borrow().clone();
+        let ids: Vec<u64> = history.recent(2).map(|sample| sample.update_id).collect();
+        assert_eq!(history.len(), 3);
+        assert_eq!(ids, [4, 5]);
+        assert_eq!(history.recent(10).count(), 3);
+    }
+}
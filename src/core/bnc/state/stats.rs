@@ -0,0 +1,305 @@
+use crate::core::bnc::config::BncCfg;
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::rest::BncRestClient;
+use crate::core::bnc::state::tasks::TaskGroup;
+use crate::core::bnc::ws::combined::{CombinedStreamConnection, CombinedStreamHandle, QueueStats};
+use crate::core::bnc::ws::worker::ticker::{ticker_stream, SymbolTickerUpdate};
+use crate::core::bnc::ws::worker::MessageSender;
+use log::{debug, info, warn};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::watch::{channel, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Rolling 24hr statistics of the displayed symbol.
+#[derive(Debug, Clone)]
+pub struct SymbolStats {
+    pub symbol: String,
+    pub ticker: SymbolTickerUpdate,
+}
+
+/// Latest statistics, none until the first ones arrive. They may be of the previously displayed symbol for a while.
+pub type StatsReceiver = Receiver<Option<SymbolStats>>;
+
+/// Passes the statistics of the symbol to the receiver, as long as the symbol is displayed.
+struct StatsRoute {
+    symbol: String,
+    sender: Arc<Sender<Option<SymbolStats>>>,
+    displayed: Receiver<String>,
+}
+
+#[async_trait::async_trait]
+impl MessageSender<SymbolTickerUpdate> for StatsRoute {
+    async fn send(&self, data: SymbolTickerUpdate) -> BncResult<()> {
+        // Messages queued before the switch are not of interest anymore.
+        if *self.displayed.borrow() != self.symbol {
+            return Err(BncError::DataRejected);
+        }
+        self.sender.send_replace(Some(SymbolStats {
+            symbol: self.symbol.clone(),
+            ticker: data,
+        }));
+        Ok(())
+    }
+}
+
+/// Keeps statistics of the displayed symbol over a single connection.
+///
+/// Switched symbol is resubscribed over the same socket via binance's live subscription protocol,
+/// see [`CombinedStreamHandle`]. Its statistics are fetched over REST meanwhile, as the stream sends them once a second.
+pub struct StatsManager {
+    /// Shared with the switcher, none once it is joined.
+    connection: Option<Arc<CombinedStreamHandle>>,
+    displayed: Sender<String>,
+    tasks: TaskGroup,
+
+    /// Stops the switcher.
+    cancel: CancellationToken,
+}
+
+impl StatsManager {
+    /// Connect to the statistics of the symbol. Fails only if there is nothing to connect to.
+    pub fn start(cfg: &BncCfg, symbol: &str) -> BncResult<(Self, StatsReceiver)> {
+        let client = BncRestClient::from_cfg(cfg)?;
+        let (sender, receiver) = channel(None);
+        let sender = Arc::new(sender);
+        let (displayed, displayed_receiver) = channel(symbol.to_string());
+
+        let mut connection = CombinedStreamConnection::from_cfg(&cfg.ws);
+        connection.subscribe(
+            &ticker_stream(symbol),
+            StatsRoute {
+                symbol: symbol.to_string(),
+                sender: sender.clone(),
+                displayed: displayed_receiver.clone(),
+            },
+        );
+        let connection = Arc::new(connection.spawn()?);
+
+        let cancel = CancellationToken::new();
+        let mut tasks = TaskGroup::new("statistics");
+        tasks.adopt(
+            "switcher",
+            stats_switcher(
+                client,
+                connection.clone(),
+                sender,
+                displayed_receiver,
+                cancel.clone(),
+            ),
+        );
+        let manager = Self {
+            connection: Some(connection),
+            displayed,
+            tasks,
+            cancel,
+        };
+        Ok((manager, receiver))
+    }
+
+    /// Keep the statistics of this symbol from now on. Nothing is sent if it is displayed already.
+    pub fn switch(&self, symbol: &str) {
+        self.displayed.send_if_modified(|displayed| {
+            if displayed == symbol {
+                return false;
+            }
+            *displayed = symbol.to_string();
+            true
+        });
+    }
+
+    /// Symbol the statistics are kept of.
+    pub fn symbol(&self) -> String {
+        self.displayed.borrow().clone()
+    }
+
+    /// Depth of the connection's stream queues. Doesn't borrow the manager, so it could be spawned.
+    pub fn queues(&self) -> impl Future<Output = BncResult<Vec<QueueStats>>> + Send + 'static {
+        let connection = self.connection.clone();
+        async move {
+            match connection {
+                Some(connection) => connection.queues().await,
+                None => Ok(vec![]),
+            }
+        }
+    }
+
+    /// Let the connection close and the switcher stop.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+        if let Some(connection) = self.connection.as_ref() {
+            connection.stop();
+        }
+    }
+
+    /// Wait for the stopped switcher and connection to finish.
+    pub async fn join(&mut self) -> BncResult<()> {
+        let switcher = self.tasks.join().await;
+        // Switcher is finished, so nobody else holds the connection unless it is left running.
+        let connection = match self.connection.take().map(Arc::try_unwrap) {
+            Some(Ok(connection)) => connection.join().await,
+            Some(Err(_)) | None => Ok(()),
+        };
+        switcher.and(connection)
+    }
+}
+
+/// Fill the statistics of the displayed symbol from the REST endpoint, unless its stream was faster.
+///
+/// Empty statistics, e.g. of the replayed symbol, are left to the stream.
+async fn seed_stats(
+    client: &BncRestClient,
+    sender: &Sender<Option<SymbolStats>>,
+    displayed: &Receiver<String>,
+    symbol: &str,
+) {
+    let ticker = match client.fetch_ticker_24h(symbol).await {
+        Ok(ticker) if ticker.close_time > 0 => ticker,
+        Ok(_) => return,
+        Err(err) => {
+            warn!("Could not fetch statistics of {}. Error: {}", symbol, err);
+            return;
+        }
+    };
+    sender.send_if_modified(|stats| {
+        let streamed = stats.as_ref().is_some_and(|stats| stats.symbol == symbol);
+        if streamed || *displayed.borrow() != symbol {
+            return false;
+        }
+        *stats = Some(SymbolStats {
+            symbol: symbol.to_string(),
+            ticker: ticker.into(),
+        });
+        true
+    });
+}
+
+/// Move the subscription of the connection to the displayed symbol, each time it is changed.
+///
+/// Rapid switches are collapsed, only the latest symbol is subscribed.
+fn stats_switcher(
+    client: BncRestClient,
+    connection: Arc<CombinedStreamHandle>,
+    sender: Arc<Sender<Option<SymbolStats>>>,
+    mut displayed: Receiver<String>,
+    cancel: CancellationToken,
+) -> JoinHandle<BncResult<()>> {
+    tokio::task::spawn(async move {
+        let mut subscribed = displayed.borrow_and_update().clone();
+        tokio::select! {
+            _ = seed_stats(&client, &sender, &displayed, &subscribed) => {}
+            _ = cancel.cancelled() => return Ok(()),
+        }
+        loop {
+            tokio::select! {
+                changed = displayed.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+                _ = cancel.cancelled() => break,
+            }
+            let symbol = displayed.borrow_and_update().clone();
+            if symbol == subscribed {
+                continue;
+            }
+
+            info!("Switching statistics from {} to {}.", subscribed, symbol);
+            if let Err(err) = connection.unsubscribe(&ticker_stream(&subscribed)).await {
+                warn!(
+                    "Could not unsubscribe statistics of {}. Error: {}",
+                    subscribed, err
+                );
+            }
+            let route = StatsRoute {
+                symbol: symbol.clone(),
+                sender: sender.clone(),
+                displayed: displayed.clone(),
+            };
+            if let Err(err) = connection.subscribe(&ticker_stream(&symbol), route).await {
+                warn!(
+                    "Could not subscribe statistics of {}. Error: {}",
+                    symbol, err
+                );
+            }
+            tokio::select! {
+                _ = seed_stats(&client, &sender, &displayed, &symbol) => {}
+                _ = cancel.cancelled() => break,
+            }
+            subscribed = symbol;
+        }
+        debug!("Statistics switcher is stopped.");
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::ws::config::WsCfg;
+
+    #[tokio::test]
+    async fn it_switches_statistics_over_the_same_connection() -> BncResult<()> {
+        let cfg = BncCfg {
+            baseurl: "synthetic://5".into(),
+            ws: WsCfg {
+                baseurl: "synthetic://5".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (mut manager, mut receiver) = StatsManager::start(&cfg, "BTCUSDT")?;
+        receiver.changed().await.unwrap();
+        let stats = receiver.borrow_and_update().clone().unwrap();
+        assert_eq!(stats.symbol, "BTCUSDT");
+
+        manager.switch("ETHUSDT");
+        assert_eq!(manager.symbol(), "ETHUSDT");
+        loop {
+            receiver.changed().await.unwrap();
+            let stats = receiver.borrow_and_update().clone().unwrap();
+            if stats.symbol == "ETHUSDT" {
+                break;
+            }
+        }
+        assert_eq!(
+            manager
+                .connection
+                .as_ref()
+                .unwrap()
+                .list_subscriptions()
+                .await?,
+            vec![ticker_stream("ETHUSDT")]
+        );
+        let queues = manager.queues().await?;
+        assert_eq!(queues.len(), 1);
+        assert_eq!(queues[0].stream, ticker_stream("ETHUSDT"));
+
+        manager.stop();
+        manager.join().await
+    }
+
+    #[tokio::test]
+    async fn it_seeds_statistics_until_they_are_streamed() {
+        let client = BncRestClient::new(reqwest::Client::new(), "synthetic://5".into());
+        let (sender, receiver) = channel(None);
+        let (_displayed, displayed_receiver) = channel("BTCUSDT".to_string());
+
+        seed_stats(&client, &sender, &displayed_receiver, "ETHUSDT").await;
+        assert!(receiver.borrow().is_none());
+
+        seed_stats(&client, &sender, &displayed_receiver, "BTCUSDT").await;
+        let seeded = receiver.borrow().clone().unwrap();
+        assert_eq!(seeded.symbol, "BTCUSDT");
+        assert!(seeded.ticker.time > 0);
+
+        let streamed = SymbolStats {
+            symbol: "BTCUSDT".into(),
+            ticker: Default::default(),
+        };
+        sender.send_replace(Some(streamed));
+        seed_stats(&client, &sender, &displayed_receiver, "BTCUSDT").await;
+        assert_eq!(receiver.borrow().as_ref().unwrap().ticker.time, 0);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::core::bnc::error::{BncError, BncResult};
+use log::warn;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::task::{JoinHandle, JoinSet};
+
+/// Counts the task as alive until it is finished or aborted.
+struct AliveGuard(Arc<AtomicUsize>);
+
+impl AliveGuard {
+    fn new(alive: Arc<AtomicUsize>) -> Self {
+        alive.fetch_add(1, Ordering::Relaxed);
+        Self(alive)
+    }
+}
+
+impl Drop for AliveGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Tasks of a single feed of the symbol, e.g. the workers and the resyncer of its book.
+///
+/// All of them are awaited together at shutdown, the first error any of them failed with is returned.
+pub struct TaskGroup {
+    /// Symbol and feed the tasks belong to, e.g. `BTCUSDT depth`.
+    key: String,
+    tasks: JoinSet<(&'static str, BncResult<()>)>,
+    alive: Arc<AtomicUsize>,
+
+    /// Amount of alive workers owned by the scaler, if scaling is enabled.
+    scaled_workers: Arc<AtomicU64>,
+}
+
+impl TaskGroup {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            tasks: JoinSet::new(),
+            alive: Default::default(),
+            scaled_workers: Default::default(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Counter of the scaled workers, to be reported by their scaler.
+    pub fn scaled_workers(&self) -> Arc<AtomicU64> {
+        self.scaled_workers.clone()
+    }
+
+    /// Track the spawned task. Its name is used to report its failure.
+    pub fn adopt(&mut self, name: &'static str, task: JoinHandle<BncResult<()>>) {
+        let guard = AliveGuard::new(self.alive.clone());
+        self.tasks.spawn(async move {
+            let _guard = guard;
+            let result = task
+                .await
+                .unwrap_or_else(|err| Err(BncError::TaskFailed(err.to_string())));
+            (name, result)
+        });
+    }
+
+    pub fn adopt_all(
+        &mut self,
+        name: &'static str,
+        tasks: impl IntoIterator<Item = JoinHandle<BncResult<()>>>,
+    ) {
+        tasks.into_iter().for_each(|task| self.adopt(name, task));
+    }
+
+    /// Amount of tasks that are still running, including the scaled workers.
+    pub fn alive(&self) -> usize {
+        self.alive.load(Ordering::Relaxed) + self.scaled_workers.load(Ordering::Relaxed) as usize
+    }
+
+    /// Wait for all the tasks to finish. Returns the first error, the rest are logged.
+    ///
+    /// Tasks are not stopped by the group - the feed is to be cancelled first.
+    pub async fn join(&mut self) -> BncResult<()> {
+        let mut first_error = None;
+        while let Some(joined) = self.tasks.join_next().await {
+            let (name, result) =
+                joined.unwrap_or_else(|err| ("task", Err(BncError::TaskFailed(err.to_string()))));
+            match (result, &first_error) {
+                (Ok(()), _) => {}
+                (Err(err), None) => first_error = Some(err),
+                (Err(err), Some(_)) => warn!("The {} of {} failed: {}", name, self.key, err),
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn it_joins_tasks_with_first_error() {
+        let mut group = TaskGroup::new("BTCUSDT price");
+        let (stop, stopped) = oneshot::channel::<()>();
+        group.adopt(
+            "worker",
+            tokio::spawn(async move {
+                let _ = stopped.await;
+                Ok(())
+            }),
+        );
+        group.adopt(
+            "resyncer",
+            tokio::spawn(async { Err(BncError::DataRejected) }),
+        );
+        assert_eq!(group.alive(), 2);
+
+        stop.send(()).unwrap();
+        assert!(matches!(group.join().await, Err(BncError::DataRejected)));
+        assert_eq!(group.alive(), 0);
+        assert!(group.join().await.is_ok());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::core::bnc::error::BncResult;
+use crate::core::bnc::state::balancer::{BalancerCfg, MessageBalancer};
+use crate::core::bnc::state::scaling::{spawn_workers, DeliveryCounters, ScalingCfg};
+use crate::core::bnc::state::tasks::TaskGroup;
+use crate::core::bnc::ws::config::{ReconnectCfg, WsCfg};
+use crate::core::bnc::ws::endpoints::EndpointPool;
+use crate::core::bnc::ws::reconnect::{
+    ReconnectPolicy, ReconnectReceiver, ReconnectSender, RECONNECT_EVENTS_CAPACITY,
+};
+use crate::core::bnc::ws::worker::ticker::{SymbolTickerUpdate, SymbolTickerWatcher};
+use crate::core::bnc::ws::worker::WsWorker;
+use log::debug;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::sync::watch::{channel, Receiver};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+pub type TickerReceiver = Receiver<SymbolTickerUpdate>;
+
+struct TickerManagerCfg {
+    ws_base_url: String,
+    endpoints: EndpointPool,
+    workers: u64,
+    reconnect: ReconnectCfg,
+    scaling: ScalingCfg,
+    balancer: BalancerCfg,
+}
+
+impl TickerManagerCfg {
+    fn from_cfg(cfg: &WsCfg) -> Self {
+        Self {
+            ws_base_url: cfg.baseurl.primary().to_string(),
+            endpoints: EndpointPool::new(cfg.baseurl.all()),
+            workers: cfg.ticker_workers_count(),
+            reconnect: cfg.reconnect.clone(),
+            scaling: cfg.scaling.clone(),
+            balancer: cfg.balancer.clone(),
+        }
+    }
+}
+
+/// Schedules workers of the rolling 24hr statistics, provides the latest statistics via watch receiver.
+pub struct TickerStateManager {
+    cfg: TickerManagerCfg,
+    tasks: TaskGroup,
+    reconnects: ReconnectSender,
+
+    /// Stops the scheduled tasks.
+    cancel: CancellationToken,
+}
+
+impl TickerStateManager {
+    pub fn from_cfg(cfg: &WsCfg) -> Self {
+        Self {
+            cfg: TickerManagerCfg::from_cfg(cfg),
+            tasks: TaskGroup::new("ticker"),
+            reconnects: broadcast::channel(RECONNECT_EVENTS_CAPACITY).0,
+            cancel: Default::default(),
+        }
+    }
+
+    pub fn init(&mut self, symbol: &str) -> TickerReceiver {
+        self.cancel = CancellationToken::new();
+        self.tasks = TaskGroup::new(format!("{} ticker", symbol));
+        let (sender, receiver) = channel(SymbolTickerUpdate::default());
+
+        let counters = Arc::new(DeliveryCounters::default());
+        let balancer = Arc::new(Mutex::new(
+            MessageBalancer::new(sender)
+                .with_counters(counters.clone())
+                .with_cfg(&self.cfg.balancer),
+        ));
+
+        let base_url = self.cfg.ws_base_url.clone();
+        let reconnect =
+            ReconnectPolicy::new(self.cfg.reconnect.clone(), Some(self.reconnects.clone()))
+                .with_endpoints(self.cfg.endpoints.clone())
+                .with_cancel(self.cancel.clone());
+        let symbol = symbol.to_string();
+        debug!(
+            "Initialising {} workers of symbol ticker receiver.",
+            self.cfg.workers
+        );
+        let workers = spawn_workers(
+            &self.cfg.scaling,
+            self.cfg.workers,
+            counters,
+            self.tasks.scaled_workers(),
+            self.cancel.clone(),
+            move || {
+                WsWorker::new(&base_url)
+                    .with_reconnect(reconnect.clone())
+                    .ticker_updates_watcher(&symbol, balancer.clone())
+            },
+        );
+
+        self.tasks.adopt_all("worker", workers);
+
+        receiver
+    }
+
+    /// Subscribe to the reconnect events of the scheduled workers.
+    pub fn reconnects(&self) -> ReconnectReceiver {
+        self.reconnects.subscribe()
+    }
+
+    /// Let the workers close their connections and stop.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Wait for the stopped tasks to finish. Returns the first error any of them failed with.
+    pub async fn join(&mut self) -> BncResult<()> {
+        self.tasks.join().await
+    }
+
+    /// Amount of scheduled tasks that are still running.
+    pub fn alive_tasks(&self) -> usize {
+        self.tasks.alive()
+    }
+}
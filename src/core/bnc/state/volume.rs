@@ -0,0 +1,451 @@
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::rest::BncRestClient;
+use crate::core::bnc::state::scaling::{spawn_workers, DeliveryCounters, ScalingCfg};
+use crate::core::bnc::state::tasks::TaskGroup;
+use crate::core::bnc::ws::config::{ReconnectCfg, WsCfg};
+use crate::core::bnc::ws::endpoints::EndpointPool;
+use crate::core::bnc::ws::reconnect::{
+    ReconnectPolicy, ReconnectReceiver, ReconnectSender, RECONNECT_EVENTS_CAPACITY,
+};
+use crate::core::bnc::ws::worker::trade::{SymbolTradeUpdate, SymbolTradeWatcher};
+use crate::core::bnc::ws::worker::{MessageSender, WsWorker};
+use log::{debug, warn};
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::watch::{channel, Receiver, Sender};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Windows the trades are summed over, the shortest one first.
+pub const VOLUME_WINDOWS: [Duration; 3] = [
+    Duration::from_secs(60),
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(60 * 60),
+];
+
+/// Trades of the symbol within a single window, ending with the latest trade.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeStats {
+    pub window: Duration,
+
+    /// Volume weighted average price of the trades, none if there are no trades.
+    pub vwap: Option<Decimal>,
+
+    /// Amount of the aggregated trades.
+    pub trades: u64,
+
+    /// Base asset volume bought by the takers.
+    pub buy_volume: Decimal,
+
+    /// Base asset volume sold by the takers.
+    pub sell_volume: Decimal,
+}
+
+impl VolumeStats {
+    pub fn volume(&self) -> Decimal {
+        self.buy_volume + self.sell_volume
+    }
+
+    /// Share of the volume bought by the takers, from 0 to 1.
+    pub fn buy_share(&self) -> Option<Decimal> {
+        self.buy_volume.checked_div(self.volume())
+    }
+}
+
+/// Recent trades the windows are filled with at the start, half of the most binance returns at once.
+pub const DEFAULT_RECENT_TRADES: u16 = 500;
+
+/// Time the recent trades are fetched within, the live ones wait for them meanwhile.
+const BACKFILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stats of every window of `VOLUME_WINDOWS`, in the same order.
+pub type RollingVolume = Vec<VolumeStats>;
+pub type VolumeReceiver = Receiver<RollingVolume>;
+
+/// Trades of a single second.
+#[derive(Debug, Clone, Default)]
+struct Bucket {
+    /// Seconds since epoch.
+    second: u64,
+    trades: u64,
+    buy_volume: Decimal,
+    sell_volume: Decimal,
+    notional: Decimal,
+}
+
+/// Trades of the longest window summed by seconds.
+///
+/// Windows end with the latest trade time, not the local clock - they roll on while the trades go on.
+struct TradeTape {
+    sender: Sender<RollingVolume>,
+    buckets: VecDeque<Bucket>,
+
+    /// Id of the latest recorded trade, to drop the ones delivered by the other workers.
+    last_trade_id: Option<u64>,
+
+    counters: Arc<DeliveryCounters>,
+}
+
+impl TradeTape {
+    fn new(sender: Sender<RollingVolume>, counters: Arc<DeliveryCounters>) -> Self {
+        Self {
+            sender,
+            buckets: VecDeque::new(),
+            last_trade_id: None,
+            counters,
+        }
+    }
+
+    /// Add the trade to its second's bucket. Returns whether it was not recorded before.
+    fn record(&mut self, trade: &SymbolTradeUpdate) -> bool {
+        match self.last_trade_id {
+            Some(last) if trade.id <= last => {
+                self.counters.duplicate();
+                return false;
+            }
+            // Missed trades can't be fetched again - the windows go on without them.
+            Some(last) if trade.id > last + 1 => self.counters.gap(),
+            _ => {}
+        }
+        self.last_trade_id = Some(trade.id);
+        self.counters.accepted();
+
+        let second = trade.time / 1000;
+        if !matches!(self.buckets.back(), Some(bucket) if bucket.second >= second) {
+            self.buckets.push_back(Bucket {
+                second,
+                ..Default::default()
+            });
+        }
+        let bucket = self.buckets.back_mut().expect("Bucket is just pushed.");
+        let (price, qty) = (trade.price.value(), trade.qty.value());
+        bucket.trades += 1;
+        bucket.notional += price * qty;
+        if trade.is_buyer_maker {
+            bucket.sell_volume += qty;
+        } else {
+            bucket.buy_volume += qty;
+        }
+
+        let longest = VOLUME_WINDOWS[VOLUME_WINDOWS.len() - 1].as_secs();
+        while matches!(self.buckets.front(), Some(bucket) if bucket.second + longest <= second) {
+            self.buckets.pop_front();
+        }
+        true
+    }
+
+    /// Sum the buckets of every window.
+    fn stats(&self) -> RollingVolume {
+        let latest = self.buckets.back().map(|bucket| bucket.second).unwrap_or(0);
+        VOLUME_WINDOWS
+            .iter()
+            .map(|window| {
+                let mut stats = VolumeStats {
+                    window: *window,
+                    ..Default::default()
+                };
+                let mut notional = Decimal::ZERO;
+                for bucket in self.buckets.iter().rev() {
+                    if bucket.second + window.as_secs() <= latest {
+                        break;
+                    }
+                    stats.trades += bucket.trades;
+                    stats.buy_volume += bucket.buy_volume;
+                    stats.sell_volume += bucket.sell_volume;
+                    notional += bucket.notional;
+                }
+                stats.vwap = notional.checked_div(stats.volume());
+                stats
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageSender<SymbolTradeUpdate> for Arc<Mutex<TradeTape>> {
+    async fn send(&self, data: SymbolTradeUpdate) -> BncResult<()> {
+        let mut lock = self.lock().await;
+        if !lock.record(&data) {
+            return Err(BncError::DataRejected);
+        }
+        lock.sender
+            .send(lock.stats())
+            .map_err(|_| BncError::DataTransmitError)
+    }
+}
+
+struct VolumeTrackerCfg {
+    ws_base_url: String,
+    endpoints: EndpointPool,
+    workers: u64,
+    reconnect: ReconnectCfg,
+    scaling: ScalingCfg,
+}
+
+impl VolumeTrackerCfg {
+    fn from_cfg(cfg: &WsCfg) -> Self {
+        Self {
+            ws_base_url: cfg.baseurl.primary().to_string(),
+            endpoints: EndpointPool::new(cfg.baseurl.all()),
+            workers: cfg.trade_workers_count(),
+            reconnect: cfg.reconnect.clone(),
+            scaling: cfg.scaling.clone(),
+        }
+    }
+}
+
+/// Schedules workers of the symbol's trades, provides rolling VWAP and volumes of the trades via watch receiver.
+pub struct VolumeTracker {
+    cfg: VolumeTrackerCfg,
+    tasks: TaskGroup,
+    reconnects: ReconnectSender,
+
+    /// Stops the scheduled tasks.
+    cancel: CancellationToken,
+
+    /// Client and amount of the recent trades fetched before the live ones, if any are.
+    backfill: Option<(BncRestClient, u16)>,
+}
+
+impl VolumeTracker {
+    pub fn from_cfg(cfg: &WsCfg) -> Self {
+        Self {
+            cfg: VolumeTrackerCfg::from_cfg(cfg),
+            tasks: TaskGroup::new("trades"),
+            reconnects: broadcast::channel(RECONNECT_EVENTS_CAPACITY).0,
+            cancel: Default::default(),
+            backfill: None,
+        }
+    }
+
+    /// Fill the windows with the recent trades, so they are not empty until the first live trade.
+    pub fn with_backfill(mut self, client: BncRestClient, limit: u16) -> Self {
+        self.backfill = Some((client, limit));
+        self
+    }
+
+    pub fn init(&mut self, symbol: &str) -> VolumeReceiver {
+        self.cancel = CancellationToken::new();
+        self.tasks = TaskGroup::new(format!("{} trades", symbol));
+        let counters = Arc::new(DeliveryCounters::default());
+        let (sender, receiver) = channel(RollingVolume::new());
+        let tape = TradeTape::new(sender, counters.clone());
+        // Receivers get all the windows, empty until the first trade.
+        let _ = tape.sender.send(tape.stats());
+        let tape = Arc::new(Mutex::new(tape));
+        if let Some((client, limit)) = self.backfill.clone() {
+            // Locked before the workers start, so the live trades follow the recent ones.
+            let guard = tape
+                .clone()
+                .try_lock_owned()
+                .expect("Tape is not shared yet.");
+            let backfill = trades_backfill(
+                client,
+                symbol.to_string(),
+                limit,
+                guard,
+                self.cancel.clone(),
+            );
+            self.tasks.adopt("backfill", backfill);
+        }
+
+        let base_url = self.cfg.ws_base_url.clone();
+        let reconnect =
+            ReconnectPolicy::new(self.cfg.reconnect.clone(), Some(self.reconnects.clone()))
+                .with_endpoints(self.cfg.endpoints.clone())
+                .with_cancel(self.cancel.clone());
+        let symbol = symbol.to_string();
+        debug!(
+            "Initialising {} workers of symbol trades receiver.",
+            self.cfg.workers
+        );
+        let workers = spawn_workers(
+            &self.cfg.scaling,
+            self.cfg.workers,
+            counters,
+            self.tasks.scaled_workers(),
+            self.cancel.clone(),
+            move || {
+                WsWorker::new(&base_url)
+                    .with_reconnect(reconnect.clone())
+                    .trade_updates_watcher(&symbol, tape.clone())
+            },
+        );
+        self.tasks.adopt_all("worker", workers);
+
+        receiver
+    }
+
+    /// Subscribe to the reconnect events of the scheduled workers.
+    pub fn reconnects(&self) -> ReconnectReceiver {
+        self.reconnects.subscribe()
+    }
+
+    /// Let the workers close their connections and stop.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Wait for the stopped tasks to finish. Returns the first error any of them failed with.
+    pub async fn join(&mut self) -> BncResult<()> {
+        self.tasks.join().await
+    }
+
+    /// Amount of scheduled tasks that are still running.
+    pub fn alive_tasks(&self) -> usize {
+        self.tasks.alive()
+    }
+}
+
+/// Record the recent trades into the locked tape. The windows start empty if they could not be fetched.
+fn trades_backfill(
+    client: BncRestClient,
+    symbol: String,
+    limit: u16,
+    mut tape: OwnedMutexGuard<TradeTape>,
+    cancel: CancellationToken,
+) -> JoinHandle<BncResult<()>> {
+    tokio::task::spawn(async move {
+        let fetch =
+            tokio::time::timeout(BACKFILL_TIMEOUT, client.fetch_recent_trades(&symbol, limit));
+        let trades = tokio::select! {
+            trades = fetch => trades,
+            _ = cancel.cancelled() => return Ok(()),
+        };
+        let trades = match trades {
+            Ok(Ok(trades)) => trades,
+            Ok(Err(err)) => {
+                warn!(
+                    "Could not fetch recent trades of {}. Error: {}",
+                    symbol, err
+                );
+                return Ok(());
+            }
+            Err(_) => {
+                warn!(
+                    "Recent trades of {} are not fetched in {:?}.",
+                    symbol, BACKFILL_TIMEOUT
+                );
+                return Ok(());
+            }
+        };
+        let recorded = trades.iter().filter(|trade| tape.record(trade)).count();
+        debug!("Backfilled {} recent trades of {}.", recorded, symbol);
+        if recorded > 0 {
+            // Nobody listens - nothing to report.
+            let _ = tape.sender.send(tape.stats());
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(
+        id: u64,
+        second: u64,
+        price: &str,
+        qty: &str,
+        is_buyer_maker: bool,
+    ) -> SymbolTradeUpdate {
+        SymbolTradeUpdate {
+            id,
+            price: price.parse().unwrap(),
+            qty: qty.parse().unwrap(),
+            time: second * 1000 + 250,
+            is_buyer_maker,
+            ..Default::default()
+        }
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn it_rolls_volume_windows() {
+        let counters = Arc::new(DeliveryCounters::default());
+        let mut tape = TradeTape::new(channel(RollingVolume::new()).0, counters.clone());
+
+        assert!(tape.record(&trade(1, 1000, "100", "2", false)));
+        assert!(tape.record(&trade(2, 1000, "100", "2", true)));
+        assert!(tape.record(&trade(3, 1200, "110", "1", false)));
+        // Delivered by another worker.
+        assert!(!tape.record(&trade(3, 1200, "110", "1", false)));
+        assert!(tape.record(&trade(5, 1250, "120", "1", true)));
+
+        let stats = tape.stats();
+        assert_eq!(stats.len(), VOLUME_WINDOWS.len());
+
+        // The first second is out of the minute and in the 5 minutes.
+        let minute = &stats[0];
+        assert_eq!(minute.trades, 2);
+        assert_eq!(minute.vwap, Some(decimal("115")));
+        assert_eq!(minute.buy_share(), Some(decimal("0.5")));
+
+        let five_minutes = &stats[1];
+        assert_eq!(five_minutes.trades, 4);
+        assert_eq!(five_minutes.buy_volume, decimal("3"));
+        assert_eq!(five_minutes.sell_volume, decimal("3"));
+        assert_eq!(five_minutes.vwap, Some(decimal("105")));
+
+        let stats = counters.stats();
+        assert_eq!((stats.accepted, stats.duplicates, stats.gaps), (4, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn it_backfills_recent_trades_before_live_ones() -> anyhow::Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let rest_url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            let body = r#"[{"a":100,"p":"100","q":"1","f":1,"l":1,"T":1000000,"m":false},
+                {"a":101,"p":"110","q":"1","f":2,"l":2,"T":1001000,"m":true}]"#;
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let _ = stream.read(&mut [0u8; 4096]).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let ws = WsCfg {
+            baseurl: "synthetic://1".into(),
+            ..Default::default()
+        };
+        let client = BncRestClient::new(reqwest::Client::new(), rest_url);
+        let mut tracker = VolumeTracker::from_cfg(&ws).with_backfill(client, 2);
+        let mut receiver = tracker.init("BTCUSDT");
+        receiver.borrow_and_update();
+
+        // Live trades of the synthetic stream start with the lower ids, so they are dropped.
+        receiver.changed().await?;
+        let stats = receiver.borrow_and_update().clone();
+        assert_eq!(stats[2].trades, 2);
+        assert_eq!(stats[2].vwap, Some(decimal("105")));
+
+        tracker.stop();
+        tracker.join().await?;
+        Ok(())
+    }
+
+    #[test]
+    fn it_drops_trades_older_than_longest_window() {
+        let mut tape = TradeTape::new(channel(RollingVolume::new()).0, Default::default());
+
+        tape.record(&trade(1, 1000, "100", "1", false));
+        tape.record(&trade(2, 4600, "100", "1", false));
+        assert_eq!(tape.buckets.len(), 1);
+        assert_eq!(tape.stats()[2].trades, 1);
+        assert_eq!(tape.stats()[0].vwap, Some(decimal("100")));
+    }
+}
@@ -0,0 +1,594 @@
+use crate::core::bnc::clock::local_millis;
+use crate::core::bnc::data::{
+    AveragePrice, InlineOrder, Kline, KlinesQuery, Price, Quantity, SymbolTicker24h,
+};
+use crate::core::bnc::snapshot::SymbolSnapshot;
+use futures::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use log::warn;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Scheme of the base urls served by the generator instead of binance, e.g. `synthetic://42` - the number is the seed.
+pub const SYNTHETIC_SCHEME: &str = "synthetic://";
+
+/// Symbols of the generated all market streams.
+pub const SYNTHETIC_MARKET: [&str; 6] = [
+    "BTCUSDT", "ETHUSDT", "BNBUSDT", "SOLUSDT", "XRPUSDT", "ADAUSDT",
+];
+
+/// Step of the random walk. Generators advance the walk of their symbol by the steps their interval takes,
+/// so all the streams of the symbol agree on its price.
+const WALK_STEP: Duration = Duration::from_millis(100);
+
+/// Decimal places of the generated prices and quantities.
+const PRICE_SCALE: u32 = 2;
+const QTY_SCALE: u32 = 3;
+
+/// Levels of each side kept around the mid price by the generated book.
+const BOOK_LEVELS: i64 = 50;
+
+/// Levels of each side changed by a single depth update, besides the best ones.
+const CHURN_LEVELS: usize = 3;
+
+pub fn is_synthetic(url: &str) -> bool {
+    url.starts_with(SYNTHETIC_SCHEME)
+}
+
+/// Seed of the synthetic url, zero if it has none.
+fn seed(url: &str) -> u64 {
+    url.trim_start_matches(SYNTHETIC_SCHEME)
+        .split('/')
+        .next()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or(0)
+}
+
+fn price(ticks: i64) -> Price {
+    Decimal::new(ticks, PRICE_SCALE).into()
+}
+
+fn qty(lots: i64) -> Quantity {
+    Decimal::new(lots, QTY_SCALE).into()
+}
+
+/// Levels as binance sends them - `[price, qty]` string pairs.
+fn levels<'a>(levels: impl Iterator<Item = (&'a i64, &'a i64)>) -> Value {
+    levels
+        .map(|(ticks, lots)| json!([price(*ticks), qty(*lots)]))
+        .collect()
+}
+
+/// Splitmix64 - tiny generator that is the same on every platform and release, unlike the std hashers.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.0;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+
+    /// Number in `[0, bound)`.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    /// Amount of the base asset between 0.001 and 10.
+    fn lots(&mut self) -> i64 {
+        1 + self.below(10_000) as i64
+    }
+
+    /// New amount of the churned level, every fifth one is removed.
+    fn churned_lots(&mut self) -> i64 {
+        match self.below(5) {
+            0 => 0,
+            _ => self.lots(),
+        }
+    }
+}
+
+/// Seed of the symbol's generators, derived from the seed of the url and the symbol(FNV-1a).
+fn symbol_seed(seed: u64, symbol: &str) -> u64 {
+    symbol
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325 ^ seed, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Random walk of the symbol's mid price, along with its statistics since the start.
+#[derive(Debug, Clone)]
+struct Walk {
+    rng: Rng,
+    step: u64,
+
+    /// Prices are in ticks of the last decimal place.
+    mid: i64,
+    open: i64,
+    high: i64,
+    low: i64,
+
+    /// Traded amounts - a random trade happens at each step.
+    volume: i64,
+    quote_volume: Decimal,
+}
+
+impl Walk {
+    fn new(seed: u64, symbol: &str) -> Self {
+        let mut rng = Rng::new(symbol_seed(seed, symbol));
+        // Somewhere between 100 and 10000.
+        let mid = 10_000 + rng.below(990_000) as i64;
+        Self {
+            rng,
+            step: 0,
+            mid,
+            open: mid,
+            high: mid,
+            low: mid,
+            volume: 0,
+            quote_volume: Decimal::ZERO,
+        }
+    }
+
+    /// Advance the walk up to the step.
+    fn advance_to(&mut self, step: u64) {
+        while self.step < step {
+            self.step += 1;
+            match self.rng.below(3) {
+                0 => self.mid = (self.mid - 1).max(BOOK_LEVELS + 1),
+                1 => self.mid += 1,
+                _ => {}
+            }
+            self.high = self.high.max(self.mid);
+            self.low = self.low.min(self.mid);
+
+            let lots = self.rng.lots();
+            self.volume += lots;
+            self.quote_volume += price(self.mid).value() * qty(lots).value();
+        }
+    }
+}
+
+/// Book of the symbol, kept around the mid price of its walk.
+#[derive(Debug, Clone)]
+struct SyntheticBook {
+    walk: Walk,
+    rng: Rng,
+    bids: BTreeMap<i64, i64>,
+    asks: BTreeMap<i64, i64>,
+    update_id: u64,
+}
+
+/// Levels changed by a single update, zero amount for the removed ones.
+type Changes = BTreeMap<i64, i64>;
+
+impl SyntheticBook {
+    fn new(seed: u64, symbol: &str) -> Self {
+        let walk = Walk::new(seed, symbol);
+        let mut rng = Rng::new(symbol_seed(seed, symbol).rotate_left(1));
+        let bids = (1..=BOOK_LEVELS)
+            .map(|offset| (walk.mid - offset, rng.lots()))
+            .collect();
+        let asks = (1..=BOOK_LEVELS)
+            .map(|offset| (walk.mid + offset, rng.lots()))
+            .collect();
+        Self {
+            walk,
+            rng,
+            bids,
+            asks,
+            update_id: 0,
+        }
+    }
+
+    /// Advance the book to the step of the walk, returns the changed bids and asks.
+    fn churn(&mut self, step: u64) -> (Changes, Changes) {
+        self.walk.advance_to(step);
+        let mid = self.walk.mid;
+        let mut bids = Changes::new();
+        let mut asks = Changes::new();
+
+        // Levels crossed by the mid price are consumed, the ones too far from it are forgotten.
+        let stale_bids: Vec<i64> = self
+            .bids
+            .keys()
+            .filter(|level| **level >= mid || **level < mid - BOOK_LEVELS)
+            .copied()
+            .collect();
+        let stale_asks: Vec<i64> = self
+            .asks
+            .keys()
+            .filter(|level| **level <= mid || **level > mid + BOOK_LEVELS)
+            .copied()
+            .collect();
+        stale_bids.into_iter().for_each(|level| {
+            bids.insert(level, 0);
+        });
+        stale_asks.into_iter().for_each(|level| {
+            asks.insert(level, 0);
+        });
+
+        // The best levels are always there, a few others are replaced or removed.
+        bids.insert(mid - 1, self.rng.lots());
+        asks.insert(mid + 1, self.rng.lots());
+        for _ in 0..CHURN_LEVELS {
+            let offset = 2 + self.rng.below(BOOK_LEVELS as u64 - 1) as i64;
+            bids.insert(mid - offset, self.rng.churned_lots());
+            let offset = 2 + self.rng.below(BOOK_LEVELS as u64 - 1) as i64;
+            asks.insert(mid + offset, self.rng.churned_lots());
+        }
+
+        for (changes, side) in [(&bids, &mut self.bids), (&asks, &mut self.asks)] {
+            for (level, lots) in changes {
+                match lots {
+                    0 => side.remove(level),
+                    lots => side.insert(*level, *lots),
+                };
+            }
+        }
+        self.update_id += 1;
+        (bids, asks)
+    }
+
+    fn snapshot(&self) -> SymbolSnapshot {
+        let orders = |side: &BTreeMap<i64, i64>| -> Vec<InlineOrder> {
+            side.iter()
+                .map(|(ticks, lots)| InlineOrder::new(price(*ticks), qty(*lots)))
+                .collect()
+        };
+        SymbolSnapshot {
+            last_update_id: self.update_id,
+            bids: orders(&self.bids),
+            asks: orders(&self.asks),
+        }
+    }
+}
+
+/// Snapshot of the symbol's synthetic book, as it is before its first depth update.
+pub fn synthetic_snapshot(base_url: &str, symbol: &str) -> SymbolSnapshot {
+    SyntheticBook::new(seed(base_url), &symbol.to_ascii_uppercase()).snapshot()
+}
+
+/// Statistics of the symbol's walk before its first step - the market has just opened.
+pub fn synthetic_ticker(base_url: &str, symbol: &str) -> SymbolTicker24h {
+    let symbol = symbol.to_ascii_uppercase();
+    let walk = Walk::new(seed(base_url), &symbol);
+    SymbolTicker24h {
+        symbol,
+        price_change: price(0),
+        price_change_percent: "0".into(),
+        weighted_avg_price: price(walk.mid),
+        last_price: price(walk.mid),
+        high: price(walk.high),
+        low: price(walk.low),
+        volume: qty(walk.volume),
+        quote_volume: walk.quote_volume.into(),
+        close_time: local_millis(),
+    }
+}
+
+/// Average price of the symbol's walk - the starting mid, as the walk has not moved yet.
+pub fn synthetic_avg_price(base_url: &str, symbol: &str) -> AveragePrice {
+    let walk = Walk::new(seed(base_url), &symbol.to_ascii_uppercase());
+    AveragePrice {
+        mins: 5,
+        price: price(walk.mid),
+        close_time: local_millis(),
+    }
+}
+
+/// Klines of the symbol's walk before its first step - flat and without trades, as the market has just opened.
+///
+/// They follow the start of the window, or lead up to its end(now by default).
+pub fn synthetic_klines(base_url: &str, query: &KlinesQuery) -> Vec<Kline> {
+    let walk = Walk::new(seed(base_url), &query.symbol.to_ascii_uppercase());
+    let length = query.interval.millis();
+    let limit = query.limit.unwrap_or(500) as u64;
+    let end = query.end_time.unwrap_or_else(local_millis);
+    let start = query
+        .start_time
+        .unwrap_or_else(|| end.saturating_sub(limit * length));
+    (0..limit)
+        .map(|index| start / length * length + index * length)
+        .take_while(|open_time| *open_time <= end)
+        .map(|open_time| Kline {
+            open_time,
+            open: price(walk.mid),
+            high: price(walk.mid),
+            low: price(walk.mid),
+            close: price(walk.mid),
+            volume: qty(0),
+            close_time: open_time + length - 1,
+            quote_volume: qty(0),
+            trades: 0,
+            taker_buy_volume: qty(0),
+            taker_buy_quote_volume: qty(0),
+        })
+        .collect()
+}
+
+/// Generator of a single stream's data.
+#[derive(Debug, Clone)]
+enum Generator {
+    BookTicker { walk: Walk, rng: Rng, id: u64 },
+    Depth { book: SyntheticBook },
+    PartialDepth { book: SyntheticBook, levels: usize },
+    Ticker { walk: Walk },
+    AggTrade { walk: Walk, rng: Rng, id: u64 },
+    MiniTickers { walks: Vec<(String, Walk)> },
+}
+
+#[derive(Debug, Clone)]
+struct SyntheticStream {
+    name: String,
+    symbol: String,
+    interval: Duration,
+    generator: Generator,
+
+    /// Messages generated so far.
+    sent: u64,
+}
+
+impl SyntheticStream {
+    /// Generator of the named stream, none if such streams are not generated.
+    fn new(seed: u64, name: &str) -> Option<Self> {
+        let (symbol, kind) = name.split_once('@')?;
+        let symbol = symbol.to_ascii_uppercase();
+        let (interval, generator) = match kind {
+            "arr" if symbol == "!MINITICKER" => (
+                Duration::from_secs(1),
+                Generator::MiniTickers {
+                    walks: SYNTHETIC_MARKET
+                        .iter()
+                        .map(|symbol| (symbol.to_string(), Walk::new(seed, symbol)))
+                        .collect(),
+                },
+            ),
+            "bookTicker" => (
+                WALK_STEP,
+                Generator::BookTicker {
+                    walk: Walk::new(seed, &symbol),
+                    rng: Rng::new(symbol_seed(seed, &symbol).rotate_left(2)),
+                    id: 0,
+                },
+            ),
+            "ticker" => (
+                Duration::from_secs(1),
+                Generator::Ticker {
+                    walk: Walk::new(seed, &symbol),
+                },
+            ),
+            "aggTrade" => (
+                Duration::from_millis(300),
+                Generator::AggTrade {
+                    walk: Walk::new(seed, &symbol),
+                    rng: Rng::new(symbol_seed(seed, &symbol).rotate_left(3)),
+                    id: 0,
+                },
+            ),
+            "depth" => (
+                Duration::from_secs(1),
+                Generator::Depth {
+                    book: SyntheticBook::new(seed, &symbol),
+                },
+            ),
+            "depth@100ms" => (
+                Duration::from_millis(100),
+                Generator::Depth {
+                    book: SyntheticBook::new(seed, &symbol),
+                },
+            ),
+            kind => {
+                let levels = kind.strip_prefix("depth")?;
+                let (levels, interval) = match levels.strip_suffix("@100ms") {
+                    Some(levels) => (levels, Duration::from_millis(100)),
+                    None => (levels, Duration::from_secs(1)),
+                };
+                (
+                    interval,
+                    Generator::PartialDepth {
+                        book: SyntheticBook::new(seed, &symbol),
+                        levels: levels.parse().ok()?,
+                    },
+                )
+            }
+        };
+        Some(Self {
+            name: name.to_string(),
+            symbol,
+            interval,
+            generator,
+            sent: 0,
+        })
+    }
+
+    /// Data of the next message, in the format of the corresponding binance stream.
+    fn next_data(&mut self, time: u64) -> Value {
+        self.sent += 1;
+        let step = self.sent * self.interval.as_millis() as u64 / WALK_STEP.as_millis() as u64;
+        let symbol = &self.symbol;
+        match &mut self.generator {
+            Generator::BookTicker { walk, rng, id } => {
+                walk.advance_to(step);
+                *id += 1;
+                json!({
+                    "u": id, "s": symbol,
+                    "b": price(walk.mid - 1), "B": qty(rng.lots()),
+                    "a": price(walk.mid + 1), "A": qty(rng.lots()),
+                })
+            }
+            Generator::Depth { book } => {
+                let (bids, asks) = book.churn(step);
+                json!({
+                    "e": "depthUpdate", "E": time, "s": symbol,
+                    "U": book.update_id, "u": book.update_id,
+                    "b": levels(bids.iter().rev()), "a": levels(asks.iter()),
+                })
+            }
+            Generator::PartialDepth { book, levels: depth } => {
+                book.churn(step);
+                json!({
+                    "lastUpdateId": book.update_id,
+                    "bids": levels(book.bids.iter().rev().take(*depth)),
+                    "asks": levels(book.asks.iter().take(*depth)),
+                })
+            }
+            Generator::Ticker { walk } => {
+                walk.advance_to(step);
+                let change = price(walk.mid - walk.open).value();
+                let percent = change / price(walk.open).value() * Decimal::ONE_HUNDRED;
+                let average = match walk.volume {
+                    0 => price(walk.mid).value(),
+                    volume => (walk.quote_volume / qty(volume).value()).round_dp(PRICE_SCALE),
+                };
+                json!({
+                    "e": "24hrTicker", "E": time, "s": symbol,
+                    "p": price(walk.mid - walk.open), "P": percent.round_dp(3).to_string(),
+                    "w": average.to_string(), "c": price(walk.mid),
+                    "h": price(walk.high), "l": price(walk.low),
+                    "v": qty(walk.volume), "q": walk.quote_volume.round_dp(QTY_SCALE).to_string(),
+                })
+            }
+            Generator::AggTrade { walk, rng, id } => {
+                walk.advance_to(step);
+                *id += 1;
+                let is_buyer_maker = rng.below(2) == 0;
+                let level = if is_buyer_maker {
+                    walk.mid - 1
+                } else {
+                    walk.mid + 1
+                };
+                json!({
+                    "e": "aggTrade", "E": time, "s": symbol,
+                    "a": id, "p": price(level), "q": qty(rng.lots()),
+                    "f": id, "l": id, "T": time, "m": is_buyer_maker,
+                })
+            }
+            Generator::MiniTickers { walks } => walks
+                .iter_mut()
+                .map(|(symbol, walk)| {
+                    walk.advance_to(step);
+                    json!({
+                        "e": "24hrMiniTicker", "E": time, "s": symbol,
+                        "c": price(walk.mid), "o": price(walk.open),
+                        "h": price(walk.high), "l": price(walk.low),
+                        "v": qty(walk.volume), "q": walk.quote_volume.round_dp(QTY_SCALE).to_string(),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn next_message(&mut self) -> Message {
+        let data = self.next_data(local_millis());
+        Message::Text(json!({ "stream": self.name, "data": data }).to_string())
+    }
+}
+
+/// Messages of the synthetic endpoint, in the format of the binance combined streams.
+///
+/// Each stream is pushed with the interval of its binance counterpart, its content depends on the seed only.
+/// Combined connections don't send their subscriptions over the socket, they start the stream over instead.
+pub fn synthetic_stream(endpoint: &str) -> BoxStream<'static, Message> {
+    let seed = seed(endpoint);
+    let names = endpoint
+        .split_once("streams=")
+        .map(|(_, names)| names)
+        .unwrap_or_default();
+
+    let streams = names.split('/').filter_map(|name| {
+        let stream = SyntheticStream::new(seed, name);
+        if stream.is_none() {
+            warn!("Stream {} is not generated synthetically.", name);
+        }
+        stream
+    });
+    let streams = streams.map(|stream| {
+        let interval = tokio::time::interval(stream.interval);
+        stream::unfold(
+            (stream, interval),
+            |(mut stream, mut interval)| async move {
+                interval.tick().await;
+                Some((stream.next_message(), (stream, interval)))
+            },
+        )
+        .boxed()
+    });
+    stream::select_all(streams).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::state::book::OrderBook;
+    use crate::core::bnc::ws::data::WsDataContainer;
+    use crate::core::bnc::ws::worker::depth::SymbolDepthUpdate;
+    use crate::core::bnc::ws::worker::mini_ticker::SymbolMiniTicker;
+    use crate::core::bnc::ws::worker::price::SymbolBookTick;
+    use crate::core::bnc::ws::worker::ticker::SymbolTickerUpdate;
+    use crate::core::bnc::ws::worker::trade::SymbolTradeUpdate;
+    use serde::de::DeserializeOwned;
+
+    fn generate(seed: u64, name: &str, messages: usize) -> Vec<Value> {
+        let mut stream = SyntheticStream::new(seed, name).unwrap();
+        (0..messages).map(|_| stream.next_data(0)).collect()
+    }
+
+    fn parse<T: DeserializeOwned>(name: &str) -> T {
+        let mut stream = SyntheticStream::new(7, name).unwrap();
+        let message = stream.next_message().into_data();
+        let container: WsDataContainer<T> = serde_json::from_slice(&message).unwrap();
+        assert_eq!(container.stream, name);
+        container.data
+    }
+
+    #[test]
+    fn it_reproduces_streams_of_the_seed() {
+        assert_eq!(
+            generate(42, "btcusdt@depth@100ms", 20),
+            generate(42, "btcusdt@depth@100ms", 20)
+        );
+        assert_ne!(
+            generate(42, "btcusdt@bookTicker", 20),
+            generate(43, "btcusdt@bookTicker", 20)
+        );
+        assert_eq!(seed("synthetic://42/stream?streams=a"), 42);
+        assert_eq!(seed("synthetic://"), 0);
+    }
+
+    #[test]
+    fn it_generates_data_of_binance_streams() {
+        parse::<SymbolBookTick>("btcusdt@bookTicker");
+        parse::<SymbolDepthUpdate>("btcusdt@depth");
+        parse::<SymbolSnapshot>("btcusdt@depth5@100ms");
+        parse::<SymbolTickerUpdate>("btcusdt@ticker");
+        parse::<SymbolTradeUpdate>("btcusdt@aggTrade");
+        let tickers = parse::<Vec<SymbolMiniTicker>>("!miniTicker@arr");
+        assert_eq!(tickers.len(), SYNTHETIC_MARKET.len());
+        assert!(SyntheticStream::new(7, "btcusdt@kline_1m").is_none());
+    }
+
+    #[test]
+    fn it_keeps_book_uncrossed_over_updates() {
+        let mut book = OrderBook::from(synthetic_snapshot("synthetic://3", "BTCUSDT"));
+        let mut stream = SyntheticStream::new(3, "btcusdt@depth@100ms").unwrap();
+        for _ in 0..500 {
+            let update: SymbolDepthUpdate = serde_json::from_value(stream.next_data(0)).unwrap();
+            assert!(book.add_depth_update(update));
+
+            let top = book.top(1, None);
+            assert!(top.best_bid().unwrap() < top.best_ask().unwrap());
+        }
+    }
+}
@@ -0,0 +1,919 @@
+use crate::core::bnc::error::{ApiErrorPayload, BncError, BncResult};
+use crate::core::bnc::replay::is_replay;
+use crate::core::bnc::synthetic::is_synthetic;
+use crate::core::bnc::ws::config::WsCfg;
+use crate::core::bnc::ws::data::WsDataContainer;
+use crate::core::bnc::ws::endpoints::EndpointPool;
+use crate::core::bnc::ws::reconnect::{
+    connect, reconnecting_duplex, ReconnectEvent, ReconnectPolicy, RECONNECT_EVENTS_CAPACITY,
+};
+use crate::core::bnc::ws::worker::{bnc_stream_connect, stream_endpoint, MessageSender};
+use futures::stream::BoxStream;
+use futures_util::StreamExt;
+use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+/// Deserializes the stream's data and passes it to the typed sender.
+#[async_trait::async_trait]
+trait StreamRoute: Send + Sync {
+    async fn route(&self, data: Value) -> BncResult<()>;
+}
+
+struct TypedRoute<T, S> {
+    sender: S,
+    message: PhantomData<fn() -> T>,
+}
+
+#[async_trait::async_trait]
+impl<T, S> StreamRoute for TypedRoute<T, S>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+    S: MessageSender<T> + Sync,
+{
+    async fn route(&self, data: Value) -> BncResult<()> {
+        let data: T = serde_json::from_value(data)?;
+        self.sender.send(data).await
+    }
+}
+
+/// Live subscription request of the connection, e.g. `{"method":"SUBSCRIBE","params":["btcusdt@aggTrade"],"id":1}`.
+#[derive(Debug, Serialize)]
+struct SubscriptionRequest {
+    method: &'static str,
+    params: Vec<String>,
+    id: u64,
+}
+
+/// Response to the subscription request, `result` is set only for the list of subscriptions.
+#[derive(Debug, Deserialize)]
+struct SubscriptionResponse {
+    id: u64,
+
+    #[serde(default)]
+    result: Option<Vec<String>>,
+
+    #[serde(default)]
+    error: Option<ApiErrorPayload>,
+}
+
+/// Any message received by the combined connection.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum CombinedMessage {
+    Response(SubscriptionResponse),
+    Data(WsDataContainer<Value>),
+}
+
+type Routes = HashMap<String, Box<dyn StreamRoute>>;
+
+/// Amount of messages of a single stream waiting to be processed, the newer ones are dropped beyond it.
+pub const STREAM_QUEUE_CAPACITY: usize = 256;
+
+/// Bounded queue of the stream's messages, drained into its route by a task of its own.
+///
+/// The socket reader never waits for a route - a flood on one stream fills only its own queue,
+/// the rest keep being processed.
+struct StreamQueue {
+    sender: mpsc::Sender<Value>,
+    dropped: u64,
+
+    /// Whether the queue is full since the last dropped message, to warn once per overflow.
+    overflowing: bool,
+}
+
+impl StreamQueue {
+    fn spawn(stream: String, route: Box<dyn StreamRoute>) -> Self {
+        let (sender, mut receiver) = mpsc::channel(STREAM_QUEUE_CAPACITY);
+        tokio::task::spawn(async move {
+            while let Some(data) = receiver.recv().await {
+                match route.route(data).await {
+                    Ok(_) => debug!("Message of {} processed.", stream),
+                    Err(BncError::DataTransmitError) => {
+                        warn!("Sender could not process message of {}.", stream)
+                    }
+                    Err(BncError::DataRejected) => {
+                        debug!("Message of {} was rejected due to some predicate.", stream)
+                    }
+                    Err(err) => error!(
+                        "Message of {} was not routed due to unexpected error. Error: {}",
+                        stream, err
+                    ),
+                }
+            }
+        });
+        Self {
+            sender,
+            dropped: 0,
+            overflowing: false,
+        }
+    }
+
+    /// Enqueue the message, it is dropped if the queue is full.
+    fn push(&mut self, stream: &str, data: Value) {
+        match self.sender.try_send(data) {
+            Ok(_) => self.overflowing = false,
+            Err(_) => {
+                self.dropped += 1;
+                if !self.overflowing {
+                    warn!("Queue of {} is full, its messages are dropped.", stream);
+                }
+                self.overflowing = true;
+            }
+        }
+    }
+
+    fn stats(&self, stream: &str) -> QueueStats {
+        QueueStats {
+            stream: stream.to_string(),
+            depth: self.sender.max_capacity() - self.sender.capacity(),
+            dropped: self.dropped,
+        }
+    }
+}
+
+/// Diagnostics of the stream's queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueStats {
+    pub stream: String,
+
+    /// Messages waiting to be processed.
+    pub depth: usize,
+
+    /// Messages dropped because the queue was full.
+    pub dropped: u64,
+}
+
+/// Command of the handle to the running connection.
+enum SubscriptionCommand {
+    Subscribe {
+        stream: String,
+        route: Box<dyn StreamRoute>,
+        reply: oneshot::Sender<BncResult<()>>,
+    },
+    Unsubscribe {
+        stream: String,
+        reply: oneshot::Sender<BncResult<()>>,
+    },
+    List {
+        reply: oneshot::Sender<BncResult<Vec<String>>>,
+    },
+
+    /// Answered by the connection itself, nothing is sent to binance.
+    Queues {
+        reply: oneshot::Sender<BncResult<Vec<QueueStats>>>,
+    },
+}
+
+/// Request that waits for its response.
+enum PendingRequest {
+    /// Route is registered right away, so the first messages aren't lost. It is dropped if binance refuses it.
+    Subscribe {
+        stream: String,
+        reply: oneshot::Sender<BncResult<()>>,
+    },
+    Unsubscribe {
+        reply: oneshot::Sender<BncResult<()>>,
+    },
+    List {
+        reply: oneshot::Sender<BncResult<Vec<String>>>,
+    },
+
+    /// Streams are subscribed again on the fresh connection, nobody waits for the response.
+    Resync,
+}
+
+/// State of the running connection - queues of its streams and requests awaiting the response.
+struct Subscriptions {
+    queues: HashMap<String, StreamQueue>,
+    pending: HashMap<u64, PendingRequest>,
+    next_id: u64,
+}
+
+impl Subscriptions {
+    fn new(routes: Routes) -> Self {
+        Self {
+            queues: routes
+                .into_iter()
+                .map(|(stream, route)| {
+                    let queue = StreamQueue::spawn(stream.clone(), route);
+                    (stream, queue)
+                })
+                .collect(),
+            pending: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Names of the streams, in order.
+    fn streams(&self) -> Vec<String> {
+        let mut streams: Vec<String> = self.queues.keys().cloned().collect();
+        streams.sort_unstable();
+        streams
+    }
+
+    /// Stats of the stream queues, in order of the stream names.
+    fn queue_stats(&self) -> Vec<QueueStats> {
+        let mut stats: Vec<QueueStats> = self
+            .queues
+            .iter()
+            .map(|(stream, queue)| queue.stats(stream))
+            .collect();
+        stats.sort_unstable_by(|a, b| a.stream.cmp(&b.stream));
+        stats
+    }
+
+    /// Register the command as pending, get the request to be sent.
+    ///
+    /// Commands the connection answers itself are completed right away, there is no request for them then.
+    fn request(&mut self, command: SubscriptionCommand) -> Option<SubscriptionRequest> {
+        let (method, params, pending) = match command {
+            SubscriptionCommand::Subscribe {
+                stream,
+                route,
+                reply,
+            } => {
+                let queue = StreamQueue::spawn(stream.clone(), route);
+                self.queues.insert(stream.clone(), queue);
+                let pending = PendingRequest::Subscribe {
+                    stream: stream.clone(),
+                    reply,
+                };
+                ("SUBSCRIBE", vec![stream], pending)
+            }
+            SubscriptionCommand::Unsubscribe { stream, reply } => {
+                self.queues.remove(&stream);
+                (
+                    "UNSUBSCRIBE",
+                    vec![stream],
+                    PendingRequest::Unsubscribe { reply },
+                )
+            }
+            SubscriptionCommand::List { reply } => {
+                ("LIST_SUBSCRIPTIONS", vec![], PendingRequest::List { reply })
+            }
+            SubscriptionCommand::Queues { reply } => {
+                let _ = reply.send(Ok(self.queue_stats()));
+                return None;
+            }
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, pending);
+        Some(SubscriptionRequest { method, params, id })
+    }
+
+    /// Complete the pending request the response belongs to.
+    fn respond(&mut self, response: SubscriptionResponse) {
+        let pending = match self.pending.remove(&response.id) {
+            Some(pending) => pending,
+            None => {
+                debug!("Response to unknown request #{} is skipped.", response.id);
+                return;
+            }
+        };
+        let error = response.error.map(BncError::from);
+        // Requester may be gone already, nothing to notify then.
+        match (pending, error) {
+            (PendingRequest::Subscribe { stream, reply }, Some(err)) => {
+                self.queues.remove(&stream);
+                let _ = reply.send(Err(err));
+            }
+            (PendingRequest::Subscribe { reply, .. }, None)
+            | (PendingRequest::Unsubscribe { reply }, None) => {
+                let _ = reply.send(Ok(()));
+            }
+            (PendingRequest::Unsubscribe { reply }, Some(err)) => {
+                let _ = reply.send(Err(err));
+            }
+            (PendingRequest::List { reply }, Some(err)) => {
+                let _ = reply.send(Err(err));
+            }
+            (PendingRequest::List { reply }, None) => {
+                let _ = reply.send(Ok(response.result.unwrap_or_default()));
+            }
+            (PendingRequest::Resync, Some(err)) => {
+                warn!("Streams could not be subscribed again. Error: {}", err)
+            }
+            (PendingRequest::Resync, None) => {}
+        }
+    }
+
+    /// Complete the pending request that could not be sent.
+    fn fail(&mut self, id: u64, err: BncError) {
+        match self.pending.remove(&id) {
+            Some(PendingRequest::Subscribe { stream, reply }) => {
+                self.queues.remove(&stream);
+                let _ = reply.send(Err(err));
+            }
+            Some(PendingRequest::Unsubscribe { reply }) => {
+                let _ = reply.send(Err(err));
+            }
+            Some(PendingRequest::List { reply }) => {
+                let _ = reply.send(Err(err));
+            }
+            Some(PendingRequest::Resync) | None => {}
+        }
+    }
+
+    /// Requests bringing the fresh connection of the endpoint with the initial streams to the current ones.
+    ///
+    /// Requests sent over the lost connection are never answered. Routes they changed are already in place,
+    /// so they are completed right away, except for the lists.
+    fn resync(&mut self, initial: &HashSet<String>) -> Vec<SubscriptionRequest> {
+        for (_, pending) in self.pending.drain() {
+            match pending {
+                PendingRequest::Subscribe { reply, .. } | PendingRequest::Unsubscribe { reply } => {
+                    let _ = reply.send(Ok(()));
+                }
+                PendingRequest::List { reply } => {
+                    let _ = reply.send(Err(BncError::DataTransmitError));
+                }
+                PendingRequest::Resync => {}
+            }
+        }
+
+        let mut added: Vec<String> = self
+            .queues
+            .keys()
+            .filter(|stream| !initial.contains(*stream))
+            .cloned()
+            .collect();
+        let mut removed: Vec<String> = initial
+            .iter()
+            .filter(|stream| !self.queues.contains_key(*stream))
+            .cloned()
+            .collect();
+        added.sort_unstable();
+        removed.sort_unstable();
+
+        let mut requests = vec![];
+        for (method, params) in [("SUBSCRIBE", added), ("UNSUBSCRIBE", removed)] {
+            if params.is_empty() {
+                continue;
+            }
+            let id = self.next_id;
+            self.next_id += 1;
+            self.pending.insert(id, PendingRequest::Resync);
+            requests.push(SubscriptionRequest { method, params, id });
+        }
+        requests
+    }
+
+    /// Pass the raw message to the queue of its stream, or complete the request it responds to.
+    fn dispatch(&mut self, message: &[u8]) -> BncResult<()> {
+        let container = match serde_json::from_slice(message)? {
+            CombinedMessage::Response(response) => {
+                self.respond(response);
+                return Ok(());
+            }
+            CombinedMessage::Data(container) => container,
+        };
+        match self.queues.get_mut(&container.stream) {
+            Some(queue) => queue.push(&container.stream, container.data),
+            None => debug!("Message of unknown stream {} is skipped.", container.stream),
+        }
+        Ok(())
+    }
+}
+
+/// Single connection carrying multiple streams at once.
+///
+/// Messages are demultiplexed by the name of their stream into its own bounded queue, see [`STREAM_QUEUE_CAPACITY`],
+/// and passed from it to the sender registered for the stream. Streams can be added and removed while the connection is alive, see [`CombinedStreamHandle`].
+///
+/// Dropped connection is restored according to the reconnect policy, the streams added and removed meanwhile
+/// are subscribed again.
+pub struct CombinedStreamConnection {
+    base_url: String,
+    routes: Routes,
+    reconnect: ReconnectPolicy,
+}
+
+impl CombinedStreamConnection {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            routes: HashMap::new(),
+            reconnect: Default::default(),
+        }
+    }
+
+    pub fn from_cfg(cfg: &WsCfg) -> Self {
+        Self::new(cfg.baseurl.primary()).with_reconnect(
+            ReconnectPolicy::new(cfg.reconnect.clone(), None)
+                .with_endpoints(EndpointPool::new(cfg.baseurl.all())),
+        )
+    }
+
+    /// Restore the dropped connection according to the given policy. Its token closes the connection once cancelled.
+    pub fn with_reconnect(mut self, reconnect: ReconnectPolicy) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Register sender of the given stream's messages, e.g. of `btcusdt@aggTrade`.
+    ///
+    /// Stream names are the ones produced by the worker modules, registering the same stream again replaces its sender.
+    pub fn subscribe<T, S>(&mut self, stream: &str, sender: S) -> &mut Self
+    where
+        T: DeserializeOwned + Send + Sync + 'static,
+        S: MessageSender<T> + Sync + 'static,
+    {
+        self.routes.insert(stream.to_string(), typed_route(sender));
+        self
+    }
+
+    /// Names of the subscribed streams, in order.
+    pub fn streams(&self) -> Vec<&str> {
+        let mut streams: Vec<&str> = self.routes.keys().map(String::as_str).collect();
+        streams.sort_unstable();
+        streams
+    }
+
+    fn endpoint(&self) -> String {
+        stream_endpoint(&self.base_url, &self.streams().join("/"))
+    }
+
+    /// Connect to all the subscribed streams and keep routing their messages. There must be at least one of them.
+    ///
+    /// Returns handle to manage subscriptions of the running connection.
+    pub fn spawn(self) -> BncResult<CombinedStreamHandle> {
+        if self.routes.is_empty() {
+            return Err(BncError::NoStreams);
+        }
+        let endpoint = self.endpoint();
+        let initial = self.routes.keys().cloned().collect();
+        let cancel = self.reconnect.cancel.clone();
+        let (commands, receiver) = mpsc::channel(16);
+        let task = tokio::task::spawn(run_connection(
+            endpoint,
+            self.reconnect,
+            initial,
+            Subscriptions::new(self.routes),
+            receiver,
+        ));
+        Ok(CombinedStreamHandle {
+            commands,
+            task,
+            cancel,
+        })
+    }
+}
+
+fn typed_route<T, S>(sender: S) -> Box<dyn StreamRoute>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+    S: MessageSender<T> + Sync + 'static,
+{
+    Box::new(TypedRoute {
+        sender,
+        message: PhantomData,
+    })
+}
+
+/// Send the request over the connection.
+fn send_request(
+    outgoing: &mpsc::UnboundedSender<Message>,
+    request: SubscriptionRequest,
+) -> BncResult<()> {
+    debug!("Sending {} request #{}.", request.method, request.id);
+    let request = Message::Text(serde_json::to_string(&request)?);
+    outgoing
+        .send(request)
+        .map_err(|_| BncError::DataTransmitError)
+}
+
+/// Whether the endpoint is played locally - synthetic and replayed ones take no requests.
+fn is_local(endpoint: &str) -> bool {
+    is_synthetic(endpoint) || is_replay(endpoint)
+}
+
+/// Messages of the endpoint, along with the sender of the requests to it.
+///
+/// Local endpoints take no requests, see [`is_local`].
+async fn connect_combined(
+    endpoint: &str,
+    reconnect: ReconnectPolicy,
+) -> BncResult<(BoxStream<'static, Message>, mpsc::UnboundedSender<Message>)> {
+    let (outgoing, receiver) = mpsc::unbounded_channel();
+    if is_local(endpoint) {
+        return Ok((bnc_stream_connect(endpoint, reconnect).await?, outgoing));
+    }
+    let (ws_stream, endpoint) = connect(endpoint, &reconnect.endpoints).await?;
+    let stream = reconnecting_duplex(&endpoint, ws_stream, reconnect, receiver);
+    Ok((stream.boxed(), outgoing))
+}
+
+/// Messages of the local endpoint carrying the current streams, the ones of the initial endpoint are replaced.
+async fn local_stream(
+    endpoint: &str,
+    subscriptions: &Subscriptions,
+    reconnect: ReconnectPolicy,
+) -> BncResult<BoxStream<'static, Message>> {
+    let streams = subscriptions.streams();
+    if streams.is_empty() {
+        return Ok(futures::stream::pending().boxed());
+    }
+    let base_url = endpoint
+        .split_once("/stream?")
+        .map_or(endpoint, |(base_url, _)| base_url);
+    bnc_stream_connect(&stream_endpoint(base_url, &streams.join("/")), reconnect).await
+}
+
+async fn run_connection(
+    endpoint: String,
+    mut reconnect: ReconnectPolicy,
+    initial: HashSet<String>,
+    mut subscriptions: Subscriptions,
+    mut commands: mpsc::Receiver<SubscriptionCommand>,
+) -> BncResult<()> {
+    // Events of the connection are watched to subscribe the streams again, the caller still receives them.
+    let (events, mut reconnects) = broadcast::channel(RECONNECT_EVENTS_CAPACITY);
+    let listener = reconnect.events.replace(events);
+    let local = is_local(&endpoint).then(|| reconnect.clone());
+    let (mut stream, outgoing) = connect_combined(&endpoint, reconnect).await?;
+    loop {
+        tokio::select! {
+            Some(command) = commands.recv() => {
+                let request = match subscriptions.request(command) {
+                    Some(request) => request,
+                    None => continue,
+                };
+                let id = request.id;
+                if let Some(reconnect) = local.as_ref() {
+                    // Local stream is started over with the current streams instead, so every request succeeds.
+                    let is_list = request.method == "LIST_SUBSCRIPTIONS";
+                    let result = is_list.then(|| subscriptions.streams());
+                    subscriptions.respond(SubscriptionResponse { id, result, error: None });
+                    if !is_list {
+                        stream = local_stream(&endpoint, &subscriptions, reconnect.clone()).await?;
+                    }
+                } else if let Err(err) = send_request(&outgoing, request) {
+                    subscriptions.fail(id, err);
+                }
+            }
+            event = reconnects.recv() => {
+                let fresh = match event {
+                    Ok(event) => {
+                        let fresh = matches!(
+                            event,
+                            ReconnectEvent::Reconnected { .. } | ReconnectEvent::Rotated { .. }
+                        );
+                        if let Some(listener) = listener.as_ref() {
+                            let _ = listener.send(event);
+                        }
+                        fresh
+                    }
+                    // Missed events could be the reconnects as well.
+                    Err(RecvError::Lagged(_)) => true,
+                    Err(RecvError::Closed) => false,
+                };
+                if fresh {
+                    info!("Subscribing streams of {} again.", endpoint);
+                    for request in subscriptions.resync(&initial) {
+                        if let Err(err) = send_request(&outgoing, request) {
+                            warn!("Streams of {} could not be subscribed again. Error: {}", endpoint, err);
+                        }
+                    }
+                }
+            }
+            message = stream.next() => {
+                let message = match message {
+                    Some(message) => message,
+                    None => break,
+                };
+                if let Err(err) = subscriptions.dispatch(&message.into_data()) {
+                    error!(
+                        "Combined stream message was not routed due to unexpected error. Error: {}",
+                        err
+                    );
+                }
+            }
+        }
+    }
+    BncResult::Ok(())
+}
+
+/// Handle of the running combined connection.
+///
+/// Streams are added and removed via binance's live subscription protocol, the socket is kept open.
+/// Local endpoints are started over with the changed streams instead, see [`is_local`].
+pub struct CombinedStreamHandle {
+    commands: mpsc::Sender<SubscriptionCommand>,
+    task: JoinHandle<BncResult<()>>,
+
+    /// Closes the connection, the one of the reconnect policy.
+    cancel: CancellationToken,
+}
+
+impl CombinedStreamHandle {
+    async fn execute<R>(
+        &self,
+        command: SubscriptionCommand,
+        reply: oneshot::Receiver<BncResult<R>>,
+    ) -> BncResult<R> {
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| BncError::DataTransmitError)?;
+        reply.await.map_err(|_| BncError::DataTransmitError)?
+    }
+
+    /// Start receiving messages of one more stream, see [`CombinedStreamConnection::subscribe`].
+    pub async fn subscribe<T, S>(&self, stream: &str, sender: S) -> BncResult<()>
+    where
+        T: DeserializeOwned + Send + Sync + 'static,
+        S: MessageSender<T> + Sync + 'static,
+    {
+        let (reply, receiver) = oneshot::channel();
+        let command = SubscriptionCommand::Subscribe {
+            stream: stream.to_string(),
+            route: typed_route(sender),
+            reply,
+        };
+        self.execute(command, receiver).await
+    }
+
+    /// Stop receiving messages of the stream.
+    pub async fn unsubscribe(&self, stream: &str) -> BncResult<()> {
+        let (reply, receiver) = oneshot::channel();
+        let command = SubscriptionCommand::Unsubscribe {
+            stream: stream.to_string(),
+            reply,
+        };
+        self.execute(command, receiver).await
+    }
+
+    /// Streams the connection is subscribed to, as reported by binance.
+    pub async fn list_subscriptions(&self) -> BncResult<Vec<String>> {
+        let (reply, receiver) = oneshot::channel();
+        self.execute(SubscriptionCommand::List { reply }, receiver)
+            .await
+    }
+
+    /// Depth of each stream's queue and the messages it dropped, e.g. to find the stream flooding the connection.
+    pub async fn queues(&self) -> BncResult<Vec<QueueStats>> {
+        let (reply, receiver) = oneshot::channel();
+        self.execute(SubscriptionCommand::Queues { reply }, receiver)
+            .await
+    }
+
+    /// Whether the connection is still running.
+    pub fn is_alive(&self) -> bool {
+        !self.task.is_finished()
+    }
+
+    /// Let the connection send the close frame and stop.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Wait for the stopped connection to finish.
+    pub async fn join(self) -> BncResult<()> {
+        self.task
+            .await
+            .unwrap_or_else(|err| Err(BncError::TaskFailed(err.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::ws::worker::ticker::{ticker_stream, SymbolTickerUpdate};
+    use crate::core::bnc::ws::worker::trade::{agg_trade_stream, SymbolTradeUpdate};
+
+    const TRADE: &str = r#"{"stream":"btcusdt@aggTrade","data":{"a":7,"p":"16500.10","q":"0.015",
+        "f":100,"l":105,"T":1672515782134,"m":true}}"#;
+
+    #[test]
+    fn it_builds_endpoint_of_all_streams() {
+        let (sender, _) = mpsc::channel::<SymbolTradeUpdate>(1);
+        let mut connection = CombinedStreamConnection::new("wss://host");
+        connection
+            .subscribe(&agg_trade_stream("BTCUSDT"), sender.clone())
+            .subscribe(&agg_trade_stream("ETHUSDT"), sender);
+
+        assert_eq!(
+            connection.endpoint(),
+            "wss://host/stream?streams=btcusdt@aggTrade/ethusdt@aggTrade"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_routes_messages_by_stream() {
+        let (trades, mut trade_receiver) = mpsc::channel::<SymbolTradeUpdate>(1);
+        let (tickers, mut ticker_receiver) = mpsc::channel::<SymbolTickerUpdate>(1);
+        let mut connection = CombinedStreamConnection::new("wss://host");
+        connection
+            .subscribe(&agg_trade_stream("BTCUSDT"), trades)
+            .subscribe(&ticker_stream("BTCUSDT"), tickers);
+        let mut subscriptions = Subscriptions::new(connection.routes);
+
+        subscriptions.dispatch(TRADE.as_bytes()).unwrap();
+        assert_eq!(trade_receiver.recv().await.unwrap().id, 7);
+        tokio::task::yield_now().await;
+        assert!(ticker_receiver.try_recv().is_err());
+
+        let unknown = r#"{"stream":"ethusdt@aggTrade","data":{}}"#;
+        subscriptions.dispatch(unknown.as_bytes()).unwrap();
+        tokio::task::yield_now().await;
+        assert!(trade_receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn it_subscribes_to_stream_at_runtime() {
+        let mut subscriptions = Subscriptions::new(HashMap::new());
+        let (trades, mut trade_receiver) = mpsc::channel::<SymbolTradeUpdate>(1);
+        let (reply, mut replied) = oneshot::channel();
+
+        let request = subscriptions
+            .request(SubscriptionCommand::Subscribe {
+                stream: agg_trade_stream("BTCUSDT"),
+                route: typed_route(trades),
+                reply,
+            })
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"method":"SUBSCRIBE","params":["btcusdt@aggTrade"],"id":1}"#
+        );
+
+        subscriptions
+            .dispatch(br#"{"result":null,"id":1}"#)
+            .unwrap();
+        assert!(replied.try_recv().unwrap().is_ok());
+
+        subscriptions.dispatch(TRADE.as_bytes()).unwrap();
+        assert_eq!(trade_receiver.recv().await.unwrap().id, 7);
+    }
+
+    #[tokio::test]
+    async fn it_drops_refused_subscription() {
+        let mut subscriptions = Subscriptions::new(HashMap::new());
+        let (trades, mut trade_receiver) = mpsc::channel::<SymbolTradeUpdate>(1);
+        let (reply, mut replied) = oneshot::channel();
+        subscriptions.request(SubscriptionCommand::Subscribe {
+            stream: agg_trade_stream("BTCUSDT"),
+            route: typed_route(trades),
+            reply,
+        });
+
+        let refusal = br#"{"error":{"code":2,"msg":"Invalid request"},"id":1}"#;
+        subscriptions.dispatch(refusal).unwrap();
+        assert!(replied.try_recv().unwrap().is_err());
+
+        subscriptions.dispatch(TRADE.as_bytes()).unwrap();
+        tokio::task::yield_now().await;
+        assert!(trade_receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn it_lists_subscriptions() {
+        let mut subscriptions = Subscriptions::new(HashMap::new());
+        let (reply, mut replied) = oneshot::channel();
+        let request = subscriptions
+            .request(SubscriptionCommand::List { reply })
+            .unwrap();
+        assert_eq!(request.method, "LIST_SUBSCRIPTIONS");
+
+        let response = br#"{"result":["btcusdt@aggTrade"],"id":1}"#;
+        subscriptions.dispatch(response).unwrap();
+        assert_eq!(
+            replied.try_recv().unwrap().unwrap(),
+            vec!["btcusdt@aggTrade".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_keeps_streams_apart_when_one_floods() {
+        // Trades are never taken out of their channel, so their queue is stuck once it fills.
+        let (trades, _trade_receiver) = mpsc::channel::<SymbolTradeUpdate>(1);
+        let (tickers, mut ticker_receiver) = mpsc::channel::<SymbolTickerUpdate>(1);
+        let mut connection = CombinedStreamConnection::new("wss://host");
+        connection
+            .subscribe(&agg_trade_stream("BTCUSDT"), trades)
+            .subscribe(&ticker_stream("ETHUSDT"), tickers);
+        let mut subscriptions = Subscriptions::new(connection.routes);
+
+        for _ in 0..STREAM_QUEUE_CAPACITY + 10 {
+            subscriptions.dispatch(TRADE.as_bytes()).unwrap();
+        }
+        let ticker = r#"{"stream":"ethusdt@ticker","data":{"E":1672515782136,"s":"ETHUSDT",
+            "p":"-12.50","P":"-1.031","w":"1205.12","x":"1212.00","c":"1199.50","Q":"0.01",
+            "b":"1199.40","B":"1.2","a":"1199.50","A":"0.8","o":"1212.00","h":"1220.00","l":"1190.00",
+            "v":"12000.5","q":"14461000.1","O":1672429382136,"C":1672515782136,"F":100,"L":200,"n":101}}"#;
+        subscriptions.dispatch(ticker.as_bytes()).unwrap();
+        assert_eq!(ticker_receiver.recv().await.unwrap().time, 1672515782136);
+
+        let (reply, mut replied) = oneshot::channel();
+        assert!(subscriptions
+            .request(SubscriptionCommand::Queues { reply })
+            .is_none());
+        let stats = replied.try_recv().unwrap().unwrap();
+        assert_eq!(stats[0].stream, "btcusdt@aggTrade");
+        // A couple of them are taken by the route already, it waits for the channel.
+        assert!(stats[0].depth >= STREAM_QUEUE_CAPACITY - 2);
+        assert!(stats[0].dropped > 0);
+        assert_eq!(stats[1].stream, "ethusdt@ticker");
+        assert_eq!(stats[1].depth, 0);
+        assert_eq!(stats[1].dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn it_switches_streams_of_local_endpoint() {
+        let (btc, mut btc_receiver) = mpsc::channel::<SymbolTickerUpdate>(1);
+        let (eth, mut eth_receiver) = mpsc::channel::<SymbolTickerUpdate>(1);
+        let mut connection = CombinedStreamConnection::new("synthetic://7");
+        connection.subscribe(&ticker_stream("BTCUSDT"), btc);
+        let handle = connection.spawn().unwrap();
+        assert!(btc_receiver.recv().await.is_some());
+
+        handle.unsubscribe(&ticker_stream("BTCUSDT")).await.unwrap();
+        handle
+            .subscribe(&ticker_stream("ETHUSDT"), eth)
+            .await
+            .unwrap();
+        assert!(eth_receiver.recv().await.is_some());
+        assert_eq!(
+            handle.list_subscriptions().await.unwrap(),
+            vec![ticker_stream("ETHUSDT")]
+        );
+        // Route of the unsubscribed stream is dropped along with its sender.
+        assert!(btc_receiver.recv().await.is_none());
+
+        handle.stop();
+        handle.join().await.unwrap();
+    }
+
+    #[test]
+    fn it_refuses_connection_without_streams() {
+        let connection = CombinedStreamConnection::new("wss://host");
+        assert!(matches!(connection.spawn(), Err(BncError::NoStreams)));
+    }
+
+    #[tokio::test]
+    async fn it_subscribes_streams_again_after_reconnect() {
+        use crate::core::bnc::ws::config::ReconnectCfg;
+        use futures_util::SinkExt;
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::accept_async;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("ws://{}", listener.local_addr().unwrap());
+        let (requests, mut received) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            // First connection is dropped right after the stream is subscribed.
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(socket).await.unwrap();
+            let request = socket.next().await.unwrap().unwrap();
+            requests.send(request).unwrap();
+            let response = r#"{"result":null,"id":1}"#;
+            socket.send(Message::Text(response.into())).await.unwrap();
+            drop(socket);
+
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(socket).await.unwrap();
+            while let Some(Ok(message)) = socket.next().await {
+                requests.send(message).unwrap();
+            }
+        });
+
+        let (trades, _trade_receiver) = mpsc::channel::<SymbolTradeUpdate>(1);
+        let cfg = ReconnectCfg {
+            backoff: 1,
+            max_backoff: 1,
+            rotate_after: None,
+            ..Default::default()
+        };
+        let mut connection = CombinedStreamConnection::new(&base_url)
+            .with_reconnect(ReconnectPolicy::new(cfg, None));
+        connection.subscribe(&agg_trade_stream("BTCUSDT"), trades.clone());
+        let handle = connection.spawn().unwrap();
+
+        handle
+            .subscribe(&agg_trade_stream("ETHUSDT"), trades)
+            .await
+            .unwrap();
+        let request = received.recv().await.unwrap().into_text().unwrap();
+        assert!(request.contains(r#""params":["ethusdt@aggTrade"]"#));
+
+        let request = received.recv().await.unwrap().into_text().unwrap();
+        assert_eq!(
+            request,
+            r#"{"method":"SUBSCRIBE","params":["ethusdt@aggTrade"],"id":2}"#
+        );
+
+        handle.stop();
+        assert!(received.recv().await.unwrap().is_close());
+        handle.join().await.unwrap();
+    }
+}
@@ -1,18 +1,233 @@
+use crate::core::bnc::state::balancer::BalancerCfg;
+use crate::core::bnc::state::scaling::ScalingCfg;
+#[cfg(feature = "impairment")]
+use crate::core::bnc::ws::impairment::ImpairmentCfg;
 use derive_getters::Getters;
 use serde::Deserialize;
 
+/// Interval the diff depth stream pushes its updates with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum UpdateSpeed {
+    #[default]
+    #[serde(rename = "1000ms")]
+    Normal,
+
+    #[serde(rename = "100ms")]
+    Fast,
+}
+
+impl UpdateSpeed {
+    /// Suffix of the stream name selecting the speed. Normal one is the default of the stream.
+    pub fn stream_suffix(&self) -> &'static str {
+        match self {
+            Self::Normal => "",
+            Self::Fast => "@100ms",
+        }
+    }
+}
+
+/// Base urls of the websocket streams - either a single one or a list to fail over through, e.g.
+/// `["wss://stream.binance.com:9443", "wss://stream.binance.com:443", "wss://data-stream.binance.vision"]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "BaseUrlsRepr")]
+pub struct BaseUrls(Vec<String>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BaseUrlsRepr {
+    Single(String),
+    List(Vec<String>),
+}
+
+impl TryFrom<BaseUrlsRepr> for BaseUrls {
+    type Error = &'static str;
+
+    fn try_from(repr: BaseUrlsRepr) -> Result<Self, Self::Error> {
+        match repr {
+            BaseUrlsRepr::Single(base_url) => Ok(Self(vec![base_url])),
+            BaseUrlsRepr::List(base_urls) if base_urls.is_empty() => {
+                Err("at least one base url is required")
+            }
+            BaseUrlsRepr::List(base_urls) => Ok(Self(base_urls)),
+        }
+    }
+}
+
+impl BaseUrls {
+    /// Base url that is tried first.
+    pub fn primary(&self) -> &str {
+        &self.0[0]
+    }
+
+    pub fn all(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl From<&str> for BaseUrls {
+    fn from(base_url: &str) -> Self {
+        Self(vec![base_url.to_string()])
+    }
+}
+
+/// Restoring of the dropped websocket connections.
+#[derive(Debug, Clone, Deserialize, Getters)]
+#[serde(default)]
+pub struct ReconnectCfg {
+    /// Attempts to restore a single dropped connection, zero for no limit.
+    pub max_retries: u32,
+
+    /// Milliseconds to wait before the first attempt. Doubled for each next one, randomised by a jitter.
+    pub backoff: u64,
+
+    /// Upper bound of milliseconds between attempts.
+    pub max_backoff: u64,
+
+    /// Milliseconds between client pings, if any. Connection that doesn't answer until the next ping is restored.
+    ///
+    /// Server pings are answered regardless of it.
+    pub ping_interval: Option<u64>,
+
+    /// Milliseconds after which the connection is replaced with a fresh one, seamlessly for the consumers.
+    ///
+    /// Binance closes connections after 24 hours, so it is a bit less by default.
+    pub rotate_after: Option<u64>,
+
+    /// Artificial latency, drops and disconnects of the connections, for testing only.
+    #[cfg(feature = "impairment")]
+    pub impairment: ImpairmentCfg,
+}
+
+impl Default for ReconnectCfg {
+    fn default() -> Self {
+        Self {
+            max_retries: 10,
+            backoff: 500,
+            max_backoff: 30000,
+            ping_interval: None,
+            rotate_after: Some(23 * 60 * 60 * 1000),
+            #[cfg(feature = "impairment")]
+            impairment: Default::default(),
+        }
+    }
+}
+
 /// Configuration of websocket BNC part.
 #[derive(Debug, Clone, Deserialize, Getters)]
 pub struct WsCfg {
-    pub baseurl: String,
+    /// Base url of the streams, or a list of them to fail over through.
+    pub baseurl: BaseUrls,
+
+    /// Default amount of redundant workers per feed. Used when feed-specific value is not set.
     pub workers: u64,
+
+    /// Amount of workers listening for best price(bookTicker) updates.
+    #[serde(default)]
+    pub price_workers: Option<u64>,
+
+    /// Amount of workers listening for depth updates.
+    #[serde(default)]
+    pub depth_workers: Option<u64>,
+
+    /// Update speed(`1000ms` or `100ms`) of the depth stream.
+    #[serde(default)]
+    pub update_speed: UpdateSpeed,
+
+    /// Amount of workers listening for rolling 24hr statistics updates.
+    #[serde(default)]
+    pub ticker_workers: Option<u64>,
+
+    /// Amount of workers listening for aggregated trades.
+    #[serde(default)]
+    pub trade_workers: Option<u64>,
+
+    /// Levels(5, 10 or 20) of the partial book stream to display instead of the full order book.
+    ///
+    /// Lighter-weight mode - no REST snapshot is needed, but only the top of the book is known.
+    #[serde(default)]
+    pub partial_depth: Option<u8>,
+
+    /// Restoring of the dropped stream connections.
+    #[serde(default)]
+    pub reconnect: ReconnectCfg,
+
+    /// Adaptive amount of redundant workers, replacing the static counts when enabled.
+    #[serde(default)]
+    pub scaling: ScalingCfg,
+
+    /// Handling of the update ids that start over, e.g. after a reconnect.
+    #[serde(default)]
+    pub balancer: BalancerCfg,
+}
+
+impl WsCfg {
+    /// Workers count of the price feed, falling back to the general `workers` value.
+    pub fn price_workers_count(&self) -> u64 {
+        self.price_workers.unwrap_or(self.workers)
+    }
+
+    /// Workers count of the depth feed, falling back to the general `workers` value.
+    pub fn depth_workers_count(&self) -> u64 {
+        self.depth_workers.unwrap_or(self.workers)
+    }
+
+    /// Workers count of the rolling ticker feed, falling back to the general `workers` value.
+    pub fn ticker_workers_count(&self) -> u64 {
+        self.ticker_workers.unwrap_or(self.workers)
+    }
+
+    /// Workers count of the trades feed, falling back to the general `workers` value.
+    pub fn trade_workers_count(&self) -> u64 {
+        self.trade_workers.unwrap_or(self.workers)
+    }
 }
 
 impl Default for WsCfg {
     fn default() -> Self {
         Self {
-            baseurl: String::from("wss://stream.binance.com:9443"),
+            baseurl: "wss://stream.binance.com:9443".into(),
             workers: 5,
+            price_workers: None,
+            depth_workers: None,
+            update_speed: UpdateSpeed::Normal,
+            ticker_workers: None,
+            trade_workers: None,
+            partial_depth: None,
+            reconnect: Default::default(),
+            scaling: Default::default(),
+            balancer: Default::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_falls_back_to_general_workers_count() {
+        let cfg = WsCfg {
+            workers: 3,
+            depth_workers: Some(7),
+            trade_workers: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(cfg.price_workers_count(), 3);
+        assert_eq!(cfg.depth_workers_count(), 7);
+        assert_eq!(cfg.ticker_workers_count(), 3);
+        assert_eq!(cfg.trade_workers_count(), 1);
+    }
+
+    #[test]
+    fn it_parses_single_and_multiple_base_urls() {
+        let single: BaseUrls = serde_json::from_str(r#""wss://a""#).unwrap();
+        assert_eq!(single.all(), ["wss://a"]);
+
+        let list: BaseUrls = serde_json::from_str(r#"["wss://a", "wss://b"]"#).unwrap();
+        assert_eq!(list.primary(), "wss://a");
+        assert_eq!(list.all(), ["wss://a", "wss://b"]);
+
+        assert!(serde_json::from_str::<BaseUrls>("[]").is_err());
+    }
+}
@@ -3,5 +3,9 @@ use serde::Deserialize;
 /// Data container simply holds some serde value.
 #[derive(Debug, Deserialize, Clone)]
 pub struct WsDataContainer<T> {
+    /// Name of the stream the data belongs to, e.g. `btcusdt@bookTicker`.
+    #[serde(default)]
+    pub stream: String,
+
     pub data: T,
 }
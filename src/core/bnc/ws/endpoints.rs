@@ -0,0 +1,138 @@
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+/// Failures of a single base url since it was connected to last time.
+#[derive(Debug)]
+struct EndpointHealth {
+    base_url: String,
+    failures: u32,
+}
+
+/// Base urls serving the same streams, shared by the workers to agree on the healthy ones.
+///
+/// Consistently failing base urls are tried last, the configured order is kept between the equally healthy ones.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointPool {
+    endpoints: Arc<Mutex<Vec<EndpointHealth>>>,
+}
+
+impl EndpointPool {
+    pub fn new(base_urls: &[String]) -> Self {
+        let endpoints = base_urls
+            .iter()
+            .map(|base_url| EndpointHealth {
+                base_url: base_url.clone(),
+                failures: 0,
+            })
+            .collect();
+        Self {
+            endpoints: Arc::new(Mutex::new(endpoints)),
+        }
+    }
+
+    /// Base url of the pool the endpoint is built upon, along with the rest of the endpoint.
+    fn split<'e>(endpoints: &[EndpointHealth], endpoint: &'e str) -> Option<(usize, &'e str)> {
+        endpoints
+            .iter()
+            .enumerate()
+            .filter_map(|(i, health)| {
+                let path = endpoint.strip_prefix(health.base_url.as_str())?;
+                (path.is_empty() || path.starts_with('/')).then_some((i, path))
+            })
+            .max_by_key(|(i, _)| endpoints[*i].base_url.len())
+    }
+
+    /// Same endpoint on all the base urls, the healthiest first.
+    ///
+    /// Endpoint that is not built upon any of the base urls is the only candidate of itself.
+    pub fn candidates(&self, endpoint: &str) -> Vec<String> {
+        let endpoints = self.endpoints.lock().expect("Endpoint pool is poisoned.");
+        let path = match Self::split(&endpoints, endpoint) {
+            Some((_, path)) => path,
+            None => return vec![endpoint.to_string()],
+        };
+
+        let mut healths: Vec<&EndpointHealth> = endpoints.iter().collect();
+        healths.sort_by_key(|health| health.failures);
+        healths
+            .into_iter()
+            .map(|health| format!("{}{}", health.base_url, path))
+            .collect()
+    }
+
+    /// Account the result of connecting to the endpoint.
+    pub fn report(&self, endpoint: &str, connected: bool) {
+        let mut endpoints = self.endpoints.lock().expect("Endpoint pool is poisoned.");
+        let i = match Self::split(&endpoints, endpoint) {
+            Some((i, _)) => i,
+            None => return,
+        };
+
+        let health = &mut endpoints[i];
+        if connected {
+            health.failures = 0;
+        } else {
+            health.failures = health.failures.saturating_add(1);
+            warn!(
+                "Base url {} failed {} time(s) in a row.",
+                health.base_url, health.failures
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> EndpointPool {
+        EndpointPool::new(&[
+            "wss://stream.binance.com:9443".into(),
+            "wss://stream.binance.com:443".into(),
+            "wss://data-stream.binance.vision".into(),
+        ])
+    }
+
+    #[test]
+    fn it_keeps_configured_order_of_healthy_endpoints() {
+        assert_eq!(
+            pool().candidates("wss://stream.binance.com:443/stream?streams=btcusdt@bookTicker"),
+            vec![
+                "wss://stream.binance.com:9443/stream?streams=btcusdt@bookTicker",
+                "wss://stream.binance.com:443/stream?streams=btcusdt@bookTicker",
+                "wss://data-stream.binance.vision/stream?streams=btcusdt@bookTicker",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_deprioritizes_failing_endpoint() {
+        let pool = pool();
+        pool.report("wss://stream.binance.com:9443/stream?streams=a", false);
+        pool.report("wss://stream.binance.com:9443/stream?streams=a", false);
+        pool.report("wss://stream.binance.com:443/stream?streams=a", false);
+
+        assert_eq!(
+            pool.candidates("wss://stream.binance.com:9443/stream?streams=b"),
+            vec![
+                "wss://data-stream.binance.vision/stream?streams=b",
+                "wss://stream.binance.com:443/stream?streams=b",
+                "wss://stream.binance.com:9443/stream?streams=b",
+            ]
+        );
+
+        pool.report("wss://stream.binance.com:9443/stream?streams=a", true);
+        assert_eq!(
+            pool.candidates("wss://stream.binance.com:9443/stream?streams=b")[0],
+            "wss://stream.binance.com:9443/stream?streams=b"
+        );
+    }
+
+    #[test]
+    fn it_keeps_unknown_endpoint_as_is() {
+        assert_eq!(
+            pool().candidates("ws://127.0.0.1:8080/stream?streams=a"),
+            vec!["ws://127.0.0.1:8080/stream?streams=a"]
+        );
+    }
+}
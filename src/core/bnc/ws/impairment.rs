@@ -0,0 +1,131 @@
+use serde::Deserialize;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Artificial network issues applied to every websocket connection, to exercise reconnects, resyncs and
+/// staleness handling locally.
+///
+/// Development only - it is compiled in with the `impairment` feature.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ImpairmentCfg {
+    /// Milliseconds every message is held back for.
+    ///
+    /// Messages are held back one after another, so the stream slows down as over a congested link.
+    pub latency: u64,
+
+    /// Upper bound of random milliseconds added to the latency of each message.
+    pub jitter: u64,
+
+    /// Share of the messages dropped, from 0 to 1.
+    pub drop_rate: f64,
+
+    /// Milliseconds after which each connection is dropped, if any.
+    pub disconnect_after: Option<u64>,
+}
+
+impl ImpairmentCfg {
+    pub fn is_active(&self) -> bool {
+        self.latency > 0
+            || self.jitter > 0
+            || self.drop_rate > 0.0
+            || self.disconnect_after.is_some()
+    }
+}
+
+/// Random number in `[0, 1)`, good enough to simulate the network.
+fn random() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random % 10000) as f64 / 10000.0
+}
+
+/// Impairment of a single stream, its disconnect deadline starts over with every connection.
+#[derive(Debug, Clone)]
+pub struct Impairment {
+    cfg: ImpairmentCfg,
+    disconnect_at: Option<Instant>,
+}
+
+impl Impairment {
+    pub fn new(cfg: ImpairmentCfg) -> Self {
+        let mut impairment = Self {
+            cfg,
+            disconnect_at: None,
+        };
+        impairment.reset();
+        impairment
+    }
+
+    /// To be called once the stream is connected again.
+    pub fn reset(&mut self) {
+        self.disconnect_at = self
+            .cfg
+            .disconnect_after
+            .map(|after| Instant::now() + Duration::from_millis(after));
+    }
+
+    /// When the current connection is to be dropped.
+    pub fn disconnect_at(&self) -> Option<Instant> {
+        self.disconnect_at
+    }
+
+    fn delay(&self, random: f64) -> Duration {
+        Duration::from_millis(self.cfg.latency + (self.cfg.jitter as f64 * random) as u64)
+    }
+
+    /// Hold the received message back, returns whether it is delivered or dropped.
+    pub async fn pass(&self) -> bool {
+        let delay = self.delay(random());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        random() >= self.cfg.drop_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_adds_jitter_to_latency() {
+        let impairment = Impairment::new(ImpairmentCfg {
+            latency: 100,
+            jitter: 50,
+            ..Default::default()
+        });
+
+        assert_eq!(impairment.delay(0.0), Duration::from_millis(100));
+        assert_eq!(impairment.delay(0.5), Duration::from_millis(125));
+        assert!(impairment.disconnect_at().is_none());
+    }
+
+    #[tokio::test]
+    async fn it_drops_messages_by_rate() {
+        let lossless = Impairment::new(Default::default());
+        let lossy = Impairment::new(ImpairmentCfg {
+            drop_rate: 1.0,
+            ..Default::default()
+        });
+
+        for _ in 0..100 {
+            assert!(lossless.pass().await);
+            assert!(!lossy.pass().await);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_restarts_disconnect_deadline() {
+        let mut impairment = Impairment::new(ImpairmentCfg {
+            disconnect_after: Some(1000),
+            ..Default::default()
+        });
+        let first = impairment.disconnect_at().unwrap();
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        impairment.reset();
+        assert!(impairment.disconnect_at().unwrap() - first >= Duration::from_millis(10));
+    }
+}
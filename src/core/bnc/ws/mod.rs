@@ -1,4 +1,9 @@
+pub mod combined;
 pub mod config;
 pub mod data;
+pub mod endpoints;
+#[cfg(feature = "impairment")]
+pub mod impairment;
+pub mod reconnect;
 // pub mod master;
 pub mod worker;
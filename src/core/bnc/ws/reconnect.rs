@@ -0,0 +1,700 @@
+use crate::core::bnc::ws::config::ReconnectCfg;
+use crate::core::bnc::ws::endpoints::EndpointPool;
+#[cfg(feature = "impairment")]
+use crate::core::bnc::ws::impairment::Impairment;
+use futures::Stream;
+use futures_util::{SinkExt, StreamExt};
+use log::{info, warn};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+
+pub type ReconnectSender = broadcast::Sender<ReconnectEvent>;
+pub type ReconnectReceiver = broadcast::Receiver<ReconnectEvent>;
+
+/// Capacity of the reconnect events' channel. Slow receivers lose the oldest events.
+pub const RECONNECT_EVENTS_CAPACITY: usize = 32;
+
+/// Lifecycle events of the reconnecting stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconnectEvent {
+    /// Connection is lost, reconnection is scheduled.
+    Disconnected { endpoint: String, reason: String },
+
+    /// Connection is restored after the given amount of attempts.
+    Reconnected { endpoint: String, attempts: u32 },
+
+    /// All the attempts failed, the stream is over.
+    GaveUp { endpoint: String, attempts: u32 },
+
+    /// Connection is replaced with a fresh one ahead of the server's connection lifetime limit.
+    Rotated { endpoint: String },
+}
+
+/// How the dropped streams are restored and whom it is reported to.
+#[derive(Debug, Clone, Default)]
+pub struct ReconnectPolicy {
+    pub cfg: ReconnectCfg,
+    pub events: Option<ReconnectSender>,
+
+    /// Base urls the connections fail over to.
+    pub endpoints: EndpointPool,
+
+    /// Closes the connection and ends the stream once cancelled.
+    pub cancel: CancellationToken,
+}
+
+impl ReconnectPolicy {
+    pub fn new(cfg: ReconnectCfg, events: Option<ReconnectSender>) -> Self {
+        Self {
+            cfg,
+            events,
+            endpoints: Default::default(),
+            cancel: Default::default(),
+        }
+    }
+
+    /// Fail over to the other base urls of the pool whenever the endpoint is unavailable.
+    pub fn with_endpoints(mut self, endpoints: EndpointPool) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Close the streams gracefully once the token is cancelled.
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    fn emit(&self, event: ReconnectEvent) {
+        if let Some(events) = self.events.as_ref() {
+            // Nobody listens - nothing to report.
+            let _ = events.send(event);
+        }
+    }
+}
+
+/// Random factor in `[0.5, 1.0)` spreading the reconnects of the redundant workers.
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    0.5 + (random % 1000) as f64 / 2000.0
+}
+
+/// Delay before the given(starting from 1) attempt - exponential backoff with jitter.
+pub fn reconnect_delay(cfg: &ReconnectCfg, attempt: u32, jitter: f64) -> Duration {
+    let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+    let delay = cfg.backoff.saturating_mul(factor).min(cfg.max_backoff);
+    Duration::from_millis((delay as f64 * jitter) as u64)
+}
+
+type WsConnection = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Connect to the endpoint on the healthiest base url of the pool, failing over to the rest.
+///
+/// Returns the connection along with the endpoint it is established to.
+pub(crate) async fn connect(
+    endpoint: &str,
+    endpoints: &EndpointPool,
+) -> Result<(WsConnection, String), WsError> {
+    let mut last_err = None;
+    for candidate in endpoints.candidates(endpoint) {
+        match connect_async(&candidate).await {
+            Ok((stream, _)) => {
+                endpoints.report(&candidate, true);
+                return Ok((stream, candidate));
+            }
+            Err(err) => {
+                warn!("Could not connect to {}. Error: {}", candidate, err);
+                endpoints.report(&candidate, false);
+                last_err = Some(err);
+            }
+        }
+    }
+    Err(last_err.expect("Endpoint is always a candidate of itself."))
+}
+
+/// What interrupted waiting for the next message.
+enum StreamEvent {
+    Message(Message),
+    Ping,
+    Lost(String),
+
+    /// Connection is old enough to be replaced.
+    RotationDue,
+
+    /// Frame of the replacement connection.
+    Replacement(Option<Result<Message, WsError>>),
+
+    /// Message to be sent over the connection, none once nobody sends them.
+    Outgoing(Option<Message>),
+
+    /// Stream is not needed anymore.
+    Cancelled,
+}
+
+struct ReconnectingState {
+    endpoint: String,
+    policy: ReconnectPolicy,
+    stream: Option<WsConnection>,
+
+    /// Client pings, if enabled. Connection is considered lost if a ping isn't answered until the next one.
+    keepalive: Option<Interval>,
+    awaiting_pong: bool,
+
+    /// When the connection is to be replaced, if rotation is enabled.
+    rotate_at: Option<Instant>,
+
+    /// Fresh connection that takes over as soon as it delivers its first message, along with its endpoint.
+    replacement: Option<(WsConnection, String)>,
+
+    /// Messages sent over the current connection, e.g. subscription requests. Kept while disconnected.
+    outgoing: Option<mpsc::UnboundedReceiver<Message>>,
+
+    #[cfg(feature = "impairment")]
+    impairment: Impairment,
+}
+
+/// Wait for the next tick of the keepalive, never completes if keepalive is disabled.
+async fn keepalive_tick(keepalive: &mut Option<Interval>) {
+    match keepalive.as_mut() {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => futures::future::pending().await,
+    }
+}
+
+/// Wait for the instant, never completes without it.
+async fn sleep_until(at: Option<Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Next frame of the connection, never completes without it.
+async fn next_frame(
+    connection: &mut Option<(WsConnection, String)>,
+) -> Option<Result<Message, WsError>> {
+    match connection.as_mut() {
+        Some((connection, _)) => connection.next().await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Next message to be sent, never completes without them.
+async fn next_outgoing(outgoing: &mut Option<mpsc::UnboundedReceiver<Message>>) -> Option<Message> {
+    match outgoing.as_mut() {
+        Some(outgoing) => outgoing.recv().await,
+        None => futures::future::pending().await,
+    }
+}
+
+fn rotation_deadline(cfg: &ReconnectCfg) -> Option<Instant> {
+    Some(Instant::now() + Duration::from_millis(cfg.rotate_after?))
+}
+
+fn keepalive(cfg: &ReconnectCfg) -> Option<Interval> {
+    let period = Duration::from_millis(cfg.ping_interval?);
+    let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    Some(interval)
+}
+
+impl ReconnectingState {
+    fn new(
+        endpoint: &str,
+        stream: WsConnection,
+        policy: ReconnectPolicy,
+        outgoing: Option<mpsc::UnboundedReceiver<Message>>,
+    ) -> Self {
+        #[cfg(feature = "impairment")]
+        if policy.cfg.impairment.is_active() {
+            warn!(
+                "Stream {} is impaired for testing: {:?}",
+                endpoint, policy.cfg.impairment
+            );
+        }
+        Self {
+            #[cfg(feature = "impairment")]
+            impairment: Impairment::new(policy.cfg.impairment.clone()),
+            endpoint: endpoint.to_string(),
+            keepalive: keepalive(&policy.cfg),
+            rotate_at: rotation_deadline(&policy.cfg),
+            policy,
+            stream: Some(stream),
+            awaiting_pong: false,
+            replacement: None,
+            outgoing,
+        }
+    }
+
+    async fn next_event(&mut self) -> Option<StreamEvent> {
+        let forced_disconnect_at = self.forced_disconnect_at();
+        let stream = self.stream.as_mut()?;
+        let event = tokio::select! {
+            message = stream.next() => match message {
+                Some(Ok(message)) => {
+                    // Any frame proves the connection is alive.
+                    self.awaiting_pong = false;
+                    match message {
+                        // Pong to it is queued by tungstenite, it is flushed right away below.
+                        Message::Ping(_) => StreamEvent::Ping,
+                        message => StreamEvent::Message(message),
+                    }
+                }
+                Some(Err(err)) => StreamEvent::Lost(err.to_string()),
+                None => StreamEvent::Lost("connection closed".to_string()),
+            },
+            _ = keepalive_tick(&mut self.keepalive) => {
+                if self.awaiting_pong {
+                    StreamEvent::Lost("ping is not answered".to_string())
+                } else {
+                    self.awaiting_pong = true;
+                    match stream.send(Message::Ping(vec![])).await {
+                        Ok(_) => return Some(StreamEvent::Ping),
+                        Err(err) => StreamEvent::Lost(err.to_string()),
+                    }
+                }
+            }
+            _ = sleep_until(self.rotate_at), if self.replacement.is_none() => StreamEvent::RotationDue,
+            frame = next_frame(&mut self.replacement) => StreamEvent::Replacement(frame),
+            message = next_outgoing(&mut self.outgoing) => StreamEvent::Outgoing(message),
+            _ = sleep_until(forced_disconnect_at) => StreamEvent::Lost("disconnect is forced by the impairment".to_string()),
+            _ = self.policy.cancel.cancelled() => StreamEvent::Cancelled,
+        };
+        Some(event)
+    }
+
+    /// Next text message, reconnecting whenever the connection is lost. None once all the attempts failed.
+    async fn next(&mut self) -> Option<Message> {
+        loop {
+            let reason = match self.next_event().await? {
+                StreamEvent::Message(message) if message.is_text() => {
+                    #[cfg(feature = "impairment")]
+                    if !self.impairment.pass().await {
+                        continue;
+                    }
+                    return Some(message);
+                }
+                StreamEvent::Message(_) => continue,
+                StreamEvent::Ping => match self.stream.as_mut()?.flush().await {
+                    Ok(_) => continue,
+                    Err(err) => err.to_string(),
+                },
+                StreamEvent::Lost(reason) => reason,
+                StreamEvent::RotationDue => {
+                    self.start_rotation().await;
+                    continue;
+                }
+                StreamEvent::Replacement(Some(Ok(message))) if message.is_text() => {
+                    self.switch_over().await;
+                    return Some(message);
+                }
+                StreamEvent::Replacement(Some(Ok(_))) => continue,
+                StreamEvent::Replacement(frame) => {
+                    let reason = match frame {
+                        Some(Err(err)) => err.to_string(),
+                        _ => "connection closed".to_string(),
+                    };
+                    warn!(
+                        "Replacement connection of {} is lost, keeping the current one. Reason: {}",
+                        self.endpoint, reason
+                    );
+                    self.postpone_rotation();
+                    continue;
+                }
+                StreamEvent::Outgoing(Some(message)) => {
+                    match self.stream.as_mut()?.send(message).await {
+                        Ok(_) => continue,
+                        Err(err) => err.to_string(),
+                    }
+                }
+                StreamEvent::Outgoing(None) => {
+                    self.outgoing = None;
+                    continue;
+                }
+                StreamEvent::Cancelled => {
+                    self.close().await;
+                    return None;
+                }
+            };
+            warn!(
+                "Stream {} is disconnected, reconnecting. Reason: {}",
+                self.endpoint, reason
+            );
+            self.policy.emit(ReconnectEvent::Disconnected {
+                endpoint: self.endpoint.clone(),
+                reason,
+            });
+            self.replacement = None;
+            self.stream = self.reconnect().await;
+            self.reset_connection_timers();
+        }
+    }
+
+    fn reset_connection_timers(&mut self) {
+        self.keepalive = keepalive(&self.policy.cfg);
+        self.awaiting_pong = false;
+        self.rotate_at = rotation_deadline(&self.policy.cfg);
+        #[cfg(feature = "impairment")]
+        self.impairment.reset();
+    }
+
+    #[cfg(feature = "impairment")]
+    fn forced_disconnect_at(&self) -> Option<Instant> {
+        self.impairment.disconnect_at()
+    }
+
+    /// Connections are never dropped on purpose without the impairment.
+    #[cfg(not(feature = "impairment"))]
+    fn forced_disconnect_at(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Send close frames over the connections, the stream is over after that.
+    async fn close(&mut self) {
+        if let Some((mut replacement, _)) = self.replacement.take() {
+            let _ = replacement.close(None).await;
+        }
+        if let Some(mut stream) = self.stream.take() {
+            // It is dropped anyway, nothing to do if the close frame can't be sent.
+            let _ = stream.close(None).await;
+        }
+        info!("Stream {} is closed.", self.endpoint);
+    }
+
+    /// Try the rotation again later, the current connection is kept meanwhile.
+    fn postpone_rotation(&mut self) {
+        self.replacement = None;
+        self.rotate_at = Some(Instant::now() + Duration::from_millis(self.policy.cfg.max_backoff));
+    }
+
+    /// Open the replacement connection. Current one keeps delivering messages until the replacement does.
+    async fn start_rotation(&mut self) {
+        match connect(&self.endpoint, &self.policy.endpoints).await {
+            Ok((replacement, endpoint)) => {
+                info!("Opened replacement connection of stream {}.", endpoint);
+                self.replacement = Some((replacement, endpoint));
+            }
+            Err(err) => {
+                warn!(
+                    "Could not open replacement connection of {}, keeping the current one. Error: {}",
+                    self.endpoint, err
+                );
+                self.postpone_rotation();
+            }
+        }
+    }
+
+    /// Make the replacement the current connection, close the old one.
+    ///
+    /// Messages received by both of them during the switch are delivered twice, balancers drop the duplicates.
+    async fn switch_over(&mut self) {
+        let (replacement, endpoint) = match self.replacement.take() {
+            Some(replacement) => replacement,
+            None => return,
+        };
+        self.endpoint = endpoint;
+        if let Some(mut previous) = self.stream.replace(replacement) {
+            // It is dropped anyway, nothing to do if the close frame can't be sent.
+            let _ = previous.close(None).await;
+        }
+        self.reset_connection_timers();
+        info!(
+            "Stream {} is switched to the fresh connection.",
+            self.endpoint
+        );
+        self.policy.emit(ReconnectEvent::Rotated {
+            endpoint: self.endpoint.clone(),
+        });
+    }
+
+    async fn reconnect(&mut self) -> Option<WsConnection> {
+        let max_retries = self.policy.cfg.max_retries;
+        let mut attempt = 0;
+        while max_retries == 0 || attempt < max_retries {
+            attempt += 1;
+            let delay = reconnect_delay(&self.policy.cfg, attempt, jitter());
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.policy.cancel.cancelled() => {
+                    info!("Reconnecting of stream {} is cancelled.", self.endpoint);
+                    return None;
+                }
+            }
+            match connect(&self.endpoint, &self.policy.endpoints).await {
+                Ok((stream, endpoint)) => {
+                    self.endpoint = endpoint;
+                    info!(
+                        "Stream {} is reconnected after {} attempt(s).",
+                        self.endpoint, attempt
+                    );
+                    self.policy.emit(ReconnectEvent::Reconnected {
+                        endpoint: self.endpoint.clone(),
+                        attempts: attempt,
+                    });
+                    return Some(stream);
+                }
+                Err(err) => warn!(
+                    "Attempt {} to reconnect to {} failed. Error: {}",
+                    attempt, self.endpoint, err
+                ),
+            }
+        }
+
+        warn!(
+            "Giving up on stream {} after {} attempt(s).",
+            self.endpoint, attempt
+        );
+        self.policy.emit(ReconnectEvent::GaveUp {
+            endpoint: self.endpoint.clone(),
+            attempts: attempt,
+        });
+        None
+    }
+}
+
+/// Text messages of the connected stream, restoring the connection according to the policy when it drops.
+///
+/// Server pings are answered as soon as they arrive, client pings are sent if enabled by the policy.
+pub(crate) fn reconnecting(
+    endpoint: &str,
+    stream: WsConnection,
+    policy: ReconnectPolicy,
+) -> impl Stream<Item = Message> {
+    unfold(ReconnectingState::new(endpoint, stream, policy, None))
+}
+
+/// Same as [`reconnecting`], the messages of the receiver are sent over the current connection as well.
+///
+/// Connection is restored to the same endpoint, so whatever the messages changed on the server is to be repeated
+/// once [`ReconnectEvent::Reconnected`] or [`ReconnectEvent::Rotated`] is reported.
+pub(crate) fn reconnecting_duplex(
+    endpoint: &str,
+    stream: WsConnection,
+    policy: ReconnectPolicy,
+    outgoing: mpsc::UnboundedReceiver<Message>,
+) -> impl Stream<Item = Message> {
+    unfold(ReconnectingState::new(
+        endpoint,
+        stream,
+        policy,
+        Some(outgoing),
+    ))
+}
+
+fn unfold(state: ReconnectingState) -> impl Stream<Item = Message> {
+    futures::stream::unfold(state, |mut state| async move {
+        let message = state.next().await?;
+        Some((message, state))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    fn fast_cfg(max_retries: u32) -> ReconnectCfg {
+        ReconnectCfg {
+            max_retries,
+            backoff: 1,
+            max_backoff: 1,
+            ping_interval: None,
+            rotate_after: None,
+            #[cfg(feature = "impairment")]
+            impairment: Default::default(),
+        }
+    }
+
+    /// Accept a single connection on a random local port, serve it with the given handler.
+    async fn serve_once<F, Fut>(handler: F) -> String
+    where
+        F: FnOnce(WebSocketStream<TcpStream>) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            handler(accept_async(socket).await.unwrap()).await;
+        });
+        endpoint
+    }
+
+    #[test]
+    fn it_grows_delay_exponentially_with_jitter() {
+        let cfg = ReconnectCfg {
+            backoff: 100,
+            max_backoff: 1000,
+            ..fast_cfg(0)
+        };
+        assert_eq!(reconnect_delay(&cfg, 1, 1.0), Duration::from_millis(100));
+        assert_eq!(reconnect_delay(&cfg, 3, 0.5), Duration::from_millis(200));
+        assert_eq!(reconnect_delay(&cfg, 30, 1.0), Duration::from_millis(1000));
+
+        let jitter = jitter();
+        assert!((0.5..1.0).contains(&jitter));
+    }
+
+    #[tokio::test]
+    async fn it_reports_giving_up_on_unreachable_endpoint() {
+        // Server goes away right after the first message, nothing listens on its port after that.
+        let endpoint = serve_once(|mut socket| async move {
+            socket.send(Message::Text("first".into())).await.unwrap();
+        })
+        .await;
+        let (connection, _) = connect_async(&endpoint).await.unwrap();
+        let (events, mut receiver) = broadcast::channel(RECONNECT_EVENTS_CAPACITY);
+        let stream = reconnecting(
+            &endpoint,
+            connection,
+            ReconnectPolicy::new(fast_cfg(2), Some(events)),
+        );
+
+        let received: Vec<Message> = stream.collect().await;
+        assert_eq!(received, vec![Message::Text("first".into())]);
+        assert!(matches!(
+            receiver.recv().await.unwrap(),
+            ReconnectEvent::Disconnected { .. }
+        ));
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            ReconnectEvent::GaveUp {
+                endpoint,
+                attempts: 2
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn it_sends_close_frame_once_cancelled() {
+        let (closes, mut close_receiver) = tokio::sync::mpsc::channel(1);
+        let endpoint = serve_once(|mut socket| async move {
+            socket.send(Message::Text("first".into())).await.unwrap();
+            while let Some(Ok(message)) = socket.next().await {
+                if let Message::Close(_) = message {
+                    closes.send(()).await.unwrap();
+                }
+            }
+        })
+        .await;
+        let (connection, _) = connect_async(&endpoint).await.unwrap();
+        let cancel = CancellationToken::new();
+        let policy = ReconnectPolicy::default().with_cancel(cancel.clone());
+        let mut stream = Box::pin(reconnecting(&endpoint, connection, policy));
+
+        assert_eq!(stream.next().await, Some(Message::Text("first".into())));
+        cancel.cancel();
+        assert_eq!(stream.next().await, None);
+
+        let close = tokio::time::timeout(Duration::from_secs(5), close_receiver.recv())
+            .await
+            .unwrap();
+        assert_eq!(close, Some(()));
+    }
+
+    #[tokio::test]
+    async fn it_answers_server_pings() {
+        let (pongs, mut pong_receiver) = tokio::sync::mpsc::channel(1);
+        let endpoint = serve_once(|mut socket| async move {
+            socket
+                .send(Message::Ping(b"alive?".to_vec()))
+                .await
+                .unwrap();
+            // Nothing else is sent, so the pong can only come from the ping handling itself.
+            while let Some(Ok(message)) = socket.next().await {
+                if let Message::Pong(payload) = message {
+                    pongs.send(payload).await.unwrap();
+                }
+            }
+        })
+        .await;
+        let (connection, _) = connect_async(&endpoint).await.unwrap();
+        let stream = reconnecting(&endpoint, connection, Default::default());
+        let consumer = tokio::spawn(stream.collect::<Vec<_>>());
+
+        let payload = tokio::time::timeout(Duration::from_secs(5), pong_receiver.recv())
+            .await
+            .unwrap();
+        assert_eq!(payload, Some(b"alive?".to_vec()));
+        consumer.abort();
+    }
+
+    #[tokio::test]
+    async fn it_sends_client_pings() {
+        let (pings, mut ping_receiver) = tokio::sync::mpsc::channel(1);
+        let endpoint = serve_once(|mut socket| async move {
+            while let Some(Ok(message)) = socket.next().await {
+                if let Message::Ping(_) = message {
+                    let _ = pings.send(()).await;
+                }
+            }
+        })
+        .await;
+        let (connection, _) = connect_async(&endpoint).await.unwrap();
+        let cfg = ReconnectCfg {
+            ping_interval: Some(10),
+            ..fast_cfg(1)
+        };
+        let stream = reconnecting(&endpoint, connection, ReconnectPolicy::new(cfg, None));
+        let consumer = tokio::spawn(stream.collect::<Vec<_>>());
+
+        let ping = tokio::time::timeout(Duration::from_secs(5), ping_receiver.recv())
+            .await
+            .unwrap();
+        assert_eq!(ping, Some(()));
+        consumer.abort();
+    }
+
+    #[tokio::test]
+    async fn it_rotates_connection_without_gaps() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for name in ["old", "new"] {
+                let (socket, _) = listener.accept().await.unwrap();
+                let mut socket = accept_async(socket).await.unwrap();
+                tokio::spawn(async move {
+                    for _ in 0..500 {
+                        if socket.send(Message::Text(name.into())).await.is_err() {
+                            return;
+                        }
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                    }
+                });
+            }
+        });
+
+        let (connection, _) = connect_async(&endpoint).await.unwrap();
+        let (events, mut receiver) = broadcast::channel(RECONNECT_EVENTS_CAPACITY);
+        let cfg = ReconnectCfg {
+            rotate_after: Some(50),
+            max_backoff: 60000,
+            ..fast_cfg(1)
+        };
+        let mut stream = Box::pin(reconnecting(
+            &endpoint,
+            connection,
+            ReconnectPolicy::new(cfg, Some(events)),
+        ));
+
+        let mut received = vec![];
+        while received.last() != Some(&Message::Text("new".into())) {
+            received.push(stream.next().await.unwrap());
+        }
+        assert_eq!(received[0], Message::Text("old".into()));
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            ReconnectEvent::Rotated { endpoint }
+        );
+    }
+}
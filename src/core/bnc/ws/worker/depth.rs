@@ -1,9 +1,11 @@
 use super::WsWorker;
 use crate::core::bnc::data::InlineOrder;
 use crate::core::bnc::error::BncResult;
-
+use crate::core::bnc::snapshot::SymbolSnapshot;
+use crate::core::bnc::ws::config::UpdateSpeed;
 use crate::core::bnc::ws::data::WsDataContainer;
-use crate::core::bnc::ws::worker::{bnc_stream_connect, MessageSender};
+use crate::core::bnc::ws::reconnect::ReconnectPolicy;
+use crate::core::bnc::ws::worker::{bnc_stream_connect, stream_endpoint, MessageSender};
 use futures::Stream;
 use futures_util::StreamExt;
 use log::{debug, warn};
@@ -20,6 +22,10 @@ pub struct SymbolDepthUpdate {
     #[serde(rename = "u")]
     pub final_update_id: u64,
 
+    /// Final update id of the previous event, sent by the futures market only - its ids are not contiguous.
+    #[serde(rename = "pu", default)]
+    pub previous_final_update_id: Option<u64>,
+
     #[serde(rename = "b")]
     pub bids: Vec<InlineOrder>,
 
@@ -34,23 +40,68 @@ pub trait SymbolDepthWatcher {
     fn depth_updates_watcher(
         &self,
         symbol: &str,
+        speed: UpdateSpeed,
         sender: impl MessageSender<SymbolDepthUpdate> + 'static,
     ) -> JoinHandle<BncResult<()>>;
 }
 
-fn depth_updates_endpoint(base_endpoint: &str, symbol: &str) -> String {
+/// Levels of the partial book streams binance provides.
+pub const PARTIAL_DEPTH_LEVELS: [u8; 3] = [5, 10, 20];
+
+pub trait SymbolPartialDepthWatcher {
+    /// Listen for the top levels of the book, send each of them as the snapshot via provided sender.
+    ///
+    /// Levels are rounded up to the supported ones, see [`PARTIAL_DEPTH_LEVELS`].
+    ///
+    /// Returns JoinHandle of the spawned task in order to store somewhere else.
+    fn partial_depth_watcher(
+        &self,
+        symbol: &str,
+        levels: u8,
+        sender: impl MessageSender<SymbolSnapshot> + 'static,
+    ) -> JoinHandle<BncResult<()>>;
+}
+
+/// Supported partial book levels that fit the requested ones.
+pub fn partial_depth_levels(levels: u8) -> u8 {
+    PARTIAL_DEPTH_LEVELS
+        .into_iter()
+        .find(|supported| *supported >= levels)
+        .unwrap_or(PARTIAL_DEPTH_LEVELS[PARTIAL_DEPTH_LEVELS.len() - 1])
+}
+
+/// Name of the symbol's partial book stream.
+pub fn partial_depth_stream(symbol: &str, levels: u8) -> String {
+    format!(
+        "{}@depth{}",
+        symbol.to_ascii_lowercase(),
+        partial_depth_levels(levels)
+    )
+}
+
+fn partial_depth_endpoint(base_endpoint: &str, symbol: &str, levels: u8) -> String {
+    stream_endpoint(base_endpoint, &partial_depth_stream(symbol, levels))
+}
+
+/// Name of the symbol's diff depth stream.
+pub fn depth_updates_stream(symbol: &str, speed: UpdateSpeed) -> String {
     format!(
-        "{base_url}/stream?streams={symbol}@depth",
-        base_url = base_endpoint,
-        symbol = symbol.to_ascii_lowercase()
+        "{}@depth{}",
+        symbol.to_ascii_lowercase(),
+        speed.stream_suffix()
     )
 }
 
+fn depth_updates_endpoint(base_endpoint: &str, symbol: &str, speed: UpdateSpeed) -> String {
+    stream_endpoint(base_endpoint, &depth_updates_stream(symbol, speed))
+}
+
 /// Connect to the BNC depth tick endpoint.
 async fn symbol_depth_ticks(
     endpoint: &str,
+    reconnect: ReconnectPolicy,
 ) -> BncResult<Pin<Box<impl Stream<Item = BncResult<SymbolDepthUpdate>>>>> {
-    let stream = bnc_stream_connect(endpoint).await?;
+    let stream = bnc_stream_connect(endpoint, reconnect).await?;
     let stream = stream.map(|message| {
         debug!("Received symbol depth update event.");
         let update: WsDataContainer<SymbolDepthUpdate> =
@@ -60,15 +111,67 @@ async fn symbol_depth_ticks(
     Ok(Box::pin(stream))
 }
 
+/// Connect to the BNC partial book endpoint.
+async fn symbol_partial_depths(
+    endpoint: &str,
+    reconnect: ReconnectPolicy,
+) -> BncResult<Pin<Box<impl Stream<Item = BncResult<SymbolSnapshot>>>>> {
+    let stream = bnc_stream_connect(endpoint, reconnect).await?;
+    let stream = stream.map(|message| {
+        debug!("Received symbol partial depth event.");
+        let update: WsDataContainer<SymbolSnapshot> = serde_json::from_slice(&message.into_data())?;
+        Ok(update.data)
+    });
+    Ok(Box::pin(stream))
+}
+
+impl<'a> SymbolPartialDepthWatcher for WsWorker<'a> {
+    fn partial_depth_watcher(
+        &self,
+        symbol: &str,
+        levels: u8,
+        sender: impl MessageSender<SymbolSnapshot> + 'static,
+    ) -> JoinHandle<BncResult<()>> {
+        let endpoint = partial_depth_endpoint(self.base_url, symbol, levels);
+        let reconnect = self.reconnect.clone();
+        tokio::task::spawn(async move {
+            let mut stream = symbol_partial_depths(&endpoint, reconnect).await?;
+            while let Some(event) = stream.next().await {
+                match event {
+                    Ok(snapshot) => {
+                        debug!(
+                            "Worker received partial depth. Last update id: {}",
+                            snapshot.last_update_id
+                        );
+                        if let Err(err) = sender.send(snapshot).await {
+                            debug!("Worker was unable to push partial depth. Error: {}", err)
+                        }
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Error occurred during worker processing the message. Err: {}",
+                            err
+                        );
+                    }
+                }
+            }
+            debug!("Worker of {} is stopped.", endpoint);
+            BncResult::Ok(())
+        })
+    }
+}
+
 impl<'a> SymbolDepthWatcher for WsWorker<'a> {
     fn depth_updates_watcher(
         &self,
         symbol: &str,
+        speed: UpdateSpeed,
         sender: impl MessageSender<SymbolDepthUpdate> + 'static,
     ) -> JoinHandle<BncResult<()>> {
-        let depth_endpoint = depth_updates_endpoint(self.base_url, symbol);
+        let depth_endpoint = depth_updates_endpoint(self.base_url, symbol, speed);
+        let reconnect = self.reconnect.clone();
         tokio::task::spawn(async move {
-            let mut stream = symbol_depth_ticks(&depth_endpoint).await?;
+            let mut stream = symbol_depth_ticks(&depth_endpoint, reconnect).await?;
             while let Some(event) = stream.next().await {
                 match event {
                     Ok(update) => {
@@ -92,6 +195,7 @@ impl<'a> SymbolDepthWatcher for WsWorker<'a> {
                     }
                 }
             }
+            debug!("Worker of {} is stopped.", depth_endpoint);
             BncResult::Ok(())
         })
     }
@@ -106,6 +210,52 @@ mod tests {
     use log::{info, LevelFilter};
     use tokio::sync::mpsc;
 
+    #[test]
+    fn it_builds_partial_depth_endpoint() {
+        assert_eq!(
+            partial_depth_endpoint("wss://host", "BTCUSDT", 10),
+            "wss://host/stream?streams=btcusdt@depth10"
+        );
+        assert_eq!(partial_depth_levels(1), 5);
+        assert_eq!(partial_depth_levels(7), 10);
+        assert_eq!(partial_depth_levels(50), 20);
+    }
+
+    #[test]
+    fn it_builds_depth_endpoint_of_update_speed() {
+        assert_eq!(
+            depth_updates_endpoint("wss://host", "BTCUSDT", UpdateSpeed::Normal),
+            "wss://host/stream?streams=btcusdt@depth"
+        );
+        assert_eq!(
+            depth_updates_endpoint("wss://host", "BTCUSDT", UpdateSpeed::Fast),
+            "wss://host/stream?streams=btcusdt@depth@100ms"
+        );
+    }
+
+    #[test]
+    fn it_parses_futures_depth_event() {
+        let message = r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1672515782136,
+            "T":1672515782130,"s":"BTCUSDT","U":157,"u":160,"pu":149,
+            "b":[["16500.10","10"]],"a":[["16500.20","100"]]}}"#;
+
+        let update: WsDataContainer<SymbolDepthUpdate> = serde_json::from_str(message).unwrap();
+        assert_eq!(update.data.first_update_id, 157);
+        assert_eq!(update.data.final_update_id, 160);
+        assert_eq!(update.data.previous_final_update_id, Some(149));
+    }
+
+    #[test]
+    fn it_parses_partial_depth_event() {
+        let message = r#"{"stream":"btcusdt@depth5","data":{"lastUpdateId":160,
+            "bids":[["0.0024","10"]],"asks":[["0.0026","100"]]}}"#;
+
+        let update: WsDataContainer<SymbolSnapshot> = serde_json::from_str(message).unwrap();
+        assert_eq!(update.data.last_update_id, 160);
+        assert_eq!(update.data.bids[0].level().to_string(), "0.0024");
+        assert_eq!(update.data.asks[0].qty().to_string(), "100");
+    }
+
     struct TestCtx {
         cfg: AppCfg,
     }
@@ -128,8 +278,11 @@ mod tests {
         let symbol = "BTCUSDT";
 
         let worker = WsWorker::from_cfg(&ctx.cfg.core.bnc.ws);
-        let mut events =
-            symbol_depth_ticks(&depth_updates_endpoint(worker.base_url, symbol)).await?;
+        let mut events = symbol_depth_ticks(
+            &depth_updates_endpoint(worker.base_url, symbol, ctx.cfg.core.bnc.ws.update_speed),
+            worker.reconnect.clone(),
+        )
+        .await?;
         let event = events.next().await.unwrap()?;
 
         info!("Successfully received event: {:?}", event);
@@ -144,7 +297,7 @@ mod tests {
 
         let worker = WsWorker::from_cfg(&ctx.cfg.core.bnc.ws);
         let (sender, mut receiver) = mpsc::channel(10);
-        let handle = worker.depth_updates_watcher(symbol, sender);
+        let handle = worker.depth_updates_watcher(symbol, ctx.cfg.core.bnc.ws.update_speed, sender);
 
         let update = receiver.recv().await.unwrap();
 
@@ -0,0 +1,150 @@
+use super::super::data::WsDataContainer;
+use super::WsWorker;
+use crate::core::bnc::data::{Price, Quantity};
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::ws::reconnect::ReconnectPolicy;
+use crate::core::bnc::ws::worker::{bnc_stream_connect, stream_endpoint, MessageSender};
+use futures::Stream;
+use futures_util::StreamExt;
+use log::{debug, error, warn};
+use serde::Deserialize;
+use std::pin::Pin;
+use tokio::task::JoinHandle;
+
+/// Stream of the mini tickers of the whole market. It is pushed once a second, carrying only the changed symbols.
+pub const ALL_MINI_TICKERS_STREAM: &str = "!miniTicker@arr";
+
+/// Rolling 24hr statistics of the symbol, reduced to its prices and volumes.
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct SymbolMiniTicker {
+    /// Event time, in milliseconds since epoch.
+    #[serde(rename = "E")]
+    pub time: u64,
+
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    /// Last price.
+    #[serde(rename = "c")]
+    pub close: Price,
+
+    #[serde(rename = "o")]
+    pub open: Price,
+
+    #[serde(rename = "h")]
+    pub high: Price,
+
+    #[serde(rename = "l")]
+    pub low: Price,
+
+    /// Traded volume of the base asset.
+    #[serde(rename = "v")]
+    pub volume: Quantity,
+
+    /// Traded volume of the quote asset.
+    #[serde(rename = "q")]
+    pub quote_volume: Quantity,
+}
+
+pub trait AllMiniTickersWatcher {
+    /// Listen for mini tickers of the whole market over a single connection, send them via provided sender.
+    ///
+    /// Returns JoinHandle of the spawned task in order to store somewhere else.
+    fn mini_tickers_watcher(
+        &self,
+        sender: impl MessageSender<Vec<SymbolMiniTicker>> + 'static,
+    ) -> JoinHandle<BncResult<()>>;
+}
+
+fn all_mini_tickers_endpoint(base_endpoint: &str) -> String {
+    stream_endpoint(base_endpoint, ALL_MINI_TICKERS_STREAM)
+}
+
+/// Connect to the BNC all market mini tickers endpoint.
+async fn all_mini_tickers(
+    endpoint: &str,
+    reconnect: ReconnectPolicy,
+) -> BncResult<Pin<Box<impl Stream<Item = BncResult<Vec<SymbolMiniTicker>>>>>> {
+    let stream = bnc_stream_connect(endpoint, reconnect).await?;
+    let stream = stream.map(|message| {
+        debug!("Received mini tickers event.");
+        let update: WsDataContainer<Vec<SymbolMiniTicker>> =
+            serde_json::from_slice(&message.into_data())?;
+        Ok(update.data)
+    });
+    Ok(Box::pin(stream))
+}
+
+impl<'a> AllMiniTickersWatcher for WsWorker<'a> {
+    fn mini_tickers_watcher(
+        &self,
+        sender: impl MessageSender<Vec<SymbolMiniTicker>> + 'static,
+    ) -> JoinHandle<BncResult<()>> {
+        let endpoint = all_mini_tickers_endpoint(self.base_url);
+        let reconnect = self.reconnect.clone();
+        let future = async move {
+            let mut stream = all_mini_tickers(&endpoint, reconnect).await?;
+            while let Some(event) = stream.next().await {
+                match event {
+                    Ok(tickers) => {
+                        debug!("Worker received {} mini tickers.", tickers.len());
+                        match sender.send(tickers).await {
+                            Err(BncError::DataTransmitError) => {
+                                warn!("Sender could not process mini tickers.")
+                            }
+                            Err(BncError::DataRejected) => {
+                                debug!("Mini tickers were rejected due to some predicate.")
+                            }
+                            Err(err) => {
+                                error!(
+                                    "Mini tickers were rejected with unexpected error. Error: {}",
+                                    err
+                                )
+                            }
+                            Ok(_) => debug!("Worker successfully sent mini tickers to consumer."),
+                        }
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Error occurred during worker processing the message. Err: {}",
+                            err
+                        );
+                    }
+                }
+            }
+            debug!("Worker of {} is stopped.", endpoint);
+            BncResult::Ok(())
+        };
+        tokio::task::spawn(future)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_all_mini_tickers_event() {
+        let message = r#"{"stream":"!miniTicker@arr","data":[
+            {"e":"24hrMiniTicker","E":1672515782136,"s":"BTCUSDT","c":"16499.50","o":"16620.00",
+            "h":"16700.00","l":"16450.00","v":"12000.5","q":"198361000.1"},
+            {"e":"24hrMiniTicker","E":1672515782136,"s":"ETHUSDT","c":"1200.10","o":"1190.00",
+            "h":"1210.00","l":"1180.00","v":"500","q":"600000"}]}"#;
+
+        let update: WsDataContainer<Vec<SymbolMiniTicker>> = serde_json::from_str(message).unwrap();
+        let tickers = update.data;
+
+        assert_eq!(tickers.len(), 2);
+        assert_eq!(tickers[0].symbol, "BTCUSDT");
+        assert_eq!(tickers[0].close.to_string(), "16499.50");
+        assert_eq!(tickers[1].open.to_string(), "1190.00");
+    }
+
+    #[test]
+    fn it_builds_all_mini_tickers_endpoint() {
+        assert_eq!(
+            all_mini_tickers_endpoint("wss://host"),
+            "wss://host/stream?streams=!miniTicker@arr"
+        );
+    }
+}
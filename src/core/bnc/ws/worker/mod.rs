@@ -1,9 +1,13 @@
 use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::replay::{is_replay, replay_stream};
+use crate::core::bnc::synthetic::{is_synthetic, synthetic_stream};
+use crate::core::bnc::ws::combined::CombinedStreamConnection;
 use crate::core::bnc::ws::config::WsCfg;
-use futures::Stream;
+use crate::core::bnc::ws::endpoints::EndpointPool;
+use crate::core::bnc::ws::reconnect::{connect, reconnecting, ReconnectPolicy};
+use futures::stream::BoxStream;
 use futures_util::StreamExt;
 use tokio::sync::mpsc::Sender as TokioSender;
-use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::Message;
 
 /// Implementors are to be used in transmitting messages from workers to messages' consumers.
@@ -32,9 +36,18 @@ impl<T: Send + Sync> MessageSender<T> for TokioSender<T> {
 /// Order book keeping.
 pub mod depth;
 
+/// Realtime rolling statistics of the whole market over a single stream.
+pub mod mini_ticker;
+
 /// Realtime symbol's best price updating.
 pub mod price;
 
+/// Realtime symbol's rolling 24hr statistics.
+pub mod ticker;
+
+/// Realtime symbol's executed trades.
+pub mod trade;
+
 /// WS worker handles realtime updates of the symbol's price.
 ///
 /// It's purpose to schedule listening threads that will send the data to the provided sender.
@@ -42,29 +55,59 @@ pub mod price;
 /// It doesn't, however, provide load balancing across child processes - so worker's results may be repeated.
 pub struct WsWorker<'a> {
     base_url: &'a str,
+    reconnect: ReconnectPolicy,
 }
 
 impl<'a> WsWorker<'a> {
     pub fn new(base_url: &'a str) -> Self {
-        Self { base_url }
+        Self {
+            base_url,
+            reconnect: Default::default(),
+        }
     }
 
     pub fn from_cfg(cfg: &'a WsCfg) -> Self {
         Self {
-            base_url: &cfg.baseurl,
+            base_url: cfg.baseurl.primary(),
+            reconnect: ReconnectPolicy::new(cfg.reconnect.clone(), None)
+                .with_endpoints(EndpointPool::new(cfg.baseurl.all())),
         }
     }
+
+    /// Restore dropped streams of the spawned watchers according to the given policy.
+    pub fn with_reconnect(mut self, reconnect: ReconnectPolicy) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Connection multiplexing several streams, they can be added and removed without reconnecting.
+    pub fn combined_connection(&self) -> CombinedStreamConnection {
+        CombinedStreamConnection::new(self.base_url)
+    }
 }
 
-/// Connect to the given stream endpoint, cut undesired messages(like ping, etc) and unwrap errors
-async fn bnc_stream_connect(endpoint: &str) -> BncResult<impl Stream<Item = Message>> {
-    let (ws_stream, _) = connect_async(endpoint).await?;
-    Ok(ws_stream.filter_map(|message| async {
-        let message = message.ok()?;
-        if message.is_text() {
-            Some(message)
-        } else {
-            None
-        }
-    }))
+/// Endpoint of the combined stream carrying all the given streams.
+pub(crate) fn stream_endpoint(base_endpoint: &str, streams: &str) -> String {
+    format!("{}/stream?streams={}", base_endpoint, streams)
+}
+
+/// Connect to the given stream endpoint, cut undesired messages(like ping, etc) and unwrap errors.
+///
+/// Once connected, the stream is restored according to the policy whenever it drops.
+///
+/// Synthetic endpoints are generated locally and replayed ones are read from the recordings, nothing is connected to.
+pub(crate) async fn bnc_stream_connect(
+    endpoint: &str,
+    reconnect: ReconnectPolicy,
+) -> BncResult<BoxStream<'static, Message>> {
+    if is_synthetic(endpoint) {
+        let cancelled = reconnect.cancel.cancelled_owned();
+        return Ok(synthetic_stream(endpoint).take_until(cancelled).boxed());
+    }
+    if is_replay(endpoint) {
+        let cancelled = reconnect.cancel.cancelled_owned();
+        return Ok(replay_stream(endpoint).take_until(cancelled).boxed());
+    }
+    let (ws_stream, endpoint) = connect(endpoint, &reconnect.endpoints).await?;
+    Ok(reconnecting(&endpoint, ws_stream, reconnect).boxed())
 }
@@ -1,72 +1,100 @@
 use super::super::data::WsDataContainer;
 use super::WsWorker;
-use crate::core::bnc::data::{InlineOrder, PriceLevel, Qty};
+use crate::core::bnc::data::{InlineOrder, Price, Quantity};
 use crate::core::bnc::error::{BncError, BncResult};
 use crate::core::bnc::snapshot::SymbolSnapshot;
-use crate::core::bnc::ws::worker::{bnc_stream_connect, MessageSender};
+use crate::core::bnc::ws::reconnect::ReconnectPolicy;
+use crate::core::bnc::ws::worker::{bnc_stream_connect, stream_endpoint, MessageSender};
 use futures::Stream;
 use futures_util::StreamExt;
 use log::{debug, error, warn};
-use serde::Deserialize;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use std::pin::Pin;
 use tokio::task::JoinHandle;
 
 /// Tick for an individual symbol's book update. Generally current best price for the provided symbol.
 #[derive(Debug, Deserialize, Clone)]
-struct SymbolBookTick {
+pub struct SymbolBookTick {
     #[serde(rename = "u")]
     id: u64,
 
     #[serde(rename = "b")]
-    bid_price: PriceLevel,
+    bid_price: Price,
 
     #[serde(rename = "B")]
-    bid_qty: Qty,
+    bid_qty: Quantity,
 
     #[serde(rename = "a")]
-    ask_price: PriceLevel,
+    ask_price: Price,
 
     #[serde(rename = "A")]
-    ask_qty: Qty,
+    ask_qty: Quantity,
 }
 
 /// Generalisation of price update.
 ///
 /// All tickers' updates should be convertable to general representation.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct SymbolPriceUpdate {
     pub id: u64,
     pub bid: InlineOrder,
     pub ask: InlineOrder,
+
+    /// Mid of the best prices weighted by the quantity of the opposite side, if both sides are known.
+    ///
+    /// Unlike the plain mid, it leans towards the thinner side - the one that is more likely to be taken first.
+    pub microprice: Option<Decimal>,
 }
 
-impl From<SymbolBookTick> for SymbolPriceUpdate {
-    fn from(tick: SymbolBookTick) -> Self {
+/// Mid of the best prices weighted by the quantities. Zero price is the one of the side that is not known.
+fn microprice(bid: &InlineOrder, ask: &InlineOrder) -> Option<Decimal> {
+    if bid.level().is_zero() || ask.level().is_zero() {
+        return None;
+    }
+    let (bid_qty, ask_qty) = (bid.qty().value(), ask.qty().value());
+    (bid.level().value() * ask_qty + ask.level().value() * bid_qty).checked_div(bid_qty + ask_qty)
+}
+
+impl SymbolPriceUpdate {
+    pub fn new(id: u64, bid: InlineOrder, ask: InlineOrder) -> Self {
         Self {
-            id: tick.id,
-            bid: InlineOrder::new(tick.bid_price, tick.bid_qty),
-            ask: InlineOrder::new(tick.ask_price, tick.ask_qty),
+            id,
+            microprice: microprice(&bid, &ask),
+            bid,
+            ask,
         }
     }
 }
 
-impl From<SymbolSnapshot> for SymbolPriceUpdate {
-    fn from(snapshot: SymbolSnapshot) -> Self {
-        let empty_snapshot_notification =
-            "Snapshot returned by binance is empty. Are we corrupted?";
-        Self {
-            bid: snapshot
+impl From<SymbolBookTick> for SymbolPriceUpdate {
+    fn from(tick: SymbolBookTick) -> Self {
+        Self::new(
+            tick.id,
+            InlineOrder::new(tick.bid_price, tick.bid_qty),
+            InlineOrder::new(tick.ask_price, tick.ask_qty),
+        )
+    }
+}
+
+impl TryFrom<SymbolSnapshot> for SymbolPriceUpdate {
+    type Error = BncError;
+
+    fn try_from(snapshot: SymbolSnapshot) -> BncResult<Self> {
+        let empty_snapshot = || BncError::MalformedData("snapshot has no bids or asks".into());
+        Ok(Self::new(
+            snapshot.last_update_id,
+            snapshot
                 .bids
                 .into_iter()
                 .last()
-                .expect(empty_snapshot_notification),
-            ask: snapshot
+                .ok_or_else(empty_snapshot)?,
+            snapshot
                 .asks
                 .into_iter()
                 .last()
-                .expect(empty_snapshot_notification),
-            id: snapshot.last_update_id,
-        }
+                .ok_or_else(empty_snapshot)?,
+        ))
     }
 }
 
@@ -81,19 +109,21 @@ pub trait SymbolPriceWatcher {
     ) -> JoinHandle<BncResult<()>>;
 }
 
+/// Name of the symbol's best price stream.
+pub fn book_ticker_stream(symbol: &str) -> String {
+    format!("{}@bookTicker", symbol.to_ascii_lowercase())
+}
+
 fn book_ticker_endpoint(base_endpoint: &str, symbol: &str) -> String {
-    format!(
-        "{base_url}/stream?streams={symbol}@bookTicker",
-        base_url = base_endpoint,
-        symbol = symbol.to_ascii_lowercase()
-    )
+    stream_endpoint(base_endpoint, &book_ticker_stream(symbol))
 }
 
 /// Connect to the BNC book tick endpoint.
 async fn symbol_book_ticks(
     endpoint: &str,
+    reconnect: ReconnectPolicy,
 ) -> BncResult<Pin<Box<impl Stream<Item = BncResult<SymbolBookTick>>>>> {
-    let stream = bnc_stream_connect(endpoint).await?;
+    let stream = bnc_stream_connect(endpoint, reconnect).await?;
     let stream = stream.map(|message| {
         debug!("Received symbol price update event.");
         let update: WsDataContainer<SymbolBookTick> = serde_json::from_slice(&message.into_data())?;
@@ -109,8 +139,9 @@ impl<'a> SymbolPriceWatcher for WsWorker<'a> {
         sender: impl MessageSender<SymbolPriceUpdate> + 'static,
     ) -> JoinHandle<BncResult<()>> {
         let book_ticker_endpoint = book_ticker_endpoint(self.base_url, symbol);
+        let reconnect = self.reconnect.clone();
         let future = async move {
-            let mut stream = symbol_book_ticks(&book_ticker_endpoint).await?;
+            let mut stream = symbol_book_ticks(&book_ticker_endpoint, reconnect).await?;
             while let Some(event) = stream.next().await {
                 match event {
                     Ok(update) => {
@@ -145,6 +176,7 @@ impl<'a> SymbolPriceWatcher for WsWorker<'a> {
                     }
                 }
             }
+            debug!("Worker of {} is stopped.", book_ticker_endpoint);
             BncResult::Ok(())
         };
         tokio::task::spawn(future)
@@ -160,6 +192,36 @@ mod tests {
     use log::{info, LevelFilter};
     use tokio::sync::mpsc;
 
+    #[test]
+    fn it_rejects_empty_snapshot() {
+        let snapshot = SymbolSnapshot {
+            last_update_id: 1,
+            bids: vec![InlineOrder::new(
+                "1.0".parse().unwrap(),
+                "1".parse().unwrap(),
+            )],
+            asks: vec![],
+        };
+
+        assert!(matches!(
+            SymbolPriceUpdate::try_from(snapshot),
+            Err(BncError::MalformedData(_))
+        ));
+    }
+
+    #[test]
+    fn it_weights_mid_price_by_opposite_quantity() {
+        let tick: SymbolBookTick = serde_json::from_str(
+            r#"{"u":400900217,"s":"BNBUSDT","b":"25.30","B":"3","a":"25.40","A":"1"}"#,
+        )
+        .unwrap();
+        let update = SymbolPriceUpdate::from(tick);
+        assert_eq!(update.microprice, Some("25.375".parse().unwrap()));
+
+        let unknown_ask = SymbolPriceUpdate::new(1, update.bid.clone(), Default::default());
+        assert_eq!(unknown_ask.microprice, None);
+    }
+
     #[tokio::test]
     async fn it_watches_for_first_symbol_update_using_tick_book() -> Result<()> {
         let cfg = AppCfg::load()?;
@@ -171,7 +233,11 @@ mod tests {
         let symbol = "BTCUSDT";
 
         let worker = WsWorker::from_cfg(&cfg.core.bnc.ws);
-        let mut events = symbol_book_ticks(&book_ticker_endpoint(worker.base_url, symbol)).await?;
+        let mut events = symbol_book_ticks(
+            &book_ticker_endpoint(worker.base_url, symbol),
+            worker.reconnect.clone(),
+        )
+        .await?;
         let event = events.next().await.unwrap()?;
 
         info!("Successfully received event: {:?}", event);
@@ -0,0 +1,163 @@
+use super::super::data::WsDataContainer;
+use super::WsWorker;
+use crate::core::bnc::data::{Price, Quantity, SymbolTicker24h};
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::ws::reconnect::ReconnectPolicy;
+use crate::core::bnc::ws::worker::{bnc_stream_connect, stream_endpoint, MessageSender};
+use futures::Stream;
+use futures_util::StreamExt;
+use log::{debug, error, warn};
+use serde::Deserialize;
+use std::pin::Pin;
+use tokio::task::JoinHandle;
+
+/// Rolling 24 hours statistics of the symbol, pushed each second.
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct SymbolTickerUpdate {
+    /// Event time, in milliseconds since epoch. Ticker has no update id, so it is used instead.
+    #[serde(rename = "E")]
+    pub time: u64,
+
+    #[serde(rename = "p")]
+    pub price_change: Price,
+
+    #[serde(rename = "P")]
+    pub price_change_percent: String,
+
+    #[serde(rename = "w")]
+    pub weighted_avg_price: Price,
+
+    #[serde(rename = "c")]
+    pub last_price: Price,
+
+    #[serde(rename = "h")]
+    pub high: Price,
+
+    #[serde(rename = "l")]
+    pub low: Price,
+
+    /// Traded volume of the base asset.
+    #[serde(rename = "v")]
+    pub volume: Quantity,
+
+    /// Traded volume of the quote asset.
+    #[serde(rename = "q")]
+    pub quote_volume: Quantity,
+}
+
+/// Statistics fetched over REST, to be displayed until the stream sends its first update.
+impl From<SymbolTicker24h> for SymbolTickerUpdate {
+    fn from(ticker: SymbolTicker24h) -> Self {
+        Self {
+            time: ticker.close_time,
+            price_change: ticker.price_change,
+            price_change_percent: ticker.price_change_percent,
+            weighted_avg_price: ticker.weighted_avg_price,
+            last_price: ticker.last_price,
+            high: ticker.high,
+            low: ticker.low,
+            volume: ticker.volume,
+            quote_volume: ticker.quote_volume,
+        }
+    }
+}
+
+pub trait SymbolTickerWatcher {
+    /// Listen for rolling 24hr statistics updates, send them via provided sender.
+    ///
+    /// Returns JoinHandle of the spawned task in order to store somewhere else.
+    fn ticker_updates_watcher(
+        &self,
+        symbol: &str,
+        sender: impl MessageSender<SymbolTickerUpdate> + 'static,
+    ) -> JoinHandle<BncResult<()>>;
+}
+
+/// Name of the symbol's rolling 24hr statistics stream.
+pub fn ticker_stream(symbol: &str) -> String {
+    format!("{}@ticker", symbol.to_ascii_lowercase())
+}
+
+fn ticker_endpoint(base_endpoint: &str, symbol: &str) -> String {
+    stream_endpoint(base_endpoint, &ticker_stream(symbol))
+}
+
+/// Connect to the BNC rolling ticker endpoint.
+async fn symbol_tickers(
+    endpoint: &str,
+    reconnect: ReconnectPolicy,
+) -> BncResult<Pin<Box<impl Stream<Item = BncResult<SymbolTickerUpdate>>>>> {
+    let stream = bnc_stream_connect(endpoint, reconnect).await?;
+    let stream = stream.map(|message| {
+        debug!("Received symbol ticker event.");
+        let update: WsDataContainer<SymbolTickerUpdate> =
+            serde_json::from_slice(&message.into_data())?;
+        Ok(update.data)
+    });
+    Ok(Box::pin(stream))
+}
+
+impl<'a> SymbolTickerWatcher for WsWorker<'a> {
+    fn ticker_updates_watcher(
+        &self,
+        symbol: &str,
+        sender: impl MessageSender<SymbolTickerUpdate> + 'static,
+    ) -> JoinHandle<BncResult<()>> {
+        let ticker_endpoint = ticker_endpoint(self.base_url, symbol);
+        let reconnect = self.reconnect.clone();
+        let future = async move {
+            let mut stream = symbol_tickers(&ticker_endpoint, reconnect).await?;
+            while let Some(event) = stream.next().await {
+                match event {
+                    Ok(update) => {
+                        debug!("Worker received symbol ticker. Ticker: {:?}", update);
+                        match sender.send(update).await {
+                            Err(BncError::DataTransmitError) => {
+                                warn!("Sender could not process ticker.")
+                            }
+                            Err(BncError::DataRejected) => {
+                                debug!("Ticker was rejected due to some predicate.")
+                            }
+                            Err(err) => {
+                                error!("Ticker was rejected with unexpected error. Error: {}", err)
+                            }
+                            Ok(_) => debug!("Worker successfully sent ticker to consumer."),
+                        }
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Error occurred during worker processing the message. Err: {}",
+                            err
+                        );
+                    }
+                }
+            }
+            debug!("Worker of {} is stopped.", ticker_endpoint);
+            BncResult::Ok(())
+        };
+        tokio::task::spawn(future)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_ticker_event() {
+        let message = r#"{"stream":"btcusdt@ticker","data":{"e":"24hrTicker","E":1672515782136,"s":"BTCUSDT",
+            "p":"-120.50","P":"-0.725","w":"16530.12","x":"16620.00","c":"16499.50","Q":"0.01",
+            "b":"16499.40","B":"1.2","a":"16499.50","A":"0.8","o":"16620.00","h":"16700.00","l":"16450.00",
+            "v":"12000.5","q":"198361000.1","O":1672429382136,"C":1672515782136,"F":100,"L":200,"n":101}}"#;
+
+        let update: WsDataContainer<SymbolTickerUpdate> = serde_json::from_str(message).unwrap();
+        let ticker = update.data;
+
+        assert_eq!(ticker.time, 1672515782136);
+        assert_eq!(ticker.price_change_percent, "-0.725");
+        assert_eq!(ticker.weighted_avg_price.to_string(), "16530.12");
+        assert_eq!(ticker.high.to_string(), "16700.00");
+        assert_eq!(ticker.low.to_string(), "16450.00");
+        assert_eq!(ticker.volume.to_string(), "12000.5");
+    }
+}
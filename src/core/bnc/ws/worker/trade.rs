@@ -0,0 +1,136 @@
+use super::super::data::WsDataContainer;
+use super::WsWorker;
+use crate::core::bnc::data::{Price, Quantity};
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::ws::reconnect::ReconnectPolicy;
+use crate::core::bnc::ws::worker::{bnc_stream_connect, stream_endpoint, MessageSender};
+use futures::Stream;
+use futures_util::StreamExt;
+use log::{debug, error, warn};
+use serde::Deserialize;
+use std::pin::Pin;
+use tokio::task::JoinHandle;
+
+/// Aggregated trade - trades of a single taker order at the same price.
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct SymbolTradeUpdate {
+    #[serde(rename = "a")]
+    pub id: u64,
+
+    #[serde(rename = "p")]
+    pub price: Price,
+
+    #[serde(rename = "q")]
+    pub qty: Quantity,
+
+    #[serde(rename = "f")]
+    pub first_trade_id: u64,
+
+    #[serde(rename = "l")]
+    pub last_trade_id: u64,
+
+    /// Trade time, in milliseconds since epoch.
+    #[serde(rename = "T")]
+    pub time: u64,
+
+    /// Whether the buyer is the maker, i.e. the trade is a sell from the taker's point of view.
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool,
+}
+
+pub trait SymbolTradeWatcher {
+    /// Listen for executed trades, send them via provided sender.
+    ///
+    /// Returns JoinHandle of the spawned task in order to store somewhere else.
+    fn trade_updates_watcher(
+        &self,
+        symbol: &str,
+        sender: impl MessageSender<SymbolTradeUpdate> + 'static,
+    ) -> JoinHandle<BncResult<()>>;
+}
+
+/// Name of the symbol's aggregated trades stream.
+pub fn agg_trade_stream(symbol: &str) -> String {
+    format!("{}@aggTrade", symbol.to_ascii_lowercase())
+}
+
+fn agg_trade_endpoint(base_endpoint: &str, symbol: &str) -> String {
+    stream_endpoint(base_endpoint, &agg_trade_stream(symbol))
+}
+
+/// Connect to the BNC aggregated trades endpoint.
+async fn symbol_trades(
+    endpoint: &str,
+    reconnect: ReconnectPolicy,
+) -> BncResult<Pin<Box<impl Stream<Item = BncResult<SymbolTradeUpdate>>>>> {
+    let stream = bnc_stream_connect(endpoint, reconnect).await?;
+    let stream = stream.map(|message| {
+        debug!("Received symbol trade event.");
+        let update: WsDataContainer<SymbolTradeUpdate> =
+            serde_json::from_slice(&message.into_data())?;
+        Ok(update.data)
+    });
+    Ok(Box::pin(stream))
+}
+
+impl<'a> SymbolTradeWatcher for WsWorker<'a> {
+    fn trade_updates_watcher(
+        &self,
+        symbol: &str,
+        sender: impl MessageSender<SymbolTradeUpdate> + 'static,
+    ) -> JoinHandle<BncResult<()>> {
+        let agg_trade_endpoint = agg_trade_endpoint(self.base_url, symbol);
+        let reconnect = self.reconnect.clone();
+        let future = async move {
+            let mut stream = symbol_trades(&agg_trade_endpoint, reconnect).await?;
+            while let Some(event) = stream.next().await {
+                match event {
+                    Ok(update) => {
+                        debug!("Worker received symbol trade. Trade: {:?}", update);
+                        match sender.send(update).await {
+                            Err(BncError::DataTransmitError) => {
+                                warn!("Sender could not process trade.")
+                            }
+                            Err(BncError::DataRejected) => {
+                                debug!("Trade was rejected due to some predicate.")
+                            }
+                            Err(err) => {
+                                error!("Trade was rejected with unexpected error. Error: {}", err)
+                            }
+                            Ok(_) => debug!("Worker successfully sent trade to consumer."),
+                        }
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Error occurred during worker processing the message. Err: {}",
+                            err
+                        );
+                    }
+                }
+            }
+            debug!("Worker of {} is stopped.", agg_trade_endpoint);
+            BncResult::Ok(())
+        };
+        tokio::task::spawn(future)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_agg_trade_event() {
+        let message = r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":1672515782136,"s":"BTCUSDT",
+            "a":12345,"p":"16500.10","q":"0.015","f":100,"l":105,"T":1672515782134,"m":true,"M":true}}"#;
+
+        let update: WsDataContainer<SymbolTradeUpdate> = serde_json::from_str(message).unwrap();
+        let trade = update.data;
+
+        assert_eq!(trade.id, 12345);
+        assert_eq!(trade.price.to_string(), "16500.10");
+        assert_eq!(trade.qty.to_string(), "0.015");
+        assert_eq!(trade.last_trade_id - trade.first_trade_id, 5);
+        assert!(trade.is_buyer_maker);
+    }
+}
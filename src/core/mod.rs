@@ -4,5 +4,11 @@ pub mod bnc;
 /// Module that contains logging configuration and implementation load util.
 pub mod logging;
 
+/// Module that contains tokio runtime configuration and storage tasks scheduling.
+pub mod runtime;
+
+/// Module that records raw updates of the symbols into JSONL/CSV files.
+pub mod recorder;
+
 /// Sum of all core sub-modules' configs.
 pub mod config;
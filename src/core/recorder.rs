@@ -0,0 +1,796 @@
+use crate::core::bnc::clock::local_millis;
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::core::bnc::state::tasks::TaskGroup;
+use crate::core::bnc::ws::config::{ReconnectCfg, UpdateSpeed, WsCfg};
+use crate::core::bnc::ws::endpoints::EndpointPool;
+use crate::core::bnc::ws::reconnect::ReconnectPolicy;
+use crate::core::bnc::ws::worker::depth::{SymbolDepthUpdate, SymbolDepthWatcher};
+use crate::core::bnc::ws::worker::price::{SymbolPriceUpdate, SymbolPriceWatcher};
+use crate::core::bnc::ws::worker::trade::{SymbolTradeUpdate, SymbolTradeWatcher};
+use crate::core::bnc::ws::worker::{MessageSender, WsWorker};
+use crate::core::runtime::spawn_storage;
+use derive_getters::Getters;
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+/// Dropped updates are reported once per this many of them.
+const DROPS_PER_WARNING: u64 = 1000;
+
+/// Format of the recorded files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    /// JSON object per line.
+    #[default]
+    Jsonl,
+
+    /// Comma separated values with the header. Depth updates take a row per level.
+    Csv,
+}
+
+impl RecordFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// What the recorder does with the update once its queue is full, i.e. the disk falls behind the feeds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowPolicy {
+    /// Hold the worker back until the queue has room. Nothing is lost, but the feed lags behind.
+    #[default]
+    Block,
+
+    /// Drop the update and count it.
+    Drop,
+}
+
+/// Configuration of the raw updates recording.
+#[derive(Debug, Clone, Deserialize, Getters)]
+#[serde(default)]
+pub struct RecorderCfg {
+    /// Record the scraped symbols alongside the UI.
+    pub enabled: bool,
+
+    /// Directory to store the records in. Each feed of the symbol is recorded into its own files.
+    pub dir: String,
+
+    pub format: RecordFormat,
+
+    /// Bytes the file grows to before the next one is started.
+    pub max_file_size: u64,
+
+    /// Level of the zstd compression of the files, e.g. 3. Files are not compressed unless it is set.
+    pub compression_level: Option<i32>,
+
+    /// Lines compressed into a single zstd frame. Lines of the unfinished frame are written out on the syncs.
+    pub frame_lines: usize,
+
+    /// Updates queued in memory for the writer, per symbol.
+    pub queue_capacity: usize,
+
+    pub overflow: OverflowPolicy,
+
+    /// Milliseconds between the syncs of the files to the disk. Left to the system if not set.
+    pub fsync_interval: Option<u64>,
+
+    pub price: bool,
+    pub depth: bool,
+    pub trades: bool,
+}
+
+impl Default for RecorderCfg {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: String::from("records"),
+            format: RecordFormat::default(),
+            max_file_size: 64 * 1024 * 1024,
+            compression_level: None,
+            frame_lines: 1000,
+            queue_capacity: 4096,
+            overflow: OverflowPolicy::default(),
+            fsync_interval: Some(1000),
+            price: true,
+            depth: true,
+            trades: true,
+        }
+    }
+}
+
+/// Update that could be recorded.
+pub trait Record {
+    /// Name of the feed, part of the files' names.
+    const FEED: &'static str;
+
+    /// Columns of the CSV rows, without the receive time.
+    fn columns() -> &'static [&'static str];
+
+    /// CSV rows of the update. Values are numbers and plain words, so they are never quoted.
+    fn rows(&self) -> Vec<Vec<String>>;
+
+    /// JSON object of the update.
+    fn json(&self) -> Value;
+}
+
+impl Record for SymbolPriceUpdate {
+    const FEED: &'static str = "price";
+
+    fn columns() -> &'static [&'static str] {
+        &["update_id", "bid_price", "bid_qty", "ask_price", "ask_qty"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.id.to_string(),
+            self.bid.level().to_string(),
+            self.bid.qty().to_string(),
+            self.ask.level().to_string(),
+            self.ask.qty().to_string(),
+        ]]
+    }
+
+    fn json(&self) -> Value {
+        json!({
+            "update_id": self.id,
+            "bid": self.bid,
+            "ask": self.ask,
+        })
+    }
+}
+
+impl Record for SymbolDepthUpdate {
+    const FEED: &'static str = "depth";
+
+    fn columns() -> &'static [&'static str] {
+        &[
+            "first_update_id",
+            "final_update_id",
+            "previous_final_update_id",
+            "side",
+            "price",
+            "qty",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let bids = self.bids.iter().map(|order| ("bid", order));
+        let asks = self.asks.iter().map(|order| ("ask", order));
+        bids.chain(asks)
+            .map(|(side, order)| {
+                vec![
+                    self.first_update_id.to_string(),
+                    self.final_update_id.to_string(),
+                    // Spot updates have none, the column is left empty.
+                    self.previous_final_update_id
+                        .map(|id| id.to_string())
+                        .unwrap_or_default(),
+                    side.to_string(),
+                    order.level().to_string(),
+                    order.qty().to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    fn json(&self) -> Value {
+        json!({
+            "first_update_id": self.first_update_id,
+            "final_update_id": self.final_update_id,
+            "previous_final_update_id": self.previous_final_update_id,
+            "bids": self.bids,
+            "asks": self.asks,
+        })
+    }
+}
+
+impl Record for SymbolTradeUpdate {
+    const FEED: &'static str = "trades";
+
+    fn columns() -> &'static [&'static str] {
+        &["trade_id", "price", "qty", "trade_time", "is_buyer_maker"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.id.to_string(),
+            self.price.to_string(),
+            self.qty.to_string(),
+            self.time.to_string(),
+            self.is_buyer_maker.to_string(),
+        ]]
+    }
+
+    fn json(&self) -> Value {
+        json!({
+            "trade_id": self.id,
+            "price": self.price,
+            "qty": self.qty,
+            "trade_time": self.time,
+            "is_buyer_maker": self.is_buyer_maker,
+        })
+    }
+}
+
+/// Zstd stream of the compressed file. Lines of a frame are compressed with the context of the previous ones.
+struct Compressor {
+    encoder: zstd::Encoder<'static, Vec<u8>>,
+    level: i32,
+
+    /// Lines of the current frame.
+    lines: usize,
+}
+
+impl Compressor {
+    fn new(level: i32) -> io::Result<Self> {
+        Ok(Self {
+            encoder: zstd::Encoder::new(Vec::new(), level)?,
+            level,
+            lines: 0,
+        })
+    }
+
+    /// Compress the lines, ending the frame once it has `frame_lines` of them.
+    /// Returns the bytes of the stream that are ready to be written.
+    fn compress(&mut self, lines: &str, frame_lines: usize) -> io::Result<Vec<u8>> {
+        self.encoder.write_all(lines.as_bytes())?;
+        self.lines += lines.matches('\n').count();
+        if self.lines >= frame_lines {
+            return self.end_frame();
+        }
+        Ok(std::mem::take(self.encoder.get_mut()))
+    }
+
+    /// Write out the lines compressed so far, keeping the frame open. Returns the bytes to be written.
+    fn flush(&mut self) -> io::Result<Vec<u8>> {
+        self.encoder.flush()?;
+        Ok(std::mem::take(self.encoder.get_mut()))
+    }
+
+    /// End the current frame, returns the rest of its bytes. The next lines start a new frame.
+    fn end_frame(&mut self) -> io::Result<Vec<u8>> {
+        let next = zstd::Encoder::new(Vec::new(), self.level)?;
+        let bytes = std::mem::replace(&mut self.encoder, next).finish()?;
+        self.lines = 0;
+        Ok(bytes)
+    }
+}
+
+/// File the feed is currently recorded into.
+struct RecordFile {
+    file: File,
+    written: u64,
+
+    /// Index of the file within the recording, the next one is started once it is full.
+    index: u32,
+
+    /// Stream the lines are compressed with, none if the file is not compressed.
+    compressor: Option<Compressor>,
+}
+
+impl RecordFile {
+    /// Write the lines, compressed if the file is.
+    async fn write(&mut self, lines: String, frame_lines: usize) -> io::Result<()> {
+        let bytes = match self.compressor.as_mut() {
+            Some(compressor) => compressor.compress(&lines, frame_lines)?,
+            None => lines.into_bytes(),
+        };
+        self.write_bytes(&bytes).await
+    }
+
+    async fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if !bytes.is_empty() {
+            self.file.write_all(bytes).await?;
+            self.written += bytes.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Write out the lines compressed so far and sync the file to the disk.
+    async fn sync(&mut self) -> io::Result<()> {
+        if let Some(compressor) = self.compressor.as_mut() {
+            let bytes = compressor.flush()?;
+            self.write_bytes(&bytes).await?;
+        }
+        self.file.flush().await?;
+        self.file.sync_data().await
+    }
+
+    /// End the frame of the written lines and flush the file, so it is complete before it is left.
+    async fn finish(&mut self) -> io::Result<()> {
+        if let Some(compressor) = self.compressor.as_mut() {
+            if compressor.lines > 0 {
+                let bytes = compressor.end_frame()?;
+                self.write_bytes(&bytes).await?;
+            }
+        }
+        self.file.flush().await
+    }
+}
+
+/// Lines of the update, queued to be written into the files of its feed.
+struct RecordLines {
+    feed: &'static str,
+    columns: &'static [&'static str],
+    lines: String,
+}
+
+/// Writes the queued lines of the symbol into the files, the only owner of them.
+struct RecordWriter {
+    dir: PathBuf,
+    symbol: String,
+    format: RecordFormat,
+    max_file_size: u64,
+    compression_level: Option<i32>,
+    frame_lines: usize,
+
+    /// Moment the recording is started at, milliseconds since epoch. All its files are named by it.
+    started: u64,
+
+    files: HashMap<&'static str, RecordFile>,
+}
+
+impl RecordWriter {
+    fn new(cfg: &RecorderCfg, symbol: &str) -> Self {
+        Self {
+            dir: PathBuf::from(&cfg.dir),
+            symbol: symbol.to_ascii_uppercase(),
+            format: cfg.format,
+            max_file_size: cfg.max_file_size,
+            compression_level: cfg.compression_level,
+            frame_lines: cfg.frame_lines.max(1),
+            started: local_millis(),
+            files: Default::default(),
+        }
+    }
+
+    fn path(&self, feed: &str, index: u32) -> PathBuf {
+        let compressed = match self.compression_level {
+            Some(_) => ".zst",
+            None => "",
+        };
+        self.dir.join(format!(
+            "{}-{}-{}-{:04}.{}{}",
+            self.symbol,
+            feed,
+            self.started,
+            index,
+            self.format.extension(),
+            compressed
+        ))
+    }
+
+    async fn open(&self, feed: &str, columns: &[&str], index: u32) -> io::Result<RecordFile> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.path(feed, index);
+        debug!("Recording {} of {} into {:?}.", feed, self.symbol, path);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        let compressor = self.compression_level.map(Compressor::new).transpose()?;
+
+        let mut file = RecordFile {
+            file,
+            written: 0,
+            index,
+            compressor,
+        };
+        if self.format == RecordFormat::Csv {
+            let header = format!("received,{}\n", columns.join(","));
+            file.write(header, self.frame_lines).await?;
+        }
+        Ok(file)
+    }
+
+    /// Append the lines to the current file of the feed, start the next one if it is full.
+    async fn append(&mut self, record: RecordLines) -> io::Result<()> {
+        let index = match self.files.get_mut(record.feed) {
+            Some(current) if current.written < self.max_file_size => None,
+            Some(current) => {
+                current.finish().await?;
+                Some(current.index + 1)
+            }
+            None => Some(0),
+        };
+        if let Some(index) = index {
+            let file = self.open(record.feed, record.columns, index).await?;
+            self.files.insert(record.feed, file);
+        }
+
+        let current = self
+            .files
+            .get_mut(record.feed)
+            .expect("File of the feed is just opened.");
+        current.write(record.lines, self.frame_lines).await
+    }
+
+    async fn sync(&mut self) -> io::Result<()> {
+        for file in self.files.values_mut() {
+            file.sync().await?;
+        }
+        Ok(())
+    }
+
+    /// End the frames of the current files and close them.
+    async fn finish(&mut self) -> io::Result<()> {
+        for (_, mut file) in self.files.drain() {
+            file.finish().await?;
+        }
+        Ok(())
+    }
+
+    /// Write the queued lines until all the recorders of the queue are dropped, then finish the files.
+    async fn run(
+        mut self,
+        mut queue: mpsc::Receiver<RecordLines>,
+        fsync_interval: Option<u64>,
+    ) -> io::Result<()> {
+        let mut sync = fsync_interval.map(|period| {
+            let period = Duration::from_millis(period);
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+        loop {
+            tokio::select! {
+                record = queue.recv() => match record {
+                    Some(record) => self.append(record).await?,
+                    None => break,
+                },
+                _ = sync_tick(&mut sync) => self.sync().await?,
+            }
+        }
+        self.finish().await
+    }
+}
+
+/// Wait for the next sync of the files, never completes if they are not synced.
+async fn sync_tick(sync: &mut Option<Interval>) {
+    match sync.as_mut() {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => futures::future::pending().await,
+    }
+}
+
+/// Queues the received updates of the symbol, along with the moments they are received at, to be appended
+/// to the files by the writer.
+///
+/// Every update it is sent is recorded, so it is meant to be fed by a single worker per feed.
+#[derive(Clone)]
+pub struct Recorder {
+    symbol: String,
+    format: RecordFormat,
+    overflow: OverflowPolicy,
+    queue: mpsc::Sender<RecordLines>,
+
+    /// Updates dropped by the recorder and its clones, as the queue was full.
+    dropped: Arc<AtomicU64>,
+}
+
+impl Recorder {
+    /// Spawn the writer of the symbol's records. It finishes the files and stops once the recorder
+    /// and all its clones are dropped.
+    pub fn spawn(cfg: &RecorderCfg, symbol: &str) -> (Self, JoinHandle<BncResult<()>>) {
+        let writer = RecordWriter::new(cfg, symbol);
+        let (queue, receiver) = mpsc::channel(cfg.queue_capacity.max(1));
+        let symbol = writer.symbol.clone();
+        let fsync_interval = cfg.fsync_interval;
+        let task = spawn_storage(async move {
+            let symbol = writer.symbol.clone();
+            let written = writer.run(receiver, fsync_interval).await;
+            written.map_err(|err| {
+                warn!("Could not record {}. Error: {}", symbol, err);
+                BncError::DataTransmitError
+            })
+        });
+        let recorder = Self {
+            symbol,
+            format: cfg.format,
+            overflow: cfg.overflow,
+            queue,
+            dropped: Default::default(),
+        };
+        (recorder, task)
+    }
+
+    /// Updates dropped so far, as the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Lines of the update received at the moment.
+    fn encode<T: Record>(&self, data: &T, received: u64) -> String {
+        match self.format {
+            RecordFormat::Jsonl => {
+                let mut object = Map::new();
+                object.insert("received".into(), received.into());
+                if let Value::Object(fields) = data.json() {
+                    object.extend(fields);
+                }
+                format!("{}\n", Value::Object(object))
+            }
+            RecordFormat::Csv => data
+                .rows()
+                .into_iter()
+                .map(|row| format!("{},{}\n", received, row.join(",")))
+                .collect(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Record + Send + Sync + 'static> MessageSender<T> for Recorder {
+    /// Queue the update. Full queue holds the worker back or drops the update, as the overflow policy says.
+    async fn send(&self, data: T) -> BncResult<()> {
+        let record = RecordLines {
+            feed: T::FEED,
+            columns: T::columns(),
+            lines: self.encode(&data, local_millis()),
+        };
+        let queued = match self.overflow {
+            OverflowPolicy::Block => self.queue.send(record).await.is_ok(),
+            OverflowPolicy::Drop => match self.queue.try_send(record) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                    if dropped % DROPS_PER_WARNING == 1 {
+                        warn!(
+                            "Recording queue of {} is full, {} updates are dropped so far.",
+                            self.symbol, dropped
+                        );
+                    }
+                    true
+                }
+                Err(TrySendError::Closed(_)) => false,
+            },
+        };
+        // Queue is closed only once the writer has failed.
+        match queued {
+            true => Ok(()),
+            false => Err(BncError::DataTransmitError),
+        }
+    }
+}
+struct RecorderManagerCfg {
+    recorder: RecorderCfg,
+    ws_base_url: String,
+    endpoints: EndpointPool,
+    reconnect: ReconnectCfg,
+    update_speed: UpdateSpeed,
+}
+
+impl RecorderManagerCfg {
+    fn from_cfg(cfg: &RecorderCfg, ws: &WsCfg) -> Self {
+        Self {
+            recorder: cfg.clone(),
+            ws_base_url: ws.baseurl.primary().to_string(),
+            endpoints: EndpointPool::new(ws.baseurl.all()),
+            reconnect: ws.reconnect.clone(),
+            update_speed: ws.update_speed,
+        }
+    }
+}
+
+/// Schedules a worker per recorded feed of the symbol, each of them feeds the same [`Recorder`].
+pub struct RecorderManager {
+    cfg: RecorderManagerCfg,
+    tasks: TaskGroup,
+
+    /// Stops the scheduled tasks.
+    cancel: CancellationToken,
+
+    /// Updates dropped by the recorder of the symbol, as its queue was full.
+    dropped: Arc<AtomicU64>,
+}
+
+impl RecorderManager {
+    pub fn from_cfg(cfg: &RecorderCfg, ws: &WsCfg) -> Self {
+        Self {
+            cfg: RecorderManagerCfg::from_cfg(cfg, ws),
+            tasks: TaskGroup::new("recording"),
+            cancel: Default::default(),
+            dropped: Default::default(),
+        }
+    }
+
+    pub fn init(&mut self, symbol: &str) {
+        self.cancel = CancellationToken::new();
+        self.tasks = TaskGroup::new(format!("{} recording", symbol));
+        // Writer finishes the files once the workers are stopped and their recorders are dropped.
+        let (recorder, writer) = Recorder::spawn(&self.cfg.recorder, symbol);
+        self.tasks.adopt("writer", writer);
+        self.dropped = recorder.dropped.clone();
+        let reconnect = ReconnectPolicy::new(self.cfg.reconnect.clone(), None)
+            .with_endpoints(self.cfg.endpoints.clone())
+            .with_cancel(self.cancel.clone());
+        let worker = WsWorker::new(&self.cfg.ws_base_url).with_reconnect(reconnect);
+
+        debug!("Initialising workers of {} recording.", symbol);
+        if self.cfg.recorder.price {
+            let task = worker.price_updates_watcher(symbol, recorder.clone());
+            self.tasks.adopt("price worker", task);
+        }
+        if self.cfg.recorder.depth {
+            let task =
+                worker.depth_updates_watcher(symbol, self.cfg.update_speed, recorder.clone());
+            self.tasks.adopt("depth worker", task);
+        }
+        if self.cfg.recorder.trades {
+            let task = worker.trade_updates_watcher(symbol, recorder);
+            self.tasks.adopt("trades worker", task);
+        }
+    }
+
+    /// Let the workers close their connections and stop.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Wait for the stopped workers and the writer to finish. Returns the first error any of them failed with.
+    pub async fn join(&mut self) -> BncResult<()> {
+        self.tasks.join().await
+    }
+
+    /// Updates dropped so far, as the disk fell behind the feeds.
+    pub fn dropped_updates(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Amount of scheduled tasks that are still running.
+    pub fn alive_tasks(&self) -> usize {
+        self.tasks.alive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::data::InlineOrder;
+
+    fn order(level: &str, qty: &str) -> InlineOrder {
+        InlineOrder::new(level.parse().unwrap(), qty.parse().unwrap())
+    }
+
+    fn cfg(name: &str, format: RecordFormat, max_file_size: u64) -> RecorderCfg {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        RecorderCfg {
+            dir: dir.to_string_lossy().into_owned(),
+            format,
+            max_file_size,
+            ..Default::default()
+        }
+    }
+
+    /// Recorded files of the feed, in the order they are written.
+    fn files(cfg: &RecorderCfg, feed: &str) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&cfg.dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                name.starts_with(&format!("BTCUSDT-{}-", feed))
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    async fn record<T: Record + Send + Sync + 'static>(cfg: &RecorderCfg, updates: Vec<T>) {
+        let (recorder, writer) = Recorder::spawn(cfg, "btcusdt");
+        for update in updates {
+            recorder.send(update).await.unwrap();
+        }
+        drop(recorder);
+        writer.await.unwrap().unwrap();
+    }
+
+    fn price(id: u64) -> SymbolPriceUpdate {
+        SymbolPriceUpdate::new(id, order("99", "1"), order("101", "2"))
+    }
+
+    #[tokio::test]
+    async fn it_compresses_records_into_frames() {
+        let mut cfg = cfg("bnc-scraper-recorder-zstd-test", RecordFormat::Csv, 1 << 20);
+        cfg.compression_level = Some(3);
+        cfg.frame_lines = 100;
+        record(&cfg, (1..=250).map(price).collect()).await;
+
+        let files = files(&cfg, "price");
+        assert!(files[0].to_string_lossy().ends_with(".csv.zst"));
+        let compressed = std::fs::read(&files[0]).unwrap();
+        let content = zstd::decode_all(compressed.as_slice()).unwrap();
+        assert!(compressed.len() * 4 < content.len());
+        let lines: Vec<String> = String::from_utf8(content)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(lines.len(), 251);
+        assert!(lines[250].ends_with(",250,99,1,101,2"));
+    }
+
+    #[tokio::test]
+    async fn it_records_depth_rows_into_csv() {
+        let cfg = cfg("bnc-scraper-recorder-csv-test", RecordFormat::Csv, 1 << 20);
+        let update = SymbolDepthUpdate {
+            previous_final_update_id: Some(8),
+            first_update_id: 10,
+            final_update_id: 12,
+            bids: vec![order("99.5", "2")],
+            asks: vec![order("100.5", "0")],
+        };
+        record(&cfg, vec![update]).await;
+
+        let content = std::fs::read_to_string(&files(&cfg, "depth")[0]).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines[0],
+            "received,first_update_id,final_update_id,previous_final_update_id,side,price,qty"
+        );
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with(",10,12,8,bid,99.5,2"));
+        assert!(lines[2].ends_with(",10,12,8,ask,100.5,0"));
+    }
+
+    #[tokio::test]
+    async fn it_rotates_full_jsonl_files() {
+        let cfg = cfg("bnc-scraper-recorder-jsonl-test", RecordFormat::Jsonl, 1);
+        record(&cfg, vec![price(1), price(2)]).await;
+
+        let files = files(&cfg, "price");
+        assert_eq!(files.len(), 2);
+        for (file, id) in files.iter().zip([1, 2]) {
+            let content = std::fs::read_to_string(file).unwrap();
+            let line: Value = serde_json::from_str(content.trim_end()).unwrap();
+            assert_eq!(line["update_id"], id);
+            assert_eq!(line["ask"], json!(["101", "2"]));
+            assert!(line["received"].as_u64().is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_drops_updates_of_full_queue() {
+        let mut cfg = cfg(
+            "bnc-scraper-recorder-drop-test",
+            RecordFormat::Jsonl,
+            1 << 20,
+        );
+        cfg.queue_capacity = 1;
+        cfg.overflow = OverflowPolicy::Drop;
+        let (recorder, writer) = Recorder::spawn(&cfg, "btcusdt");
+        // Writer does not run until the test yields, so the queue is not drained meanwhile.
+        for id in 1..=3 {
+            recorder.send(price(id)).await.unwrap();
+        }
+        assert_eq!(recorder.dropped(), 2);
+        drop(recorder);
+        writer.await.unwrap().unwrap();
+
+        let content = std::fs::read_to_string(&files(&cfg, "price")[0]).unwrap();
+        assert_eq!(content.lines().count(), 1);
+    }
+}
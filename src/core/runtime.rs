@@ -0,0 +1,104 @@
+use derive_getters::Getters;
+use serde::Deserialize;
+use std::future::Future;
+use std::io;
+use std::sync::OnceLock;
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::task::JoinHandle;
+
+/// Tokio runtime settings.
+///
+/// Application needs both io and time drivers - disabling them makes sense only when the runtime is used
+/// to drive something else in the embedding application.
+#[derive(Getters, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RuntimeCfg {
+    /// Worker threads of the main runtime. Tokio's default(CPU count) is used if not set.
+    pub worker_threads: Option<usize>,
+
+    pub enable_io: bool,
+    pub enable_time: bool,
+
+    /// If set, storage tasks(e.g. flushing snapshots to disk) are run on a dedicated runtime with this many threads.
+    pub storage_threads: Option<usize>,
+}
+
+impl Default for RuntimeCfg {
+    fn default() -> Self {
+        Self {
+            worker_threads: None,
+            enable_io: true,
+            enable_time: true,
+            storage_threads: None,
+        }
+    }
+}
+
+impl RuntimeCfg {
+    fn builder(&self, worker_threads: Option<usize>, name: &str) -> Builder {
+        let mut builder = Builder::new_multi_thread();
+        builder.thread_name(name);
+        if let Some(threads) = worker_threads {
+            builder.worker_threads(threads);
+        }
+        if self.enable_io {
+            builder.enable_io();
+        }
+        if self.enable_time {
+            builder.enable_time();
+        }
+        builder
+    }
+
+    /// Build the main runtime.
+    pub fn build(&self) -> io::Result<Runtime> {
+        self.builder(self.worker_threads, "bnc-worker").build()
+    }
+
+    /// Build the dedicated storage runtime, if it is configured.
+    pub fn build_storage(&self) -> io::Result<Option<Runtime>> {
+        self.storage_threads
+            .map(|threads| self.builder(Some(threads), "bnc-storage").build())
+            .transpose()
+    }
+}
+
+static STORAGE_HANDLE: OnceLock<Handle> = OnceLock::new();
+
+/// Make all the following storage tasks run on the given runtime. Could be set only once.
+pub fn set_storage_handle(handle: Handle) -> bool {
+    STORAGE_HANDLE.set(handle).is_ok()
+}
+
+/// Spawn storage task - on the dedicated runtime if it is set, on the current one otherwise.
+pub fn spawn_storage<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match STORAGE_HANDLE.get() {
+        Some(handle) => handle.spawn(future),
+        None => tokio::task::spawn(future),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_builds_configured_runtimes() {
+        let cfg = RuntimeCfg {
+            worker_threads: Some(1),
+            storage_threads: Some(1),
+            ..Default::default()
+        };
+
+        let runtime = cfg.build().unwrap();
+        let storage = cfg.build_storage().unwrap().unwrap();
+
+        assert_eq!(runtime.block_on(async { 2 + 2 }), 4);
+        assert_eq!(storage.metrics().num_workers(), 1);
+        assert!(RuntimeCfg::default().build_storage().unwrap().is_none());
+    }
+}
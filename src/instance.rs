@@ -0,0 +1,245 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{ErrorKind, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long we wait for the control endpoint of another instance to answer.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Times the lockfile is read before its unreadable content is taken as stale, see [`read_record`].
+const RECORD_READS: usize = 5;
+
+/// Pause between the reads of the lockfile that is being written.
+const RECORD_READ_PAUSE: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct InstanceCfg {
+    /// Whether the instance lockfile is used to detect already running scraper.
+    pub lock: bool,
+
+    /// Path to the lockfile.
+    pub lock_path: String,
+}
+
+impl Default for InstanceCfg {
+    fn default() -> Self {
+        Self {
+            lock: false,
+            lock_path: "bnc-scraper.lock".into(),
+        }
+    }
+}
+
+/// Content of the lockfile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LockRecord {
+    pid: u32,
+
+    /// Control endpoint of the instance, if it is opened.
+    control: Option<SocketAddr>,
+}
+
+/// Another scraper that holds the lock.
+#[derive(Debug, Clone)]
+pub struct RunningInstance {
+    pub pid: u32,
+
+    /// Control endpoint commands could be sent to, see [`crate::control::send_command`].
+    pub control: Option<SocketAddr>,
+}
+
+/// Result of the attempt to lock the instance.
+#[derive(Debug)]
+pub enum LockOutcome {
+    Acquired(InstanceLock),
+    Running(RunningInstance),
+}
+
+/// Lockfile held by the running scraper. Removed once dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Lock the instance, unless a live scraper already holds the lock. Stale lockfiles are taken over.
+    ///
+    /// Lockfile is created exclusively, so of the scrapers started together only one acquires it.
+    /// Stale one is removed and the creation is retried once.
+    pub fn acquire(path: impl AsRef<Path>) -> io::Result<LockOutcome> {
+        let path = path.as_ref();
+        let mut taken_over = false;
+        loop {
+            match Self::create(path) {
+                Ok(lock) => {
+                    info!("Acquired instance lock {}.", path.display());
+                    return Ok(LockOutcome::Acquired(lock));
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists && !taken_over => {}
+                Err(err) => return Err(err),
+            }
+
+            match read_record(path)? {
+                Some(record) if is_alive(&record) => {
+                    return Ok(LockOutcome::Running(RunningInstance {
+                        pid: record.pid,
+                        control: record.control,
+                    }))
+                }
+                _ => warn!("Taking over stale instance lock {}.", path.display()),
+            }
+            match std::fs::remove_file(path) {
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+            taken_over = true;
+        }
+    }
+
+    /// Create the lockfile, failing with `AlreadyExists` if there is one.
+    fn create(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+        let lock = Self {
+            path: path.to_path_buf(),
+        };
+        file.write_all(lock_content(None).as_bytes())?;
+        Ok(lock)
+    }
+
+    /// Publish the control endpoint, so the next instances could attach to this one.
+    pub fn set_control(&self, control: SocketAddr) -> io::Result<()> {
+        self.write(Some(control))
+    }
+
+    fn write(&self, control: Option<SocketAddr>) -> io::Result<()> {
+        std::fs::write(&self.path, lock_content(control))
+    }
+}
+
+/// Lockfile content of this instance.
+fn lock_content(control: Option<SocketAddr>) -> String {
+    let record = LockRecord {
+        pid: std::process::id(),
+        control,
+    };
+    serde_json::to_string(&record).expect("Lock record is always serializable.")
+}
+
+/// Record of the existing lockfile, none if it is gone or its content stays unreadable.
+///
+/// Instance that has just created the lockfile may not have written it yet, so the unreadable content is re-read
+/// for a while before it is taken as stale.
+fn read_record(path: &Path) -> io::Result<Option<LockRecord>> {
+    for _ in 0..RECORD_READS {
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                if let Ok(record) = serde_json::from_str(&content) {
+                    return Ok(Some(record));
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        std::thread::sleep(RECORD_READ_PAUSE);
+    }
+    Ok(None)
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Could not remove instance lock {}. Error: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Whether the instance that wrote the record is still running.
+///
+/// Instance with the control endpoint is alive while the endpoint accepts connections, otherwise
+/// we rely on the process table, where it is available.
+fn is_alive(record: &LockRecord) -> bool {
+    if let Some(control) = record.control {
+        return TcpStream::connect_timeout(&control, PROBE_TIMEOUT).is_ok();
+    }
+    if record.pid == std::process::id() {
+        return false;
+    }
+    if cfg!(target_os = "linux") {
+        return Path::new("/proc").join(record.pid.to_string()).exists();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("bnc-scraper-{}.lock", name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn it_acquires_and_releases_lock() {
+        let path = lock_path("release");
+        let lock = InstanceLock::acquire(&path).unwrap();
+        assert!(matches!(lock, LockOutcome::Acquired(_)));
+        assert!(path.exists());
+
+        drop(lock);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn it_detects_running_instance() {
+        let path = lock_path("running");
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let record = LockRecord {
+            pid: 1,
+            control: Some(listener.local_addr().unwrap()),
+        };
+        std::fs::write(&path, serde_json::to_string(&record).unwrap()).unwrap();
+
+        match InstanceLock::acquire(&path).unwrap() {
+            LockOutcome::Running(instance) => assert_eq!(instance.control, record.control),
+            LockOutcome::Acquired(_) => panic!("Lock of the running instance was taken over."),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_takes_over_stale_lock() {
+        let path = lock_path("stale");
+        let control = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let record = LockRecord {
+            pid: 1,
+            control: Some(control),
+        };
+        std::fs::write(&path, serde_json::to_string(&record).unwrap()).unwrap();
+
+        let lock = InstanceLock::acquire(&path).unwrap();
+        assert!(matches!(lock, LockOutcome::Acquired(_)));
+        drop(lock);
+
+        // Lockfile that is never written is taken as stale too.
+        std::fs::write(&path, "").unwrap();
+        assert!(matches!(
+            InstanceLock::acquire(&path).unwrap(),
+            LockOutcome::Acquired(_)
+        ));
+    }
+}
@@ -1,5 +1,5 @@
 /// Module that holds core app's functionality - binance interaction, base models, etc
-mod core;
+pub mod core;
 
 /// General application's configuration;
 ///
@@ -10,6 +10,31 @@ pub mod config;
 pub mod ui;
 
 pub mod app;
+
+/// Embedding API - builds and controls scraping of the symbols without any UI.
+pub mod scraper;
+
+/// Remote control endpoint - lets external scripts drive a running application.
+pub mod control;
+
+/// Instance lockfile - detects already running scraper.
+pub mod instance;
+
+/// Subscription plan - websocket connections and streams the configuration would open.
+pub mod plan;
+
+/// Process signals - terminal hangups and termination requests.
+pub mod signals;
+
+/// Resource limits - caps on the scraped symbols, connections and memory the plan may take.
+pub mod limits;
+
+/// Build information - version, commit, build date and features of the binary.
+pub mod build_info;
+
+/// Command line interface - subcommands and their flags.
+pub mod cli;
+
 /// Utilities that absorbs ui, core and provide full-featured application.
 pub mod run;
 
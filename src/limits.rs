@@ -0,0 +1,91 @@
+use crate::core::bnc::error::{BncError, BncResult};
+use crate::plan::SubscriptionPlan;
+use serde::Deserialize;
+
+/// Caps of the resources the scraped symbols may take, so an accidental configuration can't exhaust a small host.
+///
+/// Nothing is capped unless it is set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LimitsCfg {
+    pub max_symbols: Option<usize>,
+
+    /// Websocket connections, counting the ones the scaled feeds may grow to.
+    pub max_connections: Option<u64>,
+
+    /// Megabytes of memory, as roughly estimated by the subscription plan.
+    pub max_memory_mb: Option<u64>,
+}
+
+impl LimitsCfg {
+    /// Check the plan against the limits. The error names the first limit it exceeds.
+    pub fn check(&self, plan: &SubscriptionPlan) -> BncResult<()> {
+        let symbols = plan.symbols.len();
+        if let Some(max) = self.max_symbols.filter(|max| symbols > *max) {
+            return Err(BncError::LimitExceeded(format!(
+                "{} symbols, limits.max_symbols is {}",
+                symbols, max
+            )));
+        }
+
+        let connections = plan.max_connections();
+        if let Some(max) = self.max_connections.filter(|max| connections > *max) {
+            return Err(BncError::LimitExceeded(format!(
+                "up to {} connections, limits.max_connections is {}",
+                connections, max
+            )));
+        }
+
+        let memory = plan.memory_estimate().div_ceil(1024 * 1024);
+        if let Some(max) = self.max_memory_mb.filter(|max| memory > *max) {
+            return Err(BncError::LimitExceeded(format!(
+                "~{} MiB of memory, limits.max_memory_mb is {}",
+                memory, max
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::ws::config::WsCfg;
+    use crate::ui::config::UICfg;
+
+    fn plan(symbols: &[&str]) -> SubscriptionPlan {
+        let symbols: Vec<String> = symbols.iter().map(|symbol| symbol.to_string()).collect();
+        SubscriptionPlan::new(&WsCfg::default(), &UICfg::default(), &symbols)
+    }
+
+    #[test]
+    fn it_refuses_plans_beyond_limits() {
+        let limits = LimitsCfg {
+            max_symbols: Some(2),
+            max_connections: Some(35),
+            max_memory_mb: Some(100),
+        };
+        assert!(LimitsCfg::default().check(&plan(&["A", "B", "C"])).is_ok());
+        assert!(limits.check(&plan(&["A", "B"])).is_ok());
+
+        let error = limits.check(&plan(&["A", "B", "C"])).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("3 symbols, limits.max_symbols is 2"));
+
+        let limits = LimitsCfg {
+            max_symbols: None,
+            ..limits
+        };
+        let error = limits.check(&plan(&["A", "B", "C"])).unwrap_err();
+        assert!(error.to_string().contains("limits.max_connections is 35"));
+
+        let limits = LimitsCfg {
+            max_connections: None,
+            max_memory_mb: Some(1),
+            ..limits
+        };
+        let error = limits.check(&plan(&["A"])).unwrap_err();
+        assert!(error.to_string().contains("limits.max_memory_mb is 1"));
+    }
+}
@@ -1,8 +1,26 @@
 use anyhow::Result;
-use bnc_scraper::run::run_with_ui;
+use bnc_scraper::cli::{Cli, Command};
+use bnc_scraper::config::AppCfg;
+use bnc_scraper::core::runtime::set_storage_handle;
+use bnc_scraper::run::{print_plan, run};
+use clap::Parser;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    run_with_ui().await?;
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let cfg = AppCfg::load_from(&cli.config)?;
+
+    // Plan is printed without connecting, so no runtime is needed.
+    if let Command::Plan { symbols } = &cli.command {
+        print_plan(&cfg, &symbols.line());
+        return Ok(());
+    }
+
+    let storage_runtime = cfg.runtime.build_storage()?;
+    if let Some(runtime) = storage_runtime.as_ref() {
+        set_storage_handle(runtime.handle().clone());
+    }
+
+    let runtime = cfg.runtime.build()?;
+    runtime.block_on(run(cfg, cli.command))?;
     Ok(())
 }
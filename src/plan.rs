@@ -0,0 +1,398 @@
+use crate::core::bnc::state::scaling::ScalingCfg;
+use crate::core::bnc::ws::config::{UpdateSpeed, WsCfg};
+use crate::core::bnc::ws::worker::depth::{depth_updates_stream, partial_depth_stream};
+use crate::core::bnc::ws::worker::mini_ticker::ALL_MINI_TICKERS_STREAM;
+use crate::core::bnc::ws::worker::price::book_ticker_stream;
+use crate::core::bnc::ws::worker::ticker::ticker_stream;
+use crate::core::bnc::ws::worker::trade::agg_trade_stream;
+use crate::core::recorder::RecorderCfg;
+use crate::ui::config::UICfg;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+/// Connections binance accepts from a single IP within 5 minutes.
+const MAX_CONNECTIONS: u64 = 300;
+
+/// Streams binance accepts within a single connection.
+const MAX_STREAMS_PER_CONNECTION: usize = 1024;
+
+/// Rough memory taken by the state of a symbol - its book, histories and channels.
+const SYMBOL_MEMORY: u64 = 2 * 1024 * 1024;
+
+/// Rough memory taken by a connection - its socket, tls and frame buffers and the worker.
+const CONNECTION_MEMORY: u64 = 256 * 1024;
+
+/// Streams of a single feed, opened over a connection by each of its redundant workers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedFeed {
+    /// What the feed is for, e.g. `BTCUSDT book`.
+    pub purpose: String,
+    pub streams: Vec<String>,
+
+    /// Connections opened at the start.
+    pub connections: u64,
+
+    /// Connections the feed may grow to, if its workers are scaled.
+    pub max_connections: u64,
+
+    /// Expected messages per second of a single connection, none for the streams pushed on every change.
+    pub rate: Option<f64>,
+}
+
+/// Websocket connections the application would open for the configuration and the symbols.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubscriptionPlan {
+    pub symbols: Vec<String>,
+    pub feeds: Vec<PlannedFeed>,
+}
+
+/// Initial and maximal amount of the feed's workers.
+fn workers(scaling: &ScalingCfg, configured: u64) -> (u64, u64) {
+    if scaling.enabled {
+        (
+            scaling.initial_workers(configured),
+            scaling.max_workers.max(1),
+        )
+    } else {
+        (configured, configured)
+    }
+}
+
+fn price_feed(ws: &WsCfg, purpose: String, symbol: &str) -> PlannedFeed {
+    let (connections, max_connections) = workers(&ws.scaling, ws.price_workers_count());
+    PlannedFeed {
+        purpose,
+        streams: vec![book_ticker_stream(symbol)],
+        connections,
+        max_connections,
+        rate: None,
+    }
+}
+
+fn trades_feed(ws: &WsCfg, symbol: &str) -> PlannedFeed {
+    let (connections, max_connections) = workers(&ws.scaling, ws.trade_workers_count());
+    PlannedFeed {
+        purpose: format!("{} trades", symbol),
+        streams: vec![agg_trade_stream(symbol)],
+        connections,
+        max_connections,
+        rate: None,
+    }
+}
+
+fn book_feed(ws: &WsCfg, symbol: &str) -> PlannedFeed {
+    let (connections, max_connections) = workers(&ws.scaling, ws.depth_workers_count());
+    let (stream, rate) = match ws.partial_depth {
+        Some(levels) => (partial_depth_stream(symbol, levels), 1.0),
+        None => match ws.update_speed {
+            UpdateSpeed::Normal => (depth_updates_stream(symbol, ws.update_speed), 1.0),
+            UpdateSpeed::Fast => (depth_updates_stream(symbol, ws.update_speed), 10.0),
+        },
+    };
+    PlannedFeed {
+        purpose: format!("{} book", symbol),
+        streams: vec![stream],
+        connections,
+        max_connections,
+        rate: Some(rate),
+    }
+}
+
+/// Single connection of the recorder's worker, recorders are never redundant.
+fn recorder_feed(purpose: String, stream: String, rate: Option<f64>) -> PlannedFeed {
+    PlannedFeed {
+        purpose,
+        streams: vec![stream],
+        connections: 1,
+        max_connections: 1,
+        rate,
+    }
+}
+
+impl SubscriptionPlan {
+    /// Plan the feeds of the symbols and of the optional panels, in the order they are started.
+    pub fn new(ws: &WsCfg, ui: &UICfg, symbols: &[String]) -> Self {
+        let mut feeds = vec![];
+        for symbol in symbols {
+            feeds.push(book_feed(ws, symbol));
+            feeds.push(price_feed(ws, format!("{} best price", symbol), symbol));
+            feeds.push(trades_feed(ws, symbol));
+        }
+
+        if let Some(conversion) = ui.conversion.as_ref() {
+            let pair = conversion.pair.to_ascii_uppercase();
+            feeds.push(price_feed(ws, format!("{} conversion", pair), &pair));
+        }
+        if let Some(portfolio) = ui.portfolio.as_ref() {
+            // Holdings share a single combined connection.
+            let quote = portfolio.quote.to_ascii_uppercase();
+            let mut streams = vec![];
+            for holding in portfolio.holdings.iter() {
+                let asset = holding.asset.to_ascii_uppercase();
+                let stream = book_ticker_stream(&format!("{}{}", asset, quote));
+                if asset != quote && !streams.contains(&stream) {
+                    streams.push(stream);
+                }
+            }
+            if !streams.is_empty() {
+                feeds.push(PlannedFeed {
+                    purpose: "portfolio".into(),
+                    streams,
+                    connections: 1,
+                    max_connections: 1,
+                    rate: None,
+                });
+            }
+        }
+        if ui.ticker_strip {
+            feeds.push(PlannedFeed {
+                purpose: "ticker strip".into(),
+                streams: vec![ALL_MINI_TICKERS_STREAM.into()],
+                connections: 1,
+                max_connections: 1,
+                rate: Some(1.0),
+            });
+        }
+        // Displayed symbol is switched over the same connection, the first one is displayed at the start.
+        if let Some(symbol) = symbols.first().filter(|_| ui.stats) {
+            feeds.push(PlannedFeed {
+                purpose: "statistics".into(),
+                streams: vec![ticker_stream(symbol)],
+                connections: 1,
+                max_connections: 1,
+                rate: Some(1.0),
+            });
+        }
+
+        Self {
+            symbols: symbols.to_vec(),
+            feeds,
+        }
+    }
+
+    /// Plan of the recorder alone, see [`SubscriptionPlan::with_recording`].
+    pub fn recording(ws: &WsCfg, recorder: &RecorderCfg, symbols: &[String]) -> Self {
+        Self {
+            symbols: symbols.to_vec(),
+            feeds: vec![],
+        }
+        .with_recording(ws, recorder)
+    }
+
+    /// Add the connections of the recorder, it opens its own one per recorded feed of each symbol.
+    pub fn with_recording(mut self, ws: &WsCfg, recorder: &RecorderCfg) -> Self {
+        for symbol in self.symbols.iter() {
+            if recorder.price {
+                let stream = book_ticker_stream(symbol);
+                let feed = recorder_feed(format!("{} price recording", symbol), stream, None);
+                self.feeds.push(feed);
+            }
+            if recorder.depth {
+                let rate = match ws.update_speed {
+                    UpdateSpeed::Normal => 1.0,
+                    UpdateSpeed::Fast => 10.0,
+                };
+                let stream = depth_updates_stream(symbol, ws.update_speed);
+                let feed = recorder_feed(format!("{} depth recording", symbol), stream, Some(rate));
+                self.feeds.push(feed);
+            }
+            if recorder.trades {
+                let stream = agg_trade_stream(symbol);
+                let feed = recorder_feed(format!("{} trades recording", symbol), stream, None);
+                self.feeds.push(feed);
+            }
+        }
+        self
+    }
+
+    pub fn connections(&self) -> u64 {
+        self.feeds.iter().map(|feed| feed.connections).sum()
+    }
+
+    pub fn max_connections(&self) -> u64 {
+        self.feeds.iter().map(|feed| feed.max_connections).sum()
+    }
+
+    /// Distinct streams of all the feeds.
+    pub fn streams(&self) -> usize {
+        let streams: HashSet<&String> = self.feeds.iter().flat_map(|feed| &feed.streams).collect();
+        streams.len()
+    }
+
+    /// Rough bytes of memory the plan takes, if all its feeds are scaled up.
+    pub fn memory_estimate(&self) -> u64 {
+        self.symbols.len() as u64 * SYMBOL_MEMORY + self.max_connections() * CONNECTION_MEMORY
+    }
+
+    /// Expected messages per second of all the connections with known rates.
+    pub fn rate(&self) -> f64 {
+        self.feeds
+            .iter()
+            .filter_map(|feed| feed.rate.map(|rate| rate * feed.connections as f64))
+            .sum()
+    }
+
+    /// Binance limits the plan would break.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if self.max_connections() > MAX_CONNECTIONS {
+            warnings.push(format!(
+                "up to {} connections exceed the limit of {} per 5 minutes per IP, some of them would be refused",
+                self.max_connections(),
+                MAX_CONNECTIONS
+            ));
+        }
+        for feed in self.feeds.iter() {
+            if feed.streams.len() > MAX_STREAMS_PER_CONNECTION {
+                warnings.push(format!(
+                    "{} has {} streams, the limit is {} per connection",
+                    feed.purpose,
+                    feed.streams.len(),
+                    MAX_STREAMS_PER_CONNECTION
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+impl Display for SubscriptionPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Subscription plan of {}:", self.symbols.join(", "))?;
+        for feed in self.feeds.iter() {
+            let connections = if feed.max_connections > feed.connections {
+                format!("{}(up to {})", feed.connections, feed.max_connections)
+            } else {
+                feed.connections.to_string()
+            };
+            let rate = match feed.rate {
+                Some(rate) => format!("~{}/s each", rate),
+                None => "on every change".into(),
+            };
+            writeln!(
+                f,
+                "  {}: {} connection(s) of [{}], {}",
+                feed.purpose,
+                connections,
+                feed.streams.join(", "),
+                rate
+            )?;
+        }
+        writeln!(
+            f,
+            "Total: {} connection(s), up to {} if scaled; {} distinct stream(s); ~{}/s plus the ones pushed on every change; ~{} MiB of memory.",
+            self.connections(),
+            self.max_connections(),
+            self.streams(),
+            self.rate(),
+            self.memory_estimate().div_ceil(1024 * 1024)
+        )?;
+        for warning in self.warnings() {
+            writeln!(f, "Warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bnc::state::portfolio::{HoldingCfg, PortfolioCfg};
+
+    fn symbols() -> Vec<String> {
+        vec!["BTCUSDT".into(), "ETHUSDT".into()]
+    }
+
+    #[test]
+    fn it_plans_feeds_of_symbols_and_panels() {
+        let ws = WsCfg {
+            workers: 2,
+            depth_workers: Some(3),
+            update_speed: UpdateSpeed::Fast,
+            ..Default::default()
+        };
+        let ui = UICfg {
+            portfolio: Some(PortfolioCfg {
+                quote: "USDT".into(),
+                holdings: vec![
+                    HoldingCfg {
+                        asset: "btc".into(),
+                        amount: "1".into(),
+                    },
+                    HoldingCfg {
+                        asset: "USDT".into(),
+                        amount: "1".into(),
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+
+        let plan = SubscriptionPlan::new(&ws, &ui, &symbols());
+        let purposes: Vec<&str> = plan
+            .feeds
+            .iter()
+            .map(|feed| feed.purpose.as_str())
+            .collect();
+        assert_eq!(
+            purposes,
+            [
+                "BTCUSDT book",
+                "BTCUSDT best price",
+                "BTCUSDT trades",
+                "ETHUSDT book",
+                "ETHUSDT best price",
+                "ETHUSDT trades",
+                "portfolio",
+                "ticker strip",
+                "statistics"
+            ]
+        );
+        assert_eq!(plan.feeds[0].streams, ["btcusdt@depth@100ms"]);
+        assert_eq!(plan.connections(), 3 + 2 + 2 + 3 + 2 + 2 + 1 + 1 + 1);
+        // Holding of BTCUSDT shares the stream with the symbol.
+        assert_eq!(plan.streams(), 8);
+        assert_eq!(plan.rate(), 10.0 * 3.0 * 2.0 + 1.0 + 1.0);
+        assert!(plan.warnings().is_empty());
+    }
+
+    #[test]
+    fn it_warns_about_connections_limit() {
+        let ws = WsCfg {
+            scaling: ScalingCfg {
+                enabled: true,
+                max_workers: 100,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let plan = SubscriptionPlan::new(&ws, &UICfg::default(), &symbols());
+
+        assert_eq!(plan.connections(), 5 * 6 + 2);
+        assert_eq!(plan.max_connections(), 100 * 6 + 2);
+        assert_eq!(plan.warnings().len(), 1);
+        assert!(plan.to_string().contains("Warning: up to 602 connections"));
+    }
+
+    #[test]
+    fn it_plans_recorder_connections() {
+        let ws = WsCfg::default();
+        let recorder = RecorderCfg {
+            trades: false,
+            ..Default::default()
+        };
+        let plan = SubscriptionPlan::new(&ws, &UICfg::default(), &symbols());
+        let recorded = plan.clone().with_recording(&ws, &recorder);
+        assert_eq!(recorded.connections(), plan.connections() + 2 * 2);
+        assert_eq!(
+            recorded.feeds.last().unwrap().purpose,
+            "ETHUSDT depth recording"
+        );
+        // Recorder shares the streams with the symbols' own feeds.
+        assert_eq!(recorded.streams(), plan.streams());
+
+        let plan = SubscriptionPlan::recording(&ws, &RecorderCfg::default(), &symbols());
+        assert_eq!(plan.connections(), 3 * 2);
+        assert_eq!(plan.streams(), 3 * 2);
+    }
+}
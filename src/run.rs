@@ -1,65 +1,493 @@
-use crate::app::App;
+use crate::app::{App, SHUTDOWN_GRACE};
+use crate::cli::{Command, SymbolsArgs};
 use crate::config::AppCfg;
+use crate::control::{send_command, ControlCommand, ControlMessage, ControlServer};
+use crate::core::bnc::replay::REPLAY_SCHEME;
+use crate::core::bnc::rest::BncRestClient;
+use crate::core::bnc::sink::NdjsonSink;
+use crate::core::bnc::snapshot::SnapshotFetcher;
 use crate::core::logging::setup_logger;
+use crate::core::recorder::RecorderManager;
+use crate::instance::{InstanceLock, LockOutcome, RunningInstance};
+use crate::plan::SubscriptionPlan;
+use crate::scraper::ScraperBuilder;
+use crate::signals::{Signal, Signals};
+use crate::ui::budget::RenderBudget;
+use crate::ui::config::UICfg;
+use crate::ui::draw_app;
 use crate::ui::runner::{UiController, UiRunner};
+use crate::ui::AppFrame;
 use anyhow::Result;
 use crossterm::event;
-use crossterm::event::{Event, KeyCode, KeyModifiers};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 
-use log::info;
-use std::io::Stdout;
+use log::{info, warn};
+use serde_json::json;
+use std::io::{Stdout, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
 use std::time::{Duration, Instant};
 
+use tokio::sync::{mpsc, watch};
 use tui::backend::{Backend, CrosstermBackend};
-use tui::Terminal;
-
-pub fn read_symbol() -> Result<String> {
-    println!("Write symbol you are going to scrap(empty for BTCUSDT): ");
-    let symbol = std::io::stdin()
-        .lines()
-        .next()
-        .expect("You have not provided symbol.")?;
-    Ok(if symbol.is_empty() {
-        "BTCUSDT".to_string()
-    } else {
-        symbol
+
+/// Run the parsed command of the command line. Plan is printed without the runtime, see [`print_plan`].
+pub async fn run(mut cfg: AppCfg, command: Command) -> Result<()> {
+    match command {
+        Command::Tui {
+            symbols,
+            record,
+            tick_rate,
+        } => {
+            if let Some(dir) = record {
+                cfg.recorder.enabled = true;
+                cfg.recorder.dir = dir;
+            }
+            if let Some(tick_rate) = tick_rate {
+                cfg.ui.tick_rate = tick_rate;
+            }
+            run_with_ui(cfg, &ui_symbols(&symbols)?).await
+        }
+        Command::Record {
+            symbols,
+            output,
+            format,
+        } => {
+            if let Some(dir) = output {
+                cfg.recorder.dir = dir;
+            }
+            if let Some(format) = format {
+                cfg.recorder.format = format.into();
+            }
+            run_recorder(cfg, &symbols.line()).await
+        }
+        Command::Replay { symbols, input } => {
+            replay_from(&mut cfg, &input);
+            run_with_ui(cfg, &ui_symbols(&symbols)?).await
+        }
+        Command::Snapshot { symbols, output } => {
+            run_snapshot(cfg, &symbols.line(), output.as_deref()).await
+        }
+        Command::Headless { symbols, output } => {
+            run_headless(cfg, &symbols.line(), output.as_deref()).await
+        }
+        Command::Plan { symbols } => {
+            print_plan(&cfg, &symbols.line());
+            Ok(())
+        }
+    }
+}
+
+/// Symbols of the UI as a single line - the given ones, or the ones typed in if none are given.
+fn ui_symbols(symbols: &SymbolsArgs) -> Result<String> {
+    if !symbols.is_empty() {
+        return Ok(symbols.line());
+    }
+    println!(
+        "Write symbols you are going to scrap, separated by spaces or commas(empty for BTCUSDT): "
+    );
+    match std::io::stdin().lines().next() {
+        Some(line) => Ok(line?),
+        None => {
+            info!("Input is closed, default symbol is scraped.");
+            Ok(String::new())
+        }
+    }
+}
+
+/// Writer of the command's output - the file, if it is given, or stdout.
+fn output(path: Option<&Path>) -> Result<Box<dyn Write + Send>> {
+    Ok(match path {
+        Some(path) => Box::new(std::fs::File::create(path)?),
+        None => Box::new(std::io::stdout()),
+    })
+}
+
+/// Print the subscription plan of the symbols listed in the line, instead of connecting.
+pub fn print_plan(cfg: &AppCfg, line: &str) {
+    let ws = &cfg.core.bnc.ws;
+    let mut plan = SubscriptionPlan::new(ws, &cfg.ui, &parse_symbols(line));
+    if cfg.recorder.enabled {
+        plan = plan.with_recording(ws, &cfg.recorder);
+    }
+    print!("{}", plan);
+}
+
+/// Serve the market data from the recordings in the directory instead of binance, to review the past session offline.
+///
+/// Nothing replayed is persisted - neither recorded again nor cached - and the running instance is not attached to.
+pub fn replay_from(cfg: &mut AppCfg, dir: &str) {
+    let base_url = format!("{}{}", REPLAY_SCHEME, dir);
+    cfg.core.bnc.baseurl = base_url.clone();
+    cfg.core.bnc.fallback_baseurls.clear();
+    cfg.core.bnc.ws.baseurl = base_url.as_str().into();
+    cfg.core.bnc.cache.enabled = false;
+    cfg.recorder.enabled = false;
+    cfg.instance.lock = false;
+}
+
+/// Fetch the order book snapshots of the symbols listed in the line, write them as JSON lines
+/// into the file or to stdout.
+pub async fn run_snapshot(cfg: AppCfg, line: &str, path: Option<&Path>) -> Result<()> {
+    setup_logger(&cfg.logging)?;
+
+    let client = BncRestClient::from_cfg(&cfg.core.bnc)?;
+    let mut writer = output(path)?;
+    for symbol in parse_symbols(line) {
+        let snapshot = client.fetch_snapshot(&symbol).await?;
+        info!(
+            "Fetched snapshot of {}, last update id {}.",
+            symbol, snapshot.last_update_id
+        );
+        writeln!(
+            writer,
+            "{}",
+            json!({ "symbol": symbol, "snapshot": snapshot })
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Scrape the symbols listed in the line without any UI, writing every change of their best prices and books
+/// as a JSON line into the file or to stdout, until Ctrl+C is pressed or it is terminated.
+///
+/// Logs are written to the log file only, so the output could be piped as is, e.g. into `jq`.
+pub async fn run_headless(cfg: AppCfg, line: &str, path: Option<&Path>) -> Result<()> {
+    setup_logger(&cfg.logging)?;
+
+    let symbols = parse_symbols(line);
+    let bare_ui = UICfg {
+        conversion: None,
+        portfolio: None,
+        ticker_strip: false,
+        stats: false,
+        ..Default::default()
+    };
+    cfg.limits
+        .check(&SubscriptionPlan::new(&cfg.core.bnc.ws, &bare_ui, &symbols))?;
+    let mut scraper = ScraperBuilder::from_cfg(cfg.core.bnc.clone())
+        .symbols(symbols.clone())
+        .build();
+    scraper.start().await?;
+    let writer = Arc::new(Mutex::new(output(path)?));
+    for symbol in symbols.iter() {
+        scraper.attach_price_sink(symbol, NdjsonSink::new(writer.clone(), symbol, "price"));
+        scraper.attach_book_sink(symbol, NdjsonSink::new(writer.clone(), symbol, "book"));
+    }
+    info!("Streaming symbols: {}.", symbols.join(", "));
+
+    let mut signals = Signals::listen()?;
+    while signals.recv().await? == Signal::Hangup {
+        warn!("Terminal is closed, streaming carries on until terminated.");
+    }
+
+    let finished = tokio::time::timeout(SHUTDOWN_GRACE, scraper.shutdown()).await;
+    match finished {
+        Ok(Err(err)) => warn!("Streaming finished with error: {}", err),
+        Err(_) => warn!("Streaming did not stop within {:?}.", SHUTDOWN_GRACE),
+        Ok(Ok(())) => {}
+    }
+    Ok(())
+}
+
+/// Record the symbols listed in the line without any UI, until Ctrl+C is pressed or it is terminated.
+///
+/// Closing the terminal does not interrupt the recording.
+pub async fn run_recorder(cfg: AppCfg, line: &str) -> Result<()> {
+    setup_logger(&cfg.logging)?;
+
+    let symbols = parse_symbols(line);
+    cfg.limits.check(&SubscriptionPlan::recording(
+        &cfg.core.bnc.ws,
+        &cfg.recorder,
+        &symbols,
+    ))?;
+    let mut recorders: Vec<(String, RecorderManager)> = symbols
+        .into_iter()
+        .map(|symbol| {
+            let mut recorder = RecorderManager::from_cfg(&cfg.recorder, &cfg.core.bnc.ws);
+            recorder.init(&symbol);
+            (symbol, recorder)
+        })
+        .collect();
+    let recorded: Vec<&str> = recorders
+        .iter()
+        .map(|(symbol, _)| symbol.as_str())
+        .collect();
+    info!("Recording symbols: {}.", recorded.join(", "));
+    println!(
+        "Recording {} into {:?}, press Ctrl+C to stop.",
+        recorded.join(", "),
+        cfg.recorder.dir
+    );
+
+    let mut signals = Signals::listen()?;
+    while signals.recv().await? == Signal::Hangup {
+        warn!("Terminal is closed, recording carries on until terminated.");
+    }
+
+    recorders.iter().for_each(|(_, recorder)| recorder.stop());
+    let finished = tokio::time::timeout(SHUTDOWN_GRACE, async {
+        for (symbol, recorder) in recorders.iter_mut() {
+            if let Err(err) = recorder.join().await {
+                warn!("Recording of {} failed: {}", symbol, err);
+            }
+        }
+    })
+    .await;
+    if finished.is_err() {
+        warn!("Recording did not stop within {:?}.", SHUTDOWN_GRACE);
+    }
+    Ok(())
+}
+
+/// Split user's input into the symbols, defaults to BTCUSDT.
+fn parse_symbols(line: &str) -> Vec<String> {
+    let mut symbols: Vec<String> = vec![];
+    for symbol in line.split(|char: char| char == ',' || char.is_whitespace()) {
+        let symbol = symbol.to_ascii_uppercase();
+        if !symbol.is_empty() && !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        symbols.push("BTCUSDT".to_string());
+    }
+    symbols
+}
+
+/// Ask user a yes/no question, empty answer is the default one. So is the closed input, e.g. of a script.
+fn confirm(question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    println!("{} {}", question, hint);
+    let answer = match std::io::stdin().lines().next() {
+        Some(answer) => answer?,
+        None => {
+            info!("Input is closed, answered {} by default.", default);
+            return Ok(default);
+        }
+    };
+    Ok(match answer.trim() {
+        "" => default,
+        answer => answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"),
     })
 }
 
-/// Run application with UI. Use it from binaries directly.
-pub async fn run_with_ui() -> Result<()> {
-    let cfg = AppCfg::load()?;
+/// Offer to hand the symbols over to the already running instance.
+///
+/// Returns true if the running instance took them, so this one should not start.
+async fn attach(
+    instance: &RunningInstance,
+    token: Option<&str>,
+    symbols: &[String],
+) -> Result<bool> {
+    let control = match instance.control {
+        Some(control) => control,
+        None => {
+            let question = format!(
+                "Scraper is already running(pid {}) without control endpoint. Start another one anyway?",
+                instance.pid
+            );
+            return Ok(!confirm(&question, false)?);
+        }
+    };
+
+    let question = format!(
+        "Scraper is already running(pid {}). Add {} to it instead of starting another one?",
+        instance.pid,
+        symbols.join(", ")
+    );
+    if !confirm(&question, true)? {
+        return Ok(false);
+    }
+
+    for symbol in symbols {
+        let command = ControlCommand::AddSymbol {
+            symbol: symbol.clone(),
+        };
+        let reply = send_command(control, token, &command).await?;
+        match reply.error {
+            None => println!("Running scraper added {}.", symbol),
+            Some(err) => println!("Running scraper refused to add {}: {}", symbol, err),
+        }
+    }
+    Ok(true)
+}
+
+/// Run application with UI for the symbols listed in the line. Use it from binaries directly.
+pub async fn run_with_ui(cfg: AppCfg, line: &str) -> Result<()> {
     setup_logger(&cfg.logging)?;
 
-    let symbol = read_symbol()?;
-    info!("User chose symbol: {}.", symbol);
+    let symbols = parse_symbols(line);
+    info!("User chose symbols: {}.", symbols.join(", "));
+
+    let mut lock = None;
+    if cfg.instance.lock {
+        match InstanceLock::acquire(&cfg.instance.lock_path)? {
+            LockOutcome::Acquired(acquired) => lock = Some(acquired),
+            LockOutcome::Running(instance) => {
+                if attach(&instance, cfg.control.token.as_deref(), &symbols).await? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
     let tick_rate = Duration::from_millis(cfg.ui.tick_rate);
-    let mut app = App::new(&cfg, symbol);
+    let frame_budget = Duration::from_millis(cfg.ui.frame_budget);
+    let book_depth = cfg.core.bnc.book_depth;
+    let mut app = App::new(&cfg, symbols);
 
     app.init().await?;
 
+    // Without the endpoint the sender is dropped right away, so no commands are ever received.
+    let (_control, commands) = if cfg.control.enabled {
+        let (server, commands) = ControlServer::start(&cfg.control).await?;
+        if let Some(lock) = lock.as_ref() {
+            lock.set_control(server.address())?;
+        }
+        (Some(server), commands)
+    } else {
+        (None, mpsc::channel(1).1)
+    };
+
     //.. And only after that we initialise UI.
-    let mut runner: UiRunner<CrosstermBackend<Stdout>> = UiRunner::new()?;
+    let runner: UiRunner<CrosstermBackend<Stdout>> = UiRunner::new()?;
 
-    run_app(&mut runner.terminal, app, tick_rate).await?;
+    // Rendering lives on its own thread, so bursts of data tasks can't starve it.
+    let (frames_tx, frames_rx) = watch::channel(app.frame());
+    let (keys_tx, keys_rx) = mpsc::unbounded_channel();
+    let render_thread = std::thread::Builder::new()
+        .name("bnc-render".into())
+        .spawn(move || {
+            render_loop(
+                runner,
+                frames_rx,
+                keys_tx,
+                tick_rate,
+                frame_budget,
+                book_depth,
+            )
+        })?;
 
-    runner.finalize()?;
+    let exit = run_app(app, frames_tx, keys_rx, commands, tick_rate).await;
+
+    let runner = render_thread
+        .join()
+        .expect("Render thread panicked, terminal is probably corrupted.");
+    if let Ok(RunExit::Headless) = exit {
+        // Terminal is gone, there is nothing to restore or print to.
+        if let Ok(mut runner) = runner {
+            let _ = runner.finalize();
+        }
+        info!("Headless recording is finished.");
+        return Ok(());
+    }
+    runner?.finalize()?;
+    exit?;
 
     println!("Thx for using that garbage! Cya!");
 
     Ok(())
 }
 
-pub async fn run_app<B: Backend>(
-    terminal: &mut Terminal<B>,
+/// The way the application is finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunExit {
+    /// User quit, the terminal is to be restored.
+    Quit,
+
+    /// Terminal is closed while recording, the application carried on without the UI until terminated.
+    Headless,
+}
+
+/// Drive the application - process user's input and remote commands, publish prepared frames to the render loop.
+///
+/// Finishes when the application quits or the render loop is gone. If the terminal is closed while the symbols
+/// are recorded, the application carries on headless - recording and serving the control endpoint -
+/// until it is terminated.
+pub async fn run_app(
     mut app: App<'_>,
+    frames: watch::Sender<AppFrame>,
+    mut keys: mpsc::UnboundedReceiver<KeyEvent>,
+    mut commands: mpsc::Receiver<ControlMessage>,
     tick_rate: Duration,
-) -> Result<()> {
-    let last_tick = Instant::now();
+) -> Result<RunExit> {
+    let mut interval = tokio::time::interval(tick_rate);
+    let mut signals = Signals::listen()?;
+    let mut exit = RunExit::Quit;
 
     loop {
-        terminal.draw(|f| app.draw(f))?;
+        let headless = exit == RunExit::Headless;
+        let mut ui_lost = false;
+        tokio::select! {
+            key = keys.recv(), if !headless => match key {
+                // Finalize an application if CTRL + C/c is pressed.
+                Some(key) => match (key.modifiers, key.code) {
+                    (KeyModifiers::CONTROL, KeyCode::Char('c'))
+                    | (KeyModifiers::CONTROL, KeyCode::Char('C')) => app.finalize().await?,
+                    _ => app.on_key(key).await,
+                },
+                None => ui_lost = true,
+            },
+            Some((command, reply)) = commands.recv() => {
+                let _ = reply.send(app.on_command(command).await);
+            },
+            signal = signals.recv() => match signal? {
+                Signal::Hangup => ui_lost = true,
+                Signal::Terminate => {
+                    info!("Application is terminated.");
+                    app.finalize().await?;
+                }
+            },
+            _ = interval.tick() => {}
+        }
+
+        app.on_tick();
+
+        if app.should_quit() {
+            return Ok(exit);
+        }
+
+        if !headless && (ui_lost || frames.send(app.frame()).is_err()) {
+            if !app.is_recording() {
+                app.finalize().await?;
+                return Ok(exit);
+            }
+            warn!(
+                "Terminal is closed, carrying on recording {} without the UI until terminated.",
+                app.symbols().join(", ")
+            );
+            exit = RunExit::Headless;
+        }
+    }
+}
+
+/// Draw the latest frame each tick and forward user's input to the application.
+///
+/// Draw time of each frame is measured, detail is reduced while frames don't fit into the budget.
+///
+/// Runs until the application drops its frames sender; returns the runner to be finalized.
+pub fn render_loop<B: Backend>(
+    mut runner: UiRunner<B>,
+    mut frames: watch::Receiver<AppFrame>,
+    keys: mpsc::UnboundedSender<KeyEvent>,
+    tick_rate: Duration,
+    frame_budget: Duration,
+    book_depth: usize,
+) -> Result<UiRunner<B>> {
+    let mut budget = RenderBudget::new(frame_budget, book_depth);
+    loop {
+        let last_tick = Instant::now();
+        if frames.has_changed().is_err() {
+            return Ok(runner);
+        }
+
+        let frame = frames.borrow_and_update().clone();
+        runner.terminal.draw(|f| draw_app(f, &frame, &budget))?;
+        budget.record(last_tick.elapsed());
 
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
@@ -67,17 +495,24 @@ pub async fn run_app<B: Backend>(
 
         if crossterm::event::poll(timeout)? {
             if let Event::Key(key) = event::read()? {
-                // Finalize an application if CTRL + C/c is pressed.
-                match (key.modifiers, key.code) {
-                    (KeyModifiers::CONTROL, KeyCode::Char('c'))
-                    | (KeyModifiers::CONTROL, KeyCode::Char('C')) => app.finalize()?,
-                    _ => {}
+                if keys.send(key).is_err() {
+                    return Ok(runner);
                 }
             }
         }
+    }
+}
 
-        if app.should_quit() {
-            return Ok(());
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_symbols() {
+        assert_eq!(
+            parse_symbols("btcusdt, ETHUSDT  solusdt,btcusdt"),
+            vec!["BTCUSDT", "ETHUSDT", "SOLUSDT"]
+        );
+        assert_eq!(parse_symbols(" "), vec!["BTCUSDT"]);
     }
 }
@@ -0,0 +1,431 @@
+use crate::core::bnc::config::BncCfg;
+use crate::core::bnc::error::BncResult;
+use crate::core::bnc::sink::{forward, Sink};
+use crate::core::bnc::state::book::OrderBookDisplay;
+use crate::core::bnc::state::book::{OrderBookManager, OrderBookReceiver};
+use crate::core::bnc::state::price::{PriceReceiver, PriceStateManager};
+use crate::core::bnc::state::ticker::TickerReceiver;
+use crate::core::bnc::ws::combined::{CombinedStreamConnection, CombinedStreamHandle, QueueStats};
+use crate::core::bnc::ws::worker::price::SymbolPriceUpdate;
+use crate::core::bnc::ws::worker::ticker::ticker_stream;
+use log::{info, warn};
+use std::collections::BTreeMap;
+use tokio::sync::watch::channel;
+use tokio::task::JoinHandle;
+
+/// Feeds that could be scraped for a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Feed {
+    /// Best bid/ask updates.
+    Price,
+
+    /// Order book, built from the snapshot and depth updates.
+    Depth,
+
+    /// Rolling 24hr statistics. Streams of all the symbols share a single combined connection.
+    Ticker,
+}
+
+/// Collects everything needed to scrape the data, produces [`ScraperHandle`].
+///
+/// ```no_run
+/// # async fn run() -> bnc_scraper::core::bnc::error::BncResult<()> {
+/// use bnc_scraper::scraper::{Feed, ScraperBuilder};
+///
+/// let mut scraper = ScraperBuilder::new()
+///     .symbol("BTCUSDT")
+///     .feeds(&[Feed::Price])
+///     .build();
+/// scraper.start().await?;
+/// let prices = scraper.subscribe_price("BTCUSDT");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScraperBuilder {
+    cfg: BncCfg,
+    symbols: Vec<String>,
+    feeds: Vec<Feed>,
+}
+
+impl Default for ScraperBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScraperBuilder {
+    /// Builder with default binance configuration and the price and depth feeds enabled.
+    pub fn new() -> Self {
+        Self::from_cfg(BncCfg::default())
+    }
+
+    pub fn from_cfg(cfg: BncCfg) -> Self {
+        Self {
+            cfg,
+            symbols: vec![],
+            feeds: vec![Feed::Price, Feed::Depth],
+        }
+    }
+
+    /// Add symbol to be scraped.
+    pub fn symbol(mut self, symbol: impl Into<String>) -> Self {
+        let symbol = symbol.into().to_ascii_uppercase();
+        if !self.symbols.contains(&symbol) {
+            self.symbols.push(symbol);
+        }
+        self
+    }
+
+    pub fn symbols<S: Into<String>>(self, symbols: impl IntoIterator<Item = S>) -> Self {
+        symbols
+            .into_iter()
+            .fold(self, |builder, symbol| builder.symbol(symbol))
+    }
+
+    /// Replace the set of enabled feeds.
+    pub fn feeds(mut self, feeds: &[Feed]) -> Self {
+        self.feeds = feeds.to_vec();
+        self.feeds.sort();
+        self.feeds.dedup();
+        self
+    }
+
+    /// Amount of redundant workers of the price feed.
+    pub fn price_workers(mut self, workers: u64) -> Self {
+        self.cfg.ws.price_workers = Some(workers);
+        self
+    }
+
+    /// Amount of redundant workers of the depth feed.
+    pub fn depth_workers(mut self, workers: u64) -> Self {
+        self.cfg.ws.depth_workers = Some(workers);
+        self
+    }
+
+    pub fn build(self) -> ScraperHandle {
+        ScraperHandle {
+            cfg: self.cfg,
+            symbols: self.symbols,
+            feeds: self.feeds,
+            state: ScraperState::Idle,
+            markets: BTreeMap::new(),
+            tickers: None,
+        }
+    }
+}
+
+/// Lifecycle stage of the scraper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScraperState {
+    Idle,
+    Running,
+    Stopped,
+}
+
+/// Status of the symbol's feeds.
+#[derive(Debug, Clone)]
+pub struct SymbolStatus {
+    pub symbol: String,
+    pub feeds: Vec<Feed>,
+
+    /// Amount of feeds' tasks that are still running.
+    pub alive_tasks: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScraperStatus {
+    pub state: ScraperState,
+    pub symbols: Vec<SymbolStatus>,
+
+    /// Queues of the combined connection's streams, empty if it is not running.
+    pub queues: Vec<QueueStats>,
+}
+
+/// Managers and receivers of the enabled feeds of a single symbol.
+#[derive(Default)]
+struct SymbolFeeds {
+    price: Option<(PriceStateManager, PriceReceiver)>,
+    book: Option<(OrderBookManager, OrderBookReceiver)>,
+
+    /// Fed by the scraper's combined connection.
+    ticker: Option<TickerReceiver>,
+
+    /// Tasks forwarding the feeds into attached sinks. They forward the last data and end together with the feeds.
+    sinks: Vec<JoinHandle<()>>,
+}
+
+impl SymbolFeeds {
+    fn stop(&self) {
+        if let Some((manager, _)) = self.price.as_ref() {
+            manager.stop();
+        }
+        if let Some((manager, _)) = self.book.as_ref() {
+            manager.stop();
+        }
+    }
+
+    /// Wait for the stopped feeds and then for their sinks to finish.
+    /// Returns the first error any of the feeds' tasks failed with.
+    async fn join(self) -> BncResult<()> {
+        let mut result = Ok(());
+        if let Some((mut manager, _)) = self.price {
+            result = result.and(manager.join().await);
+        }
+        if let Some((mut manager, _)) = self.book {
+            result = result.and(manager.join().await);
+        }
+        // Managers are dropped by now, so the sinks get the last data and end.
+        for sink in self.sinks {
+            let _ = sink.await;
+        }
+        result
+    }
+}
+
+/// Running scraper - owns all the managers, provides receivers of their data.
+pub struct ScraperHandle {
+    cfg: BncCfg,
+    symbols: Vec<String>,
+    feeds: Vec<Feed>,
+    state: ScraperState,
+    markets: BTreeMap<String, SymbolFeeds>,
+
+    /// Connection of the tickers of all the symbols, if the feed is scraped.
+    tickers: Option<CombinedStreamHandle>,
+}
+
+impl ScraperHandle {
+    /// Fetch snapshots and schedule workers of all the symbols.
+    ///
+    /// If any symbol fails to start, already started ones are stopped.
+    pub async fn start(&mut self) -> BncResult<()> {
+        if self.state == ScraperState::Running {
+            return Ok(());
+        }
+
+        for symbol in self.symbols.clone() {
+            match self.start_symbol(&symbol).await {
+                Ok(feeds) => {
+                    self.markets.insert(symbol, feeds);
+                }
+                Err(err) => {
+                    let _ = self.shutdown().await;
+                    return Err(err);
+                }
+            }
+        }
+        if self.feeds.contains(&Feed::Ticker) {
+            if let Err(err) = self.start_tickers() {
+                let _ = self.shutdown().await;
+                return Err(err);
+            }
+        }
+
+        info!("Scraper started for symbols: {:?}.", self.symbols);
+        self.state = ScraperState::Running;
+        Ok(())
+    }
+
+    async fn start_symbol(&self, symbol: &str) -> BncResult<SymbolFeeds> {
+        let mut feeds = SymbolFeeds::default();
+        if self.feeds.contains(&Feed::Depth) {
+            let mut manager = OrderBookManager::from_cfg(&self.cfg);
+            let receiver = manager.init(symbol).await?;
+            feeds.book = Some((manager, receiver));
+        }
+        if self.feeds.contains(&Feed::Price) {
+            let mut manager = PriceStateManager::from_cfg(&self.cfg.ws);
+            let receiver = manager.init(symbol);
+            feeds.price = Some((manager, receiver));
+        }
+        Ok(feeds)
+    }
+
+    /// Connect to the tickers of all the started symbols at once.
+    fn start_tickers(&mut self) -> BncResult<()> {
+        let mut connection = CombinedStreamConnection::from_cfg(&self.cfg.ws);
+        for (symbol, feeds) in self.markets.iter_mut() {
+            let (sender, receiver) = channel(Default::default());
+            connection.subscribe(&ticker_stream(symbol), sender);
+            feeds.ticker = Some(receiver);
+        }
+        self.tickers = Some(connection.spawn()?);
+        Ok(())
+    }
+
+    /// Receiver of the symbol's best prices. None if symbol or feed is not scraped.
+    pub fn subscribe_price(&self, symbol: &str) -> Option<PriceReceiver> {
+        let (_, receiver) = self.markets.get(symbol)?.price.as_ref()?;
+        Some(receiver.clone())
+    }
+
+    /// Receiver of the symbol's order book top. None if symbol or feed is not scraped.
+    pub fn subscribe_book(&self, symbol: &str) -> Option<OrderBookReceiver> {
+        let (_, receiver) = self.markets.get(symbol)?.book.as_ref()?;
+        Some(receiver.clone())
+    }
+
+    /// Receiver of the symbol's 24hr statistics. None if symbol or feed is not scraped.
+    pub fn subscribe_ticker(&self, symbol: &str) -> Option<TickerReceiver> {
+        self.markets.get(symbol)?.ticker.clone()
+    }
+
+    /// Forward each best price change of the symbol into the sink. Returns false if the feed is not scraped.
+    pub fn attach_price_sink(
+        &mut self,
+        symbol: &str,
+        sink: impl Sink<SymbolPriceUpdate> + 'static,
+    ) -> bool {
+        let receiver = match self.subscribe_price(symbol) {
+            Some(receiver) => receiver,
+            None => return false,
+        };
+        let task = forward(receiver, sink);
+        self.markets
+            .get_mut(symbol)
+            .map(|feeds| feeds.sinks.push(task))
+            .is_some()
+    }
+
+    /// Forward each order book change of the symbol into the sink. Returns false if the feed is not scraped.
+    pub fn attach_book_sink(
+        &mut self,
+        symbol: &str,
+        sink: impl Sink<OrderBookDisplay> + 'static,
+    ) -> bool {
+        let receiver = match self.subscribe_book(symbol) {
+            Some(receiver) => receiver,
+            None => return false,
+        };
+        let task = forward(receiver, sink);
+        self.markets
+            .get_mut(symbol)
+            .map(|feeds| feeds.sinks.push(task))
+            .is_some()
+    }
+
+    /// Feeds of the symbols and depth of each stream queue of the combined connection.
+    pub async fn status(&self) -> ScraperStatus {
+        let symbols = self
+            .markets
+            .iter()
+            .map(|(symbol, feeds)| {
+                let mut status = SymbolStatus {
+                    symbol: symbol.clone(),
+                    feeds: vec![],
+                    alive_tasks: 0,
+                };
+                if let Some((manager, _)) = feeds.price.as_ref() {
+                    status.feeds.push(Feed::Price);
+                    status.alive_tasks += manager.alive_tasks();
+                }
+                if let Some((manager, _)) = feeds.book.as_ref() {
+                    status.feeds.push(Feed::Depth);
+                    status.alive_tasks += manager.alive_tasks();
+                }
+                if feeds.ticker.is_some() {
+                    status.feeds.push(Feed::Ticker);
+                }
+                status
+            })
+            .collect();
+
+        let queues = match self.tickers.as_ref().filter(|tickers| tickers.is_alive()) {
+            Some(tickers) => tickers.queues().await.unwrap_or_else(|err| {
+                warn!(
+                    "Could not get queues of the tickers' connection. Error: {}",
+                    err
+                );
+                vec![]
+            }),
+            None => vec![],
+        };
+        ScraperStatus {
+            state: self.state,
+            symbols,
+            queues,
+        }
+    }
+
+    /// Terminate all the feeds and wait for their tasks to finish. Receivers stay valid, but will not receive anything new.
+    ///
+    /// Returns the first error any of the feeds' tasks failed with.
+    pub async fn shutdown(&mut self) -> BncResult<()> {
+        self.markets.values().for_each(|feeds| feeds.stop());
+        self.state = ScraperState::Stopped;
+        let mut result = Ok(());
+        if let Some(tickers) = self.tickers.take() {
+            tickers.stop();
+            let joined = tickers.join().await;
+            if let Err(err) = joined.as_ref() {
+                warn!("Tickers' connection failed: {}", err);
+            }
+            result = result.and(joined);
+        }
+        for (symbol, feeds) in std::mem::take(&mut self.markets) {
+            let joined = feeds.join().await;
+            if let Err(err) = joined.as_ref() {
+                warn!("Feeds of {} failed: {}", symbol, err);
+            }
+            result = result.and(joined);
+        }
+        result
+    }
+}
+
+impl Drop for ScraperHandle {
+    fn drop(&mut self) {
+        self.markets.values().for_each(|feeds| feeds.stop());
+        if let Some(tickers) = self.tickers.as_ref() {
+            tickers.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_reports_queues_of_tickers() -> BncResult<()> {
+        let mut cfg = BncCfg::default();
+        cfg.ws.baseurl = "synthetic://3".into();
+        let mut scraper = ScraperBuilder::from_cfg(cfg)
+            .symbols(["BTCUSDT", "ETHUSDT"])
+            .feeds(&[Feed::Ticker])
+            .build();
+        scraper.start().await?;
+        let mut ticker = scraper.subscribe_ticker("ETHUSDT").unwrap();
+        ticker.changed().await.unwrap();
+
+        let status = scraper.status().await;
+        assert_eq!(status.state, ScraperState::Running);
+        assert_eq!(status.symbols[0].feeds, vec![Feed::Ticker]);
+        let streams: Vec<&str> = status
+            .queues
+            .iter()
+            .map(|queue| queue.stream.as_str())
+            .collect();
+        assert_eq!(streams, ["btcusdt@ticker", "ethusdt@ticker"]);
+
+        scraper.shutdown().await?;
+        assert!(scraper.status().await.queues.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_builds_idle_scraper() {
+        let scraper = ScraperBuilder::new()
+            .symbols(["btcusdt", "ETHUSDT", "BTCUSDT"])
+            .feeds(&[Feed::Depth, Feed::Price, Feed::Depth])
+            .price_workers(1)
+            .build();
+
+        assert_eq!(scraper.symbols, vec!["BTCUSDT", "ETHUSDT"]);
+        assert_eq!(scraper.feeds, vec![Feed::Price, Feed::Depth]);
+        assert_eq!(scraper.cfg.ws.price_workers_count(), 1);
+        assert_eq!(scraper.status().await.state, ScraperState::Idle);
+        assert!(scraper.subscribe_price("BTCUSDT").is_none());
+    }
+}
@@ -0,0 +1,50 @@
+use std::io;
+
+/// Signal the application is finished or detached by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Controlling terminal is closed.
+    Hangup,
+
+    /// Either Ctrl+C or the termination request.
+    Terminate,
+}
+
+/// Process signals the application listens to.
+///
+/// Hangups are never received where there are no such signals, e.g. on windows.
+pub struct Signals {
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    /// Start listening. Signals are queued from now on, so none of them is missed between the waits.
+    pub fn listen() -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            Ok(Self {
+                hangup: signal(SignalKind::hangup())?,
+                terminate: signal(SignalKind::terminate())?,
+            })
+        }
+        #[cfg(not(unix))]
+        Ok(Self {})
+    }
+
+    /// Wait for the next signal.
+    pub async fn recv(&mut self) -> io::Result<Signal> {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = self.hangup.recv() => Ok(Signal::Hangup),
+            _ = self.terminate.recv() => Ok(Signal::Terminate),
+            interrupted = tokio::signal::ctrl_c() => interrupted.map(|_| Signal::Terminate),
+        }
+        #[cfg(not(unix))]
+        tokio::signal::ctrl_c().await.map(|_| Signal::Terminate)
+    }
+}
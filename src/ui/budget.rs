@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+/// Minimal amount of book levels - we never degrade below it.
+const MIN_BOOK_DEPTH: usize = 3;
+
+/// Amount of consecutive fast frames required to restore some detail.
+const FRAMES_TO_RESTORE: u32 = 10;
+
+/// Tracks draw time of the frames and reduces rendered detail if they don't fit into the budget.
+#[derive(Debug, Clone)]
+pub struct RenderBudget {
+    budget: Duration,
+
+    /// Amount of book levels drawn when rendering is not degraded.
+    full_depth: usize,
+    book_depth: usize,
+    fast_frames: u32,
+}
+
+impl RenderBudget {
+    pub fn new(budget: Duration, full_depth: usize) -> Self {
+        Self {
+            budget,
+            full_depth,
+            book_depth: full_depth,
+            fast_frames: 0,
+        }
+    }
+
+    /// Account draw time of the last frame.
+    ///
+    /// Slow frame halves the detail immediately, while it is restored gradually after
+    /// a series of frames that took less than half of the budget.
+    pub fn record(&mut self, draw_time: Duration) {
+        if draw_time > self.budget {
+            self.book_depth = (self.book_depth / 2).max(MIN_BOOK_DEPTH.min(self.full_depth));
+            self.fast_frames = 0;
+            return;
+        }
+
+        if draw_time > self.budget / 2 {
+            self.fast_frames = 0;
+            return;
+        }
+
+        self.fast_frames += 1;
+        if self.fast_frames >= FRAMES_TO_RESTORE {
+            self.book_depth = (self.book_depth * 2).min(self.full_depth);
+            self.fast_frames = 0;
+        }
+    }
+
+    /// Amount of book levels to be drawn.
+    pub fn book_depth(&self) -> usize {
+        self.book_depth
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.book_depth < self.full_depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_degrades_and_restores_detail() {
+        let budget = Duration::from_millis(20);
+        let mut render_budget = RenderBudget::new(budget, 10);
+
+        render_budget.record(Duration::from_millis(50));
+        assert!(render_budget.is_degraded());
+        assert_eq!(render_budget.book_depth(), 5);
+
+        render_budget.record(Duration::from_millis(50));
+        render_budget.record(Duration::from_millis(50));
+        assert_eq!(render_budget.book_depth(), MIN_BOOK_DEPTH);
+
+        for _ in 0..FRAMES_TO_RESTORE * 3 {
+            render_budget.record(Duration::from_millis(1));
+        }
+        assert!(!render_budget.is_degraded());
+    }
+}
@@ -1,13 +1,71 @@
+use crate::core::bnc::data::Price;
+use crate::core::bnc::state::conversion::ConversionCfg;
+use crate::core::bnc::state::portfolio::PortfolioCfg;
+use crate::ui::format::NumberFormat;
+use crate::ui::i18n::Locale;
+use crate::ui::layout::{LayoutPreset, Pane};
 use serde::Deserialize;
 
 #[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct UICfg {
     /// Milliseconds between screen updates
     pub tick_rate: u64,
+
+    /// Milliseconds a single frame may take to draw before rendered detail is reduced.
+    pub frame_budget: u64,
+
+    /// Secondary currency the best prices are valued in, if any.
+    pub conversion: Option<ConversionCfg>,
+
+    /// Holdings shown in the portfolio panel, the panel is hidden without them.
+    pub portfolio: Option<PortfolioCfg>,
+
+    /// Whether the strip of all the scraped symbols' prices is shown above the panes.
+    pub ticker_strip: bool,
+
+    /// Whether the 24hr statistics of the displayed symbol are shown in the best prices' title.
+    pub stats: bool,
+
+    /// Initial arrangement of the order book(`split` or `ladder`), switched by `v` in runtime.
+    pub layout: LayoutPreset,
+
+    /// Steps the book levels can be grouped by, e.g. `["0.5", "1", "10"]`. Cycled by `g` in runtime.
+    pub book_grouping: Vec<Price>,
+
+    /// Panes hidden on start(`best_prices`, `order_book`), toggled by `1` and `2` in runtime.
+    ///
+    /// Visibility stored in the session file takes precedence.
+    pub hidden_panes: Vec<Pane>,
+
+    /// File the panes' visibility is kept in between the runs, none to not keep it.
+    pub session_file: Option<String>,
+
+    /// Compact notation of the quantities and the currency labels.
+    pub number_format: NumberFormat,
+
+    /// Language of the titles, prompts and status messages(`en` or `ru`).
+    pub locale: Locale,
 }
 
 impl Default for UICfg {
     fn default() -> Self {
-        Self { tick_rate: 100 }
+        Self {
+            tick_rate: 100,
+            frame_budget: 20,
+            conversion: None,
+            portfolio: None,
+            layout: LayoutPreset::default(),
+            ticker_strip: true,
+            stats: true,
+            book_grouping: ["0.01", "0.1", "1", "10"]
+                .iter()
+                .map(|step| step.parse().expect("Default grouping step is malformed."))
+                .collect(),
+            hidden_panes: vec![],
+            session_file: Some("bnc-scraper.session.json".into()),
+            number_format: NumberFormat::default(),
+            locale: Locale::default(),
+        }
     }
 }
@@ -0,0 +1,90 @@
+use crate::core::bnc::decimal::{format_compact, format_decimal, RoundingMode};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// Side of the amount the currency label is placed on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CurrencyPlacement {
+    Before,
+
+    #[default]
+    After,
+}
+
+/// How the quantities and the money are displayed.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NumberFormat {
+    /// Shorten quantities and notional values to three significant digits, e.g. `12.4k` or `1.02M`.
+    pub compact: bool,
+
+    /// Glyph displayed instead of the quote currency ticker, e.g. `$` for USDT.
+    pub currency_symbol: Option<String>,
+
+    pub currency_placement: CurrencyPlacement,
+}
+
+impl NumberFormat {
+    /// Quantity of the base asset, as precise as it is received unless it is shortened.
+    pub fn qty(&self, qty: Decimal) -> String {
+        if self.compact {
+            return format_compact(qty);
+        }
+        qty.to_string()
+    }
+
+    /// Value in the quote currency with the given decimal places, unless it is shortened.
+    pub fn notional(&self, value: Decimal, decimals: u32) -> String {
+        if self.compact {
+            return format_compact(value);
+        }
+        format_decimal(value, decimals, RoundingMode::Nearest)
+    }
+
+    /// Amount labelled with the currency. Glyphs stick to the amount, tickers are separated by a space.
+    pub fn money(&self, amount: &str, currency: &str) -> String {
+        let separator = if currency.chars().all(char::is_alphabetic) {
+            " "
+        } else {
+            ""
+        };
+        match self.currency_placement {
+            CurrencyPlacement::Before => format!("{}{}{}", currency, separator, amount),
+            CurrencyPlacement::After => format!("{}{}{}", amount, separator, currency),
+        }
+    }
+
+    /// Amount labelled with the quote currency, or with its glyph if it is set.
+    pub fn quote_money(&self, amount: &str, quote: &str) -> String {
+        self.money(amount, self.currency_symbol.as_deref().unwrap_or(quote))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_places_currency_labels() {
+        let mut format = NumberFormat::default();
+        assert_eq!(format.quote_money("10.50", "USDT"), "10.50 USDT");
+
+        format.currency_symbol = Some("$".into());
+        format.currency_placement = CurrencyPlacement::Before;
+        assert_eq!(format.quote_money("10.50", "USDT"), "$10.50");
+        assert_eq!(format.money("9.70", "EUR"), "EUR 9.70");
+    }
+
+    #[test]
+    fn it_shortens_quantities_if_compact() {
+        let value: Decimal = "12420.50000000".parse().unwrap();
+        let mut format = NumberFormat::default();
+        assert_eq!(format.qty(value), "12420.50000000");
+        assert_eq!(format.notional(value, 2), "12420.50");
+
+        format.compact = true;
+        assert_eq!(format.qty(value), "12.4k");
+        assert_eq!(format.notional(value, 2), "12.4k");
+    }
+}
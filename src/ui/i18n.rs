@@ -0,0 +1,283 @@
+use serde::Deserialize;
+use std::fmt::Display;
+
+/// Language of the user-facing strings. Logs are always written in English.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Ru,
+}
+
+impl Locale {
+    pub fn strings(&self) -> &'static Strings {
+        match self {
+            Self::En => &EN,
+            Self::Ru => &RU,
+        }
+    }
+}
+
+/// Texts displayed by the ui. Templates have `{}` placeholders, filled in order by [`fill`].
+#[derive(Debug)]
+pub struct Strings {
+    pub app_title: &'static str,
+    pub order_book: &'static str,
+    pub order_book_cached: &'static str,
+
+    /// Book's title and the step its levels are grouped by.
+    pub grouped_by: &'static str,
+    pub asks: &'static str,
+    pub bids: &'static str,
+    pub price: &'static str,
+    pub qty: &'static str,
+    pub cum_qty: &'static str,
+
+    /// Spread between the ladder's sides.
+    pub spread: &'static str,
+    pub best_prices: &'static str,
+
+    /// Title of the best prices and the 24hr change, high, low and volume of the symbol.
+    pub best_prices_stats: &'static str,
+    pub best_ask: &'static str,
+    pub best_bid: &'static str,
+    pub microprice: &'static str,
+    pub average: &'static str,
+
+    /// Total value of the portfolio.
+    pub portfolio: &'static str,
+    pub asset: &'static str,
+    pub amount: &'static str,
+    pub value: &'static str,
+    pub change: &'static str,
+    pub degraded: &'static str,
+
+    pub prompt_add: &'static str,
+    pub prompt_switch: &'static str,
+    pub prompt_seek: &'static str,
+
+    pub picker_title: &'static str,
+    pub picker_loading: &'static str,
+    pub picker_empty: &'static str,
+
+    /// Error of the symbols' loading.
+    pub picker_failed: &'static str,
+
+    pub about_title: &'static str,
+    pub about_version: &'static str,
+    pub about_commit: &'static str,
+    pub about_built: &'static str,
+    pub about_target: &'static str,
+    pub about_features: &'static str,
+    pub about_config: &'static str,
+
+    /// Shown instead of the configuration sources if there are none.
+    pub about_no_config: &'static str,
+    pub about_queues: &'static str,
+
+    /// Stream of the queue, messages waiting in it and the ones it dropped.
+    pub about_queue: &'static str,
+
+    /// Key bindings shown after the scraped symbols.
+    pub hints: &'static str,
+    pub replay_hints: &'static str,
+
+    /// Pace and position of the replay, e.g. `10x` and `+00:01:30`.
+    pub replay_status: &'static str,
+    pub replay_paused: &'static str,
+
+    /// Symbol and the error.
+    pub add_failed: &'static str,
+    pub switch_failed: &'static str,
+    pub switching: &'static str,
+    pub seek_failed: &'static str,
+    pub replay_restart_failed: &'static str,
+    pub book_resynced: &'static str,
+    pub not_scraped: &'static str,
+    pub last_symbol: &'static str,
+
+    /// Rate of the book's updates and the usual one.
+    pub book_stalled: &'static str,
+    pub book_burst: &'static str,
+}
+
+pub static EN: Strings = Strings {
+    app_title: "Binance Scrapper",
+    order_book: "Order book",
+    order_book_cached: "Order book (cached)",
+    grouped_by: "{} by {}",
+    asks: "Asks",
+    bids: "Bids",
+    price: "Price",
+    qty: "Qty",
+    cum_qty: "Cum qty",
+    spread: "spread {}",
+    best_prices: "Best prices",
+    best_prices_stats: "{} | 24h {}% high {} low {} vol {}",
+    best_ask: "Best ask",
+    best_bid: "Best bid",
+    microprice: "Microprice",
+    average: "5m average",
+    portfolio: "Portfolio: {}",
+    asset: "Asset",
+    amount: "Amount",
+    value: "Value",
+    change: "Change",
+    degraded: "degraded rendering",
+    prompt_add: "Add symbol: {}",
+    prompt_switch: "Switch to symbol: {}",
+    prompt_seek: "Seek to(seconds, h:m:s or ms since epoch): {}",
+    picker_title: "Pick symbol",
+    picker_loading: "Loading symbols...",
+    picker_empty: "No matching symbols",
+    picker_failed: "Could not load symbols: {}",
+    about_title: "About",
+    about_version: "Version",
+    about_commit: "Commit",
+    about_built: "Built",
+    about_target: "Target",
+    about_features: "Features",
+    about_config: "Config",
+    about_no_config: "defaults only",
+    about_queues: "Queues",
+    about_queue: "{} {} queued, {} dropped",
+    hints: "'a' add | 's' switch | 'p' pick | 'x' remove | '[' ']' cycle | Tab focus | H/J/K/L resize | '1' '2' panes | 'v' view | 'g' group | 'i' about",
+    replay_hints: "Space pause | 'r' speed | 't' seek",
+    replay_status: "Replay {} at {}",
+    replay_paused: "paused",
+    add_failed: "Could not add {}: {}",
+    switch_failed: "Could not switch to {}: {}",
+    switching: "Switching to {}...",
+    seek_failed: "Could not seek to {}",
+    replay_restart_failed: "Could not restart the replay: {}",
+    book_resynced: "Book of {} is resynced after missing updates",
+    not_scraped: "Symbol {} is not scraped",
+    last_symbol: "The last symbol can't be removed, add another one first",
+    book_stalled: "! Book updates stalled: {}/s, usually {}/s",
+    book_burst: "! Book updates burst: {}/s, usually {}/s",
+};
+
+pub static RU: Strings = Strings {
+    app_title: "Binance Scrapper",
+    order_book: "Стакан",
+    order_book_cached: "Стакан (из кэша)",
+    grouped_by: "{} с шагом {}",
+    asks: "Продажа",
+    bids: "Покупка",
+    price: "Цена",
+    qty: "Объём",
+    cum_qty: "Сумм. объём",
+    spread: "спред {}",
+    best_prices: "Лучшие цены",
+    best_prices_stats: "{} | 24ч {}% макс {} мин {} объём {}",
+    best_ask: "Лучшая продажа",
+    best_bid: "Лучшая покупка",
+    microprice: "Микроцена",
+    average: "Средняя за 5м",
+    portfolio: "Портфель: {}",
+    asset: "Актив",
+    amount: "Количество",
+    value: "Стоимость",
+    change: "Изменение",
+    degraded: "упрощённая отрисовка",
+    prompt_add: "Добавить символ: {}",
+    prompt_switch: "Переключиться на символ: {}",
+    prompt_seek: "Перейти к(секунды, ч:м:с или мс с начала эпохи): {}",
+    picker_title: "Выбор символа",
+    picker_loading: "Загрузка символов...",
+    picker_empty: "Нет подходящих символов",
+    picker_failed: "Не удалось загрузить символы: {}",
+    about_title: "О программе",
+    about_version: "Версия",
+    about_commit: "Коммит",
+    about_built: "Собрано",
+    about_target: "Платформа",
+    about_features: "Возможности",
+    about_config: "Конфигурация",
+    about_no_config: "только значения по умолчанию",
+    about_queues: "Очереди",
+    about_queue: "{} {} в очереди, {} отброшено",
+    hints: "'a' добавить | 's' сменить | 'p' выбрать | 'x' убрать | '[' ']' листать | Tab фокус | H/J/K/L размер | '1' '2' панели | 'v' вид | 'g' группировка | 'i' о программе",
+    replay_hints: "Пробел пауза | 'r' скорость | 't' переход",
+    replay_status: "Повтор {} на {}",
+    replay_paused: "на паузе",
+    add_failed: "Не удалось добавить {}: {}",
+    switch_failed: "Не удалось переключиться на {}: {}",
+    switching: "Переключение на {}...",
+    seek_failed: "Не удалось перейти к {}",
+    replay_restart_failed: "Не удалось перезапустить повтор: {}",
+    book_resynced: "Стакан {} пересинхронизирован после пропуска обновлений",
+    not_scraped: "Символ {} не отслеживается",
+    last_symbol: "Последний символ нельзя убрать, сначала добавьте другой",
+    book_stalled: "! Обновления стакана замерли: {}/с, обычно {}/с",
+    book_burst: "! Всплеск обновлений стакана: {}/с, обычно {}/с",
+};
+
+/// Fill the template's placeholders with the arguments in order. Placeholders without an argument are kept.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut parts = template.split("{}").peekable();
+    while let Some(part) = parts.next() {
+        filled.push_str(part);
+        if parts.peek().is_none() {
+            break;
+        }
+        match args.next() {
+            Some(arg) => filled.push_str(&arg.to_string()),
+            None => filled.push_str("{}"),
+        }
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_fills_templates_in_order() {
+        assert_eq!(
+            fill(EN.add_failed, &[&"BTCUSDT", &"Invalid symbol"]),
+            "Could not add BTCUSDT: Invalid symbol"
+        );
+        assert_eq!(
+            fill(RU.switching, &[&"ETHUSDT"]),
+            "Переключение на ETHUSDT..."
+        );
+        assert_eq!(fill("{} by {}", &[&"Order book"]), "Order book by {}");
+        assert_eq!(fill(EN.degraded, &[&1]), "degraded rendering");
+    }
+
+    #[test]
+    fn it_keeps_placeholders_of_every_locale() {
+        let placeholders = |strings: &Strings| -> Vec<usize> {
+            [
+                strings.grouped_by,
+                strings.spread,
+                strings.best_prices_stats,
+                strings.portfolio,
+                strings.about_queue,
+                strings.prompt_add,
+                strings.prompt_switch,
+                strings.prompt_seek,
+                strings.picker_failed,
+                strings.replay_status,
+                strings.add_failed,
+                strings.switch_failed,
+                strings.switching,
+                strings.seek_failed,
+                strings.replay_restart_failed,
+                strings.book_resynced,
+                strings.not_scraped,
+                strings.book_stalled,
+                strings.book_burst,
+            ]
+            .iter()
+            .map(|template| template.matches("{}").count())
+            .collect()
+        };
+        assert_eq!(placeholders(Locale::Ru.strings()), placeholders(&EN));
+    }
+}
@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use tui::layout::Direction::Vertical;
+use tui::layout::{Constraint, Layout, Rect};
+
+/// Default height of the best prices pane - enough to fit header and a single row.
+const DEFAULT_BEST_PRICES_HEIGHT: u16 = 5;
+
+/// Minimal height of any pane - borders and a single line of content.
+const MIN_PANE_HEIGHT: u16 = 3;
+
+/// Maximal height of the best prices pane - the order book can't be shrunk further anyway.
+const MAX_BEST_PRICES_HEIGHT: u16 = 30;
+
+/// Panes' width is adjusted in these steps, in percents of the screen.
+const WIDTH_STEP: u16 = 10;
+
+/// Minimal width of any pane, in percents of the screen.
+const MIN_PANE_WIDTH: u16 = 30;
+
+/// Resizable parts of the screen, in the order they are focused by Tab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pane {
+    BestPrices,
+    OrderBook,
+}
+
+impl Pane {
+    pub const ALL: [Pane; 2] = [Pane::BestPrices, Pane::OrderBook];
+
+    /// Pane that receives the focus after this one.
+    pub fn next(self) -> Self {
+        match self {
+            Self::BestPrices => Self::OrderBook,
+            Self::OrderBook => Self::BestPrices,
+        }
+    }
+}
+
+/// Arrangement of the order book pane.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutPreset {
+    /// Asks and bids side by side.
+    #[default]
+    Split,
+
+    /// Single vertical price ladder - asks above the spread, bids below it, like in DOM trading.
+    Ladder,
+}
+
+impl LayoutPreset {
+    /// Preset that is switched to after this one.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Split => Self::Ladder,
+            Self::Ladder => Self::Split,
+        }
+    }
+}
+
+/// Change of the focused pane's size requested by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resize {
+    Taller,
+    Shorter,
+    Wider,
+    Narrower,
+}
+
+/// Screen areas assigned to the panes.
+pub struct AppUiLayout {
+    pub strip: Rect,
+    pub best_prices: Rect,
+    pub order_book: Rect,
+    pub status: Rect,
+}
+
+/// Layout engine - keeps the focused pane and sizes of the panes chosen during the session.
+///
+/// Panes are stacked vertically, the order book takes all the height that is left by the best prices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaneLayout {
+    focused: Pane,
+
+    /// How the order book is drawn.
+    preset: LayoutPreset,
+
+    /// Rows taken by the best prices pane.
+    best_prices_height: u16,
+
+    /// Percents of the screen's width taken by the best prices pane.
+    best_prices_width: u16,
+
+    /// Percents of the screen's width taken by the order book pane.
+    order_book_width: u16,
+
+    best_prices_hidden: bool,
+    order_book_hidden: bool,
+}
+
+impl Default for PaneLayout {
+    fn default() -> Self {
+        Self {
+            focused: Pane::BestPrices,
+            preset: LayoutPreset::default(),
+            best_prices_height: DEFAULT_BEST_PRICES_HEIGHT,
+            best_prices_width: 100,
+            order_book_width: 100,
+            best_prices_hidden: false,
+            order_book_hidden: false,
+        }
+    }
+}
+
+impl PaneLayout {
+    pub fn with_preset(mut self, preset: LayoutPreset) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    /// Hide the panes and show the rest. The last of them stays visible anyway.
+    pub fn with_hidden(mut self, panes: &[Pane]) -> Self {
+        for pane in Pane::ALL {
+            if self.is_visible(pane) == panes.contains(&pane) {
+                self.toggle(pane);
+            }
+        }
+        self
+    }
+
+    /// Panes that are hidden at the moment.
+    pub fn hidden(&self) -> Vec<Pane> {
+        Pane::ALL
+            .into_iter()
+            .filter(|pane| !self.is_visible(*pane))
+            .collect()
+    }
+
+    pub fn is_visible(&self, pane: Pane) -> bool {
+        match pane {
+            Pane::BestPrices => !self.best_prices_hidden,
+            Pane::OrderBook => !self.order_book_hidden,
+        }
+    }
+
+    /// Hide the visible pane or show the hidden one. The only visible pane can't be hidden.
+    ///
+    /// Focus is moved away from the hidden pane.
+    pub fn toggle(&mut self, pane: Pane) {
+        if self.is_visible(pane) && !self.is_visible(pane.next()) {
+            return;
+        }
+        match pane {
+            Pane::BestPrices => self.best_prices_hidden = !self.best_prices_hidden,
+            Pane::OrderBook => self.order_book_hidden = !self.order_book_hidden,
+        }
+        if !self.is_visible(self.focused) {
+            self.focused = self.focused.next();
+        }
+    }
+
+    pub fn focused(&self) -> Pane {
+        self.focused
+    }
+
+    pub fn preset(&self) -> LayoutPreset {
+        self.preset
+    }
+
+    /// Draw the order book with the next preset.
+    pub fn switch_preset(&mut self) {
+        self.preset = self.preset.next();
+    }
+
+    /// Move focus to the next visible pane.
+    pub fn focus_next(&mut self) {
+        if self.is_visible(self.focused.next()) {
+            self.focused = self.focused.next();
+        }
+    }
+
+    /// Resize the focused pane. Order book is resized at the expense of the best prices and vice versa.
+    pub fn resize(&mut self, resize: Resize) {
+        let width = match self.focused {
+            Pane::BestPrices => &mut self.best_prices_width,
+            Pane::OrderBook => &mut self.order_book_width,
+        };
+
+        match (resize, self.focused) {
+            (Resize::Wider, _) => *width = (*width + WIDTH_STEP).min(100),
+            (Resize::Narrower, _) => *width = width.saturating_sub(WIDTH_STEP).max(MIN_PANE_WIDTH),
+            (Resize::Taller, Pane::BestPrices) | (Resize::Shorter, Pane::OrderBook) => {
+                self.best_prices_height = (self.best_prices_height + 1).min(MAX_BEST_PRICES_HEIGHT)
+            }
+            (Resize::Shorter, Pane::BestPrices) | (Resize::Taller, Pane::OrderBook) => {
+                self.best_prices_height = self
+                    .best_prices_height
+                    .saturating_sub(1)
+                    .max(MIN_PANE_HEIGHT)
+            }
+        }
+    }
+
+    /// Assign areas of the screen to the panes. Hidden panes get empty areas, the rest take their space.
+    pub fn split(&self, area: Rect) -> AppUiLayout {
+        // Leave the order book at least the minimal height, however the best prices are grown.
+        let best_prices_height = self
+            .best_prices_height
+            .min(area.height.saturating_sub(MIN_PANE_HEIGHT + 4))
+            .max(MIN_PANE_HEIGHT);
+        let (best_prices, order_book) = match (self.best_prices_hidden, self.order_book_hidden) {
+            (true, _) => (Constraint::Length(0), Constraint::Min(0)),
+            (false, true) => (Constraint::Min(0), Constraint::Length(0)),
+            (false, false) => (Constraint::Length(best_prices_height), Constraint::Min(0)),
+        };
+
+        let chunks = Layout::default()
+            .direction(Vertical)
+            .constraints([
+                Constraint::Length(1),
+                best_prices,
+                order_book,
+                Constraint::Length(1),
+            ])
+            .margin(1)
+            .split(area);
+
+        AppUiLayout {
+            strip: chunks[0],
+            best_prices: with_width(chunks[1], self.best_prices_width),
+            order_book: with_width(chunks[2], self.order_book_width),
+            status: chunks[3],
+        }
+    }
+}
+
+/// Shrink the area to the percents of its width, keeping it centered.
+fn with_width(area: Rect, percents: u16) -> Rect {
+    let width = (area.width as u32 * percents as u32 / 100) as u16;
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        width,
+        ..area
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 100,
+        height: 40,
+    };
+
+    #[test]
+    fn it_cycles_focus() {
+        let mut layout = PaneLayout::default();
+        assert_eq!(layout.focused(), Pane::BestPrices);
+        layout.focus_next();
+        assert_eq!(layout.focused(), Pane::OrderBook);
+        layout.focus_next();
+        assert_eq!(layout.focused(), Pane::BestPrices);
+    }
+
+    #[test]
+    fn it_resizes_focused_pane() {
+        let mut layout = PaneLayout::default();
+        let initial = layout.split(SCREEN);
+
+        layout.focus_next();
+        layout.resize(Resize::Taller);
+        layout.resize(Resize::Narrower);
+        let resized = layout.split(SCREEN);
+
+        assert_eq!(resized.order_book.height, initial.order_book.height + 1);
+        assert_eq!(resized.best_prices.height, initial.best_prices.height - 1);
+        assert_eq!(resized.order_book.width, initial.order_book.width * 9 / 10);
+        assert_eq!(resized.best_prices.width, initial.best_prices.width);
+    }
+
+    #[test]
+    fn it_hides_panes() {
+        let mut layout = PaneLayout::default();
+        let initial = layout.split(SCREEN);
+        layout.toggle(Pane::BestPrices);
+        assert!(!layout.is_visible(Pane::BestPrices));
+        assert_eq!(layout.focused(), Pane::OrderBook);
+        layout.focus_next();
+        assert_eq!(layout.focused(), Pane::OrderBook);
+
+        let areas = layout.split(SCREEN);
+        assert_eq!(areas.best_prices.height, 0);
+        // Screen without the borders, the strip and the status.
+        assert_eq!(areas.order_book.height, SCREEN.height - 4);
+
+        // The last visible pane stays.
+        layout.toggle(Pane::OrderBook);
+        assert!(layout.is_visible(Pane::OrderBook));
+
+        layout.toggle(Pane::BestPrices);
+        assert_eq!(layout.split(SCREEN).best_prices, initial.best_prices);
+
+        let layout = PaneLayout::default().with_hidden(&[Pane::BestPrices, Pane::OrderBook]);
+        assert!(!layout.is_visible(Pane::BestPrices));
+        assert!(layout.is_visible(Pane::OrderBook));
+        assert_eq!(layout.hidden(), [Pane::BestPrices]);
+
+        // Panes are shown again once they are not listed.
+        assert!(layout.with_hidden(&[]).hidden().is_empty());
+    }
+
+    #[test]
+    fn it_keeps_panes_visible() {
+        let mut layout = PaneLayout::default();
+        for _ in 0..100 {
+            layout.resize(Resize::Taller);
+            layout.resize(Resize::Narrower);
+        }
+        let areas = layout.split(SCREEN);
+
+        assert!(areas.order_book.height >= MIN_PANE_HEIGHT);
+        assert!(areas.best_prices.width >= SCREEN.width * MIN_PANE_WIDTH / 100 - 1);
+
+        for _ in 0..100 {
+            layout.resize(Resize::Shorter);
+        }
+        assert_eq!(layout.split(SCREEN).best_prices.height, MIN_PANE_HEIGHT);
+    }
+}
@@ -1,89 +1,462 @@
-use crate::core::bnc::state::book::{OrderBookDisplay, TableDisplay};
-
-use crate::core::bnc::ws::worker::price::SymbolPriceUpdate;
+use crate::ui::budget::RenderBudget;
+use crate::ui::i18n::{fill, Locale, Strings};
+use crate::ui::layout::{LayoutPreset, Pane, PaneLayout};
+use crate::ui::text::{fit_width, inner_height, inner_width};
+use crate::ui::view::{
+    AboutView, BookView, LadderRung, LevelView, PickerView, PortfolioView, QuoteView, StripView,
+};
 use tui::backend::Backend;
-use tui::layout::Direction::Vertical;
 use tui::layout::{Constraint, Direction, Layout, Rect};
-use tui::style::{Modifier, Style};
-use tui::widgets::{Block, Borders, List, ListItem, Row, Table};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph, Row, Table};
 use tui::Frame;
+use unicode_width::UnicodeWidthStr;
 
+pub mod budget;
 pub mod config;
+pub mod format;
+pub mod i18n;
+pub mod layout;
+pub mod picker;
 pub mod runner;
+pub mod session;
+pub mod text;
+pub mod view;
+
+/// Price, qty and cum qty columns of the book's levels.
+const LEVEL_COLUMNS: [Constraint; 3] = [Constraint::Ratio(1, 3); 3];
+
+/// Width of each of the three level columns(price, qty and cum qty) within the bordered area.
+fn level_column_width(width: u16) -> usize {
+    // Columns are separated by a single cell.
+    inner_width(width).saturating_sub(2) / 3
+}
+
+fn level_row(level: &LevelView, column_width: usize) -> Row<'static> {
+    let cell = |text: &str| fit_width(text, column_width).into_owned();
+    Row::new(vec![
+        cell(&level.price),
+        cell(&level.qty),
+        cell(&level.cum_qty),
+    ])
+}
+
+fn levels_header(strings: &'static Strings) -> Row<'static> {
+    Row::new(vec![strings.price, strings.qty, strings.cum_qty])
+        .style(Style::default().add_modifier(Modifier::BOLD))
+}
 
-fn orders_to_listitems(orders: &TableDisplay) -> Vec<ListItem> {
-    orders
+/// Table of the book's side, the best level first.
+fn levels_table<'a>(
+    levels: &[LevelView],
+    depth: usize,
+    area: Rect,
+    title: &'a str,
+    strings: &'static Strings,
+) -> Table<'a> {
+    let column_width = level_column_width(area.width);
+    let rows: Vec<Row> = levels
         .iter()
-        .take(10)
-        .map(|order| ListItem::new(format!("{}/{}", order.0, order.1)))
-        .collect()
+        .take(depth)
+        .map(|level| level_row(level, column_width))
+        .collect();
+    Table::new(rows)
+        .header(levels_header(strings))
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .widths(&LEVEL_COLUMNS)
+}
+
+/// Outer block of the pane, focused one has its border highlighted.
+fn pane_block(title: &str, focused: bool) -> Block<'_> {
+    let block = Block::default().title(title).borders(Borders::ALL);
+    if focused {
+        block.border_style(Style::default().fg(Color::Yellow))
+    } else {
+        block
+    }
+}
+
+fn book_title(book: &BookView, strings: &Strings) -> String {
+    let title = if book.cached {
+        strings.order_book_cached
+    } else {
+        strings.order_book
+    };
+    match book.grouping.as_ref() {
+        Some(step) => fill(strings.grouped_by, &[&title, step]),
+        None => title.to_string(),
+    }
+}
+
+fn quote_title(quote: &QuoteView, strings: &Strings) -> String {
+    match quote.stats.as_ref() {
+        Some(stats) => fill(
+            strings.best_prices_stats,
+            &[
+                &strings.best_prices,
+                &stats.change_percent,
+                &stats.high,
+                &stats.low,
+                &stats.volume,
+            ],
+        ),
+        None => strings.best_prices.to_string(),
+    }
 }
 
-pub fn draw_order_book<B: Backend>(frame: &mut Frame<B>, area: Rect, book: &OrderBookDisplay) {
-    let block = Block::default().title("Order book").borders(Borders::ALL);
+pub fn draw_order_book<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    book: &BookView,
+    depth: usize,
+    focused: bool,
+    strings: &'static Strings,
+) {
+    let title = book_title(book, strings);
+    let block = pane_block(&title, focused);
     let chunks = Layout::default()
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
         .direction(Direction::Horizontal)
         .margin(1)
         .split(area);
+    // Levels that fit into the bordered tables below their headers.
+    let depth = depth.min(inner_height(chunks[0].height).saturating_sub(1));
 
-    let asks = List::new(orders_to_listitems(&book.asks))
-        .block(Block::default().borders(Borders::ALL).title("Asks"));
-
-    let bids = List::new(orders_to_listitems(&book.bids))
-        .block(Block::default().borders(Borders::ALL).title("Bids"));
+    let asks = levels_table(&book.asks, depth, chunks[0], strings.asks, strings);
+    let bids = levels_table(&book.bids, depth, chunks[1], strings.bids, strings);
 
     frame.render_widget(block, area);
     frame.render_widget(asks, chunks[0]);
     frame.render_widget(bids, chunks[1]);
 }
 
-pub fn draw_best_price<B: Backend>(frame: &mut Frame<B>, area: Rect, update: &SymbolPriceUpdate) {
-    let block = Block::default().title("Best prices").borders(Borders::ALL);
+/// Draw the book as a single price ladder, quantities beside the prices.
+pub fn draw_ladder<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    book: &BookView,
+    depth: usize,
+    focused: bool,
+    strings: &'static Strings,
+) {
+    let title = book_title(book, strings);
+    let block = pane_block(&title, focused);
+
+    let column_width = level_column_width(area.width);
+    // Both sides fit around the header and the spread rows, so the spread stays in sight.
+    let depth = depth.min(inner_height(area.height).saturating_sub(2) / 2);
+    let cell = |text: &str| fit_width(text, column_width).into_owned();
+
+    let rows = book.ladder(depth).into_iter().map(|rung| match rung {
+        LadderRung::Ask(level) => {
+            level_row(level, column_width).style(Style::default().fg(Color::Red))
+        }
+        LadderRung::Spread(spread) => {
+            let spread = fill(strings.spread, &[&spread.unwrap_or("-")]);
+            Row::new(vec![cell(&spread)]).style(Style::default().add_modifier(Modifier::DIM))
+        }
+        LadderRung::Bid(level) => {
+            level_row(level, column_width).style(Style::default().fg(Color::Green))
+        }
+    });
+
+    let table = Table::new(rows)
+        .header(levels_header(strings))
+        .block(block)
+        .widths(&LEVEL_COLUMNS);
+
+    frame.render_widget(table, area);
+}
+
+pub fn draw_best_price<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    quote: &QuoteView,
+    focused: bool,
+    strings: &'static Strings,
+) {
+    let title = quote_title(quote, strings);
+    let block = pane_block(&title, focused);
+
+    // Four equal columns separated by a single cell.
+    let column_width = inner_width(area.width).saturating_sub(3) / 4;
+
+    let best_ask = fit_width(&quote.ask, column_width).into_owned();
+    let best_bid = fit_width(&quote.bid, column_width).into_owned();
+    let microprice = fit_width(&quote.microprice, column_width).into_owned();
+    let average = fit_width(&quote.average, column_width).into_owned();
 
-    let best_ask = update.ask.to_string();
+    let widths = [Constraint::Length(column_width as u16); 4];
+
+    let table = Table::new(vec![Row::new(vec![
+        best_ask, best_bid, microprice, average,
+    ])])
+    .header(
+        Row::new(vec![
+            strings.best_ask,
+            strings.best_bid,
+            strings.microprice,
+            strings.average,
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD))
+        .bottom_margin(1),
+    )
+    .block(block)
+    .widths(&widths);
+
+    frame.render_widget(table, area);
+}
 
-    let best_bid = update.bid.to_string();
+pub fn draw_portfolio<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    portfolio: &PortfolioView,
+    strings: &'static Strings,
+) {
+    let title = fill(strings.portfolio, &[&portfolio.total]);
+    let block = Block::default().title(title).borders(Borders::ALL);
 
-    let table = Table::new(vec![Row::new(vec![best_ask, best_bid])])
+    let rows = portfolio.holdings.iter().map(|holding| {
+        Row::new(vec![
+            holding.asset.as_str(),
+            holding.amount.as_str(),
+            holding.price.as_str(),
+            holding.value.as_str(),
+            holding.change.as_str(),
+        ])
+    });
+
+    let widths = [Constraint::Ratio(1, 5); 5];
+    let table = Table::new(rows)
         .header(
-            Row::new(vec!["Best ask", "Best bid"])
-                .style(Style::default().add_modifier(Modifier::BOLD))
-                .bottom_margin(1),
+            Row::new(vec![
+                strings.asset,
+                strings.amount,
+                strings.price,
+                strings.value,
+                strings.change,
+            ])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
         )
         .block(block)
-        .widths(&[
-            Constraint::Length(15),
-            Constraint::Length(15),
-            Constraint::Length(10),
-        ]);
+        .widths(&widths);
 
     frame.render_widget(table, area);
 }
 
-pub struct AppUiLayout {
-    pub best_prices: Rect,
-    pub order_book: Rect,
+/// Draw the strip as a single line, items that don't fit are cut.
+pub fn draw_strip<B: Backend>(frame: &mut Frame<B>, area: Rect, strip: &StripView) {
+    let mut spans = vec![];
+    let mut width = area.width as usize;
+    for item in strip.items.iter() {
+        let style = match item.rising {
+            Some(true) => Style::default().fg(Color::Green),
+            Some(false) => Style::default().fg(Color::Red),
+            None => Style::default(),
+        };
+        let text = format!(" {} {} {} ", item.symbol, item.price, item.change);
+        let text = fit_width(&text, width).into_owned();
+        width = width.saturating_sub(text.width());
+        spans.push(Span::styled(text, style));
+        if width == 0 {
+            break;
+        }
+    }
+    frame.render_widget(Paragraph::new(Spans::from(spans)), area);
 }
 
-pub fn get_global_layout<B: Backend>(frame: &Frame<B>) -> AppUiLayout {
-    let chunks = Layout::default()
-        .direction(Vertical)
-        .constraints([Constraint::Length(5), Constraint::Min(0)])
-        .margin(1)
-        .split(frame.size());
+/// Area of the given percents of the screen in its center.
+fn centered(area: Rect, width_percent: u16, height_percent: u16) -> Rect {
+    let width = area.width * width_percent / 100;
+    let height = area.height * height_percent / 100;
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Draw the picker over whatever is drawn below it - the query, then the matching symbols.
+pub fn draw_picker<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    picker: &PickerView,
+    strings: &'static Strings,
+) {
+    let area = centered(area, 40, 60);
+    let width = inner_width(area.width);
+    // Query takes the first line, the rest are the symbols.
+    let visible = inner_height(area.height).saturating_sub(1);
+
+    let mut lines = vec![Spans::from(
+        fit_width(&format!("> {}", picker.query), width).into_owned(),
+    )];
+    if picker.loading {
+        lines.push(Spans::from(strings.picker_loading));
+    } else if picker.items.is_empty() {
+        lines.push(Spans::from(strings.picker_empty));
+    }
+    // Items are scrolled to keep the highlighted one in sight.
+    let offset = (picker.selected + 1).saturating_sub(visible);
+    for (index, item) in picker.items.iter().enumerate().skip(offset).take(visible) {
+        let style = if index == picker.selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        lines.push(Spans::from(Span::styled(
+            fit_width(item, width).into_owned(),
+            style,
+        )));
+    }
+
+    let block = pane_block(strings.picker_title, true);
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Draw the build information over whatever is drawn below it, labels are aligned.
+pub fn draw_about<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    about: &AboutView,
+    strings: &'static Strings,
+) {
+    let area = centered(area, 60, 50);
+    let width = inner_width(area.width);
+    let config = match about.config_sources.is_empty() {
+        true => strings.about_no_config.to_string(),
+        false => about.config_sources.join(", "),
+    };
+    let queues = match about.queues.is_empty() {
+        true => "-".to_string(),
+        false => about.queues.join(", "),
+    };
+    let rows = [
+        (strings.about_version, about.version.as_str()),
+        (strings.about_commit, about.commit.as_str()),
+        (strings.about_built, about.built.as_str()),
+        (strings.about_target, about.target.as_str()),
+        (strings.about_features, about.features.as_str()),
+        (strings.about_config, config.as_str()),
+        (strings.about_queues, queues.as_str()),
+    ];
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| label.width())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Spans> = rows
+        .iter()
+        .map(|(label, value)| {
+            let padding = " ".repeat(label_width - label.width() + 1);
+            let line = format!("{}:{}{}", label, padding, value);
+            Spans::from(fit_width(&line, width).into_owned())
+        })
+        .collect();
+
+    let block = pane_block(strings.about_title, true);
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
 
-    AppUiLayout {
-        best_prices: chunks[0],
-        order_book: chunks[1],
+pub fn draw_status<B: Backend>(frame: &mut Frame<B>, area: Rect, status: &str) {
+    let status = fit_width(status, area.width as usize).into_owned();
+    frame.render_widget(Paragraph::new(status), area);
+}
+
+/// Everything that is needed to draw the application - prepared by the app, drawn by the render thread.
+#[derive(Clone, Default)]
+pub struct AppFrame {
+    pub book: Option<BookView>,
+    pub quote: Option<QuoteView>,
+    pub status: String,
+    pub layout: PaneLayout,
+    pub portfolio: Option<PortfolioView>,
+    pub strip: Option<StripView>,
+
+    /// Language the titles and the headers are drawn in.
+    pub locale: Locale,
+
+    /// Symbol picker, if it is open.
+    pub picker: Option<PickerView>,
+
+    /// Build information, if it is shown.
+    pub about: Option<AboutView>,
+}
+
+/// Draw prepared application's state on the provided frame, with the detail allowed by the render budget.
+pub fn draw_app<B: Backend>(frame: &mut Frame<B>, app: &AppFrame, budget: &RenderBudget) {
+    let strings = app.locale.strings();
+    draw_background(frame, strings);
+    let mut layout = app.layout.split(frame.size());
+    if let Some(portfolio) = app.portfolio.as_ref() {
+        // Portfolio shares the screen with the order book.
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(layout.order_book);
+        layout.order_book = chunks[0];
+        draw_portfolio(frame, chunks[1], portfolio, strings);
+    }
+    if let Some(strip) = app.strip.as_ref() {
+        draw_strip(frame, layout.strip, strip);
+    }
+    let focused = app.layout.focused();
+    if let Some(book) = app
+        .book
+        .as_ref()
+        .filter(|_| app.layout.is_visible(Pane::OrderBook))
+    {
+        let draw_book = match app.layout.preset() {
+            LayoutPreset::Split => draw_order_book,
+            LayoutPreset::Ladder => draw_ladder,
+        };
+        draw_book(
+            frame,
+            layout.order_book,
+            book,
+            budget.book_depth(),
+            focused == Pane::OrderBook,
+            strings,
+        );
+    }
+    if let Some(quote) = app
+        .quote
+        .as_ref()
+        .filter(|_| app.layout.is_visible(Pane::BestPrices))
+    {
+        draw_best_price(
+            frame,
+            layout.best_prices,
+            quote,
+            focused == Pane::BestPrices,
+            strings,
+        );
+    }
+
+    if budget.is_degraded() {
+        let status = format!("{} | {}", app.status, strings.degraded);
+        draw_status(frame, layout.status, &status);
+    } else {
+        draw_status(frame, layout.status, &app.status);
+    }
+
+    if let Some(picker) = app.picker.as_ref() {
+        draw_picker(frame, frame.size(), picker, strings);
+    }
+    if let Some(about) = app.about.as_ref() {
+        draw_about(frame, frame.size(), about, strings);
     }
 }
 
-pub fn draw_background<B: Backend>(frame: &mut Frame<B>) {
+pub fn draw_background<B: Backend>(frame: &mut Frame<B>, strings: &'static Strings) {
     let size = frame.size();
 
     let block = Block::default()
-        .title("Binance Scrapper")
+        .title(strings.app_title)
         .borders(Borders::ALL);
 
     frame.render_widget(block, size);
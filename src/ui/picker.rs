@@ -0,0 +1,169 @@
+use crate::ui::view::PickerView;
+
+/// How well the symbol matches the query, the lower the better.
+///
+/// Symbols starting with the query come first, then the ones containing it, then the ones having its chars
+/// in order - the fewer chars are skipped between them, the better. `None` if the chars are not in order.
+fn fuzzy_score(symbol: &str, query: &str) -> Option<(u8, usize)> {
+    if symbol.starts_with(query) {
+        return Some((0, 0));
+    }
+    if symbol.contains(query) {
+        return Some((1, 0));
+    }
+    let mut skipped = 0;
+    let mut chars = symbol.chars();
+    for wanted in query.chars() {
+        loop {
+            match chars.next() {
+                Some(char) if char == wanted => break,
+                Some(_) => skipped += 1,
+                None => return None,
+            }
+        }
+    }
+    Some((2, skipped))
+}
+
+/// Modal list of the exchange's symbols, filtered by the typed query.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolPicker {
+    /// Symbols to pick from, unknown until they are loaded.
+    symbols: Option<Vec<String>>,
+    query: String,
+
+    /// Index of the highlighted one of the matching symbols.
+    selected: usize,
+}
+
+impl SymbolPicker {
+    pub fn new(symbols: Option<Vec<String>>) -> Self {
+        Self {
+            symbols,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.symbols.is_none()
+    }
+
+    pub fn set_symbols(&mut self, symbols: Vec<String>) {
+        self.symbols = Some(symbols);
+        self.selected = 0;
+    }
+
+    pub fn push(&mut self, char: char) {
+        self.query.push(char.to_ascii_uppercase());
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    /// Highlight the next matching symbol, wrapping around.
+    pub fn next(&mut self) {
+        let len = self.matches().len().max(1);
+        self.selected = (self.selected + 1) % len;
+    }
+
+    pub fn previous(&mut self) {
+        let len = self.matches().len().max(1);
+        self.selected = (self.selected + len - 1) % len;
+    }
+
+    /// Symbols matching the query, the best matches first.
+    pub fn matches(&self) -> Vec<&str> {
+        let symbols = match self.symbols.as_ref() {
+            Some(symbols) => symbols,
+            None => return vec![],
+        };
+        let mut matches: Vec<((u8, usize), &str)> = symbols
+            .iter()
+            .filter_map(|symbol| {
+                fuzzy_score(symbol, &self.query).map(|score| (score, symbol.as_str()))
+            })
+            .collect();
+        matches.sort_unstable_by(|(a_score, a), (b_score, b)| {
+            a_score
+                .cmp(b_score)
+                .then(a.len().cmp(&b.len()))
+                .then(a.cmp(b))
+        });
+        matches.into_iter().map(|(_, symbol)| symbol).collect()
+    }
+
+    /// Highlighted symbol, if any matches the query.
+    pub fn picked(&self) -> Option<String> {
+        self.matches()
+            .get(self.selected)
+            .map(|symbol| symbol.to_string())
+    }
+
+    pub fn view(&self) -> PickerView {
+        PickerView {
+            query: self.query.clone(),
+            items: self.matches().into_iter().map(String::from).collect(),
+            selected: self.selected,
+            loading: self.is_loading(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picker() -> SymbolPicker {
+        let symbols = [
+            "ETHBTC", "BTCUSDT", "ETHUSDT", "BTCUSDC", "WBTCUSDT", "BNBUSDT",
+        ];
+        SymbolPicker::new(Some(
+            symbols.iter().map(|symbol| symbol.to_string()).collect(),
+        ))
+    }
+
+    #[test]
+    fn it_ranks_fuzzy_matches() {
+        let mut picker = picker();
+        "btc".chars().for_each(|char| picker.push(char));
+        assert_eq!(
+            picker.matches(),
+            vec!["BTCUSDC", "BTCUSDT", "ETHBTC", "WBTCUSDT"]
+        );
+
+        // Chars skipped between the typed ones are the same, shorter symbols come first.
+        picker.pop();
+        picker.pop();
+        picker.push('u');
+        assert_eq!(
+            picker.matches(),
+            vec!["BNBUSDT", "BTCUSDC", "BTCUSDT", "WBTCUSDT"]
+        );
+    }
+
+    #[test]
+    fn it_picks_highlighted_symbol() {
+        let mut picker = picker();
+        "ethu".chars().for_each(|char| picker.push(char));
+        assert_eq!(picker.picked().as_deref(), Some("ETHUSDT"));
+
+        picker.next();
+        assert_eq!(picker.picked().as_deref(), Some("ETHUSDT"));
+
+        // ETHBTC is shorter, so it comes first.
+        picker.pop();
+        assert_eq!(picker.picked().as_deref(), Some("ETHBTC"));
+        picker.previous();
+        assert_eq!(picker.picked().as_deref(), Some("ETHUSDT"));
+
+        let mut loading = SymbolPicker::new(None);
+        loading.push('b');
+        assert!(loading.is_loading());
+        assert_eq!(loading.picked(), None);
+        loading.set_symbols(vec!["BTCUSDT".into()]);
+        assert_eq!(loading.picked().as_deref(), Some("BTCUSDT"));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::ui::layout::Pane;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Choices of the user kept between the runs, so the screen is not to be set up again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// Panes hidden by the user, the rest are shown.
+    pub hidden_panes: Vec<Pane>,
+}
+
+/// File the session is stored in.
+#[derive(Debug, Clone)]
+pub struct SessionFile {
+    path: PathBuf,
+}
+
+impl SessionFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the stored session. Missing or malformed file is treated as absent one.
+    pub fn load(&self) -> Option<Session> {
+        let data = std::fs::read(&self.path).ok()?;
+        match serde_json::from_slice(&data) {
+            Ok(session) => Some(session),
+            Err(err) => {
+                debug!(
+                    "Session {} is malformed. Error: {}",
+                    self.path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, session: &Session) -> io::Result<()> {
+        let data = serde_json::to_vec(session).expect("Session is always serializable.");
+        std::fs::write(&self.path, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_stores_session() {
+        let path = std::env::temp_dir().join("bnc-scraper-session.json");
+        let file = SessionFile::new(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(file.load(), None);
+
+        let session = Session {
+            hidden_panes: vec![Pane::OrderBook],
+        };
+        file.save(&session).unwrap();
+        assert_eq!(file.load(), Some(session));
+
+        std::fs::write(&path, "garbage").unwrap();
+        assert_eq!(file.load(), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use std::borrow::Cow;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const ELLIPSIS: char = '…';
+
+/// Fit text into the given amount of terminal cells, replacing the tail with an ellipsis if it doesn't fit.
+///
+/// Width is measured in displayed cells rather than chars, so wide(e.g. CJK) characters are accounted properly.
+pub fn fit_width(text: &str, width: usize) -> Cow<'_, str> {
+    if text.width() <= width {
+        return Cow::Borrowed(text);
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+
+    // One cell is reserved for the ellipsis.
+    let mut fitted = String::new();
+    let mut fitted_width = 0;
+    for char in text.chars() {
+        let char_width = char.width().unwrap_or(0);
+        if fitted_width + char_width > width - 1 {
+            break;
+        }
+        fitted.push(char);
+        fitted_width += char_width;
+    }
+    fitted.push(ELLIPSIS);
+    Cow::Owned(fitted)
+}
+
+/// Inner width of the area wrapped into a block with borders.
+pub fn inner_width(width: u16) -> usize {
+    width.saturating_sub(2) as usize
+}
+
+/// Inner height of the area wrapped into a block with borders.
+pub fn inner_height(height: u16) -> usize {
+    height.saturating_sub(2) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_fitting_text() {
+        assert_eq!(fit_width("BTCUSDT", 7), "BTCUSDT");
+        assert_eq!(fit_width("", 0), "");
+    }
+
+    #[test]
+    fn it_truncates_long_symbols_with_ellipsis() {
+        let symbol = "1000SATSFDUSDLONGSYMBOLNAME";
+        let fitted = fit_width(symbol, 10);
+
+        assert_eq!(fitted, "1000SATSF…");
+        assert_eq!(fitted.width(), 10);
+        assert_eq!(fit_width(symbol, 1), "…");
+        assert_eq!(fit_width(symbol, 0), "");
+    }
+
+    #[test]
+    fn it_truncates_wide_characters_by_cells() {
+        // Each of these chars takes two cells.
+        let text = "币安币安币安";
+        assert_eq!(text.width(), 12);
+
+        let fitted = fit_width(text, 6);
+        assert_eq!(fitted, "币安…");
+        assert!(fitted.width() <= 6);
+
+        // Wide char that doesn't fit into the remaining cell is dropped entirely.
+        let fitted = fit_width(text, 4);
+        assert_eq!(fitted, "币…");
+        assert_eq!(fitted.width(), 3);
+    }
+
+    #[test]
+    fn it_measures_numbers_exactly() {
+        let order = "43250.01000000/0.00150000";
+        assert_eq!(fit_width(order, 25), order);
+        assert_eq!(fit_width(order, 15).width(), 15);
+    }
+}
@@ -0,0 +1,196 @@
+//! Presentation model of the application - everything the ui draws, already formatted.
+//!
+//! It is produced by the app, so the ui doesn't depend on the exchange's types.
+
+/// Price level of the book.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LevelView {
+    pub price: String,
+    pub qty: String,
+
+    /// Quantity of this level and all the better ones.
+    pub cum_qty: String,
+}
+
+/// Best prices of the displayed symbol.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuoteView {
+    pub ask: String,
+    pub bid: String,
+
+    /// Mid of the best prices weighted by their quantities.
+    pub microprice: String,
+
+    /// Average price of the last minutes.
+    pub average: String,
+
+    /// Rolling 24hr statistics, once they arrive.
+    pub stats: Option<StatsView>,
+}
+
+/// Rolling 24hr statistics of the displayed symbol.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatsView {
+    pub change_percent: String,
+    pub high: String,
+    pub low: String,
+    pub volume: String,
+}
+
+/// Top of the displayed symbol's order book, levels are in the order they are displayed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookView {
+    pub asks: Vec<LevelView>,
+    pub bids: Vec<LevelView>,
+
+    /// Difference between the best ask and bid, if both are known.
+    pub spread: Option<String>,
+
+    /// Whether the book is restored from cache and is not live yet.
+    pub cached: bool,
+
+    /// Step the levels are grouped by, if they are.
+    pub grouping: Option<String>,
+}
+
+/// Single row of the price ladder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LadderRung<'a> {
+    Ask(&'a LevelView),
+    Spread(Option<&'a str>),
+    Bid(&'a LevelView),
+}
+
+impl BookView {
+    /// Rows of the price ladder with the given depth of each side - from the farthest ask down to the best one,
+    /// the spread, then from the best bid down to the farthest one.
+    pub fn ladder(&self, depth: usize) -> Vec<LadderRung<'_>> {
+        let asks = self.asks.iter().take(depth).rev().map(LadderRung::Ask);
+        let spread = LadderRung::Spread(self.spread.as_deref());
+        let bids = self.bids.iter().take(depth).map(LadderRung::Bid);
+        asks.chain(std::iter::once(spread)).chain(bids).collect()
+    }
+}
+
+/// Single symbol of the ticker strip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StripItemView {
+    pub symbol: String,
+    pub price: String,
+    pub change: String,
+
+    /// Direction of the change, none if it is unknown or there is no change.
+    pub rising: Option<bool>,
+}
+
+/// Watched symbols in the order they are shown, the strip is cycled through them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StripView {
+    pub items: Vec<StripItemView>,
+}
+
+/// Symbol picker shown over the panes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PickerView {
+    pub query: String,
+
+    /// Symbols matching the query, the best matches first.
+    pub items: Vec<String>,
+
+    /// Index of the highlighted item.
+    pub selected: usize,
+
+    /// Whether the symbols are still being loaded.
+    pub loading: bool,
+}
+
+/// Build of the binary and the configuration it runs with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AboutView {
+    pub version: String,
+    pub commit: String,
+    pub built: String,
+    pub target: String,
+    pub features: String,
+
+    /// Files and environment the configuration is loaded from, none if the defaults are used.
+    pub config_sources: Vec<String>,
+
+    /// Stream queues of the statistics connection, e.g. `btcusdt@ticker 0 queued, 0 dropped`.
+    pub queues: Vec<String>,
+}
+
+/// Side of the taker of the trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// Single print of the trade tape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TradeView {
+    pub price: String,
+    pub qty: String,
+    pub time: String,
+    pub side: TradeSide,
+}
+
+/// Latest trades of the displayed symbol, the newest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TapeView {
+    pub trades: Vec<TradeView>,
+}
+
+/// Valuation of a single holding, unknown values are shown as `-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoldingView {
+    pub asset: String,
+    pub amount: String,
+    pub price: String,
+    pub value: String,
+    pub change: String,
+}
+
+/// Valuation of all the holdings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortfolioView {
+    /// Total value labelled with the quote currency.
+    pub total: String,
+    pub holdings: Vec<HoldingView>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: &str) -> LevelView {
+        LevelView {
+            price: price.into(),
+            qty: "1".into(),
+            cum_qty: "1".into(),
+        }
+    }
+
+    #[test]
+    fn it_builds_ladder_around_spread() {
+        let book = BookView {
+            asks: vec![level("101"), level("102"), level("103")],
+            bids: vec![level("99"), level("98"), level("97")],
+            spread: Some("2".into()),
+            cached: false,
+            grouping: None,
+        };
+
+        assert_eq!(
+            book.ladder(2),
+            vec![
+                LadderRung::Ask(&book.asks[1]),
+                LadderRung::Ask(&book.asks[0]),
+                LadderRung::Spread(Some("2")),
+                LadderRung::Bid(&book.bids[0]),
+                LadderRung::Bid(&book.bids[1]),
+            ]
+        );
+    }
+}